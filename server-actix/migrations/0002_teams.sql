@@ -0,0 +1,21 @@
+CREATE TABLE IF NOT EXISTS {schema}.{table_teams} (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS {schema}.{table_memberships} (
+    id TEXT PRIMARY KEY,
+    team_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    role TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    UNIQUE (team_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS {table_memberships}_user_id_idx ON {schema}.{table_memberships} (user_id);
+
+-- boards created before teams existed belong to no team, and so are visible to nobody
+ALTER TABLE {schema}.{table_boards} ADD COLUMN IF NOT EXISTS team_id TEXT NOT NULL DEFAULT '';
+
+CREATE INDEX IF NOT EXISTS {table_boards}_team_id_idx ON {schema}.{table_boards} (team_id);
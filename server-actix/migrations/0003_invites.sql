@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS {schema}.{table_invites} (
+    id TEXT PRIMARY KEY,
+    board_id TEXT NOT NULL,
+    created_by TEXT NOT NULL,
+    expires_at BIGINT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS {table_invites}_board_id_idx ON {schema}.{table_invites} (board_id);
@@ -0,0 +1,37 @@
+CREATE TABLE IF NOT EXISTS {schema}.{table_cards} (
+    id TEXT PRIMARY KEY,
+    board_id TEXT NOT NULL,
+    column_id TEXT NOT NULL,
+    group_id TEXT,
+    merged_into TEXT,
+    title TEXT NOT NULL,
+    author TEXT NOT NULL,
+    author_name TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS {table_cards}_board_id_idx ON {schema}.{table_cards} (board_id);
+
+CREATE TABLE IF NOT EXISTS {schema}.{table_groups} (
+    id TEXT PRIMARY KEY,
+    board_id TEXT NOT NULL,
+    column_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS {table_groups}_board_id_idx ON {schema}.{table_groups} (board_id);
+
+CREATE TABLE IF NOT EXISTS {schema}.{table_votes} (
+    id TEXT PRIMARY KEY,
+    board_id TEXT NOT NULL,
+    card_id TEXT,
+    group_id TEXT,
+    author TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    CHECK ((card_id IS NULL) <> (group_id IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS {table_votes}_board_id_idx ON {schema}.{table_votes} (board_id);
+
+CREATE INDEX IF NOT EXISTS {table_columns}_board_id_idx ON {schema}.{table_columns} (board_id);
@@ -0,0 +1,5 @@
+ALTER TABLE {schema}.{table_boards} ADD COLUMN IF NOT EXISTS archived_at BIGINT;
+
+ALTER TABLE {schema}.{table_boards} ADD COLUMN IF NOT EXISTS deleted_at BIGINT;
+
+CREATE INDEX IF NOT EXISTS {table_boards}_deleted_at_idx ON {schema}.{table_boards} (deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,31 @@
+-- rows left behind by the old single row board delete would block the constraints below
+DELETE FROM {schema}.{table_columns} WHERE board_id NOT IN (SELECT id FROM {schema}.{table_boards});
+DELETE FROM {schema}.{table_groups} WHERE board_id NOT IN (SELECT id FROM {schema}.{table_boards});
+DELETE FROM {schema}.{table_cards} WHERE board_id NOT IN (SELECT id FROM {schema}.{table_boards});
+DELETE FROM {schema}.{table_votes} WHERE board_id NOT IN (SELECT id FROM {schema}.{table_boards});
+DELETE FROM {schema}.{table_invites} WHERE board_id NOT IN (SELECT id FROM {schema}.{table_boards});
+DELETE FROM {schema}.{table_memberships} WHERE team_id NOT IN (SELECT id FROM {schema}.{table_teams});
+
+ALTER TABLE {schema}.{table_memberships}
+    ADD CONSTRAINT {table_memberships}_team_id_fkey FOREIGN KEY (team_id) REFERENCES {schema}.{table_teams} (id) ON DELETE CASCADE;
+
+ALTER TABLE {schema}.{table_columns}
+    ADD CONSTRAINT {table_columns}_board_id_fkey FOREIGN KEY (board_id) REFERENCES {schema}.{table_boards} (id) ON DELETE CASCADE;
+
+ALTER TABLE {schema}.{table_groups}
+    ADD CONSTRAINT {table_groups}_board_id_fkey FOREIGN KEY (board_id) REFERENCES {schema}.{table_boards} (id) ON DELETE CASCADE,
+    ADD CONSTRAINT {table_groups}_column_id_fkey FOREIGN KEY (column_id) REFERENCES {schema}.{table_columns} (id) ON DELETE CASCADE;
+
+ALTER TABLE {schema}.{table_cards}
+    ADD CONSTRAINT {table_cards}_board_id_fkey FOREIGN KEY (board_id) REFERENCES {schema}.{table_boards} (id) ON DELETE CASCADE,
+    ADD CONSTRAINT {table_cards}_column_id_fkey FOREIGN KEY (column_id) REFERENCES {schema}.{table_columns} (id) ON DELETE CASCADE,
+    ADD CONSTRAINT {table_cards}_group_id_fkey FOREIGN KEY (group_id) REFERENCES {schema}.{table_groups} (id) ON DELETE SET NULL,
+    ADD CONSTRAINT {table_cards}_merged_into_fkey FOREIGN KEY (merged_into) REFERENCES {schema}.{table_cards} (id) ON DELETE SET NULL;
+
+ALTER TABLE {schema}.{table_votes}
+    ADD CONSTRAINT {table_votes}_board_id_fkey FOREIGN KEY (board_id) REFERENCES {schema}.{table_boards} (id) ON DELETE CASCADE,
+    ADD CONSTRAINT {table_votes}_card_id_fkey FOREIGN KEY (card_id) REFERENCES {schema}.{table_cards} (id) ON DELETE CASCADE,
+    ADD CONSTRAINT {table_votes}_group_id_fkey FOREIGN KEY (group_id) REFERENCES {schema}.{table_groups} (id) ON DELETE CASCADE;
+
+ALTER TABLE {schema}.{table_invites}
+    ADD CONSTRAINT {table_invites}_board_id_fkey FOREIGN KEY (board_id) REFERENCES {schema}.{table_boards} (id) ON DELETE CASCADE;
@@ -0,0 +1,22 @@
+-- existing rows keep their creation order until someone moves them
+ALTER TABLE {schema}.{table_columns} ADD COLUMN IF NOT EXISTS position INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE {schema}.{table_cards} ADD COLUMN IF NOT EXISTS position INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS {schema}.{table_tags} (
+    id TEXT PRIMARY KEY,
+    board_id TEXT NOT NULL REFERENCES {schema}.{table_boards} (id) ON DELETE CASCADE,
+    title TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS {table_tags}_board_id_idx ON {schema}.{table_tags} (board_id);
+
+CREATE TABLE IF NOT EXISTS {schema}.{table_card_tags} (
+    id TEXT PRIMARY KEY,
+    board_id TEXT NOT NULL REFERENCES {schema}.{table_boards} (id) ON DELETE CASCADE,
+    card_id TEXT NOT NULL REFERENCES {schema}.{table_cards} (id) ON DELETE CASCADE,
+    tag_id TEXT NOT NULL REFERENCES {schema}.{table_tags} (id) ON DELETE CASCADE,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS {table_card_tags}_board_id_idx ON {schema}.{table_card_tags} (board_id);
@@ -0,0 +1,22 @@
+CREATE TABLE IF NOT EXISTS {schema}.{table_comments} (
+    id TEXT PRIMARY KEY,
+    board_id TEXT NOT NULL REFERENCES {schema}.{table_boards} (id) ON DELETE CASCADE,
+    card_id TEXT NOT NULL REFERENCES {schema}.{table_cards} (id) ON DELETE CASCADE,
+    author TEXT NOT NULL,
+    author_name TEXT NOT NULL,
+    text TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS {table_comments}_board_id_idx ON {schema}.{table_comments} (board_id);
+
+CREATE TABLE IF NOT EXISTS {schema}.{table_action_items} (
+    id TEXT PRIMARY KEY,
+    board_id TEXT NOT NULL REFERENCES {schema}.{table_boards} (id) ON DELETE CASCADE,
+    title TEXT NOT NULL,
+    assignee TEXT,
+    done BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS {table_action_items}_board_id_idx ON {schema}.{table_action_items} (board_id);
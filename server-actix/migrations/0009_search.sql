@@ -0,0 +1,5 @@
+-- {search_config} is PG_SEARCH_CONFIG, which the search has to use too or it cannot use these,
+-- changing it later takes dropping them so this runs again
+CREATE INDEX IF NOT EXISTS {table_boards}_title_search_idx ON {schema}.{table_boards} USING GIN (to_tsvector('{search_config}', title));
+CREATE INDEX IF NOT EXISTS {table_cards}_title_search_idx ON {schema}.{table_cards} USING GIN (to_tsvector('{search_config}', title));
+CREATE INDEX IF NOT EXISTS {table_comments}_text_search_idx ON {schema}.{table_comments} USING GIN (to_tsvector('{search_config}', text));
@@ -0,0 +1,29 @@
+CREATE TABLE IF NOT EXISTS {schema}.{table_audit_log} (
+    id TEXT PRIMARY KEY,
+    actor TEXT NOT NULL,
+    action TEXT NOT NULL,
+    entity_type TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    -- no foreign key, entries outlive the boards they are about
+    board_id TEXT,
+    before JSONB,
+    after JSONB,
+    ip TEXT,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS {table_audit_log}_created_at_idx ON {schema}.{table_audit_log} (created_at);
+CREATE INDEX IF NOT EXISTS {table_audit_log}_entity_idx ON {schema}.{table_audit_log} (entity_type, entity_id);
+CREATE INDEX IF NOT EXISTS {table_audit_log}_board_id_idx ON {schema}.{table_audit_log} (board_id);
+CREATE INDEX IF NOT EXISTS {table_audit_log}_actor_idx ON {schema}.{table_audit_log} (actor);
+
+-- append only, even for whoever has the database credentials of the app
+CREATE OR REPLACE FUNCTION {schema}.{table_audit_log}_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'audit entries cannot be changed or removed';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS {table_audit_log}_append_only ON {schema}.{table_audit_log};
+CREATE TRIGGER {table_audit_log}_append_only BEFORE UPDATE OR DELETE OR TRUNCATE ON {schema}.{table_audit_log}
+    FOR EACH STATEMENT EXECUTE FUNCTION {schema}.{table_audit_log}_append_only();
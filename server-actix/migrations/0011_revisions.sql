@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS {schema}.{table_revisions} (
+    id TEXT PRIMARY KEY,
+    board_id TEXT NOT NULL REFERENCES {schema}.{table_boards} (id) ON DELETE CASCADE,
+    card_id TEXT NOT NULL REFERENCES {schema}.{table_cards} (id) ON DELETE CASCADE,
+    -- none for revisions of the card itself
+    comment_id TEXT REFERENCES {schema}.{table_comments} (id) ON DELETE CASCADE,
+    text TEXT NOT NULL,
+    editor TEXT NOT NULL,
+    editor_name TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS {table_revisions}_card_id_idx ON {schema}.{table_revisions} (card_id);
+CREATE INDEX IF NOT EXISTS {table_revisions}_board_id_idx ON {schema}.{table_revisions} (board_id);
@@ -0,0 +1,32 @@
+-- which of these files have run, so the server can tell when the schema is behind it,
+-- migrate() records each file in here as it runs it, so none of them insert their own version
+CREATE TABLE IF NOT EXISTS {schema}.{table_migrations} (
+    version TEXT PRIMARY KEY,
+    applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- nothing recorded the earlier files as they ran, so each counts as applied
+-- only if the last thing it makes is there, and is otherwise left to run
+INSERT INTO {schema}.{table_migrations} (version)
+SELECT version FROM (VALUES
+    ('0001_init', to_regclass('{schema}.{table_columns}') IS NOT NULL),
+    ('0002_teams', to_regclass('{schema}.{table_boards}_team_id_idx') IS NOT NULL),
+    ('0003_invites', to_regclass('{schema}.{table_invites}_board_id_idx') IS NOT NULL),
+    ('0004_cards', to_regclass('{schema}.{table_columns}_board_id_idx') IS NOT NULL),
+    ('0005_board_states', to_regclass('{schema}.{table_boards}_deleted_at_idx') IS NOT NULL),
+    ('0006_foreign_keys', EXISTS (
+        SELECT 1 FROM pg_constraint
+        WHERE conname = '{table_invites}_board_id_fkey' AND conrelid = to_regclass('{schema}.{table_invites}')
+    )),
+    ('0007_tags_and_positions', to_regclass('{schema}.{table_card_tags}_board_id_idx') IS NOT NULL),
+    ('0008_comments_and_action_items', to_regclass('{schema}.{table_action_items}_board_id_idx') IS NOT NULL),
+    ('0009_search', to_regclass('{schema}.{table_comments}_text_search_idx') IS NOT NULL),
+    ('0010_audit_log', EXISTS (
+        SELECT 1 FROM pg_trigger
+        WHERE tgname = '{table_audit_log}_append_only' AND tgrelid = to_regclass('{schema}.{table_audit_log}')
+    )),
+    ('0011_revisions', to_regclass('{schema}.{table_revisions}_board_id_idx') IS NOT NULL),
+    ('0012_schema_migrations', true)
+) AS earlier (version, applied)
+WHERE applied
+ON CONFLICT (version) DO NOTHING;
@@ -0,0 +1,6 @@
+-- created_at only goes to the millisecond and the ids are random, so entries get numbered as they go in
+-- adding the column numbers the ones already there in the order they sit in the table, which for a table
+-- nothing is ever updated or deleted in is the order they went in
+ALTER TABLE {schema}.{table_audit_log} ADD COLUMN IF NOT EXISTS seq BIGSERIAL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS {table_audit_log}_seq_idx ON {schema}.{table_audit_log} (seq);
@@ -0,0 +1,117 @@
+
+use std::net::SocketAddr;
+
+use actix_web::HttpRequest;
+use serde::Serialize;
+use tracing::error;
+
+use crate::auth::Caller;
+use crate::models::{Service, AuditEntry};
+use crate::handlers::new_id;
+
+
+pub const ACTION_CREATE: &'static str = "create";
+pub const ACTION_UPDATE: &'static str = "update";
+pub const ACTION_DELETE: &'static str = "delete";
+pub const ACTION_RESTORE: &'static str = "restore";
+pub const ACTION_PURGE: &'static str = "purge";
+pub const ACTION_ARCHIVE: &'static str = "archive";
+pub const ACTION_UNARCHIVE: &'static str = "unarchive";
+pub const ACTION_CLONE: &'static str = "clone";
+pub const ACTION_IMPORT: &'static str = "import";
+pub const ACTION_MOVE: &'static str = "move";
+pub const ACTION_MERGE: &'static str = "merge";
+pub const ACTION_UNMERGE: &'static str = "unmerge";
+pub const ACTION_ROTATE: &'static str = "rotate";
+pub const ACTION_JOIN: &'static str = "join";
+pub const ACTION_UNDO: &'static str = "undo";
+pub const ACTION_REDO: &'static str = "redo";
+
+pub const ENTITY_BOARD: &'static str = "board";
+pub const ENTITY_COLUMN: &'static str = "column";
+pub const ENTITY_CARD: &'static str = "card";
+pub const ENTITY_GROUP: &'static str = "group";
+pub const ENTITY_VOTE: &'static str = "vote";
+pub const ENTITY_TAG: &'static str = "tag";
+pub const ENTITY_CARD_TAG: &'static str = "card_tag";
+pub const ENTITY_COMMENT: &'static str = "comment";
+pub const ENTITY_ACTION_ITEM: &'static str = "action_item";
+pub const ENTITY_TEAM: &'static str = "team";
+pub const ENTITY_MEMBERSHIP: &'static str = "membership";
+pub const ENTITY_INVITE: &'static str = "invite";
+pub const ENTITY_API_KEY: &'static str = "api_key";
+
+// the purge runs on its own, without anyone asking for it
+pub const ACTOR_SYSTEM: &'static str = "system";
+// whatever an operator changed with bareretro-admin
+pub const ACTOR_ADMIN: &'static str = "admin";
+
+
+pub struct Change {
+    action: &'static str,
+    entity_type: &'static str,
+    entity_id: String,
+    board_id: Option<String>,
+    before: Option<serde_json::Value>,
+    after: Option<serde_json::Value>,
+}
+
+impl Change {
+    pub fn new (action: &'static str, entity_type: &'static str, entity_id: &String, board_id: Option<&String>) -> Self {
+        Change {
+            action: action,
+            entity_type: entity_type,
+            entity_id: entity_id.clone(),
+            board_id: board_id.cloned(),
+            before: None,
+            after: None,
+        }
+    }
+
+    pub fn before<T : Serialize> (self, item: &T) -> Self {
+        Change { before: serde_json::to_value(item).ok(), ..self }
+    }
+
+    pub fn after<T : Serialize> (self, item: &T) -> Self {
+        Change { after: serde_json::to_value(item).ok(), ..self }
+    }
+}
+
+// the change already happened by now, so a failure here is only logged and counted rather than failing the request,
+// board history replays these entries, so operators need to see when one went missing
+pub async fn record (service: &Service, actor: &String, ip: Option<String>, change: Change) {
+    let now = service.time_provider.unix_ts_ms();
+    let entry = AuditEntry {
+        id: new_id(service, now),
+        seq: 0,
+        actor: actor.clone(),
+        action: String::from(change.action),
+        entity_type: String::from(change.entity_type),
+        entity_id: change.entity_id,
+        board_id: change.board_id,
+        before: change.before,
+        after: change.after,
+        ip: ip,
+        created_at: now,
+    };
+
+    if let Err(why) = service.storage.add_audit_entry(&entry).await {
+        service.metrics.audit_failed(&entry.action, &entry.entity_type);
+        error!(
+            action = %entry.action, entity_type = %entry.entity_type, entity_id = %entry.entity_id, actor = %entry.actor, error = %why,
+            "Audit failed!",
+        );
+    }
+}
+
+// same address the rate limit goes by, without the port when it has one
+pub fn client_ip (req: &HttpRequest) -> Option<String> {
+    req.connection_info().remote_addr().map(|addr| match addr.parse::<SocketAddr>() {
+        Ok(socket) => socket.ip().to_string(),
+        Err(_) => String::from(addr),
+    })
+}
+
+pub async fn audit (service: &Service, req: &HttpRequest, caller: &Caller, change: Change) {
+    record(service, &caller.user_id, client_ip(req), change).await
+}
@@ -0,0 +1,291 @@
+
+use actix_web::{HttpRequest, HttpResponse, http::header};
+use serde::{Serialize, Deserialize};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use crate::models::{MyError, Service, Board, Membership, Role, Invite};
+
+
+// TODO: take the user from the oauth token instead, once that exchange exists
+// until then the proxy in front authenticates users and passes them on in this header,
+// vouching for it with the proxy secret, see proxy_secret in config.example.toml
+const HEADER_USER_ID: &'static str = "X-User-Id";
+const HEADER_PROXY_SECRET: &'static str = "X-Proxy-Secret";
+const BEARER_PREFIX: &'static str = "Bearer ";
+
+const TOKEN_KIND_INVITE: &'static str = "invite";
+const TOKEN_KIND_GUEST: &'static str = "guest";
+const TOKEN_KIND_API_KEY: &'static str = "api_key";
+
+const INVITES_DISABLED: &'static str = "Invites are disabled, no INVITE_SECRET is configured";
+const API_KEYS_DISABLED: &'static str = "API keys are disabled, no API_KEY_SECRET is configured";
+
+// guests act as participants at most, and only on the board they were invited to
+const GUEST_ROLE: Role = Role::Participant;
+
+
+#[derive(Debug, Clone)]
+pub struct Caller {
+    pub user_id: String,
+    pub display_name: String,
+    // only set for guests, who are limited to this one board
+    pub board_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Claims {
+    kind: String,
+    #[serde(default)]
+    invite_id: String,
+    #[serde(default)]
+    board_id: String,
+    #[serde(default)]
+    key_id: String,
+    #[serde(default)]
+    user_id: String,
+    #[serde(default)]
+    display_name: String,
+    exp: i64,
+}
+
+// a token is the base64 json claims, a dot, and the base64 hmac of those claims
+// https://docs.rs/hmac/0.12.0/hmac/index.html
+fn sign (secret: &String, disabled: &'static str, claims: &Claims) -> Result<String, MyError> {
+    if secret.is_empty() {
+        return Err(String::from(disabled))
+    }
+    let payload = serde_json::to_vec(claims).map_err(|why| format!("Failed encoding claims: {}", why))?;
+    let payload = base64::encode_config(payload, base64::URL_SAFE_NO_PAD);
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).map_err(|why| format!("Invalid secret: {}", why))?;
+    mac.update(payload.as_bytes());
+    let signature = base64::encode_config(mac.finalize().into_bytes(), base64::URL_SAFE_NO_PAD);
+
+    Ok(format!("{}.{}", payload, signature))
+}
+
+fn verify (secret: &String, disabled: &'static str, token: &str, kind: &'static str, now: i64) -> Result<Claims, MyError> {
+    if secret.is_empty() {
+        return Err(String::from(disabled))
+    }
+    let (payload, signature) = match token.split_once('.') {
+        Some(parts) => parts,
+        None => return Err(String::from("Malformed token")),
+    };
+    let signature = base64::decode_config(signature, base64::URL_SAFE_NO_PAD).map_err(|_| String::from("Malformed token signature"))?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).map_err(|why| format!("Invalid secret: {}", why))?;
+    mac.update(payload.as_bytes());
+    mac.verify_slice(&signature).map_err(|_| String::from("Invalid token signature"))?;
+
+    let payload = base64::decode_config(payload, base64::URL_SAFE_NO_PAD).map_err(|_| String::from("Malformed token payload"))?;
+    let claims: Claims = serde_json::from_slice(&payload).map_err(|why| format!("Malformed token claims: {}", why))?;
+    if claims.kind != kind {
+        return Err(format!("Expected {} token but got {}", kind, claims.kind))
+    }
+    if claims.exp <= now {
+        return Err(String::from("Token expired"))
+    }
+    Ok(claims)
+}
+
+pub fn sign_invite (service: &Service, invite: &Invite) -> Result<String, MyError> {
+    sign(&service.config.invite_secret, INVITES_DISABLED, &Claims {
+        kind: String::from(TOKEN_KIND_INVITE),
+        invite_id: invite.id.clone(),
+        board_id: invite.board_id.clone(),
+        key_id: String::new(),
+        user_id: String::new(),
+        display_name: String::new(),
+        exp: invite.expires_at,
+    })
+}
+
+pub fn sign_guest_session (service: &Service, invite: &Invite, user_id: &String, display_name: &String, exp: i64) -> Result<String, MyError> {
+    sign(&service.config.invite_secret, INVITES_DISABLED, &Claims {
+        kind: String::from(TOKEN_KIND_GUEST),
+        invite_id: invite.id.clone(),
+        board_id: invite.board_id.clone(),
+        key_id: String::new(),
+        user_id: user_id.clone(),
+        display_name: display_name.clone(),
+        exp: exp,
+    })
+}
+
+// made by bareretro-admin for scripts, a key acts as its user until it expires
+// nothing is stored, so there is no taking back one key, rotating the secret takes back all of them
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiKey {
+    pub id: String,
+    pub user_id: String,
+    // only for whoever reads the audit log, to tell what a key was made for
+    pub name: String,
+    pub expires_at: i64,
+}
+
+pub fn sign_api_key (service: &Service, key: &ApiKey) -> Result<String, MyError> {
+    sign(&service.config.api_key_secret, API_KEYS_DISABLED, &Claims {
+        kind: String::from(TOKEN_KIND_API_KEY),
+        invite_id: String::new(),
+        board_id: String::new(),
+        key_id: key.id.clone(),
+        user_id: key.user_id.clone(),
+        display_name: key.user_id.clone(),
+        exp: key.expires_at,
+    })
+}
+
+// only to tell which secret to check a token with, verify still checks the kind it signed
+fn unverified_kind (token: &str) -> Option<String> {
+    #[derive(Deserialize)]
+    struct Kind {
+        kind: String,
+    }
+    let payload = base64::decode_config(token.split_once('.')?.0, base64::URL_SAFE_NO_PAD).ok()?;
+    serde_json::from_slice::<Kind>(&payload).ok().map(|claims| claims.kind)
+}
+
+// the signature only proves we issued the token, the invite must also still be
+// the live one for its board and not yet expired, so rotating or revoking it locks everyone out
+async fn live_invite (service: &Service, token: &str, kind: &'static str) -> Result<(Claims, Invite), HttpResponse> {
+    let now = service.time_provider.unix_ts_ms();
+    let claims = verify(&service.config.invite_secret, INVITES_DISABLED, token, kind, now)
+        .map_err(|why| HttpResponse::Unauthorized().body(format!("Invalid {} token! {}", kind, why)))?;
+
+    match service.storage.get_invite(&claims.invite_id).await {
+        Ok(invite) if invite.board_id == claims.board_id && invite.expires_at > now => Ok((claims, invite)),
+        Ok(invite) if invite.board_id == claims.board_id => Err(HttpResponse::Unauthorized().body(format!("Invalid {} token! Invite expired", kind))),
+        _ => Err(HttpResponse::Unauthorized().body(format!("Invalid {} token! Invite was revoked", kind))),
+    }
+}
+
+pub async fn check_invite (service: &Service, token: &str) -> Result<Invite, HttpResponse> {
+    let (_, invite) = live_invite(service, token, TOKEN_KIND_INVITE).await?;
+    Ok(invite)
+}
+
+// the digests are compared rather than the secrets, so how long that takes says nothing about the secret
+fn from_proxy (secret: &String, sent: Option<&str>) -> bool {
+    match sent {
+        Some(sent) if !secret.is_empty() => Sha256::digest(sent.as_bytes()) == Sha256::digest(secret.as_bytes()),
+        _ => false,
+    }
+}
+
+pub async fn caller (req: &HttpRequest, service: &Service) -> Result<Caller, HttpResponse> {
+    let bearer = req.headers().get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix(BEARER_PREFIX));
+    if let Some(token) = bearer.filter(|token| unverified_kind(token).as_deref() == Some(TOKEN_KIND_API_KEY)) {
+        let now = service.time_provider.unix_ts_ms();
+        let claims = verify(&service.config.api_key_secret, API_KEYS_DISABLED, token, TOKEN_KIND_API_KEY, now)
+            .map_err(|why| HttpResponse::Unauthorized().body(format!("Invalid API key! {}", why)))?;
+        return Ok(Caller {
+            user_id: claims.user_id,
+            display_name: claims.display_name,
+            board_id: None,
+        })
+    }
+    if let Some(token) = bearer {
+        let (claims, _) = live_invite(service, token, TOKEN_KIND_GUEST).await?;
+        return Ok(Caller {
+            user_id: claims.user_id,
+            display_name: claims.display_name,
+            board_id: Some(claims.board_id),
+        })
+    }
+
+    let user_id = req.headers().get(HEADER_USER_ID).and_then(|value| value.to_str().ok());
+    let proxy_secret = req.headers().get(HEADER_PROXY_SECRET).and_then(|value| value.to_str().ok());
+    if user_id.is_some() && !from_proxy(&service.config.proxy_secret, proxy_secret) {
+        return Err(HttpResponse::Unauthorized().body(format!("{} is only accepted from the trusted proxy!", HEADER_USER_ID)))
+    }
+    match user_id {
+        Some(user_id) if !user_id.is_empty() => Ok(Caller {
+            user_id: user_id.to_string(),
+            display_name: user_id.to_string(),
+            board_id: None,
+        }),
+        _ => Err(HttpResponse::Unauthorized().body(format!("Missing {} header!", HEADER_USER_ID))),
+    }
+}
+
+pub async fn caller_team_ids (service: &Service, caller: &Caller) -> Result<Vec<String>, HttpResponse> {
+    if caller.board_id.is_some() {
+        return Ok(vec![])
+    }
+    match service.storage.list_user_memberships(&caller.user_id).await {
+        Ok(memberships) => Ok(memberships.into_iter().map(|m| m.team_id).collect()),
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("List memberships failed! {}", why))),
+    }
+}
+
+pub async fn check_team_role (service: &Service, caller: &Caller, team_id: &String, role: Role) -> Result<Membership, HttpResponse> {
+    if caller.board_id.is_some() {
+        return Err(HttpResponse::Forbidden().body("Guests may only access the board they were invited to"))
+    }
+    let user_id = &caller.user_id;
+    let memberships = match service.storage.list_user_memberships(user_id).await {
+        Ok(memberships) => memberships,
+        Err(why) => return Err(HttpResponse::InternalServerError().body(format!("List memberships failed! {}", why))),
+    };
+
+    match memberships.into_iter().find(|m| &m.team_id == team_id) {
+        Some(membership) if membership.role >= role => Ok(membership),
+        Some(membership) => Err(HttpResponse::Forbidden().body(
+            format!("Role {} required for team {}, but user {} is {}", role.as_str(), team_id, user_id, membership.role.as_str())
+        )),
+        None => Err(HttpResponse::Forbidden().body(
+            format!("User {} is not a member of team {}", user_id, team_id)
+        )),
+    }
+}
+
+// admins come from config rather than storage, and are never guests
+pub fn check_admin (service: &Service, caller: &Caller) -> Result<(), HttpResponse> {
+    if caller.board_id.is_none() && service.config.admin_user_ids.contains(&caller.user_id) {
+        Ok(())
+    } else {
+        Err(HttpResponse::Forbidden().body(format!("User {} is not an admin", caller.user_id)))
+    }
+}
+
+// membership only, whatever state the board is in, for archiving and restoring it
+pub async fn check_board_member (service: &Service, caller: &Caller, board_id: &String, role: Role) -> Result<Board, HttpResponse> {
+    if let Some(guest_board_id) = &caller.board_id {
+        if guest_board_id != board_id {
+            return Err(HttpResponse::Forbidden().body("Guests may only access the board they were invited to"))
+        }
+        if role > GUEST_ROLE {
+            return Err(HttpResponse::Forbidden().body(
+                format!("Role {} required for board {}, which guests cannot have", role.as_str(), board_id)
+            ))
+        }
+    }
+
+    let board = match service.storage.get_board(board_id).await {
+        Ok(board) => board,
+        Err(why) => return Err(HttpResponse::NotFound().body(
+            format!("Could not find board for id {}: {}", board_id, why)
+        )),
+    };
+
+    if caller.board_id.is_none() {
+        check_team_role(service, caller, &board.team_id, role).await?;
+    }
+    Ok(board)
+}
+
+// deleted boards are gone to everyone, archived ones can still be read but not changed
+pub async fn check_board_role (service: &Service, caller: &Caller, board_id: &String, role: Role) -> Result<Board, HttpResponse> {
+    let board = check_board_member(service, caller, board_id, role).await?;
+    if board.deleted_at.is_some() {
+        return Err(HttpResponse::NotFound().body(format!("Board {} was deleted", board_id)))
+    }
+    if board.archived_at.is_some() && role > Role::Viewer {
+        return Err(HttpResponse::Conflict().body(format!("Board {} is archived and read-only", board_id)))
+    }
+    Ok(board)
+}
@@ -0,0 +1,311 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use serde::{Serialize, Deserialize};
+use sha2::{Sha256, Digest};
+
+use crate::models::{MyError, Storage, Row, Board, BoardContents, AuditFilter, IfEmpty};
+
+
+// bumped whenever rows are dumped differently, a restore only takes its own version
+pub const DUMP_VERSION: u32 = 1;
+
+// everything in a storage, as rows tagged with their kind, so it can be loaded into any other backend
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Dump {
+    pub version: u32,
+    // the backend it was taken from, for whoever reads the file
+    pub source: String,
+    pub created_at: i64,
+    pub manifest: Manifest,
+    pub rows: Vec<Row>,
+}
+
+// by kind, to tell a file that was cut short or edited, and to check a restore against
+pub type Manifest = BTreeMap<String, Tally>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tally {
+    pub count: usize,
+    // sha256 of the rows as json, one per line, in id order, so it does not matter how a backend lists them
+    pub checksum: String,
+}
+
+// an audit entry's seq is handed out by whichever storage it went into, so it is left out,
+// the order of the entries is kept by restoring them oldest first
+fn checked_json (row: &Row) -> Result<String, MyError> {
+    let failed = |why: serde_json::Error| format!("Failed encoding {} {}: {}", row.kind(), row.id(), why);
+    let mut value = serde_json::to_value(row).map_err(failed)?;
+    if let (Row::AuditEntry(_), Some(fields)) = (row, value.get_mut("data").and_then(|data| data.as_object_mut())) {
+        fields.remove("seq");
+    }
+    serde_json::to_string(&value).map_err(failed)
+}
+
+pub fn manifest (rows: &[Row]) -> Result<Manifest, MyError> {
+    let mut by_kind: BTreeMap<&'static str, Vec<(&String, String)>> = BTreeMap::new();
+    for row in rows.iter() {
+        let json = checked_json(row)?;
+        by_kind.entry(row.kind()).or_default().push((row.id(), json));
+    }
+
+    let mut manifest = Manifest::new();
+    for (kind, mut rows) in by_kind.into_iter() {
+        rows.sort();
+        let mut hasher = Sha256::new();
+        for (_, json) in rows.iter() {
+            hasher.update(json.as_bytes());
+            hasher.update(b"\n");
+        }
+        let checksum = hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect();
+        manifest.insert(String::from(kind), Tally { count: rows.len(), checksum: checksum });
+    }
+    Ok(manifest)
+}
+
+// how two manifests disagree, one line per kind
+pub fn differences (actual: &Manifest, expected: &Manifest) -> Vec<String> {
+    let missing = Tally { count: 0, checksum: String::from("-") };
+    let kinds: BTreeSet<&String> = actual.keys().chain(expected.keys()).collect();
+    kinds.into_iter().filter_map(|kind| {
+        let (actual, expected) = (actual.get(kind).unwrap_or(&missing), expected.get(kind).unwrap_or(&missing));
+        match actual == expected {
+            true => None,
+            false if actual.count != expected.count => Some(format!("{}: {} rows, expected {}", kind, actual.count, expected.count)),
+            false => Some(format!("{}: checksum {}, expected {}", kind, actual.checksum, expected.checksum)),
+        }
+    }).collect()
+}
+
+// every row the storage holds, only through the storage trait, parents before their children
+pub async fn read_rows (storage: &dyn Storage) -> Result<Vec<Row>, MyError> {
+    let failed = |what: &'static str| move |why: MyError| format!("List {} failed! {}", what, why);
+    let mut rows = vec![];
+
+    let mut teams = storage.list_all_teams().await.map_err(failed("teams"))?;
+    teams.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+    for team in teams.into_iter() {
+        let memberships = storage.list_team_memberships(&team.id).await.map_err(failed("memberships"))?;
+        rows.push(Row::Team(team));
+        rows.extend(memberships.into_iter().map(Row::Membership));
+    }
+
+    let mut boards = storage.list_boards().await.map_err(failed("boards"))?;
+    boards.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+    for board in boards.into_iter() {
+        let id = board.id.clone();
+        rows.push(Row::Board(board));
+        rows.extend(storage.list_board_columns(&id).await.map_err(failed("columns"))?.into_iter().map(Row::Column));
+        rows.extend(storage.list_board_groups(&id).await.map_err(failed("groups"))?.into_iter().map(Row::Group));
+        // merged cards point at other cards, which have to exist first
+        let (cards, merged): (Vec<_>, Vec<_>) = storage.list_board_cards(&id).await.map_err(failed("cards"))?
+            .into_iter().partition(|card| card.merged_into.is_none());
+        let card_ids: Vec<String> = cards.iter().chain(merged.iter()).map(|card| card.id.clone()).collect();
+        rows.extend(cards.into_iter().chain(merged).map(Row::Card));
+        rows.extend(storage.list_board_votes(&id).await.map_err(failed("votes"))?.into_iter().map(Row::Vote));
+        rows.extend(storage.list_board_tags(&id).await.map_err(failed("tags"))?.into_iter().map(Row::Tag));
+        rows.extend(storage.list_board_card_tags(&id).await.map_err(failed("card tags"))?.into_iter().map(Row::CardTag));
+        rows.extend(storage.list_board_comments(&id).await.map_err(failed("comments"))?.into_iter().map(Row::Comment));
+        for card_id in card_ids.iter() {
+            rows.extend(storage.list_card_revisions(card_id).await.map_err(failed("revisions"))?.into_iter().map(Row::Revision));
+        }
+        rows.extend(storage.list_board_action_items(&id).await.map_err(failed("action items"))?.into_iter().map(Row::ActionItem));
+        rows.extend(storage.list_board_invites(&id).await.map_err(failed("invites"))?.into_iter().map(Row::Invite));
+    }
+
+    // oldest first, so going back in they are numbered in the order they were
+    let entries = storage.list_audit_entries(&AuditFilter { oldest_first: true, ..AuditFilter::default() }).await.map_err(failed("audit entries"))?;
+    rows.extend(entries.into_iter().map(Row::AuditEntry));
+
+    Ok(rows)
+}
+
+pub async fn dump (storage: &dyn Storage, now: i64) -> Result<Dump, MyError> {
+    let rows = read_rows(storage).await?;
+    Ok(Dump {
+        version: DUMP_VERSION,
+        source: String::from(storage.name()),
+        created_at: now,
+        manifest: manifest(&rows)?,
+        rows: rows,
+    })
+}
+
+fn contents (board: &Board) -> BoardContents {
+    BoardContents {
+        board: board.clone(),
+        columns: vec![],
+        groups: vec![],
+        cards: vec![],
+        votes: vec![],
+        tags: vec![],
+        card_tags: vec![],
+        comments: vec![],
+        action_items: vec![],
+    }
+}
+
+async fn add (storage: &dyn Storage, row: &Row) -> Result<bool, MyError> {
+    match row {
+        Row::Team(item) => storage.add_team(item).await,
+        Row::Membership(item) => storage.add_membership(item).await,
+        Row::Revision(item) => storage.add_revision(item).await,
+        Row::Invite(item) => storage.add_invite(item).await,
+        Row::AuditEntry(item) => storage.add_audit_entry(item).await,
+        // whatever else is on a board goes in with it
+        row => Err(format!("Cannot restore {} on its own", row.kind())),
+    }
+}
+
+fn added (row: &Row, result: Result<bool, MyError>) -> Result<(), MyError> {
+    match result {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(format!("Restore {} {} failed, it was not added", row.kind(), row.id())),
+        Err(why) => Err(format!("Restore {} {} failed! {}", row.kind(), row.id(), why)),
+    }
+}
+
+// what a restore put in so far, so a failed one can take it out again
+#[derive(Default)]
+struct Added {
+    teams: Vec<String>,
+    boards: Vec<String>,
+    audit_entries: usize,
+}
+
+// boards first, deleting one takes its revisions and invites with it, and a team its members
+// the log only ever grows, so entries that went in stay
+async fn take_out (storage: &dyn Storage, added: &Added) -> Result<(), MyError> {
+    let mut problems = vec![];
+    for id in added.boards.iter().rev() {
+        if let Err(why) = storage.delete_board(id).await {
+            problems.push(format!("board {}: {}", id, why));
+        }
+    }
+    for id in added.teams.iter().rev() {
+        match storage.delete_team_cascade(id).await {
+            Ok(IfEmpty::Deleted(_)) => (),
+            Ok(IfEmpty::NotEmpty(what, count)) => problems.push(format!("team {}: still has {} {}", id, count, what)),
+            Err(why) => problems.push(format!("team {}: {}", id, why)),
+        }
+    }
+    if added.audit_entries > 0 {
+        problems.push(format!("{} audit entries stay, the log cannot be taken back", added.audit_entries));
+    }
+    match problems.is_empty() {
+        true => Ok(()),
+        false => Err(problems.join(", ")),
+    }
+}
+
+fn without_audit (manifest: &Manifest) -> Manifest {
+    manifest.iter().filter(|(kind, _)| kind.as_str() != "audit_entry").map(|(kind, tally)| (kind.clone(), tally.clone())).collect()
+}
+
+// loads a dump into an empty storage, each board all or nothing, then reads it all back
+// to check it holds exactly what was dumped, answering with what it now holds
+// nothing is audited, so the restored log stays a copy of the dumped one
+// a restore that fails takes out what it put in, short of audit entries, those it only adds
+// once everything else checks out, so it takes one failing part way through them to leave any behind
+pub async fn restore (storage: &dyn Storage, dump: &Dump) -> Result<Manifest, MyError> {
+    if dump.version != DUMP_VERSION {
+        return Err(format!("Dump version {} is not supported, expected {}", dump.version, DUMP_VERSION))
+    }
+    let problems = differences(&manifest(&dump.rows)?, &dump.manifest);
+    if !problems.is_empty() {
+        return Err(format!("Dump does not match its manifest: {}", problems.join(", ")))
+    }
+    storage.health_check().await?;
+    if !read_rows(storage).await?.is_empty() {
+        return Err(format!("Refusing to restore into {} storage that is not empty", storage.name()))
+    }
+
+    let mut added = Added::default();
+    match load(storage, dump, &mut added).await {
+        Ok(restored) => Ok(restored),
+        Err(why) => match take_out(storage, &added).await {
+            Ok(()) => Err(format!("{}, took out what it had restored", why)),
+            Err(left) => Err(format!("{}, and could not take out what it had restored: {}", why, left)),
+        },
+    }
+}
+
+async fn load (storage: &dyn Storage, dump: &Dump, done: &mut Added) -> Result<Manifest, MyError> {
+    let mut boards: Vec<BoardContents> = vec![];
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let mut later: Vec<&Row> = vec![];
+    for row in dump.rows.iter() {
+        if let Row::Board(board) = row {
+            positions.insert(board.id.clone(), boards.len());
+            boards.push(contents(board));
+        }
+    }
+    for row in dump.rows.iter() {
+        let board_id = match row {
+            Row::Column(item) => &item.board_id,
+            Row::Group(item) => &item.board_id,
+            Row::Card(item) => &item.board_id,
+            Row::Vote(item) => &item.board_id,
+            Row::Tag(item) => &item.board_id,
+            Row::CardTag(item) => &item.board_id,
+            Row::Comment(item) => &item.board_id,
+            Row::ActionItem(item) => &item.board_id,
+            Row::Board(_) => continue,
+            row => {
+                later.push(row);
+                continue
+            },
+        };
+        let board = match positions.get(board_id) {
+            Some(position) => &mut boards[*position],
+            None => return Err(format!("Dump has {} {} for board {}, which it does not have", row.kind(), row.id(), board_id)),
+        };
+        match row.clone() {
+            Row::Column(item) => board.columns.push(item),
+            Row::Group(item) => board.groups.push(item),
+            Row::Card(item) => board.cards.push(item),
+            Row::Vote(item) => board.votes.push(item),
+            Row::Tag(item) => board.tags.push(item),
+            Row::CardTag(item) => board.card_tags.push(item),
+            Row::Comment(item) => board.comments.push(item),
+            Row::ActionItem(item) => board.action_items.push(item),
+            _ => (),
+        }
+    }
+
+    // teams and their members before the boards, what hangs off cards and boards after them, the log last
+    let rank = |row: &Row| match row {
+        Row::Team(_) => 0,
+        Row::Membership(_) => 1,
+        Row::AuditEntry(_) => 3,
+        _ => 2,
+    };
+    later.sort_by_key(|row| rank(row));
+    for row in later.iter().filter(|row| rank(row) < 2) {
+        added(row, add(storage, row).await)?;
+        if let Row::Team(team) = row {
+            done.teams.push(team.id.clone());
+        }
+    }
+    for board in boards.iter() {
+        added(&Row::Board(board.board.clone()), storage.add_board_contents(board).await)?;
+        done.boards.push(board.board.id.clone());
+    }
+    for row in later.iter().filter(|row| rank(row) == 2) {
+        added(row, add(storage, row).await)?;
+    }
+
+    let problems = differences(&without_audit(&manifest(&read_rows(storage).await?)?), &without_audit(&dump.manifest));
+    if !problems.is_empty() {
+        return Err(format!("Restored storage does not match the dump: {}", problems.join(", ")))
+    }
+    for row in later.iter().filter(|row| rank(row) == 3) {
+        added(row, add(storage, row).await)?;
+        done.audit_entries += 1;
+    }
+
+    let restored = manifest(&read_rows(storage).await?)?;
+    let problems = differences(&restored, &dump.manifest);
+    if !problems.is_empty() {
+        return Err(format!("Restored storage does not match the dump: {}", problems.join(", ")))
+    }
+    Ok(restored)
+}
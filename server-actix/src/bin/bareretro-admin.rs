@@ -0,0 +1,467 @@
+#![allow(clippy::redundant_field_names)]
+
+use std::fmt::Write;
+use std::fs;
+use std::process::ExitCode;
+use clap::{Parser, Subcommand};
+use dotenv::dotenv;
+use serde::Serialize;
+
+use bareretro::config::Config;
+use bareretro::models::{MyError, Service, Board, BoardContents, ImportBoard};
+use bareretro::startup::build_service;
+use bareretro::metrics::Metrics;
+use bareretro::purge::purge_deleted_boards;
+use bareretro::backup::{dump, restore, Dump, Manifest};
+use bareretro::importers::{import, Source};
+use bareretro::handlers::contents::read_board_contents;
+use bareretro::handlers::exports::{markdown, csv, FORMAT_JSON, FORMAT_MARKDOWN, FORMAT_CSV};
+use bareretro::handlers::imports::{prepare_import, import_summary};
+use bareretro::handlers::new_id;
+use bareretro::auth::{ApiKey, sign_api_key};
+use bareretro::audit::{
+    record, Change, ACTOR_ADMIN, ENTITY_BOARD, ENTITY_API_KEY, ACTION_CREATE,
+    ACTION_DELETE, ACTION_RESTORE, ACTION_PURGE, ACTION_ARCHIVE, ACTION_UNARCHIVE, ACTION_IMPORT,
+};
+
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1_000;
+const DEFAULT_API_KEY_DAYS: i64 = 90;
+
+
+// configured exactly like the server, from CONFIG_FILE and the same env vars,
+// and goes straight to its storage, so no api, users or roles are involved
+// the events backend keeps its log open, so stop the server before pointing this at the same dir
+#[derive(Parser)]
+#[command(name = "bareretro-admin", about = "Manage bareretro data directly in its storage")]
+struct Cli {
+    #[arg(long, global = true, help = "Print json instead of text")]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(about = "Apply whatever migrations the storage is missing")]
+    Migrate,
+    #[command(subcommand, about = "Boards of every team")]
+    Boards(BoardsCommand),
+    #[command(about = "Remove boards deleted for longer than the retention window, for good")]
+    Purge {
+        #[arg(long, help = "Instead of the configured board_retention_ms")]
+        older_than_days: Option<i64>,
+    },
+    #[command(about = "Write everything in the storage to a file any backend can restore from")]
+    Dump { file: String },
+    #[command(about = "Load a dump into an empty storage and check it holds exactly what was dumped")]
+    Restore { file: String },
+    #[command(subcommand, about = "Keys for scripts to call the api as a user, signed with api_key_secret")]
+    ApiKeys(ApiKeysCommand),
+}
+
+#[derive(Subcommand)]
+enum ApiKeysCommand {
+    #[command(about = "Make a key that acts as the user until it expires, it is only ever printed this once")]
+    Create {
+        #[arg(long)]
+        user: String,
+        #[arg(long, default_value = "", help = "What the key is for, kept in the audit log")]
+        name: String,
+        #[arg(long, default_value_t = DEFAULT_API_KEY_DAYS)]
+        expires_in_days: i64,
+    },
+}
+
+#[derive(Subcommand)]
+enum BoardsCommand {
+    #[command(about = "List boards, deleted and archived ones only when asked for")]
+    List {
+        #[arg(long)]
+        team: Option<String>,
+        #[arg(long)]
+        deleted: bool,
+        #[arg(long)]
+        archived: bool,
+    },
+    #[command(about = "Show a board and how much is on it")]
+    Inspect { id: String },
+    #[command(about = "Mark a board deleted, so the purge removes it after the retention window")]
+    Delete {
+        id: String,
+        #[arg(long, help = "Remove it and everything on it for good, right away")]
+        purge: bool,
+    },
+    #[command(about = "Undelete a board the purge has not removed yet")]
+    Restore { id: String },
+    #[command(about = "Make a board read-only")]
+    Archive { id: String },
+    #[command(about = "Make an archived board writable again")]
+    Unarchive { id: String },
+    #[command(about = "Print a board as json, markdown or csv")]
+    Export {
+        id: String,
+        #[arg(long, default_value = FORMAT_JSON)]
+        format: String,
+    },
+    #[command(about = "Add a board from a json export, or a csv or trello export, under fresh ids")]
+    Import {
+        file: String,
+        #[arg(long)]
+        team: String,
+        #[arg(long, help = "User id that owns the board, and authors whatever the file leaves out")]
+        owner: String,
+        #[arg(long, default_value = FORMAT_JSON, help = "json, csv or trello")]
+        format: String,
+        #[arg(long)]
+        title: Option<String>,
+        #[arg(long, help = "Only report what would be created")]
+        dry_run: bool,
+    },
+}
+
+// what a command answers with, as text for people and as json for scripts
+struct Output {
+    text: String,
+    json: serde_json::Value,
+}
+
+fn output<T : Serialize> (value: &T, text: String) -> Result<Output, MyError> {
+    let json = serde_json::to_value(value).map_err(|why| format!("Failed encoding output: {}", why))?;
+    Ok(Output { text: text, json: json })
+}
+
+#[derive(Serialize)]
+struct Migrated {
+    applied: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Purged {
+    purged: usize,
+}
+
+#[derive(Serialize)]
+struct Backup {
+    file: String,
+    manifest: Manifest,
+}
+
+#[derive(Serialize)]
+struct CreatedApiKey {
+    #[serde(flatten)]
+    api_key: ApiKey,
+    key: String,
+}
+
+#[derive(Serialize)]
+struct BoardSummary {
+    board: Board,
+    columns: usize,
+    groups: usize,
+    cards: usize,
+    votes: usize,
+    tags: usize,
+    comments: usize,
+    action_items: usize,
+}
+
+fn status (board: &Board) -> &'static str {
+    match (board.deleted_at, board.archived_at) {
+        (Some(_), _) => "deleted",
+        (None, Some(_)) => "archived",
+        (None, None) => "active",
+    }
+}
+
+fn board_line (board: &Board) -> String {
+    format!("{}  {:<8}  {}  {}", board.id, status(board), board.team_id, board.title)
+}
+
+async fn get_board (service: &Service, id: &String) -> Result<Board, MyError> {
+    service.storage.get_board(id).await.map_err(|why| format!("Could not find board for id {}: {}", id, why))
+}
+
+async fn update_board (service: &Service, action: &'static str, before: &Board, board: Board) -> Result<Output, MyError> {
+    match service.storage.update_board(&board).await {
+        Ok(true) => {
+            record(service, &String::from(ACTOR_ADMIN), None, Change::new(action, ENTITY_BOARD, &board.id, Some(&board.id)).before(before).after(&board)).await;
+            output(&board, board_line(&board))
+        },
+        Ok(false) => Err(format!("Could not find board for id {}", board.id)),
+        Err(why) => Err(format!("Update board failed! {}", why)),
+    }
+}
+
+async fn migrate (service: &Service) -> Result<Output, MyError> {
+    let applied = service.storage.migrate().await?;
+    let text = match applied.is_empty() {
+        true => String::from("already up to date"),
+        false => applied.iter().map(|version| format!("applied {}", version)).collect::<Vec<String>>().join("\n"),
+    };
+    output(&Migrated { applied: applied }, text)
+}
+
+async fn purge (service: &Service) -> Result<Output, MyError> {
+    let purged = purge_deleted_boards(service).await?;
+    output(&Purged { purged: purged }, format!("purged {} boards", purged))
+}
+
+fn backup (verb: &'static str, file: String, manifest: Manifest) -> Result<Output, MyError> {
+    let mut text = format!("{} {}", verb, file);
+    for (kind, tally) in manifest.iter() {
+        let _ = write!(text, "\n{:<14}{:>8}  {}", kind, tally.count, tally.checksum);
+    }
+    output(&Backup { file: file, manifest: manifest }, text)
+}
+
+async fn dump_storage (service: &Service, file: String) -> Result<Output, MyError> {
+    let dump = dump(&*service.storage, service.time_provider.unix_ts_ms()).await?;
+    let body = serde_json::to_vec(&dump).map_err(|why| format!("Failed encoding dump: {}", why))?;
+    fs::write(&file, body).map_err(|why| format!("Could not write {}: {}", file, why))?;
+    backup("dumped to", file, dump.manifest)
+}
+
+async fn restore_storage (service: &Service, file: String) -> Result<Output, MyError> {
+    let body = fs::read(&file).map_err(|why| format!("Could not read {}: {}", file, why))?;
+    let dump = serde_json::from_slice::<Dump>(&body).map_err(|why| format!("Invalid dump in {}: {}", file, why))?;
+    let manifest = restore(&*service.storage, &dump).await?;
+    backup("restored and verified", file, manifest)
+}
+
+// the key itself stays out of the audit log, anyone reading it could use it otherwise
+async fn create_api_key (service: &Service, user: String, name: String, expires_in_days: i64) -> Result<Output, MyError> {
+    let expires_in_ms = match expires_in_days.checked_mul(DAY_MS) {
+        Some(expires_in_ms) if expires_in_days > 0 => expires_in_ms,
+        _ => return Err(format!("Invalid --expires-in-days {}, it has to be at least 1 and at most {}", expires_in_days, i64::MAX / DAY_MS)),
+    };
+    if user.is_empty() {
+        return Err(String::from("An API key needs a --user to act as"))
+    }
+    let now = service.time_provider.unix_ts_ms();
+    let api_key = ApiKey {
+        id: new_id(service, now),
+        user_id: user,
+        name: name,
+        expires_at: now.checked_add(expires_in_ms).ok_or_else(|| format!("Invalid --expires-in-days {}, that is too far out", expires_in_days))?,
+    };
+    let key = sign_api_key(service, &api_key)?;
+    record(service, &String::from(ACTOR_ADMIN), None, Change::new(ACTION_CREATE, ENTITY_API_KEY, &api_key.id, None).after(&api_key)).await;
+
+    let text = format!("api key {} for {} until {}\n{}", api_key.id, api_key.user_id, api_key.expires_at, key);
+    output(&CreatedApiKey { api_key: api_key, key: key }, text)
+}
+
+async fn list_boards (service: &Service, team: Option<String>, deleted: bool, archived: bool) -> Result<Output, MyError> {
+    let mut boards: Vec<Board> = service.storage.list_boards().await
+        .map_err(|why| format!("List boards failed! {}", why))?
+        .into_iter()
+        .filter(|board| team.as_ref().is_none_or(|team| &board.team_id == team))
+        .filter(|board| deleted || board.deleted_at.is_none())
+        .filter(|board| archived || board.archived_at.is_none())
+        .collect();
+    boards.sort_by_key(|board| board.created_at);
+    let text = boards.iter().map(board_line).collect::<Vec<String>>().join("\n");
+    output(&boards, text)
+}
+
+async fn inspect_board (service: &Service, id: &String) -> Result<Output, MyError> {
+    let contents = read_board_contents(service, get_board(service, id).await?).await?;
+    let summary = BoardSummary {
+        columns: contents.columns.len(),
+        groups: contents.groups.len(),
+        cards: contents.cards.len(),
+        votes: contents.votes.len(),
+        tags: contents.tags.len(),
+        comments: contents.comments.len(),
+        action_items: contents.action_items.len(),
+        board: contents.board,
+    };
+
+    let mut text = String::new();
+    let board = &summary.board;
+    let _ = writeln!(text, "{}  {}", board.id, board.title);
+    let _ = writeln!(text, "team          {}", board.team_id);
+    let _ = writeln!(text, "owner         {}", board.owner);
+    let _ = writeln!(text, "status        {}", status(board));
+    let _ = writeln!(text, "created_at    {}", board.created_at);
+    for (kind, count) in [
+        ("columns", summary.columns), ("groups", summary.groups), ("cards", summary.cards), ("votes", summary.votes),
+        ("tags", summary.tags), ("comments", summary.comments), ("action_items", summary.action_items),
+    ] {
+        let _ = writeln!(text, "{:<14}{}", kind, count);
+    }
+    output(&summary, text.trim_end().to_string())
+}
+
+async fn delete_board (service: &Service, id: &String, purge: bool) -> Result<Output, MyError> {
+    let board = get_board(service, id).await?;
+    if purge {
+        let deletion = service.storage.delete_board(id).await.map_err(|why| format!("Purge board failed! {}", why))?;
+        record(service, &String::from(ACTOR_ADMIN), None, Change::new(ACTION_PURGE, ENTITY_BOARD, id, Some(id)).before(&board).after(&deletion)).await;
+        return output(&deletion, format!("purged {} with {} cards, {} votes and {} comments", id, deletion.cards, deletion.votes, deletion.comments))
+    }
+    if board.deleted_at.is_some() {
+        return Err(format!("Board {} was already deleted", id))
+    }
+    let now = service.time_provider.unix_ts_ms();
+    update_board(service, ACTION_DELETE, &board, Board { deleted_at: Some(now), ..board.clone() }).await
+}
+
+async fn restore_board (service: &Service, id: &String) -> Result<Output, MyError> {
+    let board = get_board(service, id).await?;
+    if board.deleted_at.is_none() {
+        return Err(format!("Board {} is not deleted", id))
+    }
+    update_board(service, ACTION_RESTORE, &board, Board { deleted_at: None, ..board.clone() }).await
+}
+
+async fn archive_board (service: &Service, id: &String, archive: bool) -> Result<Output, MyError> {
+    let board = get_board(service, id).await?;
+    if archive == board.archived_at.is_some() {
+        return Err(format!("Board {} is {}", id, if archive { "already archived" } else { "not archived" }))
+    }
+    match archive {
+        true => {
+            let now = service.time_provider.unix_ts_ms();
+            update_board(service, ACTION_ARCHIVE, &board, Board { archived_at: Some(now), ..board.clone() }).await
+        },
+        false => update_board(service, ACTION_UNARCHIVE, &board, Board { archived_at: None, ..board.clone() }).await,
+    }
+}
+
+// the export itself, whichever output was asked for, so it can go straight into a file
+async fn export_board (service: &Service, id: &String, format: &str) -> Result<String, MyError> {
+    let contents = read_board_contents(service, get_board(service, id).await?).await?;
+    match format {
+        FORMAT_JSON => serde_json::to_string_pretty(&contents).map_err(|why| format!("Export board failed! {}", why)),
+        FORMAT_MARKDOWN => Ok(markdown(&contents)),
+        FORMAT_CSV => csv(&contents),
+        format => Err(format!("Unknown export format '{}'", format)),
+    }
+}
+
+async fn import_board (service: &Service, file: &String, query: ImportBoard, owner: &String, format: &str) -> Result<Output, MyError> {
+    let body = fs::read(file).map_err(|why| format!("Could not read {}: {}", file, why))?;
+    service.storage.get_team(&query.team_id).await.map_err(|why| format!("Could not find team for id {}: {}", query.team_id, why))?;
+
+    let (mut contents, warnings) = match format {
+        FORMAT_JSON => (
+            serde_json::from_slice::<BoardContents>(&body).map_err(|why| format!("Invalid board to import! {}", why))?,
+            vec![],
+        ),
+        format => {
+            let source = Source {
+                title: query.title.clone(),
+                author: owner.clone(),
+                author_name: owner.clone(),
+                now: service.time_provider.unix_ts_ms(),
+                limit_bytes: service.config.import_limit_bytes,
+            };
+            let imported = import(format, &body, &source)?;
+            (imported.contents, imported.warnings)
+        },
+    };
+    if let Some(title) = &query.title {
+        contents.board.title = title.clone();
+    }
+
+    let contents = prepare_import(service, owner, &query, &contents)?;
+    if !query.dry_run {
+        service.storage.add_board_contents(&contents).await.map_err(|why| format!("Import board failed! {}", why))?;
+        record(service, &String::from(ACTOR_ADMIN), None, Change::new(ACTION_IMPORT, ENTITY_BOARD, &contents.board.id, Some(&contents.board.id)).after(&contents)).await;
+    }
+
+    let summary = import_summary(&query, contents, warnings);
+    let mut text = format!(
+        "{} {} with {} columns, {} cards, {} votes and {} comments",
+        if summary.dry_run { "would import" } else { "imported" },
+        board_line(&summary.board), summary.columns, summary.cards, summary.votes, summary.comments,
+    );
+    for warning in summary.warnings.iter() {
+        let _ = write!(text, "\nwarning: {}", warning);
+    }
+    output(&summary, text)
+}
+
+async fn run (service: &Service, command: Command) -> Result<Output, MyError> {
+    match command {
+        Command::Migrate => migrate(service).await,
+        Command::Purge { .. } => purge(service).await,
+        Command::Dump { file } => dump_storage(service, file).await,
+        Command::Restore { file } => restore_storage(service, file).await,
+        Command::ApiKeys(ApiKeysCommand::Create { user, name, expires_in_days }) => create_api_key(service, user, name, expires_in_days).await,
+        Command::Boards(command) => match command {
+            BoardsCommand::List { team, deleted, archived } => list_boards(service, team, deleted, archived).await,
+            BoardsCommand::Inspect { id } => inspect_board(service, &id).await,
+            BoardsCommand::Delete { id, purge } => delete_board(service, &id, purge).await,
+            BoardsCommand::Restore { id } => restore_board(service, &id).await,
+            BoardsCommand::Archive { id } => archive_board(service, &id, true).await,
+            BoardsCommand::Unarchive { id } => archive_board(service, &id, false).await,
+            BoardsCommand::Export { id, format } => export_board(service, &id, &format).await
+                .map(|export| Output { text: export, json: serde_json::Value::Null }),
+            BoardsCommand::Import { file, team, owner, format, title, dry_run } => {
+                let query = ImportBoard { team_id: team, title: title, dry_run: dry_run };
+                import_board(service, &file, query, &owner, &format).await
+            },
+        },
+    }
+}
+
+#[actix_rt::main]
+async fn main () -> ExitCode {
+    dotenv().ok();
+    let cli = Cli::parse();
+
+    let mut config = match Config::load() {
+        Ok(config) => config,
+        Err(errors) => {
+            for why in errors.iter() {
+                eprintln!("invalid config: {}", why);
+            }
+            return ExitCode::from(2)
+        },
+    };
+    // there is nobody to answer with storage errors here, so fail right away instead
+    config.require_storage = true;
+    // anything under a day would purge boards deleted moments ago, and the config checks are bypassed here
+    if let Command::Purge { older_than_days: Some(days) } = &cli.command {
+        match days.checked_mul(DAY_MS) {
+            Some(retention_ms) if *days > 0 => config.board_retention_ms = retention_ms,
+            _ => {
+                eprintln!("invalid --older-than-days {}, it has to be at least 1 and at most {}", days, i64::MAX / DAY_MS);
+                return ExitCode::from(2)
+            },
+        }
+    }
+
+    let service = match build_service(config, Metrics::new()) {
+        Ok(service) => service,
+        Err(why) => {
+            eprintln!("error: {}", why);
+            return ExitCode::from(2)
+        },
+    };
+
+    // exports are printed as they are, json or not
+    let raw = matches!(&cli.command, Command::Boards(BoardsCommand::Export { .. }));
+    let result = run(&service, cli.command).await;
+    if let Err(why) = service.storage.close().await {
+        eprintln!("error: closing storage failed: {}", why);
+    }
+
+    match result {
+        Ok(output) => {
+            if cli.json && !raw {
+                println!("{}", serde_json::to_string_pretty(&output.json).unwrap_or_default());
+            } else if !output.text.is_empty() {
+                println!("{}", output.text);
+            }
+            ExitCode::SUCCESS
+        },
+        Err(why) => {
+            eprintln!("error: {}", why);
+            ExitCode::FAILURE
+        },
+    }
+}
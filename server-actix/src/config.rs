@@ -0,0 +1,486 @@
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr};
+use std::str::FromStr;
+use serde::Deserialize;
+use tracing_subscriber::EnvFilter;
+
+use crate::logging::{redacted, LOG_FORMAT_JSON, LOG_FORMAT_LOGFMT, DEFAULT_LOG_LEVEL};
+
+
+// names a toml file to start from, env vars still win over whatever it sets
+pub const CONFIG_FILE_VAR: &'static str = "CONFIG_FILE";
+
+const DEFAULT_PORT: u16 = 8080;
+const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_INVITE_TTL_MS: i64 = 7 * 24 * 60 * 60 * 1_000;
+const DEFAULT_GUEST_SESSION_TTL_MS: i64 = 12 * 60 * 60 * 1_000;
+const DEFAULT_BOARD_RETENTION_MS: i64 = 30 * 24 * 60 * 60 * 1_000;
+const DEFAULT_PURGE_INTERVAL_MS: i64 = 60 * 60 * 1_000;
+const DEFAULT_IMPORT_LIMIT_BYTES: usize = 16 * 1024 * 1024;
+const DEFAULT_UNDO_LIMIT: usize = 50;
+
+const DEFAULT_PG_HOST: &'static str = "postgres";
+const DEFAULT_PG_PORT: u16 = 5432;
+const DEFAULT_PG_USER: &'static str = "postgres";
+const DEFAULT_PG_DBNAME: &'static str = "postgres";
+const DEFAULT_PG_SCHEMA: &'static str = "bareretro";
+const DEFAULT_PG_SEARCH_CONFIG: &'static str = "english";
+
+const DEFAULT_EVENTS_DIR: &'static str = "events";
+const DEFAULT_EVENTS_SNAPSHOT_EVERY: u64 = 1_000;
+
+
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub bind_address: IpAddr,
+    pub port: u16,
+    // as many as there are cores when not given
+    pub workers: Option<usize>,
+    // how long requests still running get to finish once asked to stop, before they are cut off
+    pub shutdown_timeout_secs: u64,
+    pub provider: String,
+    // refuses to start rather than serve nothing but errors from an invalid storage
+    pub require_storage: bool,
+    // signs invite and guest session tokens, invites are disabled while empty
+    pub invite_secret: String,
+    // the X-User-Id header is only believed from whoever also sends this in X-Proxy-Secret,
+    // which should be the authenticating proxy in front and nothing else, users can't sign in while empty
+    pub proxy_secret: String,
+    // signs the API keys bareretro-admin makes, they are refused while empty and all taken back by changing it
+    pub api_key_secret: String,
+    pub invite_ttl_ms: i64,
+    pub guest_session_ttl_ms: i64,
+    // how long deleted boards can still be restored, before the purge removes them for good
+    pub board_retention_ms: i64,
+    pub purge_interval_ms: i64,
+    // board exports are much bigger than anything else we accept
+    pub import_limit_bytes: usize,
+    // who may read the audit log, there is no admin role on teams for it
+    pub admin_user_ids: Vec<String>,
+    // steps each user can undo per board, zero turns undo off
+    pub undo_limit: usize,
+    // json or logfmt
+    pub log_format: String,
+    pub log_level: String,
+    pub postgres: PostgresConfig,
+    pub events: EventsConfig,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PostgresConfig {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub password: String,
+    pub dbname: String,
+    // shared by all workers, deadpool decides when not given
+    pub pool_size: Option<usize>,
+    pub schema: String,
+    pub search_config: String,
+    pub table_boards: String,
+    pub table_columns: String,
+    pub table_teams: String,
+    pub table_memberships: String,
+    pub table_invites: String,
+    pub table_cards: String,
+    pub table_groups: String,
+    pub table_votes: String,
+    pub table_tags: String,
+    pub table_card_tags: String,
+    pub table_comments: String,
+    pub table_revisions: String,
+    pub table_action_items: String,
+    pub table_audit_log: String,
+    pub table_migrations: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventsConfig {
+    pub dir: String,
+    // events between snapshots
+    pub snapshot_every: u64,
+}
+
+impl Default for Config {
+    fn default () -> Self {
+        Config {
+            bind_address: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: DEFAULT_PORT,
+            workers: None,
+            shutdown_timeout_secs: DEFAULT_SHUTDOWN_TIMEOUT_SECS,
+            provider: String::new(),
+            require_storage: false,
+            invite_secret: String::new(),
+            proxy_secret: String::new(),
+            api_key_secret: String::new(),
+            invite_ttl_ms: DEFAULT_INVITE_TTL_MS,
+            guest_session_ttl_ms: DEFAULT_GUEST_SESSION_TTL_MS,
+            board_retention_ms: DEFAULT_BOARD_RETENTION_MS,
+            purge_interval_ms: DEFAULT_PURGE_INTERVAL_MS,
+            import_limit_bytes: DEFAULT_IMPORT_LIMIT_BYTES,
+            admin_user_ids: vec![],
+            undo_limit: DEFAULT_UNDO_LIMIT,
+            log_format: String::from(LOG_FORMAT_LOGFMT),
+            log_level: String::from(DEFAULT_LOG_LEVEL),
+            postgres: PostgresConfig::default(),
+            events: EventsConfig::default(),
+        }
+    }
+}
+
+impl Default for PostgresConfig {
+    fn default () -> Self {
+        PostgresConfig {
+            host: String::from(DEFAULT_PG_HOST),
+            port: DEFAULT_PG_PORT,
+            user: String::from(DEFAULT_PG_USER),
+            password: String::new(),
+            dbname: String::from(DEFAULT_PG_DBNAME),
+            pool_size: None,
+            schema: String::from(DEFAULT_PG_SCHEMA),
+            search_config: String::from(DEFAULT_PG_SEARCH_CONFIG),
+            table_boards: String::from("boards"),
+            table_columns: String::from("columns"),
+            table_teams: String::from("teams"),
+            table_memberships: String::from("memberships"),
+            table_invites: String::from("invites"),
+            table_cards: String::from("cards"),
+            table_groups: String::from("card_groups"),
+            table_votes: String::from("votes"),
+            table_tags: String::from("tags"),
+            table_card_tags: String::from("card_tags"),
+            table_comments: String::from("comments"),
+            table_revisions: String::from("revisions"),
+            table_action_items: String::from("action_items"),
+            table_audit_log: String::from("audit_log"),
+            table_migrations: String::from("schema_migrations"),
+        }
+    }
+}
+
+impl Default for EventsConfig {
+    fn default () -> Self {
+        EventsConfig {
+            dir: String::from(DEFAULT_EVENTS_DIR),
+            snapshot_every: DEFAULT_EVENTS_SNAPSHOT_EVERY,
+        }
+    }
+}
+
+// by hand, to keep the secrets out of the logs
+impl std::fmt::Debug for Config {
+    fn fmt (&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Config")
+            .field("bind_address", &self.bind_address)
+            .field("port", &self.port)
+            .field("workers", &self.workers)
+            .field("shutdown_timeout_secs", &self.shutdown_timeout_secs)
+            .field("provider", &self.provider)
+            .field("require_storage", &self.require_storage)
+            .field("invite_secret", &redacted(&self.invite_secret))
+            .field("proxy_secret", &redacted(&self.proxy_secret))
+            .field("api_key_secret", &redacted(&self.api_key_secret))
+            .field("invite_ttl_ms", &self.invite_ttl_ms)
+            .field("guest_session_ttl_ms", &self.guest_session_ttl_ms)
+            .field("board_retention_ms", &self.board_retention_ms)
+            .field("purge_interval_ms", &self.purge_interval_ms)
+            .field("import_limit_bytes", &self.import_limit_bytes)
+            .field("admin_user_ids", &self.admin_user_ids)
+            .field("undo_limit", &self.undo_limit)
+            .field("log_format", &self.log_format)
+            .field("log_level", &self.log_level)
+            .field("postgres", &self.postgres)
+            .field("events", &self.events)
+            .finish()
+    }
+}
+
+// the table names are plain and many, the connection is what matters in the logs
+impl std::fmt::Debug for PostgresConfig {
+    fn fmt (&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PostgresConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("user", &self.user)
+            .field("password", &redacted(&self.password))
+            .field("dbname", &self.dbname)
+            .field("pool_size", &self.pool_size)
+            .field("schema", &self.schema)
+            .field("search_config", &self.search_config)
+            .finish()
+    }
+}
+
+// overrides settings from env vars, collecting whatever does not parse instead of stopping at it
+struct Env {
+    errors: Vec<String>,
+}
+
+impl Env {
+    fn string (&mut self, name: &str, setting: &mut String) {
+        if let Ok(value) = env::var(name) {
+            *setting = value;
+        }
+    }
+
+    fn parse<T> (&mut self, name: &str, setting: &mut T) where T: FromStr, T::Err: Display {
+        if let Ok(value) = env::var(name) {
+            match value.parse::<T>() {
+                Ok(value) => *setting = value,
+                Err(why) => self.errors.push(format!("{}: '{}' is not valid: {}", name, value, why)),
+            }
+        }
+    }
+
+    // empty counts as not given, to leave the default in place
+    fn parse_option<T> (&mut self, name: &str, setting: &mut Option<T>) where T: FromStr, T::Err: Display {
+        if let Ok(value) = env::var(name) {
+            if value.is_empty() {
+                *setting = None;
+                return
+            }
+            match value.parse::<T>() {
+                Ok(value) => *setting = Some(value),
+                Err(why) => self.errors.push(format!("{}: '{}' is not valid: {}", name, value, why)),
+            }
+        }
+    }
+
+    // comma separated
+    fn list (&mut self, name: &str, setting: &mut Vec<String>) {
+        if let Ok(value) = env::var(name) {
+            *setting = value.split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect();
+        }
+    }
+}
+
+// names go into sql as is, so nothing but what an unquoted identifier allows
+fn check_identifier (errors: &mut Vec<String>, setting: &str, value: &str) {
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        errors.push(format!("{}: '{}' must be letters, digits and underscores only", setting, value));
+    }
+}
+
+fn check_positive (errors: &mut Vec<String>, setting: &str, value: i64) {
+    if value <= 0 {
+        errors.push(format!("{}: must be more than zero, not {}", setting, value));
+    }
+}
+
+impl Config {
+    // the file first, if any, then env vars over it, then checks everything,
+    // answering with every problem found rather than only the first
+    pub fn load () -> Result<Config, Vec<String>> {
+        let mut config = match env::var(CONFIG_FILE_VAR) {
+            Ok(path) if !path.is_empty() => Self::from_file(&path).map_err(|why| vec![why])?,
+            _ => Config::default(),
+        };
+
+        let mut errors = config.apply_env();
+        errors.extend(config.validate());
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn from_file (path: &str) -> Result<Config, String> {
+        let text = fs::read_to_string(path).map_err(|why| format!("{}: could not read {}: {}", CONFIG_FILE_VAR, path, why))?;
+        toml::from_str(&text).map_err(|why| format!("{}: invalid {}: {}", CONFIG_FILE_VAR, path, why))
+    }
+
+    fn apply_env (&mut self) -> Vec<String> {
+        let mut env = Env { errors: vec![] };
+        env.parse("BIND_ADDRESS", &mut self.bind_address);
+        env.parse("PORT", &mut self.port);
+        env.parse_option("WORKERS", &mut self.workers);
+        env.parse("SHUTDOWN_TIMEOUT_SECS", &mut self.shutdown_timeout_secs);
+        env.string("STORAGE_PROVIDER", &mut self.provider);
+        env.parse("REQUIRE_STORAGE", &mut self.require_storage);
+        env.string("INVITE_SECRET", &mut self.invite_secret);
+        env.string("PROXY_SECRET", &mut self.proxy_secret);
+        env.string("API_KEY_SECRET", &mut self.api_key_secret);
+        env.parse("INVITE_TTL_MS", &mut self.invite_ttl_ms);
+        env.parse("GUEST_SESSION_TTL_MS", &mut self.guest_session_ttl_ms);
+        env.parse("BOARD_RETENTION_MS", &mut self.board_retention_ms);
+        env.parse("PURGE_INTERVAL_MS", &mut self.purge_interval_ms);
+        env.parse("IMPORT_LIMIT_BYTES", &mut self.import_limit_bytes);
+        env.list("ADMIN_USER_IDS", &mut self.admin_user_ids);
+        env.parse("UNDO_LIMIT", &mut self.undo_limit);
+        env.string("LOG_FORMAT", &mut self.log_format);
+        env.string("LOG_LEVEL", &mut self.log_level);
+
+        let pg = &mut self.postgres;
+        env.string("PG_HOST", &mut pg.host);
+        env.parse("PG_PORT", &mut pg.port);
+        env.string("PG_USER", &mut pg.user);
+        env.string("PG_PASS", &mut pg.password);
+        env.string("PG_DBNAME", &mut pg.dbname);
+        env.parse_option("PG_POOL_SIZE", &mut pg.pool_size);
+        env.string("PG_SCHEMA", &mut pg.schema);
+        env.string("PG_SEARCH_CONFIG", &mut pg.search_config);
+        env.string("PG_TABLE_BOARDS", &mut pg.table_boards);
+        env.string("PG_TABLE_COLUMNS", &mut pg.table_columns);
+        env.string("PG_TABLE_TEAMS", &mut pg.table_teams);
+        env.string("PG_TABLE_MEMBERSHIPS", &mut pg.table_memberships);
+        env.string("PG_TABLE_INVITES", &mut pg.table_invites);
+        env.string("PG_TABLE_CARDS", &mut pg.table_cards);
+        env.string("PG_TABLE_GROUPS", &mut pg.table_groups);
+        env.string("PG_TABLE_VOTES", &mut pg.table_votes);
+        env.string("PG_TABLE_TAGS", &mut pg.table_tags);
+        env.string("PG_TABLE_CARD_TAGS", &mut pg.table_card_tags);
+        env.string("PG_TABLE_COMMENTS", &mut pg.table_comments);
+        env.string("PG_TABLE_REVISIONS", &mut pg.table_revisions);
+        env.string("PG_TABLE_ACTION_ITEMS", &mut pg.table_action_items);
+        env.string("PG_TABLE_AUDIT_LOG", &mut pg.table_audit_log);
+        env.string("PG_TABLE_MIGRATIONS", &mut pg.table_migrations);
+
+        env.string("EVENTS_DIR", &mut self.events.dir);
+        env.parse("EVENTS_SNAPSHOT_EVERY", &mut self.events.snapshot_every);
+        env.errors
+    }
+
+    // only what can be told without connecting to anything, the storage checks the rest itself
+    fn validate (&self) -> Vec<String> {
+        let mut errors = vec![];
+        if self.port == 0 {
+            errors.push(String::from("port: must be set, not 0"));
+        }
+        if self.workers == Some(0) {
+            errors.push(String::from("workers: must be at least 1"));
+        }
+        check_positive(&mut errors, "invite_ttl_ms", self.invite_ttl_ms);
+        check_positive(&mut errors, "guest_session_ttl_ms", self.guest_session_ttl_ms);
+        check_positive(&mut errors, "board_retention_ms", self.board_retention_ms);
+        check_positive(&mut errors, "purge_interval_ms", self.purge_interval_ms);
+        check_positive(&mut errors, "import_limit_bytes", self.import_limit_bytes as i64);
+        if self.log_format != LOG_FORMAT_JSON && self.log_format != LOG_FORMAT_LOGFMT {
+            errors.push(format!("log_format: '{}' must be {} or {}", self.log_format, LOG_FORMAT_JSON, LOG_FORMAT_LOGFMT));
+        }
+        if let Err(why) = EnvFilter::try_new(&self.log_level) {
+            errors.push(format!("log_level: '{}' is not valid: {}", self.log_level, why));
+        }
+
+        // only the storage in use has to make sense
+        match self.provider.as_str() {
+            "postgres" => {
+                let pg = &self.postgres;
+                if pg.host.is_empty() {
+                    errors.push(String::from("postgres.host: must be set"));
+                }
+                if pg.port == 0 {
+                    errors.push(String::from("postgres.port: must be set, not 0"));
+                }
+                if pg.pool_size == Some(0) {
+                    errors.push(String::from("postgres.pool_size: must be at least 1"));
+                }
+                check_identifier(&mut errors, "postgres.schema", &pg.schema);
+                check_identifier(&mut errors, "postgres.search_config", &pg.search_config);
+                for (setting, table) in [
+                    ("postgres.table_boards", &pg.table_boards),
+                    ("postgres.table_columns", &pg.table_columns),
+                    ("postgres.table_teams", &pg.table_teams),
+                    ("postgres.table_memberships", &pg.table_memberships),
+                    ("postgres.table_invites", &pg.table_invites),
+                    ("postgres.table_cards", &pg.table_cards),
+                    ("postgres.table_groups", &pg.table_groups),
+                    ("postgres.table_votes", &pg.table_votes),
+                    ("postgres.table_tags", &pg.table_tags),
+                    ("postgres.table_card_tags", &pg.table_card_tags),
+                    ("postgres.table_comments", &pg.table_comments),
+                    ("postgres.table_revisions", &pg.table_revisions),
+                    ("postgres.table_action_items", &pg.table_action_items),
+                    ("postgres.table_audit_log", &pg.table_audit_log),
+                    ("postgres.table_migrations", &pg.table_migrations),
+                ] {
+                    check_identifier(&mut errors, setting, table);
+                }
+            },
+            "events" => {
+                if self.events.dir.is_empty() {
+                    errors.push(String::from("events.dir: must be set"));
+                }
+                if self.events.snapshot_every == 0 {
+                    errors.push(String::from("events.snapshot_every: must be at least 1"));
+                }
+            },
+            _ => (),
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_are_valid () {
+        assert_eq!(Config::default().validate(), Vec::<String>::new());
+        assert_eq!(Config { provider: String::from("postgres"), ..Config::default() }.validate(), Vec::<String>::new());
+        assert_eq!(Config { provider: String::from("events"), ..Config::default() }.validate(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn collects_every_error () {
+        let config = Config {
+            port: 0,
+            workers: Some(0),
+            provider: String::from("postgres"),
+            invite_ttl_ms: -1,
+            import_limit_bytes: 0,
+            log_format: String::from("xml"),
+            postgres: PostgresConfig {
+                host: String::new(),
+                pool_size: Some(0),
+                schema: String::from("app; DROP"),
+                table_cards: String::new(),
+                ..PostgresConfig::default()
+            },
+            ..Config::default()
+        };
+        assert_eq!(config.validate(), vec![
+            "port: must be set, not 0",
+            "workers: must be at least 1",
+            "invite_ttl_ms: must be more than zero, not -1",
+            "import_limit_bytes: must be more than zero, not 0",
+            "log_format: 'xml' must be json or logfmt",
+            "postgres.host: must be set",
+            "postgres.pool_size: must be at least 1",
+            "postgres.schema: 'app; DROP' must be letters, digits and underscores only",
+            "postgres.table_cards: '' must be letters, digits and underscores only",
+        ]);
+    }
+
+    // settings of a storage nobody uses can be whatever they like
+    #[test]
+    fn checks_only_the_storage_in_use () {
+        let broken = Config {
+            postgres: PostgresConfig { host: String::new(), ..PostgresConfig::default() },
+            events: EventsConfig { dir: String::new(), snapshot_every: 0 },
+            ..Config::default()
+        };
+        assert_eq!(Config { provider: String::from("memory"), ..broken.clone() }.validate(), Vec::<String>::new());
+        assert_eq!(Config { provider: String::from("postgres"), ..broken.clone() }.validate(), vec!["postgres.host: must be set"]);
+        assert_eq!(Config { provider: String::from("events"), ..broken }.validate(), vec![
+            "events.dir: must be set",
+            "events.snapshot_every: must be at least 1",
+        ]);
+    }
+
+    #[test]
+    fn refuses_a_bad_log_level () {
+        let errors = Config { log_level: String::from("info,=["), ..Config::default() }.validate();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("log_level: 'info,=[' is not valid"), "{}", errors[0]);
+    }
+}
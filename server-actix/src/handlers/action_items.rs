@@ -0,0 +1,131 @@
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use tracing::debug;
+
+use crate::auth::{Caller, caller, check_board_role};
+use crate::models::{Service, CreateActionItem, UpdateActionItem, ActionItem, Role, Row};
+use crate::audit::{audit, Change, ACTION_CREATE, ACTION_UPDATE, ACTION_DELETE, ENTITY_ACTION_ITEM};
+use crate::undo::Step;
+use super::{new_id, check_rate_limit};
+
+
+async fn action_item_for (service: &Service, caller: &Caller, id: &String, role: Role) -> Result<ActionItem, HttpResponse> {
+    let action_item = match service.storage.get_action_item(id).await {
+        Ok(action_item) => action_item,
+        Err(why) => return Err(HttpResponse::NotFound().body(
+            format!("Could not find action item for id {}: {}", id, why)
+        )),
+    };
+    check_board_role(service, caller, &action_item.board_id, role).await?;
+    Ok(action_item)
+}
+
+pub async fn add_action_item (
+    req: HttpRequest,
+    payload: web::Json<CreateActionItem>,
+    service: web::Data<Service>,
+) -> Result<web::Json<ActionItem>, HttpResponse> {
+    debug!("add action item");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+    check_board_role(&service, &caller, &payload.board_id, Role::Participant).await?;
+
+    if payload.title.trim().is_empty() {
+        return Err(HttpResponse::BadRequest().body("Invalid request for create action item!"))
+    }
+
+    let now = service.time_provider.unix_ts_ms();
+    let action_item = ActionItem {
+        id: new_id(&service, now),
+        board_id: payload.board_id.clone(),
+        title: payload.title.clone(),
+        assignee: payload.assignee.clone().filter(|assignee| !assignee.is_empty()),
+        done: false,
+        created_at: now,
+    };
+
+    match service.storage.add_action_item(&action_item).await {
+        Ok(_) => {
+            audit(&service, &req, &caller, Change::new(ACTION_CREATE, ENTITY_ACTION_ITEM, &action_item.id, Some(&action_item.board_id)).after(&action_item)).await;
+            service.undo.remember(&action_item.board_id, &caller.user_id, Step::new().added(Row::ActionItem(action_item.clone())));
+            Ok(web::Json(action_item))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Add action item failed! {}", why))),
+    }
+}
+
+pub async fn list_action_items (
+    req: HttpRequest,
+    service: web::Data<Service>,
+) -> Result<web::Json<Vec<ActionItem>>, HttpResponse> {
+    debug!("list action items");
+    let caller = caller(&req, &service).await?;
+
+    let board_id = req.match_info().get("id").unwrap().to_string();
+    check_board_role(&service, &caller, &board_id, Role::Viewer).await?;
+
+    match service.storage.list_board_action_items(&board_id).await {
+        Ok(mut action_items) => {
+            action_items.sort_by_key(|action_item| action_item.created_at);
+            Ok(web::Json(action_items))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("List action items failed! {}", why))),
+    }
+}
+
+pub async fn update_action_item (
+    req: HttpRequest,
+    payload: web::Json<UpdateActionItem>,
+    service: web::Data<Service>,
+) -> Result<web::Json<ActionItem>, HttpResponse> {
+    debug!("update action item");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let before = action_item_for(&service, &caller, &id, Role::Participant).await?;
+    let mut action_item = before.clone();
+
+    if let Some(title) = &payload.title {
+        if title.trim().is_empty() {
+            return Err(HttpResponse::BadRequest().body("Invalid request for update action item!"))
+        }
+        action_item.title = title.clone();
+    }
+    if let Some(assignee) = &payload.assignee {
+        action_item.assignee = Some(assignee.clone()).filter(|assignee| !assignee.is_empty());
+    }
+    if let Some(done) = payload.done {
+        action_item.done = done;
+    }
+
+    match service.storage.update_action_item(&action_item).await {
+        Ok(_) => {
+            audit(&service, &req, &caller, Change::new(ACTION_UPDATE, ENTITY_ACTION_ITEM, &id, Some(&action_item.board_id)).before(&before).after(&action_item)).await;
+            service.undo.remember(&action_item.board_id, &caller.user_id, Step::new().changed(Row::ActionItem(before), Row::ActionItem(action_item.clone())));
+            Ok(web::Json(action_item))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Update action item failed! {}", why))),
+    }
+}
+
+pub async fn delete_action_item (
+    req: HttpRequest,
+    service: web::Data<Service>
+) -> Result<HttpResponse, HttpResponse> {
+    debug!("delete action item");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let action_item = action_item_for(&service, &caller, &id, Role::Participant).await?;
+
+    match service.storage.delete_action_item(&id).await {
+        Ok(_) => {
+            audit(&service, &req, &caller, Change::new(ACTION_DELETE, ENTITY_ACTION_ITEM, &id, Some(&action_item.board_id)).before(&action_item)).await;
+            service.undo.remember(&action_item.board_id, &caller.user_id, Step::new().removed(Row::ActionItem(action_item.clone())));
+            Ok(HttpResponse::Ok().body("Action item deleted"))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Delete action item failed! {}", why))),
+    }
+}
@@ -0,0 +1,32 @@
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use tracing::debug;
+
+use crate::auth::{caller, check_admin};
+use crate::models::{Service, AuditFilter, AuditEntry};
+
+
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+
+
+// newest first, every filter is optional
+pub async fn list_audit (
+    req: HttpRequest,
+    query: web::Query<AuditFilter>,
+    service: web::Data<Service>,
+) -> Result<web::Json<Vec<AuditEntry>>, HttpResponse> {
+    debug!("list audit");
+    let caller = caller(&req, &service).await?;
+    check_admin(&service, &caller)?;
+
+    let filter = AuditFilter {
+        limit: Some(query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT)),
+        ..query.into_inner()
+    };
+
+    match service.storage.list_audit_entries(&filter).await {
+        Ok(entries) => Ok(web::Json(entries)),
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("List audit entries failed! {}", why))),
+    }
+}
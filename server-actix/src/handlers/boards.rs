@@ -0,0 +1,251 @@
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use tracing::debug;
+
+use crate::auth::{Caller, caller, caller_team_ids, check_team_role, check_board_member, check_board_role};
+use crate::models::{Service, CreateBoard, CloneBoard, ListBoards, Board, BoardDeletion, BoardContents, Role};
+use super::{new_id, check_rate_limit};
+use crate::audit::{audit, Change, ACTION_CREATE, ACTION_DELETE, ACTION_RESTORE, ACTION_PURGE, ACTION_ARCHIVE, ACTION_UNARCHIVE, ACTION_CLONE, ENTITY_BOARD};
+use super::contents::{board_contents, with_fresh_ids};
+
+
+pub async fn add_board (
+    req: HttpRequest,
+    payload: web::Json<CreateBoard>,
+    service: web::Data<Service>,
+) -> Result<web::Json<Board>, HttpResponse> {
+    debug!("add board");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+    check_team_role(&service, &caller, &payload.team_id, Role::Facilitator).await?;
+
+    // TODO validate request
+    if true {
+        let now = service.time_provider.unix_ts_ms();
+        let id = new_id(&service, now);
+
+        let board = Board {
+            id: id.clone(),
+            team_id: payload.team_id.clone(),
+            title: payload.title.clone(),
+            owner: caller.user_id.clone(),
+            created_at: now,
+            archived_at: None,
+            deleted_at: None,
+        };
+
+        match service.storage.add_board(&board).await {
+            Ok(_) => {
+                audit(&service, &req, &caller, Change::new(ACTION_CREATE, ENTITY_BOARD, &board.id, Some(&board.id)).after(&board)).await;
+                Ok(web::Json(board))
+            },
+            Err(why) => Err(HttpResponse::InternalServerError().body(format!("Add board failed! {}", why))),
+        }
+    } else {
+        Err(HttpResponse::BadRequest().body("Invalid request for create board!"))
+    }
+}
+
+pub async fn list_boards (
+    req: HttpRequest,
+    query: web::Query<ListBoards>,
+    service: web::Data<Service>,
+) -> Result<web::Json<Vec<Board>>, HttpResponse> {
+    debug!("list boards");
+    let caller = caller(&req, &service).await?;
+    if let Some(board_id) = &caller.board_id {
+        let board = check_board_role(&service, &caller, board_id, Role::Viewer).await?;
+        return Ok(web::Json(vec![board]))
+    }
+    let team_ids = caller_team_ids(&service, &caller).await?;
+
+    match service.storage.list_team_boards(&team_ids).await {
+        Ok(boards) => Ok(web::Json(boards.into_iter()
+            .filter(|board| query.archived.matches(board.archived_at.is_some()))
+            .filter(|board| query.deleted.matches(board.deleted_at.is_some()))
+            .collect())),
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("List boards failed! {}", why))),
+    }
+}
+
+pub async fn get_board (
+    req: HttpRequest,
+    service: web::Data<Service>
+) -> Result<web::Json<Board>, HttpResponse> {
+    debug!("get board");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let board = check_board_role(&service, &caller, &id, Role::Viewer).await?;
+    Ok(web::Json(board))
+}
+
+async fn update_board (
+    service: &Service,
+    req: &HttpRequest,
+    caller: &Caller,
+    action: &'static str,
+    before: &Board,
+    board: Board,
+) -> Result<web::Json<Board>, HttpResponse> {
+    match service.storage.update_board(&board).await {
+        Ok(true) => {
+            audit(service, req, caller, Change::new(action, ENTITY_BOARD, &board.id, Some(&board.id)).before(before).after(&board)).await;
+            Ok(web::Json(board))
+        },
+        Ok(false) => Err(HttpResponse::NotFound().body(format!("Could not find board for id {}", board.id))),
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Update board failed! {}", why))),
+    }
+}
+
+// only marks the board deleted, it can be restored until the purge catches up with it
+pub async fn delete_board (
+    req: HttpRequest,
+    service: web::Data<Service>
+) -> Result<web::Json<Board>, HttpResponse> {
+    debug!("delete board");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let board = check_board_member(&service, &caller, &id, Role::Facilitator).await?;
+    if board.deleted_at.is_some() {
+        return Err(HttpResponse::NotFound().body(format!("Board {} was already deleted", id)))
+    }
+
+    let now = service.time_provider.unix_ts_ms();
+    update_board(&service, &req, &caller, ACTION_DELETE, &board, Board { deleted_at: Some(now), ..board.clone() }).await
+}
+
+pub async fn restore_board (
+    req: HttpRequest,
+    service: web::Data<Service>
+) -> Result<web::Json<Board>, HttpResponse> {
+    debug!("restore board");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let board = check_board_member(&service, &caller, &id, Role::Facilitator).await?;
+    if board.deleted_at.is_none() {
+        return Err(HttpResponse::Conflict().body(format!("Board {} is not deleted", id)))
+    }
+
+    update_board(&service, &req, &caller, ACTION_RESTORE, &board, Board { deleted_at: None, ..board.clone() }).await
+}
+
+// skips the retention window, for a board that was already deleted
+pub async fn purge_board (
+    req: HttpRequest,
+    service: web::Data<Service>
+) -> Result<web::Json<BoardDeletion>, HttpResponse> {
+    debug!("purge board");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let board = check_board_member(&service, &caller, &id, Role::Owner).await?;
+    if board.deleted_at.is_none() {
+        return Err(HttpResponse::Conflict().body(format!("Board {} must be deleted before it can be purged", id)))
+    }
+
+    match service.storage.delete_board(&id).await {
+        Ok(deletion) => {
+            service.undo.forget_board(&id);
+            audit(&service, &req, &caller, Change::new(ACTION_PURGE, ENTITY_BOARD, &id, Some(&id)).before(&board).after(&deletion)).await;
+            Ok(web::Json(deletion))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Purge board failed! {}", why))),
+    }
+}
+
+pub async fn archive_board (
+    req: HttpRequest,
+    service: web::Data<Service>
+) -> Result<web::Json<Board>, HttpResponse> {
+    debug!("archive board");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let board = check_board_role(&service, &caller, &id, Role::Viewer).await?;
+    check_board_member(&service, &caller, &id, Role::Facilitator).await?;
+    if board.archived_at.is_some() {
+        return Err(HttpResponse::Conflict().body(format!("Board {} is already archived", id)))
+    }
+
+    let now = service.time_provider.unix_ts_ms();
+    update_board(&service, &req, &caller, ACTION_ARCHIVE, &board, Board { archived_at: Some(now), ..board.clone() }).await
+}
+
+pub async fn unarchive_board (
+    req: HttpRequest,
+    service: web::Data<Service>
+) -> Result<web::Json<Board>, HttpResponse> {
+    debug!("unarchive board");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let board = check_board_role(&service, &caller, &id, Role::Viewer).await?;
+    check_board_member(&service, &caller, &id, Role::Facilitator).await?;
+    if board.archived_at.is_none() {
+        return Err(HttpResponse::Conflict().body(format!("Board {} is not archived", id)))
+    }
+
+    update_board(&service, &req, &caller, ACTION_UNARCHIVE, &board, Board { archived_at: None, ..board.clone() }).await
+}
+
+// copies the columns and tags, and the cards if asked, under fresh ids
+// votes, comments and action items never carry over, the new board starts a new round
+pub async fn clone_board (
+    req: HttpRequest,
+    payload: web::Json<CloneBoard>,
+    service: web::Data<Service>,
+) -> Result<web::Json<BoardContents>, HttpResponse> {
+    debug!("clone board");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let source = check_board_role(&service, &caller, &id, Role::Viewer).await?;
+
+    let team_id = payload.team_id.clone().unwrap_or_else(|| source.team_id.clone());
+    check_team_role(&service, &caller, &team_id, Role::Facilitator).await?;
+
+    let title = payload.title.clone().unwrap_or_else(|| format!("{} (copy)", source.title));
+    if title.trim().is_empty() {
+        return Err(HttpResponse::BadRequest().body("Invalid request for clone board!"))
+    }
+
+    let source = board_contents(&service, source).await?;
+    let source = if payload.include_cards {
+        BoardContents { votes: vec![], comments: vec![], action_items: vec![], ..source }
+    } else {
+        BoardContents { groups: vec![], cards: vec![], votes: vec![], card_tags: vec![], comments: vec![], action_items: vec![], ..source }
+    };
+
+    let now = service.time_provider.unix_ts_ms();
+    let contents = with_fresh_ids(&service, &source, now);
+    let contents = BoardContents {
+        board: Board {
+            team_id: team_id,
+            title: title,
+            owner: caller.user_id.clone(),
+            created_at: now,
+            archived_at: None,
+            deleted_at: None,
+            ..contents.board
+        },
+        ..contents
+    };
+
+    match service.storage.add_board_contents(&contents).await {
+        Ok(_) => {
+            audit(&service, &req, &caller, Change::new(ACTION_CLONE, ENTITY_BOARD, &contents.board.id, Some(&contents.board.id)).after(&contents)).await;
+            Ok(web::Json(contents))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Clone board failed! {}", why))),
+    }
+}
@@ -0,0 +1,339 @@
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use tracing::debug;
+
+use crate::auth::{Caller, caller, check_board_role};
+use crate::models::{Service, CreateCard, EditCard, MoveCard, MergeCard, Card, CardStack, Vote, CardTag, Role, Row};
+use crate::audit::{audit, Change, ACTION_CREATE, ACTION_UPDATE, ACTION_DELETE, ACTION_MOVE, ACTION_MERGE, ACTION_UNMERGE, ENTITY_CARD};
+use crate::undo::Step;
+use super::{new_id, check_rate_limit};
+use super::columns::column_for;
+use super::groups::group_for;
+use super::revisions::{card_revision, add_revisions};
+
+
+pub async fn card_for (service: &Service, caller: &Caller, id: &String, role: Role) -> Result<Card, HttpResponse> {
+    let card = match service.storage.get_card(id).await {
+        Ok(card) => card,
+        Err(why) => return Err(HttpResponse::NotFound().body(
+            format!("Could not find card for id {}: {}", id, why)
+        )),
+    };
+    check_board_role(service, caller, &card.board_id, role).await?;
+    Ok(card)
+}
+
+// authors may change their own cards, anyone else needs to be running the retro
+async fn check_card_author (service: &Service, caller: &Caller, card: &Card) -> Result<(), HttpResponse> {
+    if card.author != caller.user_id {
+        check_board_role(service, caller, &card.board_id, Role::Facilitator).await?;
+    }
+    Ok(())
+}
+
+async fn update_card (service: &Service, card: &Card) -> Result<(), HttpResponse> {
+    match service.storage.update_card(card).await {
+        Ok(_) => Ok(()),
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Update card failed! {}", why))),
+    }
+}
+
+fn card_stack (card: &Card, cards: &Vec<Card>, votes: &Vec<Vote>, card_tags: &Vec<CardTag>) -> CardStack {
+    let merged: Vec<CardStack> = cards.iter()
+        .filter(|other| other.merged_into.as_ref() == Some(&card.id))
+        .map(|other| card_stack(other, cards, votes, card_tags))
+        .collect();
+    let own_votes = votes.iter().filter(|vote| vote.card_id.as_ref() == Some(&card.id)).count();
+
+    CardStack {
+        card: card.clone(),
+        votes: own_votes + merged.iter().map(|stack| stack.votes).sum::<usize>(),
+        tag_ids: card_tags.iter()
+            .filter(|card_tag| card_tag.card_id == card.id)
+            .map(|card_tag| card_tag.tag_id.clone())
+            .collect(),
+        merged: merged,
+    }
+}
+
+pub fn card_stacks (cards: &Vec<Card>, votes: &Vec<Vote>, card_tags: &Vec<CardTag>) -> Vec<CardStack> {
+    let mut cards = cards.clone();
+    cards.sort_by_key(|card| (card.position, card.created_at));
+    cards.iter()
+        .filter(|card| card.merged_into.is_none())
+        .map(|card| card_stack(card, &cards, votes, card_tags))
+        .collect()
+}
+
+async fn list_card_tags (service: &Service, board_id: &String) -> Result<Vec<CardTag>, HttpResponse> {
+    match service.storage.list_board_card_tags(board_id).await {
+        Ok(card_tags) => Ok(card_tags),
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("List card tags failed! {}", why))),
+    }
+}
+
+pub async fn add_card (
+    req: HttpRequest,
+    payload: web::Json<CreateCard>,
+    service: web::Data<Service>,
+) -> Result<web::Json<Card>, HttpResponse> {
+    debug!("add card");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+    let column = column_for(&service, &caller, &payload.column_id, Role::Participant).await?;
+
+    if payload.title.trim().is_empty() {
+        return Err(HttpResponse::BadRequest().body("Invalid request for create card!"))
+    }
+    if let Some(group_id) = &payload.group_id {
+        let group = group_for(&service, &caller, group_id, Role::Participant).await?;
+        if group.column_id != column.id {
+            return Err(HttpResponse::BadRequest().body("Group is in a different column!"))
+        }
+    }
+
+    // new cards go to the bottom of their column
+    let position = match service.storage.list_board_cards(&column.board_id).await {
+        Ok(cards) => cards.iter()
+            .filter(|card| card.column_id == column.id)
+            .map(|card| card.position + 1)
+            .max()
+            .unwrap_or(0),
+        Err(why) => return Err(HttpResponse::InternalServerError().body(format!("List cards failed! {}", why))),
+    };
+
+    let now = service.time_provider.unix_ts_ms();
+    let card = Card {
+        id: new_id(&service, now),
+        board_id: column.board_id,
+        column_id: column.id,
+        group_id: payload.group_id.clone(),
+        merged_into: None,
+        title: payload.title.clone(),
+        author: caller.user_id.clone(),
+        author_name: caller.display_name.clone(),
+        position: position,
+        created_at: now,
+    };
+
+    match service.storage.add_card(&card).await {
+        Ok(_) => {
+            audit(&service, &req, &caller, Change::new(ACTION_CREATE, ENTITY_CARD, &card.id, Some(&card.board_id)).after(&card)).await;
+            service.undo.remember(&card.board_id, &caller.user_id, Step::new().added(Row::Card(card.clone())));
+            Ok(web::Json(card))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Add card failed! {}", why))),
+    }
+}
+
+pub async fn list_cards (
+    req: HttpRequest,
+    service: web::Data<Service>,
+) -> Result<web::Json<Vec<CardStack>>, HttpResponse> {
+    debug!("list cards");
+    let caller = caller(&req, &service).await?;
+
+    let board_id = req.match_info().get("id").unwrap().to_string();
+    check_board_role(&service, &caller, &board_id, Role::Viewer).await?;
+
+    let cards = match service.storage.list_board_cards(&board_id).await {
+        Ok(cards) => cards,
+        Err(why) => return Err(HttpResponse::InternalServerError().body(format!("List cards failed! {}", why))),
+    };
+    let votes = match service.storage.list_board_votes(&board_id).await {
+        Ok(votes) => votes,
+        Err(why) => return Err(HttpResponse::InternalServerError().body(format!("List votes failed! {}", why))),
+    };
+    let card_tags = list_card_tags(&service, &board_id).await?;
+
+    Ok(web::Json(card_stacks(&cards, &votes, &card_tags)))
+}
+
+// moves a card to another column or position, or stacks it under (or out of) a group
+pub async fn move_card (
+    req: HttpRequest,
+    payload: web::Json<MoveCard>,
+    service: web::Data<Service>,
+) -> Result<web::Json<Card>, HttpResponse> {
+    debug!("move card");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let before = card_for(&service, &caller, &id, Role::Participant).await?;
+    let mut card = before.clone();
+
+    if let Some(column_id) = &payload.column_id {
+        let column = column_for(&service, &caller, column_id, Role::Participant).await?;
+        if column.board_id != card.board_id {
+            return Err(HttpResponse::BadRequest().body("Column is on a different board!"))
+        }
+        // a group lives in one column, so moving out of it leaves the group too
+        if column.id != card.column_id {
+            card.group_id = None;
+        }
+        card.column_id = column.id;
+    }
+    match &payload.group_id {
+        Some(Some(group_id)) => {
+            let group = group_for(&service, &caller, group_id, Role::Participant).await?;
+            if group.board_id != card.board_id {
+                return Err(HttpResponse::BadRequest().body("Group is on a different board!"))
+            }
+            // the group decides where its cards live
+            card.column_id = group.column_id;
+            card.group_id = Some(group.id);
+        },
+        Some(None) => card.group_id = None,
+        None => (),
+    }
+    if let Some(position) = payload.position {
+        card.position = position;
+    }
+
+    update_card(&service, &card).await?;
+    audit(&service, &req, &caller, Change::new(ACTION_MOVE, ENTITY_CARD, &id, Some(&card.board_id)).before(&before).after(&card)).await;
+    service.undo.remember(&card.board_id, &caller.user_id, Step::new().changed(Row::Card(before), Row::Card(card.clone())));
+    Ok(web::Json(card))
+}
+
+// rewords the card, keeping what it said before as a revision
+pub async fn edit_card_title (
+    service: &Service,
+    req: &HttpRequest,
+    caller: &Caller,
+    card: Card,
+    title: &String,
+) -> Result<Card, HttpResponse> {
+    check_card_author(service, caller, &card).await?;
+    if title.trim().is_empty() {
+        return Err(HttpResponse::BadRequest().body("Invalid request for edit card!"))
+    }
+    if &card.title == title {
+        return Ok(card)
+    }
+
+    let now = service.time_provider.unix_ts_ms();
+    let edited = Card { title: title.clone(), ..card.clone() };
+    update_card(service, &edited).await?;
+    let step = Step::new().changed(Row::Card(card.clone()), Row::Card(edited.clone()));
+    let step = add_revisions(
+        service,
+        card_revision(service, &card, &card.author, &card.author_name, card.created_at),
+        card_revision(service, &edited, &caller.user_id, &caller.display_name, now),
+        step,
+    ).await?;
+
+    audit(service, req, caller, Change::new(ACTION_UPDATE, ENTITY_CARD, &card.id, Some(&card.board_id)).before(&card).after(&edited)).await;
+    service.undo.remember(&card.board_id, &caller.user_id, step);
+    Ok(edited)
+}
+
+pub async fn edit_card (
+    req: HttpRequest,
+    payload: web::Json<EditCard>,
+    service: web::Data<Service>,
+) -> Result<web::Json<Card>, HttpResponse> {
+    debug!("edit card");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let card = card_for(&service, &caller, &id, Role::Participant).await?;
+    Ok(web::Json(edit_card_title(&service, &req, &caller, card, &payload.title).await?))
+}
+
+pub async fn delete_card (
+    req: HttpRequest,
+    service: web::Data<Service>
+) -> Result<HttpResponse, HttpResponse> {
+    debug!("delete card");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let card = card_for(&service, &caller, &id, Role::Participant).await?;
+    check_card_author(&service, &caller, &card).await?;
+
+    // anything merged into it goes back to being a card of its own, the rest goes along with it
+    match service.storage.delete_card_cascade(&id).await {
+        Ok(changes) => {
+            audit(&service, &req, &caller, Change::new(ACTION_DELETE, ENTITY_CARD, &id, Some(&card.board_id)).before(&card)).await;
+            service.undo.remember(&card.board_id, &caller.user_id, Step::new().with(changes));
+            Ok(HttpResponse::Ok().body("Card deleted"))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Delete card failed! {}", why))),
+    }
+}
+
+// folds another card into this one, keeping its text, author and votes intact
+pub async fn merge_card (
+    req: HttpRequest,
+    payload: web::Json<MergeCard>,
+    service: web::Data<Service>,
+) -> Result<web::Json<CardStack>, HttpResponse> {
+    debug!("merge card");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let target = card_for(&service, &caller, &id, Role::Participant).await?;
+    let source = card_for(&service, &caller, &payload.card_id, Role::Participant).await?;
+
+    if source.id == target.id || source.board_id != target.board_id {
+        return Err(HttpResponse::BadRequest().body("Can only merge two different cards on the same board!"))
+    }
+    if target.merged_into.is_some() {
+        return Err(HttpResponse::BadRequest().body(format!("Card {} is itself merged, merge into its stack instead", id)))
+    }
+    if source.merged_into.is_some() {
+        return Err(HttpResponse::BadRequest().body(format!("Card {} is already merged", source.id)))
+    }
+
+    // keep stacks one level deep, so each card can be unmerged on its own
+    let changes = match service.storage.merge_cards(&source.id, &target.id).await {
+        Ok(changes) => changes,
+        Err(why) => return Err(HttpResponse::InternalServerError().body(format!("Merge card failed! {}", why))),
+    };
+    for change in changes.iter() {
+        if let (Some(Row::Card(before)), Some(Row::Card(after))) = (&change.before, &change.after) {
+            audit(&service, &req, &caller, Change::new(ACTION_MERGE, ENTITY_CARD, &after.id, Some(&after.board_id)).before(before).after(after)).await;
+        }
+    }
+    service.undo.remember(&target.board_id, &caller.user_id, Step::new().with(changes));
+
+    let cards = match service.storage.list_board_cards(&target.board_id).await {
+        Ok(cards) => cards,
+        Err(why) => return Err(HttpResponse::InternalServerError().body(format!("List cards failed! {}", why))),
+    };
+    let votes = match service.storage.list_board_votes(&target.board_id).await {
+        Ok(votes) => votes,
+        Err(why) => return Err(HttpResponse::InternalServerError().body(format!("List votes failed! {}", why))),
+    };
+    let card_tags = list_card_tags(&service, &target.board_id).await?;
+    Ok(web::Json(card_stack(&target, &cards, &votes, &card_tags)))
+}
+
+// splits a merged card back out, with the column, group, text and votes it had before
+pub async fn unmerge_card (
+    req: HttpRequest,
+    service: web::Data<Service>
+) -> Result<web::Json<Card>, HttpResponse> {
+    debug!("unmerge card");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let card = card_for(&service, &caller, &id, Role::Participant).await?;
+
+    if card.merged_into.is_none() {
+        return Err(HttpResponse::BadRequest().body(format!("Card {} is not merged", id)))
+    }
+
+    let before = card;
+    let card = Card { merged_into: None, ..before.clone() };
+    update_card(&service, &card).await?;
+    audit(&service, &req, &caller, Change::new(ACTION_UNMERGE, ENTITY_CARD, &id, Some(&card.board_id)).before(&before).after(&card)).await;
+    service.undo.remember(&card.board_id, &caller.user_id, Step::new().changed(Row::Card(before), Row::Card(card.clone())));
+    Ok(web::Json(card))
+}
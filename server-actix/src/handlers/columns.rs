@@ -0,0 +1,137 @@
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use tracing::debug;
+
+use crate::auth::{Caller, caller, check_board_role};
+use crate::models::{Service, CreateColumn, UpdateColumn, Column, Role, Row, IfEmpty};
+use crate::audit::{audit, Change, ACTION_CREATE, ACTION_UPDATE, ACTION_DELETE, ENTITY_COLUMN};
+use crate::undo::Step;
+use super::{new_id, check_rate_limit};
+
+
+pub async fn column_for (service: &Service, caller: &Caller, id: &String, role: Role) -> Result<Column, HttpResponse> {
+    let column = match service.storage.get_column(id).await {
+        Ok(column) => column,
+        Err(why) => return Err(HttpResponse::NotFound().body(
+            format!("Could not find column for id {}: {}", id, why)
+        )),
+    };
+    check_board_role(service, caller, &column.board_id, role).await?;
+    Ok(column)
+}
+
+pub async fn add_column (
+    req: HttpRequest,
+    payload: web::Json<CreateColumn>,
+    service: web::Data<Service>,
+) -> Result<web::Json<Column>, HttpResponse> {
+    debug!("add column");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+    check_board_role(&service, &caller, &payload.board_id, Role::Facilitator).await?;
+
+    if payload.title.trim().is_empty() {
+        return Err(HttpResponse::BadRequest().body("Invalid request for create column!"))
+    }
+
+    // new columns go on the end
+    let position = match service.storage.list_board_columns(&payload.board_id).await {
+        Ok(columns) => columns.iter().map(|column| column.position + 1).max().unwrap_or(0),
+        Err(why) => return Err(HttpResponse::InternalServerError().body(format!("List columns failed! {}", why))),
+    };
+
+    let now = service.time_provider.unix_ts_ms();
+    let column = Column {
+        id: new_id(&service, now),
+        board_id: payload.board_id.clone(),
+        title: payload.title.clone(),
+        position: position,
+        created_at: now,
+    };
+
+    match service.storage.add_column(&column).await {
+        Ok(_) => {
+            audit(&service, &req, &caller, Change::new(ACTION_CREATE, ENTITY_COLUMN, &column.id, Some(&column.board_id)).after(&column)).await;
+            service.undo.remember(&column.board_id, &caller.user_id, Step::new().added(Row::Column(column.clone())));
+            Ok(web::Json(column))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Add column failed! {}", why))),
+    }
+}
+
+pub async fn list_columns (
+    req: HttpRequest,
+    service: web::Data<Service>,
+) -> Result<web::Json<Vec<Column>>, HttpResponse> {
+    debug!("list columns");
+    let caller = caller(&req, &service).await?;
+
+    let board_id = req.match_info().get("id").unwrap().to_string();
+    check_board_role(&service, &caller, &board_id, Role::Viewer).await?;
+
+    match service.storage.list_board_columns(&board_id).await {
+        Ok(mut columns) => {
+            columns.sort_by_key(|column| (column.position, column.created_at));
+            Ok(web::Json(columns))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("List columns failed! {}", why))),
+    }
+}
+
+// renames the column, or moves it to another position on the board
+pub async fn update_column (
+    req: HttpRequest,
+    payload: web::Json<UpdateColumn>,
+    service: web::Data<Service>,
+) -> Result<web::Json<Column>, HttpResponse> {
+    debug!("update column");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let before = column_for(&service, &caller, &id, Role::Facilitator).await?;
+    let mut column = before.clone();
+
+    if let Some(title) = &payload.title {
+        if title.trim().is_empty() {
+            return Err(HttpResponse::BadRequest().body("Invalid request for update column!"))
+        }
+        column.title = title.clone();
+    }
+    if let Some(position) = payload.position {
+        column.position = position;
+    }
+
+    match service.storage.update_column(&column).await {
+        Ok(_) => {
+            audit(&service, &req, &caller, Change::new(ACTION_UPDATE, ENTITY_COLUMN, &id, Some(&column.board_id)).before(&before).after(&column)).await;
+            service.undo.remember(&column.board_id, &caller.user_id, Step::new().changed(Row::Column(before), Row::Column(column.clone())));
+            Ok(web::Json(column))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Update column failed! {}", why))),
+    }
+}
+
+pub async fn delete_column (
+    req: HttpRequest,
+    service: web::Data<Service>
+) -> Result<HttpResponse, HttpResponse> {
+    debug!("delete column");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let column = column_for(&service, &caller, &id, Role::Facilitator).await?;
+
+    // only once it is empty, checked in the same go as the delete
+    match service.storage.delete_empty_column(&id).await {
+        Ok(IfEmpty::Deleted(true)) => {
+            audit(&service, &req, &caller, Change::new(ACTION_DELETE, ENTITY_COLUMN, &id, Some(&column.board_id)).before(&column)).await;
+            service.undo.remember(&column.board_id, &caller.user_id, Step::new().removed(Row::Column(column.clone())));
+            Ok(HttpResponse::Ok().body("Column deleted"))
+        },
+        Ok(IfEmpty::Deleted(false)) => Err(HttpResponse::NotFound().body(format!("Could not find column for id {}", id))),
+        Ok(IfEmpty::NotEmpty(what, _)) => Err(HttpResponse::Conflict().body(format!("Column {} still has {}", id, what))),
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Delete column failed! {}", why))),
+    }
+}
@@ -0,0 +1,163 @@
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use tracing::debug;
+
+use crate::auth::{Caller, caller, check_board_role};
+use crate::models::{Service, CreateComment, EditComment, Comment, Role, Row};
+use crate::audit::{audit, Change, ACTION_CREATE, ACTION_UPDATE, ACTION_DELETE, ENTITY_COMMENT};
+use crate::undo::Step;
+use super::{new_id, check_rate_limit};
+use super::cards::card_for;
+use super::revisions::{comment_revision, add_revisions, delete_revisions, list_card_revisions};
+
+
+pub async fn comment_for (service: &Service, caller: &Caller, id: &String, role: Role) -> Result<Comment, HttpResponse> {
+    let comment = match service.storage.get_comment(id).await {
+        Ok(comment) => comment,
+        Err(why) => return Err(HttpResponse::NotFound().body(
+            format!("Could not find comment for id {}: {}", id, why)
+        )),
+    };
+    check_board_role(service, caller, &comment.board_id, role).await?;
+    Ok(comment)
+}
+
+// authors may change their own comments, anyone else needs to be running the retro
+async fn check_comment_author (service: &Service, caller: &Caller, comment: &Comment) -> Result<(), HttpResponse> {
+    if comment.author != caller.user_id {
+        check_board_role(service, caller, &comment.board_id, Role::Facilitator).await?;
+    }
+    Ok(())
+}
+
+
+pub async fn add_comment (
+    req: HttpRequest,
+    payload: web::Json<CreateComment>,
+    service: web::Data<Service>,
+) -> Result<web::Json<Comment>, HttpResponse> {
+    debug!("add comment");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let card_id = req.match_info().get("id").unwrap().to_string();
+    let card = card_for(&service, &caller, &card_id, Role::Participant).await?;
+
+    if payload.text.trim().is_empty() {
+        return Err(HttpResponse::BadRequest().body("Invalid request for create comment!"))
+    }
+
+    let now = service.time_provider.unix_ts_ms();
+    let comment = Comment {
+        id: new_id(&service, now),
+        board_id: card.board_id,
+        card_id: card.id,
+        author: caller.user_id.clone(),
+        author_name: caller.display_name.clone(),
+        text: payload.text.clone(),
+        created_at: now,
+    };
+
+    match service.storage.add_comment(&comment).await {
+        Ok(_) => {
+            audit(&service, &req, &caller, Change::new(ACTION_CREATE, ENTITY_COMMENT, &comment.id, Some(&comment.board_id)).after(&comment)).await;
+            service.undo.remember(&comment.board_id, &caller.user_id, Step::new().added(Row::Comment(comment.clone())));
+            Ok(web::Json(comment))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Add comment failed! {}", why))),
+    }
+}
+
+pub async fn list_comments (
+    req: HttpRequest,
+    service: web::Data<Service>,
+) -> Result<web::Json<Vec<Comment>>, HttpResponse> {
+    debug!("list comments");
+    let caller = caller(&req, &service).await?;
+
+    let board_id = req.match_info().get("id").unwrap().to_string();
+    check_board_role(&service, &caller, &board_id, Role::Viewer).await?;
+
+    match service.storage.list_board_comments(&board_id).await {
+        Ok(mut comments) => {
+            comments.sort_by_key(|comment| comment.created_at);
+            Ok(web::Json(comments))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("List comments failed! {}", why))),
+    }
+}
+
+// rewords the comment, keeping what it said before as a revision
+pub async fn edit_comment_text (
+    service: &Service,
+    req: &HttpRequest,
+    caller: &Caller,
+    comment: Comment,
+    text: &String,
+) -> Result<Comment, HttpResponse> {
+    check_comment_author(service, caller, &comment).await?;
+    if text.trim().is_empty() {
+        return Err(HttpResponse::BadRequest().body("Invalid request for edit comment!"))
+    }
+    if &comment.text == text {
+        return Ok(comment)
+    }
+
+    let now = service.time_provider.unix_ts_ms();
+    let edited = Comment { text: text.clone(), ..comment.clone() };
+    if let Err(why) = service.storage.update_comment(&edited).await {
+        return Err(HttpResponse::InternalServerError().body(format!("Update comment failed! {}", why)))
+    }
+    let step = Step::new().changed(Row::Comment(comment.clone()), Row::Comment(edited.clone()));
+    let step = add_revisions(
+        service,
+        comment_revision(service, &comment, &comment.author, &comment.author_name, comment.created_at),
+        comment_revision(service, &edited, &caller.user_id, &caller.display_name, now),
+        step,
+    ).await?;
+
+    audit(service, req, caller, Change::new(ACTION_UPDATE, ENTITY_COMMENT, &comment.id, Some(&comment.board_id)).before(&comment).after(&edited)).await;
+    service.undo.remember(&comment.board_id, &caller.user_id, step);
+    Ok(edited)
+}
+
+pub async fn edit_comment (
+    req: HttpRequest,
+    payload: web::Json<EditComment>,
+    service: web::Data<Service>,
+) -> Result<web::Json<Comment>, HttpResponse> {
+    debug!("edit comment");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let comment = comment_for(&service, &caller, &id, Role::Participant).await?;
+    Ok(web::Json(edit_comment_text(&service, &req, &caller, comment, &payload.text).await?))
+}
+
+pub async fn delete_comment (
+    req: HttpRequest,
+    service: web::Data<Service>
+) -> Result<HttpResponse, HttpResponse> {
+    debug!("delete comment");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let comment = comment_for(&service, &caller, &id, Role::Participant).await?;
+    check_comment_author(&service, &caller, &comment).await?;
+
+    let revisions = list_card_revisions(&service, &comment.card_id).await?.into_iter()
+        .filter(|revision| revision.comment_id.as_ref() == Some(&id))
+        .collect();
+    let step = delete_revisions(&service, revisions, Step::new()).await?;
+
+    match service.storage.delete_comment(&id).await {
+        Ok(_) => {
+            audit(&service, &req, &caller, Change::new(ACTION_DELETE, ENTITY_COMMENT, &id, Some(&comment.board_id)).before(&comment)).await;
+            service.undo.remember(&comment.board_id, &caller.user_id, step.removed(Row::Comment(comment.clone())));
+            Ok(HttpResponse::Ok().body("Comment deleted"))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Delete comment failed! {}", why))),
+    }
+}
@@ -0,0 +1,190 @@
+
+use std::collections::{HashMap, HashSet};
+
+use actix_web::HttpResponse;
+
+use crate::models::{MyError, Service, Board, BoardContents, Column, CardGroup, Card, Vote, Tag, CardTag, Comment, ActionItem};
+use super::new_id;
+
+
+// everything on a board, read in one go for copying or exporting it
+pub async fn read_board_contents (service: &Service, board: Board) -> Result<BoardContents, MyError> {
+    let columns = match service.storage.list_board_columns(&board.id).await {
+        Ok(columns) => columns,
+        Err(why) => return Err(format!("List columns failed! {}", why)),
+    };
+    let groups = match service.storage.list_board_groups(&board.id).await {
+        Ok(groups) => groups,
+        Err(why) => return Err(format!("List groups failed! {}", why)),
+    };
+    let cards = match service.storage.list_board_cards(&board.id).await {
+        Ok(cards) => cards,
+        Err(why) => return Err(format!("List cards failed! {}", why)),
+    };
+    let votes = match service.storage.list_board_votes(&board.id).await {
+        Ok(votes) => votes,
+        Err(why) => return Err(format!("List votes failed! {}", why)),
+    };
+    let tags = match service.storage.list_board_tags(&board.id).await {
+        Ok(tags) => tags,
+        Err(why) => return Err(format!("List tags failed! {}", why)),
+    };
+    let card_tags = match service.storage.list_board_card_tags(&board.id).await {
+        Ok(card_tags) => card_tags,
+        Err(why) => return Err(format!("List card tags failed! {}", why)),
+    };
+    let comments = match service.storage.list_board_comments(&board.id).await {
+        Ok(comments) => comments,
+        Err(why) => return Err(format!("List comments failed! {}", why)),
+    };
+    let action_items = match service.storage.list_board_action_items(&board.id).await {
+        Ok(action_items) => action_items,
+        Err(why) => return Err(format!("List action items failed! {}", why)),
+    };
+
+    Ok(BoardContents {
+        board: board,
+        columns: columns,
+        groups: groups,
+        cards: cards,
+        votes: votes,
+        tags: tags,
+        card_tags: card_tags,
+        comments: comments,
+        action_items: action_items,
+    })
+}
+
+pub async fn board_contents (service: &Service, board: Board) -> Result<BoardContents, HttpResponse> {
+    read_board_contents(service, board).await.map_err(|why| HttpResponse::InternalServerError().body(why))
+}
+
+fn unique_ids<'a> (kind: &'static str, ids: impl Iterator<Item = &'a String>) -> Result<HashSet<&'a String>, MyError> {
+    let mut seen = HashSet::new();
+    for id in ids {
+        if !seen.insert(id) {
+            return Err(format!("Duplicate {} id {}", kind, id))
+        }
+    }
+    Ok(seen)
+}
+
+fn check_ref (kind: &'static str, id: &String, target: &'static str, ids: &HashSet<&String>, target_id: &String) -> Result<(), MyError> {
+    if ids.contains(target_id) {
+        Ok(())
+    } else {
+        Err(format!("{} {} points at {} {}, which is not on the board", kind, id, target, target_id))
+    }
+}
+
+// everything on the board must only point at other things on the same board,
+// since the ids are about to be swapped for fresh ones
+pub fn check_contents (contents: &BoardContents) -> Result<(), MyError> {
+    let columns = unique_ids("column", contents.columns.iter().map(|column| &column.id))?;
+    let groups = unique_ids("group", contents.groups.iter().map(|group| &group.id))?;
+    let cards = unique_ids("card", contents.cards.iter().map(|card| &card.id))?;
+    let tags = unique_ids("tag", contents.tags.iter().map(|tag| &tag.id))?;
+    unique_ids("vote", contents.votes.iter().map(|vote| &vote.id))?;
+    unique_ids("card tag", contents.card_tags.iter().map(|card_tag| &card_tag.id))?;
+    unique_ids("comment", contents.comments.iter().map(|comment| &comment.id))?;
+    unique_ids("action item", contents.action_items.iter().map(|action_item| &action_item.id))?;
+
+    for group in contents.groups.iter() {
+        check_ref("Group", &group.id, "column", &columns, &group.column_id)?;
+    }
+    for card in contents.cards.iter() {
+        check_ref("Card", &card.id, "column", &columns, &card.column_id)?;
+        if let Some(group_id) = &card.group_id {
+            check_ref("Card", &card.id, "group", &groups, group_id)?;
+        }
+        if let Some(merged_into) = &card.merged_into {
+            // stacks are one level deep, see merge_card
+            match contents.cards.iter().find(|other| &other.id == merged_into) {
+                Some(other) if other.merged_into.is_none() => (),
+                Some(_) => return Err(format!("Card {} is merged into card {}, which is itself merged", card.id, merged_into)),
+                None => check_ref("Card", &card.id, "card", &cards, merged_into)?,
+            }
+        }
+    }
+    for vote in contents.votes.iter() {
+        match (&vote.card_id, &vote.group_id) {
+            (Some(card_id), None) => check_ref("Vote", &vote.id, "card", &cards, card_id)?,
+            (None, Some(group_id)) => check_ref("Vote", &vote.id, "group", &groups, group_id)?,
+            _ => return Err(format!("Vote {} must be cast on exactly one of a card or a group", vote.id)),
+        }
+    }
+    for card_tag in contents.card_tags.iter() {
+        check_ref("Card tag", &card_tag.id, "card", &cards, &card_tag.card_id)?;
+        check_ref("Card tag", &card_tag.id, "tag", &tags, &card_tag.tag_id)?;
+    }
+    for comment in contents.comments.iter() {
+        check_ref("Comment", &comment.id, "card", &cards, &comment.card_id)?;
+    }
+    Ok(())
+}
+
+// the same board under new ids, with every reference between them kept intact,
+// ids only have to be unique within a kind, so a column and a card sharing one still get one each
+pub fn with_fresh_ids (service: &Service, contents: &BoardContents, now: i64) -> BoardContents {
+    let mut ids: HashMap<(&'static str, String), String> = HashMap::new();
+    let mut fresh_id = |kind: &'static str, old: &String| ids.entry((kind, old.clone())).or_insert_with(|| new_id(service, now)).clone();
+
+    let board = Board {
+        id: fresh_id("board", &contents.board.id),
+        ..contents.board.clone()
+    };
+    let board_id = board.id.clone();
+
+    BoardContents {
+        columns: contents.columns.iter().map(|column| Column {
+            id: fresh_id("column", &column.id),
+            board_id: board_id.clone(),
+            ..column.clone()
+        }).collect(),
+        groups: contents.groups.iter().map(|group| CardGroup {
+            id: fresh_id("group", &group.id),
+            board_id: board_id.clone(),
+            column_id: fresh_id("column", &group.column_id),
+            ..group.clone()
+        }).collect(),
+        cards: contents.cards.iter().map(|card| Card {
+            id: fresh_id("card", &card.id),
+            board_id: board_id.clone(),
+            column_id: fresh_id("column", &card.column_id),
+            group_id: card.group_id.as_ref().map(|id| fresh_id("group", id)),
+            merged_into: card.merged_into.as_ref().map(|id| fresh_id("card", id)),
+            ..card.clone()
+        }).collect(),
+        votes: contents.votes.iter().map(|vote| Vote {
+            id: fresh_id("vote", &vote.id),
+            board_id: board_id.clone(),
+            card_id: vote.card_id.as_ref().map(|id| fresh_id("card", id)),
+            group_id: vote.group_id.as_ref().map(|id| fresh_id("group", id)),
+            ..vote.clone()
+        }).collect(),
+        tags: contents.tags.iter().map(|tag| Tag {
+            id: fresh_id("tag", &tag.id),
+            board_id: board_id.clone(),
+            ..tag.clone()
+        }).collect(),
+        card_tags: contents.card_tags.iter().map(|card_tag| CardTag {
+            id: fresh_id("card tag", &card_tag.id),
+            board_id: board_id.clone(),
+            card_id: fresh_id("card", &card_tag.card_id),
+            tag_id: fresh_id("tag", &card_tag.tag_id),
+            ..card_tag.clone()
+        }).collect(),
+        comments: contents.comments.iter().map(|comment| Comment {
+            id: fresh_id("comment", &comment.id),
+            board_id: board_id.clone(),
+            card_id: fresh_id("card", &comment.card_id),
+            ..comment.clone()
+        }).collect(),
+        action_items: contents.action_items.iter().map(|action_item| ActionItem {
+            id: fresh_id("action item", &action_item.id),
+            board_id: board_id.clone(),
+            ..action_item.clone()
+        }).collect(),
+        board: board,
+    }
+}
@@ -0,0 +1,303 @@
+
+use std::fmt::Write;
+
+use actix_web::{web, HttpRequest, HttpResponse, http::header};
+use tracing::debug;
+
+use crate::auth::{caller, check_board_role};
+use crate::models::{MyError, Service, ExportBoard, BoardContents, CardStack, Comment, Role};
+use super::contents::board_contents;
+use super::cards::card_stacks;
+
+
+pub const FORMAT_MARKDOWN: &'static str = "markdown";
+pub const FORMAT_JSON: &'static str = "json";
+pub const FORMAT_CSV: &'static str = "csv";
+
+const CSV_HEADERS: [&'static str; 10] = [
+    "id", "column", "group", "title", "author", "author_name", "votes", "tags", "merged_into", "created_at",
+];
+
+
+// every line already starts with our own markup, so only what works mid line needs escaping
+const MARKDOWN_SPECIAL: &'static str = "\\`*_[]<>#|~";
+
+// markdown is line based, so keep whatever people typed on one line,
+// and escaped, so a title can't turn into a link or bold the rest of the export
+fn inline (text: &String) -> String {
+    let mut out = String::new();
+    for c in text.split_whitespace().collect::<Vec<&str>>().join(" ").chars() {
+        if MARKDOWN_SPECIAL.contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+// backslashes don't escape in code spans, so the fence has to be longer than any run of backticks inside
+fn code (text: &String) -> String {
+    let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let longest = text.split(|c| c != '`').map(|run| run.len()).max().unwrap_or(0);
+    let fence = "`".repeat(longest + 1);
+    let pad = if text.starts_with('`') || text.ends_with('`') { " " } else { "" };
+    format!("{}{}{}{}{}", fence, pad, text, pad, fence)
+}
+
+fn votes_str (votes: usize) -> String {
+    match votes {
+        1 => String::from("1 vote"),
+        n => format!("{} votes", n),
+    }
+}
+
+fn write_card (out: &mut String, contents: &BoardContents, stack: &CardStack, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let card = &stack.card;
+    let tags: Vec<String> = contents.tags.iter()
+        .filter(|tag| stack.tag_ids.contains(&tag.id))
+        .map(|tag| format!(" {}", code(&tag.title)))
+        .collect();
+    let _ = writeln!(out, "{}- **{}** ({}) _{}_{}", indent, inline(&card.title), votes_str(stack.votes), inline(&card.author_name), tags.concat());
+
+    let mut comments: Vec<&Comment> = contents.comments.iter().filter(|comment| comment.card_id == card.id).collect();
+    comments.sort_by_key(|comment| comment.created_at);
+    for comment in comments {
+        let _ = writeln!(out, "{}  > **{}**: {}", indent, inline(&comment.author_name), inline(&comment.text));
+    }
+    for merged in stack.merged.iter() {
+        write_card(out, contents, merged, depth + 1);
+    }
+}
+
+// most voted first, which is the order the retro discussed them in
+fn write_cards (out: &mut String, contents: &BoardContents, mut stacks: Vec<&CardStack>) {
+    stacks.sort_by_key(|stack| (std::cmp::Reverse(stack.votes), stack.card.position, stack.card.created_at));
+    if !stacks.is_empty() {
+        out.push('\n');
+    }
+    for stack in stacks {
+        write_card(out, contents, stack, 0);
+    }
+}
+
+pub fn markdown (contents: &BoardContents) -> String {
+    let stacks = card_stacks(&contents.cards, &contents.votes, &contents.card_tags);
+    let mut columns = contents.columns.clone();
+    columns.sort_by_key(|column| (column.position, column.created_at));
+
+    let mut out = String::new();
+    let _ = writeln!(out, "# {}", inline(&contents.board.title));
+
+    for column in columns.iter() {
+        let _ = writeln!(out, "\n## {}", inline(&column.title));
+        write_cards(&mut out, contents, stacks.iter()
+            .filter(|stack| stack.card.column_id == column.id && stack.card.group_id.is_none())
+            .collect());
+
+        let mut groups: Vec<(usize, _)> = contents.groups.iter()
+            .filter(|group| group.column_id == column.id)
+            .map(|group| (contents.votes.iter().filter(|vote| vote.group_id.as_ref() == Some(&group.id)).count(), group))
+            .collect();
+        groups.sort_by_key(|(votes, group)| (std::cmp::Reverse(*votes), group.created_at));
+        for (votes, group) in groups {
+            let _ = writeln!(out, "\n### {} ({})", inline(&group.title), votes_str(votes));
+            write_cards(&mut out, contents, stacks.iter()
+                .filter(|stack| stack.card.group_id.as_ref() == Some(&group.id))
+                .collect());
+        }
+    }
+
+    if !contents.action_items.is_empty() {
+        let mut action_items = contents.action_items.clone();
+        action_items.sort_by_key(|action_item| action_item.created_at);
+
+        let _ = writeln!(out, "\n## Action items\n");
+        for action_item in action_items.iter() {
+            let assignee = match &action_item.assignee {
+                Some(assignee) => format!(" (@{})", inline(assignee)),
+                None => String::new(),
+            };
+            let _ = writeln!(out, "- [{}] {}{}", if action_item.done { "x" } else { " " }, inline(&action_item.title), assignee);
+        }
+    }
+
+    out
+}
+
+// spreadsheets run cells that start like a formula, so those get a quote in front and stay text,
+// a leading tab or carriage return counts too, some of them skip past those before looking
+fn cell (text: String) -> String {
+    match text.chars().next() {
+        Some('=') | Some('+') | Some('-') | Some('@') | Some('\t') | Some('\r') => format!("'{}", text),
+        _ => text,
+    }
+}
+
+// one row per card, merged ones included, with only the votes cast on that card itself,
+// column by column as the board shows them, then in the order within the column
+pub fn csv (contents: &BoardContents) -> Result<String, MyError> {
+    let column_order = |column_id: &String| contents.columns.iter()
+        .find(|column| &column.id == column_id)
+        .map(|column| (column.position, column.created_at));
+    let mut cards = contents.cards.clone();
+    // a card whose column is missing has nowhere to go but the end
+    cards.sort_by_key(|card| (column_order(&card.column_id).is_none(), column_order(&card.column_id), card.position, card.created_at));
+
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record(CSV_HEADERS).map_err(|why| format!("Failed writing csv: {}", why))?;
+    for card in cards.iter() {
+        let column = contents.columns.iter().find(|column| column.id == card.column_id).map(|column| column.title.clone());
+        let group = contents.groups.iter().find(|group| Some(&group.id) == card.group_id.as_ref()).map(|group| group.title.clone());
+        let votes = contents.votes.iter().filter(|vote| vote.card_id.as_ref() == Some(&card.id)).count();
+        let tags: Vec<String> = contents.card_tags.iter()
+            .filter(|card_tag| card_tag.card_id == card.id)
+            .filter_map(|card_tag| contents.tags.iter().find(|tag| tag.id == card_tag.tag_id))
+            .map(|tag| tag.title.clone())
+            .collect();
+
+        writer.write_record(&[
+            card.id.clone(),
+            cell(column.unwrap_or_default()),
+            cell(group.unwrap_or_default()),
+            cell(card.title.clone()),
+            cell(card.author.clone()),
+            cell(card.author_name.clone()),
+            votes.to_string(),
+            cell(tags.join(";")),
+            card.merged_into.clone().unwrap_or_default(),
+            card.created_at.to_string(),
+        ]).map_err(|why| format!("Failed writing csv: {}", why))?;
+    }
+
+    let bytes = writer.into_inner().map_err(|why| format!("Failed writing csv: {}", why))?;
+    String::from_utf8(bytes).map_err(|why| format!("Failed writing csv: {}", why))
+}
+
+fn attachment (content_type: &'static str, filename: String, body: String) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header((header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)))
+        .body(body)
+}
+
+pub async fn export_board (
+    req: HttpRequest,
+    query: web::Query<ExportBoard>,
+    service: web::Data<Service>,
+) -> Result<HttpResponse, HttpResponse> {
+    debug!("export board");
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let board = check_board_role(&service, &caller, &id, Role::Viewer).await?;
+    let contents = board_contents(&service, board).await?;
+
+    // json is the full board, for importing it again with POST /api/boards/import
+    match query.format.as_str() {
+        FORMAT_MARKDOWN => Ok(attachment("text/markdown; charset=utf-8", format!("{}.md", id), markdown(&contents))),
+        FORMAT_JSON => match serde_json::to_string_pretty(&contents) {
+            Ok(json) => Ok(attachment("application/json", format!("{}.json", id), json)),
+            Err(why) => Err(HttpResponse::InternalServerError().body(format!("Export board failed! {}", why))),
+        },
+        FORMAT_CSV => match csv(&contents) {
+            Ok(csv) => Ok(attachment("text/csv; charset=utf-8", format!("{}.csv", id), csv)),
+            Err(why) => Err(HttpResponse::InternalServerError().body(format!("Export board failed! {}", why))),
+        },
+        format => Err(HttpResponse::BadRequest().body(format!("Unknown export format '{}'", format))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Board, Column, CardGroup, Card, Vote, Tag, CardTag, ActionItem};
+
+    fn column (id: &str, title: &str, position: i32) -> Column {
+        Column { id: id.into(), board_id: "b1".into(), title: title.into(), position: position, created_at: 0 }
+    }
+
+    fn card (id: &str, column_id: &str, title: &str, position: i32) -> Card {
+        Card {
+            id: id.into(),
+            board_id: "b1".into(),
+            column_id: column_id.into(),
+            group_id: None,
+            merged_into: None,
+            title: title.into(),
+            author: "alice".into(),
+            author_name: "Alice".into(),
+            position: position,
+            created_at: 0,
+        }
+    }
+
+    fn vote (id: &str, card_id: Option<&str>, group_id: Option<&str>) -> Vote {
+        Vote { id: id.into(), board_id: "b1".into(), card_id: card_id.map(String::from), group_id: group_id.map(String::from), author: "bob".into(), created_at: 0 }
+    }
+
+    fn comment (id: &str, text: &str, created_at: i64) -> Comment {
+        Comment { id: id.into(), board_id: "b1".into(), card_id: "k1".into(), author: "bob".into(), author_name: "Bob".into(), text: text.into(), created_at: created_at }
+    }
+
+    // columns listed out of order, a card in a column that is gone, one merged into another and one in a group
+    fn contents () -> BoardContents {
+        BoardContents {
+            board: Board { id: "b1".into(), team_id: "t1".into(), title: "Sprint #1".into(), owner: "alice".into(), created_at: 0, archived_at: None, deleted_at: None },
+            columns: vec![column("c2", "Later", 1), column("c1", "First", 0)],
+            groups: vec![CardGroup { id: "g1".into(), board_id: "b1".into(), column_id: "c1".into(), title: "Ideas".into(), created_at: 0 }],
+            cards: vec![
+                card("k4", "gone", "Orphan", 0),
+                card("k3", "c2", "\tTabbed", 0),
+                card("k1", "c1", "Pairing *fast*", 1),
+                card("k2", "c1", "=SUM(A1)", 0),
+                Card { merged_into: Some("k1".into()), ..card("k5", "c1", "Mob\nprogramming", 2) },
+                Card { group_id: Some("g1".into()), ..card("k6", "c1", "+1 retro", 3) },
+            ],
+            votes: vec![vote("v1", Some("k1"), None), vote("v2", Some("k5"), None), vote("v3", None, Some("g1"))],
+            tags: vec![Tag { id: "t1".into(), board_id: "b1".into(), title: "a`b".into(), created_at: 0 }],
+            card_tags: vec![CardTag { id: "ct1".into(), board_id: "b1".into(), card_id: "k1".into(), tag_id: "t1".into(), created_at: 0 }],
+            comments: vec![comment("m2", "second", 2), comment("m1", "see [x](y)", 1)],
+            action_items: vec![ActionItem { id: "a1".into(), board_id: "b1".into(), title: "Ship <it>".into(), assignee: Some("bob_x".into()), done: true, created_at: 0 }],
+        }
+    }
+
+    #[test]
+    fn markdown_escapes_and_orders () {
+        assert_eq!(markdown(&contents()), "\
+            # Sprint \\#1\n\
+            \n## First\n\n\
+            - **Pairing \\*fast\\*** (2 votes) _Alice_ ``a`b``\n  \
+              > **Bob**: see \\[x\\](y)\n  \
+              > **Bob**: second\n  \
+              - **Mob programming** (1 vote) _Alice_\n\
+            - **=SUM(A1)** (0 votes) _Alice_\n\
+            \n### Ideas (1 vote)\n\n\
+            - **+1 retro** (0 votes) _Alice_\n\
+            \n## Later\n\n\
+            - **Tabbed** (0 votes) _Alice_\n\
+            \n## Action items\n\n\
+            - [x] Ship \\<it\\> (@bob\\_x)\n"
+        );
+    }
+
+    #[test]
+    fn code_fences_outgrow_backticks () {
+        assert_eq!(code(&"plain".into()), "`plain`");
+        assert_eq!(code(&"a``b".into()), "```a``b```");
+        assert_eq!(code(&"`edge`".into()), "`` `edge` ``");
+    }
+
+    #[test]
+    fn csv_keeps_formulas_as_text_in_board_order () {
+        assert_eq!(csv(&contents()).unwrap(), "\
+            id,column,group,title,author,author_name,votes,tags,merged_into,created_at\n\
+            k2,First,,'=SUM(A1),alice,Alice,0,,,0\n\
+            k1,First,,Pairing *fast*,alice,Alice,1,a`b,,0\n\
+            k5,First,,\"Mob\nprogramming\",alice,Alice,1,,k1,0\n\
+            k6,First,Ideas,'+1 retro,alice,Alice,0,,,0\n\
+            k3,Later,,'\tTabbed,alice,Alice,0,,,0\n\
+            k4,,,Orphan,alice,Alice,0,,,0\n"
+        );
+    }
+}
@@ -0,0 +1,140 @@
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use tracing::debug;
+
+use crate::auth::{Caller, caller, check_board_role};
+use crate::models::{Service, CreateGroup, RenameGroup, CardGroup, GroupStack, Role, Row};
+use crate::audit::{audit, Change, ACTION_CREATE, ACTION_UPDATE, ACTION_DELETE, ENTITY_GROUP};
+use crate::undo::Step;
+use super::{new_id, check_rate_limit};
+use super::columns::column_for;
+
+
+pub async fn group_for (service: &Service, caller: &Caller, id: &String, role: Role) -> Result<CardGroup, HttpResponse> {
+    let group = match service.storage.get_group(id).await {
+        Ok(group) => group,
+        Err(why) => return Err(HttpResponse::NotFound().body(
+            format!("Could not find group for id {}: {}", id, why)
+        )),
+    };
+    check_board_role(service, caller, &group.board_id, role).await?;
+    Ok(group)
+}
+
+pub async fn add_group (
+    req: HttpRequest,
+    payload: web::Json<CreateGroup>,
+    service: web::Data<Service>,
+) -> Result<web::Json<CardGroup>, HttpResponse> {
+    debug!("add group");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+    let column = column_for(&service, &caller, &payload.column_id, Role::Participant).await?;
+
+    if payload.title.trim().is_empty() {
+        return Err(HttpResponse::BadRequest().body("Invalid request for create group!"))
+    }
+
+    let now = service.time_provider.unix_ts_ms();
+    let group = CardGroup {
+        id: new_id(&service, now),
+        board_id: column.board_id,
+        column_id: column.id,
+        title: payload.title.clone(),
+        created_at: now,
+    };
+
+    match service.storage.add_group(&group).await {
+        Ok(_) => {
+            audit(&service, &req, &caller, Change::new(ACTION_CREATE, ENTITY_GROUP, &group.id, Some(&group.board_id)).after(&group)).await;
+            service.undo.remember(&group.board_id, &caller.user_id, Step::new().added(Row::Group(group.clone())));
+            Ok(web::Json(group))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Add group failed! {}", why))),
+    }
+}
+
+pub async fn list_groups (
+    req: HttpRequest,
+    service: web::Data<Service>,
+) -> Result<web::Json<Vec<GroupStack>>, HttpResponse> {
+    debug!("list groups");
+    let caller = caller(&req, &service).await?;
+
+    let board_id = req.match_info().get("id").unwrap().to_string();
+    check_board_role(&service, &caller, &board_id, Role::Viewer).await?;
+
+    let groups = match service.storage.list_board_groups(&board_id).await {
+        Ok(groups) => groups,
+        Err(why) => return Err(HttpResponse::InternalServerError().body(format!("List groups failed! {}", why))),
+    };
+    let cards = match service.storage.list_board_cards(&board_id).await {
+        Ok(cards) => cards,
+        Err(why) => return Err(HttpResponse::InternalServerError().body(format!("List cards failed! {}", why))),
+    };
+    let votes = match service.storage.list_board_votes(&board_id).await {
+        Ok(votes) => votes,
+        Err(why) => return Err(HttpResponse::InternalServerError().body(format!("List votes failed! {}", why))),
+    };
+
+    // group votes are their own, card votes stay on the cards
+    Ok(web::Json(groups.into_iter().map(|group| GroupStack {
+        votes: votes.iter().filter(|vote| vote.group_id.as_ref() == Some(&group.id)).count(),
+        card_ids: cards.iter()
+            .filter(|card| card.group_id.as_ref() == Some(&group.id))
+            .map(|card| card.id.clone())
+            .collect(),
+        group: group,
+    }).collect()))
+}
+
+pub async fn rename_group (
+    req: HttpRequest,
+    payload: web::Json<RenameGroup>,
+    service: web::Data<Service>,
+) -> Result<web::Json<CardGroup>, HttpResponse> {
+    debug!("rename group");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let group = group_for(&service, &caller, &id, Role::Participant).await?;
+
+    if payload.title.trim().is_empty() {
+        return Err(HttpResponse::BadRequest().body("Invalid request for rename group!"))
+    }
+
+    let before = group;
+    let group = CardGroup { title: payload.title.clone(), ..before.clone() };
+    match service.storage.update_group(&group).await {
+        Ok(_) => {
+            audit(&service, &req, &caller, Change::new(ACTION_UPDATE, ENTITY_GROUP, &id, Some(&group.board_id)).before(&before).after(&group)).await;
+            service.undo.remember(&group.board_id, &caller.user_id, Step::new().changed(Row::Group(before), Row::Group(group.clone())));
+            Ok(web::Json(group))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Update group failed! {}", why))),
+    }
+}
+
+// unstacks the cards, which stay where they are, and drops the votes cast on the group
+pub async fn delete_group (
+    req: HttpRequest,
+    service: web::Data<Service>
+) -> Result<HttpResponse, HttpResponse> {
+    debug!("delete group");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let group = group_for(&service, &caller, &id, Role::Participant).await?;
+
+    // its cards stay in the column, its votes go with it
+    match service.storage.delete_group_cascade(&id).await {
+        Ok(changes) => {
+            audit(&service, &req, &caller, Change::new(ACTION_DELETE, ENTITY_GROUP, &id, Some(&group.board_id)).before(&group)).await;
+            service.undo.remember(&group.board_id, &caller.user_id, Step::new().with(changes));
+            Ok(HttpResponse::Ok().body("Group deleted"))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Delete group failed! {}", why))),
+    }
+}
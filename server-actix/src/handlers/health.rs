@@ -0,0 +1,26 @@
+use actix_web::{web, HttpResponse};
+use tracing::{debug, warn};
+
+use crate::models::{Service, Readiness};
+
+
+// liveness, answering at all is enough, a broken storage is for readiness to tell
+pub async fn get_health () -> HttpResponse {
+    HttpResponse::Ok().body("ok")
+}
+
+// ready once the storage can be reached and is up to date, an invalid one never is
+pub async fn get_readiness (
+    service: web::Data<Service>,
+) -> HttpResponse {
+    debug!("get readiness");
+    let storage = service.storage.name();
+
+    match service.storage.health_check().await {
+        Ok(_) => HttpResponse::Ok().json(&Readiness { ready: true, storage: storage, error: None }),
+        Err(why) => {
+            warn!(storage, error = %why, "not ready");
+            HttpResponse::ServiceUnavailable().json(&Readiness { ready: false, storage: storage, error: Some(why) })
+        },
+    }
+}
@@ -0,0 +1,85 @@
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use tracing::debug;
+
+use crate::auth::{caller, check_board_role};
+use crate::models::{Service, AuditFilter, AuditEntry, BoardChange, BoardHistory, BoardStateAt, ReconstructedBoard, Role};
+use crate::history::board_at;
+
+
+const DEFAULT_LIMIT: usize = 1000;
+const MAX_LIMIT: usize = 10000;
+
+
+// oldest first, up to but not including until
+async fn board_entries (service: &Service, filter: AuditFilter) -> Result<Vec<AuditEntry>, HttpResponse> {
+    match service.storage.list_audit_entries(&AuditFilter { oldest_first: true, ..filter }).await {
+        Ok(entries) => Ok(entries),
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("List audit entries failed! {}", why))),
+    }
+}
+
+// every change on the board in the order it happened, for the client to scrub through
+// page forward by passing the seq of the last change as after, several changes can share a millisecond
+// so the time alone cannot tell where the page ended
+pub async fn board_history (
+    req: HttpRequest,
+    query: web::Query<BoardHistory>,
+    service: web::Data<Service>,
+) -> Result<web::Json<Vec<BoardChange>>, HttpResponse> {
+    debug!("board history");
+    let caller = caller(&req, &service).await?;
+
+    let board_id = req.match_info().get("id").unwrap().to_string();
+    check_board_role(&service, &caller, &board_id, Role::Viewer).await?;
+
+    let entries = board_entries(&service, AuditFilter {
+        board_id: Some(board_id),
+        since: query.since,
+        until: query.until,
+        after: query.after,
+        limit: Some(query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT)),
+        ..AuditFilter::default()
+    }).await?;
+
+    // the client address stays in the audit log, for admins only
+    Ok(web::Json(entries.into_iter().map(|entry| BoardChange {
+        id: entry.id,
+        seq: entry.seq,
+        actor: entry.actor,
+        action: entry.action,
+        entity_type: entry.entity_type,
+        entity_id: entry.entity_id,
+        before: entry.before,
+        after: entry.after,
+        created_at: entry.created_at,
+    }).collect()))
+}
+
+// the whole board as it stood at the given time, replayed from its history
+pub async fn board_state_at (
+    req: HttpRequest,
+    query: web::Query<BoardStateAt>,
+    service: web::Data<Service>,
+) -> Result<web::Json<ReconstructedBoard>, HttpResponse> {
+    debug!("board state at");
+    let caller = caller(&req, &service).await?;
+
+    let board_id = req.match_info().get("id").unwrap().to_string();
+    check_board_role(&service, &caller, &board_id, Role::Viewer).await?;
+
+    let entries = board_entries(&service, AuditFilter {
+        board_id: Some(board_id.clone()),
+        until: Some(query.at.saturating_add(1)),
+        ..AuditFilter::default()
+    }).await?;
+    match board_at(&entries) {
+        Ok(Some(contents)) => Ok(web::Json(ReconstructedBoard {
+            reconstructed: true,
+            at: query.at,
+            contents: contents,
+        })),
+        Ok(None) => Err(HttpResponse::NotFound().body(format!("Board {} has no history as of {}", board_id, query.at))),
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Replay board failed! {}", why))),
+    }
+}
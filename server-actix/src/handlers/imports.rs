@@ -0,0 +1,117 @@
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use tracing::debug;
+
+use crate::auth::{Caller, caller, check_team_role};
+use crate::models::{MyError, Service, ImportBoard, ImportSummary, BoardContents, Board, Role};
+use crate::importers::{import, Source};
+use crate::audit::{audit, Change, ACTION_IMPORT, ENTITY_BOARD};
+use super::check_rate_limit;
+use super::contents::{check_contents, with_fresh_ids};
+
+
+// checks the board and gives it fresh ids, in the team and owned by whoever imports it
+pub fn prepare_import (service: &Service, owner: &String, query: &ImportBoard, contents: &BoardContents) -> Result<BoardContents, MyError> {
+    if contents.board.title.trim().is_empty() {
+        return Err(String::from("Invalid request for import board!"))
+    }
+    if let Err(why) = check_contents(contents) {
+        return Err(format!("Invalid board to import! {}", why))
+    }
+
+    let now = service.time_provider.unix_ts_ms();
+    let contents = with_fresh_ids(service, contents, now);
+    Ok(BoardContents {
+        board: Board {
+            team_id: query.team_id.clone(),
+            owner: owner.clone(),
+            deleted_at: None,
+            ..contents.board
+        },
+        ..contents
+    })
+}
+
+pub fn import_summary (query: &ImportBoard, contents: BoardContents, warnings: Vec<String>) -> ImportSummary {
+    ImportSummary {
+        dry_run: query.dry_run,
+        columns: contents.columns.len(),
+        groups: contents.groups.len(),
+        cards: contents.cards.len(),
+        votes: contents.votes.len(),
+        tags: contents.tags.len(),
+        comments: contents.comments.len(),
+        action_items: contents.action_items.len(),
+        warnings: warnings,
+        board: contents.board,
+    }
+}
+
+// adds the board to the team under fresh ids, unless this is a dry run
+async fn add_imported (
+    service: &Service,
+    req: &HttpRequest,
+    caller: &Caller,
+    query: &ImportBoard,
+    contents: &BoardContents,
+    warnings: Vec<String>,
+) -> Result<web::Json<ImportSummary>, HttpResponse> {
+    let contents = prepare_import(service, &caller.user_id, query, contents)
+        .map_err(|why| HttpResponse::BadRequest().body(why))?;
+
+    if !query.dry_run {
+        if let Err(why) = service.storage.add_board_contents(&contents).await {
+            return Err(HttpResponse::InternalServerError().body(format!("Import board failed! {}", why)))
+        }
+        audit(service, req, caller, Change::new(ACTION_IMPORT, ENTITY_BOARD, &contents.board.id, Some(&contents.board.id)).after(&contents)).await;
+    }
+
+    Ok(web::Json(import_summary(query, contents, warnings)))
+}
+
+// takes a board as exported with ?format=json, from this or any other instance,
+// and adds it to the team under fresh ids, so the same export can be imported twice
+pub async fn import_board (
+    req: HttpRequest,
+    query: web::Query<ImportBoard>,
+    payload: web::Json<BoardContents>,
+    service: web::Data<Service>,
+) -> Result<web::Json<ImportSummary>, HttpResponse> {
+    debug!("import board");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+    check_team_role(&service, &caller, &query.team_id, Role::Facilitator).await?;
+
+    let mut contents = payload.into_inner();
+    if let Some(title) = &query.title {
+        contents.board.title = title.clone();
+    }
+    add_imported(&service, &req, &caller, &query, &contents, vec![]).await
+}
+
+// takes another tool's export, see crate::importers for what each format maps onto
+pub async fn import_board_from (
+    req: HttpRequest,
+    query: web::Query<ImportBoard>,
+    body: web::Bytes,
+    service: web::Data<Service>,
+) -> Result<web::Json<ImportSummary>, HttpResponse> {
+    debug!("import board from");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+    check_team_role(&service, &caller, &query.team_id, Role::Facilitator).await?;
+
+    let format = req.match_info().get("format").unwrap().to_string();
+    let source = Source {
+        title: query.title.clone(),
+        author: caller.user_id.clone(),
+        author_name: caller.display_name.clone(),
+        now: service.time_provider.unix_ts_ms(),
+        limit_bytes: service.config.import_limit_bytes,
+    };
+    let imported = match import(&format, &body, &source) {
+        Ok(imported) => imported,
+        Err(why) => return Err(HttpResponse::BadRequest().body(format!("Import board failed! {}", why))),
+    };
+    add_imported(&service, &req, &caller, &query, &imported.contents, imported.warnings).await
+}
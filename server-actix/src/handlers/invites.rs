@@ -0,0 +1,148 @@
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use tracing::debug;
+
+use crate::auth::{caller, check_board_role, check_invite, sign_invite, sign_guest_session};
+use crate::models::{Service, Role, Invite, InviteLink, JoinInvite, GuestSession};
+use crate::audit::{audit, record, client_ip, Change, ACTION_ROTATE, ACTION_DELETE, ACTION_JOIN, ENTITY_INVITE};
+use super::{new_id, check_rate_limit};
+
+
+async fn board_invite (service: &Service, board_id: &String) -> Result<Option<Invite>, HttpResponse> {
+    match service.storage.list_board_invites(board_id).await {
+        Ok(invites) => Ok(invites.into_iter().max_by_key(|invite| invite.created_at)),
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("List invites failed! {}", why))),
+    }
+}
+
+fn invite_link (service: &Service, invite: &Invite) -> Result<web::Json<InviteLink>, HttpResponse> {
+    match sign_invite(service, invite) {
+        Ok(token) => Ok(web::Json(InviteLink {
+            board_id: invite.board_id.clone(),
+            token: token,
+            expires_at: invite.expires_at,
+        })),
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Sign invite failed! {}", why))),
+    }
+}
+
+async fn revoke_invites (service: &Service, board_id: &String) -> Result<Vec<Invite>, HttpResponse> {
+    let invites = match service.storage.list_board_invites(board_id).await {
+        Ok(invites) => invites,
+        Err(why) => return Err(HttpResponse::InternalServerError().body(format!("List invites failed! {}", why))),
+    };
+    for invite in invites.iter() {
+        if let Err(why) = service.storage.delete_invite(&invite.id).await {
+            return Err(HttpResponse::InternalServerError().body(format!("Delete invite failed! {}", why)))
+        }
+    }
+    Ok(invites)
+}
+
+pub async fn get_invite (
+    req: HttpRequest,
+    service: web::Data<Service>
+) -> Result<web::Json<InviteLink>, HttpResponse> {
+    debug!("get invite");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let board_id = req.match_info().get("id").unwrap().to_string();
+    check_board_role(&service, &caller, &board_id, Role::Facilitator).await?;
+
+    match board_invite(&service, &board_id).await? {
+        Some(invite) => invite_link(&service, &invite),
+        None => Err(HttpResponse::NotFound().body(format!("No invite for board {}", board_id))),
+    }
+}
+
+// issues a fresh link, which revokes the previous one and every guest who joined with it
+pub async fn rotate_invite (
+    req: HttpRequest,
+    service: web::Data<Service>
+) -> Result<web::Json<InviteLink>, HttpResponse> {
+    debug!("rotate invite");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let board_id = req.match_info().get("id").unwrap().to_string();
+    check_board_role(&service, &caller, &board_id, Role::Facilitator).await?;
+
+    let revoked = revoke_invites(&service, &board_id).await?;
+
+    let now = service.time_provider.unix_ts_ms();
+    let invite = Invite {
+        id: new_id(&service, now),
+        board_id: board_id.clone(),
+        created_by: caller.user_id.clone(),
+        expires_at: now + service.config.invite_ttl_ms,
+        created_at: now,
+    };
+    // sign first, so a missing secret does not leave an unusable invite behind
+    let link = invite_link(&service, &invite)?;
+
+    match service.storage.add_invite(&invite).await {
+        Ok(_) => {
+            let mut change = Change::new(ACTION_ROTATE, ENTITY_INVITE, &invite.id, Some(&board_id)).after(&invite);
+            if let Some(previous) = revoked.iter().max_by_key(|previous| previous.created_at) {
+                change = change.before(previous);
+            }
+            audit(&service, &req, &caller, change).await;
+            Ok(link)
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Add invite failed! {}", why))),
+    }
+}
+
+pub async fn delete_invite (
+    req: HttpRequest,
+    service: web::Data<Service>
+) -> Result<HttpResponse, HttpResponse> {
+    debug!("delete invite");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let board_id = req.match_info().get("id").unwrap().to_string();
+    check_board_role(&service, &caller, &board_id, Role::Facilitator).await?;
+
+    for invite in revoke_invites(&service, &board_id).await?.iter() {
+        audit(&service, &req, &caller, Change::new(ACTION_DELETE, ENTITY_INVITE, &invite.id, Some(&board_id)).before(invite)).await;
+    }
+    Ok(HttpResponse::Ok().body("Invite revoked"))
+}
+
+pub async fn join_invite (
+    req: HttpRequest,
+    payload: web::Json<JoinInvite>,
+    service: web::Data<Service>,
+) -> Result<web::Json<GuestSession>, HttpResponse> {
+    debug!("join invite");
+    check_rate_limit(&req)?;
+
+    let display_name = payload.display_name.trim().to_string();
+    if display_name.is_empty() {
+        return Err(HttpResponse::BadRequest().body("Invalid request for join invite!"))
+    }
+
+    let invite = check_invite(&service, &payload.token).await?;
+
+    let now = service.time_provider.unix_ts_ms();
+    let user_id = format!("guest-{}", new_id(&service, now));
+    // a session never outlives the invite it came from
+    let expires_at = std::cmp::min(now + service.config.guest_session_ttl_ms, invite.expires_at);
+
+    match sign_guest_session(&service, &invite, &user_id, &display_name, expires_at) {
+        Ok(token) => {
+            // no caller yet, the guest only comes into being here
+            record(&service, &user_id, client_ip(&req), Change::new(ACTION_JOIN, ENTITY_INVITE, &invite.id, Some(&invite.board_id))).await;
+            Ok(web::Json(GuestSession {
+                board_id: invite.board_id,
+                user_id: user_id,
+                display_name: display_name,
+                token: token,
+                expires_at: expires_at,
+            }))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Sign guest session failed! {}", why))),
+    }
+}
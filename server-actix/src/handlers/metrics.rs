@@ -0,0 +1,18 @@
+use actix_web::{web, HttpResponse};
+use tracing::debug;
+
+use crate::models::Service;
+
+
+// for prometheus to scrape, outside of /api and without a caller, like any other exporter
+pub async fn get_metrics (
+    service: web::Data<Service>,
+) -> Result<HttpResponse, HttpResponse> {
+    debug!("get metrics");
+    service.metrics.set_pool_status(service.storage.pool_status());
+
+    match service.metrics.render() {
+        Ok(text) => Ok(HttpResponse::Ok().content_type(prometheus::TEXT_FORMAT).body(text)),
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Get metrics failed! {}", why))),
+    }
+}
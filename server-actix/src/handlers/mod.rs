@@ -0,0 +1,68 @@
+
+use actix_web::{HttpRequest, HttpResponse};
+
+use crate::models::Service;
+
+mod boards;
+mod columns;
+mod cards;
+mod groups;
+mod votes;
+mod invites;
+mod teams;
+mod tags;
+mod comments;
+mod action_items;
+pub mod exports;
+pub mod imports;
+pub mod contents;
+mod search;
+mod admin;
+mod history;
+mod undo;
+mod revisions;
+mod metrics;
+mod health;
+
+pub use self::boards::{add_board, list_boards, get_board, delete_board, restore_board, purge_board, archive_board, unarchive_board, clone_board};
+pub use self::columns::{add_column, list_columns, update_column, delete_column};
+pub use self::cards::{add_card, list_cards, edit_card, move_card, delete_card, merge_card, unmerge_card};
+pub use self::groups::{add_group, list_groups, rename_group, delete_group};
+pub use self::votes::{vote_card, vote_group, list_votes, delete_vote};
+pub use self::invites::{get_invite, rotate_invite, delete_invite, join_invite};
+pub use self::teams::{add_team, list_teams, get_team, delete_team, list_members, put_member, delete_member};
+pub use self::tags::{add_tag, list_tags, delete_tag, tag_card, untag_card};
+pub use self::comments::{add_comment, list_comments, edit_comment, delete_comment};
+pub use self::action_items::{add_action_item, list_action_items, update_action_item, delete_action_item};
+pub use self::exports::export_board;
+pub use self::imports::{import_board, import_board_from};
+pub use self::search::search;
+pub use self::admin::list_audit;
+pub use self::history::{board_history, board_state_at};
+pub use self::undo::{undo_board, redo_board};
+pub use self::revisions::{list_revisions, restore_revision};
+pub use self::metrics::get_metrics;
+pub use self::health::{get_health, get_readiness};
+
+
+// TODO: add oauth exchange for token here
+
+pub fn new_id (service: &Service, now: i64) -> String {
+    service.id_generator.new_id(now)
+}
+
+fn check_rate_limit (req: &HttpRequest) -> Result<bool, HttpResponse> {
+    let valid_ip = match req.connection_info().remote_addr() {
+        Some(ip) => ip != "0.0.0.0",
+        _ => false
+    };
+    if valid_ip {
+        Ok(true)
+    } else {
+        Err(HttpResponse::TooManyRequests().finish())
+    }
+}
+
+pub fn not_found () -> HttpResponse {
+    HttpResponse::NotFound().body("404 DNE")
+}
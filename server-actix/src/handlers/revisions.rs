@@ -0,0 +1,128 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use tracing::debug;
+
+use crate::auth::{Caller, caller};
+use crate::models::{Service, Card, Comment, Revision, Role, Row};
+use crate::undo::Step;
+use super::{new_id, check_rate_limit};
+use super::cards::{card_for, edit_card_title};
+use super::comments::{comment_for, edit_comment_text};
+
+
+pub fn card_revision (service: &Service, card: &Card, editor: &String, editor_name: &String, at: i64) -> Revision {
+    Revision {
+        id: new_id(service, at),
+        board_id: card.board_id.clone(),
+        card_id: card.id.clone(),
+        comment_id: None,
+        text: card.title.clone(),
+        editor: editor.clone(),
+        editor_name: editor_name.clone(),
+        created_at: at,
+    }
+}
+
+pub fn comment_revision (service: &Service, comment: &Comment, editor: &String, editor_name: &String, at: i64) -> Revision {
+    Revision {
+        id: new_id(service, at),
+        board_id: comment.board_id.clone(),
+        card_id: comment.card_id.clone(),
+        comment_id: Some(comment.id.clone()),
+        text: comment.text.clone(),
+        editor: editor.clone(),
+        editor_name: editor_name.clone(),
+        created_at: at,
+    }
+}
+
+pub async fn list_card_revisions (service: &Service, card_id: &String) -> Result<Vec<Revision>, HttpResponse> {
+    match service.storage.list_card_revisions(card_id).await {
+        Ok(revisions) => Ok(revisions),
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("List revisions failed! {}", why))),
+    }
+}
+
+// the first edit keeps the text it replaced as well, as written by the author when created,
+// and whatever was added goes into the step, so undoing the edit takes it back out again
+pub async fn add_revisions (service: &Service, original: Revision, revision: Revision, mut step: Step) -> Result<Step, HttpResponse> {
+    let revisions = list_card_revisions(service, &revision.card_id).await?;
+    let mut added = vec![revision];
+    if !revisions.iter().any(|existing| existing.comment_id == original.comment_id) {
+        added.insert(0, original);
+    }
+
+    for revision in added.into_iter() {
+        if let Err(why) = service.storage.add_revision(&revision).await {
+            return Err(HttpResponse::InternalServerError().body(format!("Add revision failed! {}", why)))
+        }
+        step = step.added(Row::Revision(revision));
+    }
+    Ok(step)
+}
+
+// whatever is removed along with a card or comment, so undoing that puts its history back too
+pub async fn delete_revisions (service: &Service, revisions: Vec<Revision>, mut step: Step) -> Result<Step, HttpResponse> {
+    for revision in revisions.into_iter() {
+        if let Err(why) = service.storage.delete_revision(&revision.id).await {
+            return Err(HttpResponse::InternalServerError().body(format!("Delete revision failed! {}", why)))
+        }
+        step = step.removed(Row::Revision(revision));
+    }
+    Ok(step)
+}
+
+// every version of the card and its comments, oldest first
+async fn sorted_revisions (service: &Service, card_id: &String) -> Result<Vec<Revision>, HttpResponse> {
+    let mut revisions = list_card_revisions(service, card_id).await?;
+    revisions.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+    Ok(revisions)
+}
+
+pub async fn list_revisions (
+    req: HttpRequest,
+    service: web::Data<Service>,
+) -> Result<web::Json<Vec<Revision>>, HttpResponse> {
+    debug!("list revisions");
+    let caller = caller(&req, &service).await?;
+
+    let card_id = req.match_info().get("id").unwrap().to_string();
+    card_for(&service, &caller, &card_id, Role::Viewer).await?;
+
+    Ok(web::Json(sorted_revisions(&service, &card_id).await?))
+}
+
+async fn revision_for (service: &Service, caller: &Caller, card_id: &String, id: &String) -> Result<Revision, HttpResponse> {
+    card_for(service, caller, card_id, Role::Viewer).await?;
+    match service.storage.get_revision(id).await {
+        Ok(revision) if &revision.card_id == card_id => Ok(revision),
+        Ok(_) => Err(HttpResponse::NotFound().body(format!("Revision {} is not of card {}", id, card_id))),
+        Err(why) => Err(HttpResponse::NotFound().body(format!("Could not find revision for id {}: {}", id, why))),
+    }
+}
+
+// puts the text of an older revision back, as a new edit, so nothing in between is lost,
+// and answers with the revisions as they are now
+pub async fn restore_revision (
+    req: HttpRequest,
+    service: web::Data<Service>,
+) -> Result<web::Json<Vec<Revision>>, HttpResponse> {
+    debug!("restore revision");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let card_id = req.match_info().get("id").unwrap().to_string();
+    let id = req.match_info().get("revision_id").unwrap().to_string();
+    let revision = revision_for(&service, &caller, &card_id, &id).await?;
+
+    match &revision.comment_id {
+        Some(comment_id) => {
+            let comment = comment_for(&service, &caller, comment_id, Role::Participant).await?;
+            edit_comment_text(&service, &req, &caller, comment, &revision.text).await?;
+        },
+        None => {
+            let card = card_for(&service, &caller, &card_id, Role::Participant).await?;
+            edit_card_title(&service, &req, &caller, card, &revision.text).await?;
+        },
+    }
+    Ok(web::Json(sorted_revisions(&service, &card_id).await?))
+}
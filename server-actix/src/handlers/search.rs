@@ -0,0 +1,45 @@
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use tracing::debug;
+
+use crate::auth::{caller, caller_team_ids};
+use crate::models::{Service, SearchQuery, SearchHit};
+use super::check_rate_limit;
+
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+
+
+// only ever looks at boards on the caller's own teams
+pub async fn search (
+    req: HttpRequest,
+    query: web::Query<SearchQuery>,
+    service: web::Data<Service>,
+) -> Result<web::Json<Vec<SearchHit>>, HttpResponse> {
+    debug!("search");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+    if caller.board_id.is_some() {
+        return Err(HttpResponse::Forbidden().body("Guests may only access the board they were invited to"))
+    }
+
+    if query.q.trim().is_empty() {
+        return Err(HttpResponse::BadRequest().body("Invalid request for search!"))
+    }
+    let mut team_ids = caller_team_ids(&service, &caller).await?;
+    if let Some(team_id) = &query.team_id {
+        if !team_ids.contains(team_id) {
+            return Err(HttpResponse::Forbidden().body(
+                format!("User {} is not a member of team {}", caller.user_id, team_id)
+            ))
+        }
+        team_ids = vec![team_id.clone()];
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+
+    match service.storage.search(&team_ids, &query.q, limit).await {
+        Ok(hits) => Ok(web::Json(hits)),
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Search failed! {}", why))),
+    }
+}
@@ -0,0 +1,174 @@
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use tracing::debug;
+
+use crate::auth::{caller, check_board_role};
+use crate::models::{Service, CreateTag, Tag, TagCard, CardTag, Role, Row};
+use crate::audit::{audit, Change, ACTION_CREATE, ACTION_DELETE, ENTITY_TAG, ENTITY_CARD_TAG};
+use crate::undo::Step;
+use super::{new_id, check_rate_limit};
+use super::cards::card_for;
+
+
+pub async fn add_tag (
+    req: HttpRequest,
+    payload: web::Json<CreateTag>,
+    service: web::Data<Service>,
+) -> Result<web::Json<Tag>, HttpResponse> {
+    debug!("add tag");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+    check_board_role(&service, &caller, &payload.board_id, Role::Participant).await?;
+
+    if payload.title.trim().is_empty() {
+        return Err(HttpResponse::BadRequest().body("Invalid request for create tag!"))
+    }
+
+    let now = service.time_provider.unix_ts_ms();
+    let tag = Tag {
+        id: new_id(&service, now),
+        board_id: payload.board_id.clone(),
+        title: payload.title.clone(),
+        created_at: now,
+    };
+
+    match service.storage.add_tag(&tag).await {
+        Ok(_) => {
+            audit(&service, &req, &caller, Change::new(ACTION_CREATE, ENTITY_TAG, &tag.id, Some(&tag.board_id)).after(&tag)).await;
+            service.undo.remember(&tag.board_id, &caller.user_id, Step::new().added(Row::Tag(tag.clone())));
+            Ok(web::Json(tag))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Add tag failed! {}", why))),
+    }
+}
+
+pub async fn list_tags (
+    req: HttpRequest,
+    service: web::Data<Service>,
+) -> Result<web::Json<Vec<Tag>>, HttpResponse> {
+    debug!("list tags");
+    let caller = caller(&req, &service).await?;
+
+    let board_id = req.match_info().get("id").unwrap().to_string();
+    check_board_role(&service, &caller, &board_id, Role::Viewer).await?;
+
+    match service.storage.list_board_tags(&board_id).await {
+        Ok(tags) => Ok(web::Json(tags)),
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("List tags failed! {}", why))),
+    }
+}
+
+// takes the tag off every card as well
+pub async fn delete_tag (
+    req: HttpRequest,
+    service: web::Data<Service>
+) -> Result<HttpResponse, HttpResponse> {
+    debug!("delete tag");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let tag = match service.storage.get_tag(&id).await {
+        Ok(tag) => tag,
+        Err(why) => return Err(HttpResponse::NotFound().body(
+            format!("Could not find tag for id {}: {}", id, why)
+        )),
+    };
+    check_board_role(&service, &caller, &tag.board_id, Role::Participant).await?;
+    let mut step = Step::new();
+
+    let card_tags = match service.storage.list_board_card_tags(&tag.board_id).await {
+        Ok(card_tags) => card_tags,
+        Err(why) => return Err(HttpResponse::InternalServerError().body(format!("List card tags failed! {}", why))),
+    };
+    for card_tag in card_tags.iter().filter(|card_tag| card_tag.tag_id == id) {
+        if let Err(why) = service.storage.delete_card_tag(&card_tag.id).await {
+            return Err(HttpResponse::InternalServerError().body(format!("Delete card tag failed! {}", why)))
+        }
+        audit(&service, &req, &caller, Change::new(ACTION_DELETE, ENTITY_CARD_TAG, &card_tag.id, Some(&card_tag.board_id)).before(card_tag)).await;
+        step = step.removed(Row::CardTag(card_tag.clone()));
+    }
+
+    match service.storage.delete_tag(&id).await {
+        Ok(_) => {
+            audit(&service, &req, &caller, Change::new(ACTION_DELETE, ENTITY_TAG, &id, Some(&tag.board_id)).before(&tag)).await;
+            service.undo.remember(&tag.board_id, &caller.user_id, step.removed(Row::Tag(tag.clone())));
+            Ok(HttpResponse::Ok().body("Tag deleted"))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Delete tag failed! {}", why))),
+    }
+}
+
+pub async fn tag_card (
+    req: HttpRequest,
+    payload: web::Json<TagCard>,
+    service: web::Data<Service>,
+) -> Result<web::Json<CardTag>, HttpResponse> {
+    debug!("tag card");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let card_id = req.match_info().get("id").unwrap().to_string();
+    let card = card_for(&service, &caller, &card_id, Role::Participant).await?;
+
+    match service.storage.get_tag(&payload.tag_id).await {
+        Ok(tag) if tag.board_id == card.board_id => (),
+        _ => return Err(HttpResponse::BadRequest().body(format!("No tag {} on this board!", payload.tag_id))),
+    }
+    let card_tags = match service.storage.list_board_card_tags(&card.board_id).await {
+        Ok(card_tags) => card_tags,
+        Err(why) => return Err(HttpResponse::InternalServerError().body(format!("List card tags failed! {}", why))),
+    };
+    if let Some(existing) = card_tags.into_iter().find(|card_tag| card_tag.card_id == card_id && card_tag.tag_id == payload.tag_id) {
+        return Ok(web::Json(existing))
+    }
+
+    let now = service.time_provider.unix_ts_ms();
+    let card_tag = CardTag {
+        id: new_id(&service, now),
+        board_id: card.board_id,
+        card_id: card.id,
+        tag_id: payload.tag_id.clone(),
+        created_at: now,
+    };
+
+    match service.storage.add_card_tag(&card_tag).await {
+        Ok(_) => {
+            audit(&service, &req, &caller, Change::new(ACTION_CREATE, ENTITY_CARD_TAG, &card_tag.id, Some(&card_tag.board_id)).after(&card_tag)).await;
+            service.undo.remember(&card_tag.board_id, &caller.user_id, Step::new().added(Row::CardTag(card_tag.clone())));
+            Ok(web::Json(card_tag))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Add card tag failed! {}", why))),
+    }
+}
+
+pub async fn untag_card (
+    req: HttpRequest,
+    service: web::Data<Service>
+) -> Result<HttpResponse, HttpResponse> {
+    debug!("untag card");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let card_id = req.match_info().get("id").unwrap().to_string();
+    let tag_id = req.match_info().get("tag_id").unwrap().to_string();
+    let card = card_for(&service, &caller, &card_id, Role::Participant).await?;
+
+    let card_tags = match service.storage.list_board_card_tags(&card.board_id).await {
+        Ok(card_tags) => card_tags,
+        Err(why) => return Err(HttpResponse::InternalServerError().body(format!("List card tags failed! {}", why))),
+    };
+    let card_tag = match card_tags.into_iter().find(|card_tag| card_tag.card_id == card_id && card_tag.tag_id == tag_id) {
+        Some(card_tag) => card_tag,
+        None => return Err(HttpResponse::NotFound().body(format!("Card {} is not tagged {}", card_id, tag_id))),
+    };
+
+    match service.storage.delete_card_tag(&card_tag.id).await {
+        Ok(_) => {
+            audit(&service, &req, &caller, Change::new(ACTION_DELETE, ENTITY_CARD_TAG, &card_tag.id, Some(&card_tag.board_id)).before(&card_tag)).await;
+            service.undo.remember(&card_tag.board_id, &caller.user_id, Step::new().removed(Row::CardTag(card_tag.clone())));
+            Ok(HttpResponse::Ok().body("Card untagged"))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Delete card tag failed! {}", why))),
+    }
+}
@@ -0,0 +1,234 @@
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use tracing::debug;
+
+use crate::auth::{caller, caller_team_ids, check_team_role};
+use crate::models::{Service, CreateTeam, Team, UpsertMembership, Membership, Role, Row, IfEmpty};
+use crate::audit::{audit, Change, ACTION_CREATE, ACTION_UPDATE, ACTION_DELETE, ENTITY_TEAM, ENTITY_MEMBERSHIP};
+use super::{new_id, check_rate_limit};
+
+
+pub async fn add_team (
+    req: HttpRequest,
+    payload: web::Json<CreateTeam>,
+    service: web::Data<Service>,
+) -> Result<web::Json<Team>, HttpResponse> {
+    debug!("add team");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    if payload.name.trim().is_empty() {
+        return Err(HttpResponse::BadRequest().body("Invalid request for create team!"))
+    }
+
+    let now = service.time_provider.unix_ts_ms();
+    let team = Team {
+        id: new_id(&service, now),
+        name: payload.name.clone(),
+        created_at: now,
+    };
+    // whoever creates the team owns it, otherwise nobody could ever manage it
+    let membership = Membership {
+        id: new_id(&service, now),
+        team_id: team.id.clone(),
+        user_id: caller.user_id.clone(),
+        role: Role::Owner,
+        created_at: now,
+    };
+
+    match service.storage.add_team_with_owner(&team, &membership).await {
+        Ok(_) => {
+            audit(&service, &req, &caller, Change::new(ACTION_CREATE, ENTITY_TEAM, &team.id, None).after(&team)).await;
+            audit(&service, &req, &caller, Change::new(ACTION_CREATE, ENTITY_MEMBERSHIP, &membership.id, None).after(&membership)).await;
+            Ok(web::Json(team))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Add team failed! {}", why))),
+    }
+}
+
+pub async fn list_teams (
+    req: HttpRequest,
+    service: web::Data<Service>,
+) -> Result<web::Json<Vec<Team>>, HttpResponse> {
+    debug!("list teams");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+    let team_ids = caller_team_ids(&service, &caller).await?;
+
+    match service.storage.list_teams(&team_ids).await {
+        Ok(teams) => Ok(web::Json(teams)),
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("List teams failed! {}", why))),
+    }
+}
+
+pub async fn get_team (
+    req: HttpRequest,
+    service: web::Data<Service>
+) -> Result<web::Json<Team>, HttpResponse> {
+    debug!("get team");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    check_team_role(&service, &caller, &id, Role::Viewer).await?;
+
+    match service.storage.get_team(&id).await {
+        Ok(item) => Ok(web::Json(item)),
+        Err(why) => Err(HttpResponse::NotFound().body(
+            format!("Could not find team for id {}: {}",  id, why)
+        ))
+    }
+}
+
+pub async fn delete_team (
+    req: HttpRequest,
+    service: web::Data<Service>
+) -> Result<HttpResponse, HttpResponse> {
+    debug!("delete team");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    check_team_role(&service, &caller, &id, Role::Owner).await?;
+    let team = match service.storage.get_team(&id).await {
+        Ok(team) => team,
+        Err(why) => return Err(HttpResponse::NotFound().body(
+            format!("Could not find team for id {}: {}", id, why)
+        )),
+    };
+
+    // its memberships go with it, but only while it has no boards left
+    match service.storage.delete_team_cascade(&id).await {
+        Ok(IfEmpty::Deleted(changes)) => {
+            for change in changes.iter() {
+                if let Some(Row::Membership(membership)) = &change.before {
+                    audit(&service, &req, &caller, Change::new(ACTION_DELETE, ENTITY_MEMBERSHIP, &membership.id, None).before(membership)).await;
+                }
+            }
+            audit(&service, &req, &caller, Change::new(ACTION_DELETE, ENTITY_TEAM, &id, None).before(&team)).await;
+            Ok(HttpResponse::Ok().body("Team deleted"))
+        },
+        Ok(IfEmpty::NotEmpty(what, count)) => Err(HttpResponse::Conflict().body(
+            format!("Team {} still has {} {}", id, count, what)
+        )),
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Delete team failed! {}", why))),
+    }
+}
+
+pub async fn list_members (
+    req: HttpRequest,
+    service: web::Data<Service>,
+) -> Result<web::Json<Vec<Membership>>, HttpResponse> {
+    debug!("list members");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let team_id = req.match_info().get("id").unwrap().to_string();
+    check_team_role(&service, &caller, &team_id, Role::Viewer).await?;
+
+    match service.storage.list_team_memberships(&team_id).await {
+        Ok(memberships) => Ok(web::Json(memberships)),
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("List members failed! {}", why))),
+    }
+}
+
+// a team must always keep at least one owner
+fn is_last_owner (memberships: &Vec<Membership>, user_id: &String) -> bool {
+    let owners: Vec<&Membership> = memberships.iter().filter(|m| m.role == Role::Owner).collect();
+    owners.len() == 1 && &owners[0].user_id == user_id
+}
+
+pub async fn put_member (
+    req: HttpRequest,
+    payload: web::Json<UpsertMembership>,
+    service: web::Data<Service>,
+) -> Result<web::Json<Membership>, HttpResponse> {
+    debug!("put member");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let team_id = req.match_info().get("id").unwrap().to_string();
+    check_team_role(&service, &caller, &team_id, Role::Owner).await?;
+
+    if payload.user_id.trim().is_empty() {
+        return Err(HttpResponse::BadRequest().body("Invalid request for put member!"))
+    }
+
+    let memberships = match service.storage.list_team_memberships(&team_id).await {
+        Ok(memberships) => memberships,
+        Err(why) => return Err(HttpResponse::InternalServerError().body(format!("List members failed! {}", why))),
+    };
+
+    match memberships.iter().find(|m| m.user_id == payload.user_id) {
+        Some(existing) => {
+            if payload.role != Role::Owner && is_last_owner(&memberships, &existing.user_id) {
+                return Err(HttpResponse::Conflict().body("Cannot demote the last owner of a team"))
+            }
+            let membership = Membership {
+                role: payload.role,
+                ..existing.clone()
+            };
+            match service.storage.update_membership(&membership).await {
+                Ok(_) => {
+                    audit(&service, &req, &caller, Change::new(ACTION_UPDATE, ENTITY_MEMBERSHIP, &membership.id, None).before(existing).after(&membership)).await;
+                    Ok(web::Json(membership))
+                },
+                Err(why) => Err(HttpResponse::InternalServerError().body(format!("Update member failed! {}", why))),
+            }
+        },
+        None => {
+            let now = service.time_provider.unix_ts_ms();
+            let membership = Membership {
+                id: new_id(&service, now),
+                team_id: team_id,
+                user_id: payload.user_id.clone(),
+                role: payload.role,
+                created_at: now,
+            };
+            match service.storage.add_membership(&membership).await {
+                Ok(_) => {
+                    audit(&service, &req, &caller, Change::new(ACTION_CREATE, ENTITY_MEMBERSHIP, &membership.id, None).after(&membership)).await;
+                    Ok(web::Json(membership))
+                },
+                Err(why) => Err(HttpResponse::InternalServerError().body(format!("Add member failed! {}", why))),
+            }
+        },
+    }
+}
+
+pub async fn delete_member (
+    req: HttpRequest,
+    service: web::Data<Service>
+) -> Result<HttpResponse, HttpResponse> {
+    debug!("delete member");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let team_id = req.match_info().get("id").unwrap().to_string();
+    let member_id = req.match_info().get("user_id").unwrap().to_string();
+    // anyone may leave a team on their own, only owners may remove others
+    let role = if member_id == caller.user_id { Role::Viewer } else { Role::Owner };
+    check_team_role(&service, &caller, &team_id, role).await?;
+
+    let memberships = match service.storage.list_team_memberships(&team_id).await {
+        Ok(memberships) => memberships,
+        Err(why) => return Err(HttpResponse::InternalServerError().body(format!("List members failed! {}", why))),
+    };
+    let membership = match memberships.iter().find(|m| m.user_id == member_id) {
+        Some(membership) => membership,
+        None => return Err(HttpResponse::NotFound().body(
+            format!("Could not find member {} in team {}", member_id, team_id)
+        )),
+    };
+    if is_last_owner(&memberships, &member_id) {
+        return Err(HttpResponse::Conflict().body("Cannot remove the last owner of a team"))
+    }
+
+    match service.storage.delete_membership(&membership.id).await {
+        Ok(_) => {
+            audit(&service, &req, &caller, Change::new(ACTION_DELETE, ENTITY_MEMBERSHIP, &membership.id, None).before(membership)).await;
+            Ok(HttpResponse::Ok().body("Member removed"))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Delete member failed! {}", why))),
+    }
+}
@@ -0,0 +1,108 @@
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use tracing::debug;
+
+use crate::auth::{Caller, caller, check_board_role};
+use crate::models::{Service, Row, Role};
+use crate::audit::{audit, Change, ACTION_UNDO, ACTION_REDO};
+use crate::undo::{apply, Step, RowChange};
+use super::check_rate_limit;
+
+
+// the entity alone, as every other audit entry has it, without the kind it is tagged with
+fn data (row: &Row) -> serde_json::Value {
+    serde_json::to_value(row).ok()
+        .and_then(|tagged| tagged.get("data").cloned())
+        .unwrap_or_default()
+}
+
+// every row it put back goes into the audit log too, so the board history stays whole
+async fn apply_step (
+    service: &Service,
+    req: &HttpRequest,
+    caller: &Caller,
+    board_id: &String,
+    action: &'static str,
+    step: &Step,
+) -> Result<bool, HttpResponse> {
+    let applied = match apply(service, step).await {
+        Ok(applied) => applied,
+        Err(why) => return Err(HttpResponse::InternalServerError().body(format!("Apply {} failed! {}", action, why))),
+    };
+    if applied {
+        for change in step.changes().iter() {
+            if let Some(row) = change.before.as_ref().or(change.after.as_ref()) {
+                let mut entry = Change::new(action, row.kind(), row.id(), Some(board_id));
+                if let Some(before) = &change.before {
+                    entry = entry.before(&data(before));
+                }
+                if let Some(after) = &change.after {
+                    entry = entry.after(&data(after));
+                }
+                audit(service, req, caller, entry).await;
+            }
+        }
+    }
+    Ok(applied)
+}
+
+// takes back the caller's own last change on the board, exactly as it was, positions and all
+pub async fn undo_board (
+    req: HttpRequest,
+    service: web::Data<Service>,
+) -> Result<web::Json<Vec<RowChange>>, HttpResponse> {
+    debug!("undo board");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let board_id = req.match_info().get("id").unwrap().to_string();
+    check_board_role(&service, &caller, &board_id, Role::Participant).await?;
+
+    let step = match service.undo.pop_undo(&board_id, &caller.user_id) {
+        Some(step) => step,
+        None => return Err(HttpResponse::Conflict().body("Nothing to undo")),
+    };
+    let inverse = step.inverse();
+    match apply_step(&service, &req, &caller, &board_id, ACTION_UNDO, &inverse).await {
+        Ok(true) => {
+            service.undo.undone(&board_id, &caller.user_id, step);
+            Ok(web::Json(inverse.changes().clone()))
+        },
+        // someone else changed the same things since, so this step can never apply again
+        Ok(false) => Err(HttpResponse::Conflict().body("Changed by someone else since, cannot undo")),
+        // none of it went through, so it is still there to try again
+        Err(why) => {
+            service.undo.redone(&board_id, &caller.user_id, step);
+            Err(why)
+        },
+    }
+}
+
+pub async fn redo_board (
+    req: HttpRequest,
+    service: web::Data<Service>,
+) -> Result<web::Json<Vec<RowChange>>, HttpResponse> {
+    debug!("redo board");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let board_id = req.match_info().get("id").unwrap().to_string();
+    check_board_role(&service, &caller, &board_id, Role::Participant).await?;
+
+    let step = match service.undo.pop_redo(&board_id, &caller.user_id) {
+        Some(step) => step,
+        None => return Err(HttpResponse::Conflict().body("Nothing to redo")),
+    };
+    match apply_step(&service, &req, &caller, &board_id, ACTION_REDO, &step).await {
+        Ok(true) => {
+            let changes = step.changes().clone();
+            service.undo.redone(&board_id, &caller.user_id, step);
+            Ok(web::Json(changes))
+        },
+        Ok(false) => Err(HttpResponse::Conflict().body("Changed by someone else since, cannot redo")),
+        Err(why) => {
+            service.undo.undone(&board_id, &caller.user_id, step);
+            Err(why)
+        },
+    }
+}
@@ -0,0 +1,114 @@
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use tracing::debug;
+
+use crate::auth::{Caller, caller, check_board_role};
+use crate::models::{Service, Vote, Role, Row};
+use crate::audit::{audit, Change, ACTION_CREATE, ACTION_DELETE, ENTITY_VOTE};
+use crate::undo::Step;
+use super::{new_id, check_rate_limit};
+use super::cards::card_for;
+use super::groups::group_for;
+
+
+async fn add_vote (service: &Service, req: &HttpRequest, caller: &Caller, vote: Vote) -> Result<web::Json<Vote>, HttpResponse> {
+    match service.storage.add_vote(&vote).await {
+        Ok(_) => {
+            audit(service, req, caller, Change::new(ACTION_CREATE, ENTITY_VOTE, &vote.id, Some(&vote.board_id)).after(&vote)).await;
+            service.undo.remember(&vote.board_id, &caller.user_id, Step::new().added(Row::Vote(vote.clone())));
+            Ok(web::Json(vote))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Add vote failed! {}", why))),
+    }
+}
+
+pub async fn vote_card (
+    req: HttpRequest,
+    service: web::Data<Service>,
+) -> Result<web::Json<Vote>, HttpResponse> {
+    debug!("vote card");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let card_id = req.match_info().get("id").unwrap().to_string();
+    let card = card_for(&service, &caller, &card_id, Role::Participant).await?;
+
+    let now = service.time_provider.unix_ts_ms();
+    add_vote(&service, &req, &caller, Vote {
+        id: new_id(&service, now),
+        board_id: card.board_id,
+        card_id: Some(card.id),
+        group_id: None,
+        author: caller.user_id.clone(),
+        created_at: now,
+    }).await
+}
+
+pub async fn vote_group (
+    req: HttpRequest,
+    service: web::Data<Service>,
+) -> Result<web::Json<Vote>, HttpResponse> {
+    debug!("vote group");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let group_id = req.match_info().get("id").unwrap().to_string();
+    let group = group_for(&service, &caller, &group_id, Role::Participant).await?;
+
+    let now = service.time_provider.unix_ts_ms();
+    add_vote(&service, &req, &caller, Vote {
+        id: new_id(&service, now),
+        board_id: group.board_id,
+        card_id: None,
+        group_id: Some(group.id),
+        author: caller.user_id.clone(),
+        created_at: now,
+    }).await
+}
+
+pub async fn list_votes (
+    req: HttpRequest,
+    service: web::Data<Service>,
+) -> Result<web::Json<Vec<Vote>>, HttpResponse> {
+    debug!("list votes");
+    let caller = caller(&req, &service).await?;
+
+    let board_id = req.match_info().get("id").unwrap().to_string();
+    check_board_role(&service, &caller, &board_id, Role::Viewer).await?;
+
+    match service.storage.list_board_votes(&board_id).await {
+        Ok(votes) => Ok(web::Json(votes)),
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("List votes failed! {}", why))),
+    }
+}
+
+pub async fn delete_vote (
+    req: HttpRequest,
+    service: web::Data<Service>
+) -> Result<HttpResponse, HttpResponse> {
+    debug!("delete vote");
+    check_rate_limit(&req)?;
+    let caller = caller(&req, &service).await?;
+
+    let id = req.match_info().get("id").unwrap().to_string();
+    let vote = match service.storage.get_vote(&id).await {
+        Ok(vote) => vote,
+        Err(why) => return Err(HttpResponse::NotFound().body(
+            format!("Could not find vote for id {}: {}", id, why)
+        )),
+    };
+    // votes can only be taken back by whoever cast them
+    check_board_role(&service, &caller, &vote.board_id, Role::Participant).await?;
+    if vote.author != caller.user_id {
+        return Err(HttpResponse::Forbidden().body(format!("Vote {} was cast by someone else", id)))
+    }
+
+    match service.storage.delete_vote(&id).await {
+        Ok(_) => {
+            audit(&service, &req, &caller, Change::new(ACTION_DELETE, ENTITY_VOTE, &id, Some(&vote.board_id)).before(&vote)).await;
+            service.undo.remember(&vote.board_id, &caller.user_id, Step::new().removed(Row::Vote(vote.clone())));
+            Ok(HttpResponse::Ok().body("Vote deleted"))
+        },
+        Err(why) => Err(HttpResponse::InternalServerError().body(format!("Delete vote failed! {}", why))),
+    }
+}
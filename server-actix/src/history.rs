@@ -0,0 +1,141 @@
+use serde::de::DeserializeOwned;
+
+use crate::models::{MyError, AuditEntry, Board, BoardContents, Column, Card, CardGroup, Vote, Tag, CardTag, Comment, ActionItem};
+use crate::audit::{
+    ACTION_DELETE, ACTION_PURGE, ACTION_CLONE, ACTION_IMPORT,
+    ENTITY_BOARD, ENTITY_COLUMN, ENTITY_CARD, ENTITY_GROUP, ENTITY_VOTE, ENTITY_TAG, ENTITY_CARD_TAG, ENTITY_COMMENT, ENTITY_ACTION_ITEM,
+};
+
+
+// rebuilds a board from its audit trail, oldest entry first, each one replacing
+// whatever it touched with its after snapshot, or removing it on delete
+// children that went along with a delete are dropped by the same rules as the foreign keys,
+// since only the row that was asked for is audited
+
+fn parse<T : DeserializeOwned> (entry: &AuditEntry) -> Result<T, MyError> {
+    match &entry.after {
+        Some(after) => serde_json::from_value(after.clone())
+            .map_err(|why| format!("Audit entry {} has an unexpected {}: {}", entry.id, entry.entity_type, why)),
+        None => Err(format!("Audit entry {} has nothing after {} {}", entry.id, entry.action, entry.entity_type)),
+    }
+}
+
+fn upsert<T> (items: &mut Vec<T>, item: T, id: fn(&T) -> &String) {
+    match items.iter_mut().find(|existing| id(existing) == id(&item)) {
+        Some(existing) => *existing = item,
+        None => items.push(item),
+    }
+}
+
+fn empty (board: Board) -> BoardContents {
+    BoardContents {
+        board: board,
+        columns: vec![],
+        groups: vec![],
+        cards: vec![],
+        votes: vec![],
+        tags: vec![],
+        card_tags: vec![],
+        comments: vec![],
+        action_items: vec![],
+    }
+}
+
+fn delete_card (contents: &mut BoardContents, id: &String) {
+    contents.cards.retain(|card| &card.id != id);
+    contents.votes.retain(|vote| vote.card_id.as_ref() != Some(id));
+    contents.card_tags.retain(|card_tag| &card_tag.card_id != id);
+    contents.comments.retain(|comment| &comment.card_id != id);
+    for card in contents.cards.iter_mut().filter(|card| card.merged_into.as_ref() == Some(id)) {
+        card.merged_into = None;
+    }
+}
+
+fn delete_group (contents: &mut BoardContents, id: &String) {
+    contents.groups.retain(|group| &group.id != id);
+    contents.votes.retain(|vote| vote.group_id.as_ref() != Some(id));
+    for card in contents.cards.iter_mut().filter(|card| card.group_id.as_ref() == Some(id)) {
+        card.group_id = None;
+    }
+}
+
+fn delete (contents: &mut BoardContents, entity_type: &str, id: &String) {
+    match entity_type {
+        ENTITY_COLUMN => {
+            let groups: Vec<String> = contents.groups.iter().filter(|group| &group.column_id == id).map(|group| group.id.clone()).collect();
+            for group in groups.iter() {
+                delete_group(contents, group);
+            }
+            let cards: Vec<String> = contents.cards.iter().filter(|card| &card.column_id == id).map(|card| card.id.clone()).collect();
+            for card in cards.iter() {
+                delete_card(contents, card);
+            }
+            contents.columns.retain(|column| &column.id != id);
+        },
+        ENTITY_CARD => delete_card(contents, id),
+        ENTITY_GROUP => delete_group(contents, id),
+        ENTITY_VOTE => contents.votes.retain(|vote| &vote.id != id),
+        ENTITY_TAG => {
+            contents.tags.retain(|tag| &tag.id != id);
+            contents.card_tags.retain(|card_tag| &card_tag.tag_id != id);
+        },
+        ENTITY_CARD_TAG => contents.card_tags.retain(|card_tag| &card_tag.id != id),
+        ENTITY_COMMENT => contents.comments.retain(|comment| &comment.id != id),
+        ENTITY_ACTION_ITEM => contents.action_items.retain(|action_item| &action_item.id != id),
+        _ => (),
+    }
+}
+
+fn upsert_entry (contents: &mut BoardContents, entry: &AuditEntry) -> Result<(), MyError> {
+    match entry.entity_type.as_str() {
+        ENTITY_COLUMN => upsert(&mut contents.columns, parse::<Column>(entry)?, |item| &item.id),
+        ENTITY_CARD => upsert(&mut contents.cards, parse::<Card>(entry)?, |item| &item.id),
+        ENTITY_GROUP => upsert(&mut contents.groups, parse::<CardGroup>(entry)?, |item| &item.id),
+        ENTITY_VOTE => upsert(&mut contents.votes, parse::<Vote>(entry)?, |item| &item.id),
+        ENTITY_TAG => upsert(&mut contents.tags, parse::<Tag>(entry)?, |item| &item.id),
+        ENTITY_CARD_TAG => upsert(&mut contents.card_tags, parse::<CardTag>(entry)?, |item| &item.id),
+        ENTITY_COMMENT => upsert(&mut contents.comments, parse::<Comment>(entry)?, |item| &item.id),
+        ENTITY_ACTION_ITEM => upsert(&mut contents.action_items, parse::<ActionItem>(entry)?, |item| &item.id),
+        _ => (),
+    }
+    Ok(())
+}
+
+// none while the board does not exist (yet, or any more)
+pub fn apply (state: Option<BoardContents>, entry: &AuditEntry) -> Result<Option<BoardContents>, MyError> {
+    if entry.entity_type == ENTITY_BOARD {
+        return match entry.action.as_str() {
+            ACTION_PURGE => Ok(None),
+            // these bring the whole board along at once
+            ACTION_CLONE | ACTION_IMPORT => Ok(Some(parse::<BoardContents>(entry)?)),
+            _ => {
+                let board = parse::<Board>(entry)?;
+                Ok(Some(match state {
+                    Some(contents) => BoardContents { board: board, ..contents },
+                    None => empty(board),
+                }))
+            },
+        }
+    }
+
+    let mut contents = match state {
+        Some(contents) => contents,
+        None => return Ok(None),
+    };
+    // undo and redo can take a row away without it being a delete
+    if entry.action == ACTION_DELETE || entry.after.is_none() {
+        delete(&mut contents, &entry.entity_type, &entry.entity_id);
+    } else {
+        upsert_entry(&mut contents, entry)?;
+    }
+    Ok(Some(contents))
+}
+
+// entries for the one board, in the order they happened
+pub fn board_at (entries: &Vec<AuditEntry>) -> Result<Option<BoardContents>, MyError> {
+    let mut state = None;
+    for entry in entries.iter() {
+        state = apply(state, entry)?;
+    }
+    Ok(state)
+}
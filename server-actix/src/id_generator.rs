@@ -0,0 +1,45 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use rand::Rng;
+use dyn_clonable::clonable;
+
+
+// ids lead with the time they were made at, so they sort roughly by age
+#[clonable]
+pub trait IdGenerator : Clone + Send + Sync {
+    fn new_id (&self, now: i64) -> String;
+}
+
+#[derive(Debug, Clone)]
+pub struct RandomIdGenerator {
+}
+
+impl IdGenerator for RandomIdGenerator {
+    fn new_id (&self, now: i64) -> String {
+        // https://rust-lang-nursery.github.io/rust-cookbook/algorithms/randomness.html
+        let n: u64 = rand::thread_rng().gen();
+        format!("{:016x}{:016x}", now, n)
+    }
+}
+
+// counts up instead, for responses that are the same every run
+// clones share the count, like every worker shares the one service
+#[derive(Debug, Clone)]
+pub struct SequentialIdGenerator {
+    next: Arc<AtomicU64>,
+}
+
+impl SequentialIdGenerator {
+    pub fn new (first: u64) -> Self {
+        SequentialIdGenerator {
+            next: Arc::new(AtomicU64::new(first)),
+        }
+    }
+}
+
+impl IdGenerator for SequentialIdGenerator {
+    fn new_id (&self, now: i64) -> String {
+        let n = self.next.fetch_add(1, Ordering::SeqCst);
+        format!("{:016x}{:016x}", now, n)
+    }
+}
@@ -0,0 +1,194 @@
+
+use crate::models::{MyError, Column, Card, CardTag};
+use super::{Source, Imported, empty_board, tag_id, cast_votes};
+
+
+const DEFAULT_TITLE: &'static str = "Imported board";
+
+const HEADERS_COLUMN: [&'static str; 3] = ["column", "list", "category"];
+const HEADERS_TITLE: [&'static str; 3] = ["card", "title", "text"];
+const HEADERS_AUTHOR: [&'static str; 2] = ["author", "author_name"];
+const HEADERS_VOTES: [&'static str; 1] = ["votes"];
+const HEADERS_TAGS: [&'static str; 2] = ["tags", "labels"];
+
+
+fn header_at (headers: &csv::StringRecord, names: &[&'static str]) -> Option<usize> {
+    headers.iter().position(|header| names.contains(&header.to_lowercase().as_str()))
+}
+
+// one card per row, with a header row naming at least the column and card text:
+//   column,card,author,votes,tags
+//   Went well,Shipped on time,alice,3,release;team
+// columns come out in the order they first show up
+pub fn parse (body: &[u8], source: &Source) -> Result<Imported, MyError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(body);
+    let headers = reader.headers().map_err(|why| format!("Could not read csv headers: {}", why))?.clone();
+
+    let column_at = header_at(&headers, &HEADERS_COLUMN)
+        .ok_or_else(|| format!("No column header, expected one of {}", HEADERS_COLUMN.join(", ")))?;
+    let title_at = header_at(&headers, &HEADERS_TITLE)
+        .ok_or_else(|| format!("No card header, expected one of {}", HEADERS_TITLE.join(", ")))?;
+    let author_at = header_at(&headers, &HEADERS_AUTHOR);
+    let votes_at = header_at(&headers, &HEADERS_VOTES);
+    let tags_at = header_at(&headers, &HEADERS_TAGS);
+
+    let title = source.title.clone().unwrap_or_else(|| String::from(DEFAULT_TITLE));
+    let mut contents = empty_board(title, source);
+    let mut warnings = vec![];
+    let now = source.now;
+
+    for (n, record) in reader.records().enumerate() {
+        // the header is line 1
+        let line = n + 2;
+        let record = record.map_err(|why| format!("Could not read csv line {}: {}", line, why))?;
+        let field = |at: Option<usize>| at.and_then(|at| record.get(at)).unwrap_or("");
+
+        let column_title = field(Some(column_at));
+        let card_title = field(Some(title_at));
+        if column_title.is_empty() || card_title.is_empty() {
+            warnings.push(format!("Skipped line {}, it has no column or card text", line));
+            continue
+        }
+
+        let column_id = match contents.columns.iter().find(|column| column.title == column_title) {
+            Some(column) => column.id.clone(),
+            None => {
+                let column = Column {
+                    id: format!("column-{}", contents.columns.len()),
+                    board_id: contents.board.id.clone(),
+                    title: column_title.to_string(),
+                    position: contents.columns.len() as i32,
+                    created_at: now,
+                };
+                let id = column.id.clone();
+                contents.columns.push(column);
+                id
+            },
+        };
+
+        let author_name = match field(author_at) {
+            "" => source.author_name.clone(),
+            author_name => author_name.to_string(),
+        };
+        let card = Card {
+            id: format!("card-{}", contents.cards.len()),
+            board_id: contents.board.id.clone(),
+            column_id: column_id.clone(),
+            group_id: None,
+            merged_into: None,
+            title: card_title.to_string(),
+            author: source.author.clone(),
+            author_name: author_name,
+            position: contents.cards.iter().filter(|card| card.column_id == column_id).count() as i32,
+            created_at: now,
+        };
+
+        let votes = match field(votes_at) {
+            "" => 0,
+            votes => votes.parse::<usize>().unwrap_or_else(|_| {
+                warnings.push(format!("Ignored votes '{}' on line {}, not a number", votes, line));
+                0
+            }),
+        };
+        cast_votes(&mut contents, &mut warnings, &card.id, &card.title, votes, source);
+
+        let tag_titles: Vec<String> = field(tags_at)
+            .split([';', ','])
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        for tag_title in tag_titles {
+            let tag_id = tag_id(&mut contents, &tag_title, now);
+            if contents.card_tags.iter().any(|card_tag| card_tag.card_id == card.id && card_tag.tag_id == tag_id) {
+                continue
+            }
+            contents.card_tags.push(CardTag {
+                id: format!("card-tag-{}", contents.card_tags.len()),
+                board_id: contents.board.id.clone(),
+                card_id: card.id.clone(),
+                tag_id: tag_id,
+                created_at: now,
+            });
+        }
+
+        contents.cards.push(card);
+    }
+
+    Ok(Imported {
+        contents: contents,
+        warnings: warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source () -> Source {
+        Source { title: None, author: "alice".into(), author_name: "Alice".into(), now: 7, limit_bytes: 1_000_000 }
+    }
+
+    fn titles<'a> (items: impl Iterator<Item = &'a String>) -> Vec<&'a str> {
+        items.map(|title| title.as_str()).collect()
+    }
+
+    #[test]
+    fn takes_any_header_name_in_any_case () {
+        let body = "Title,Author_Name,List,Labels\nShipped,Bob,Went well,\nSlow CI,,To improve,\nDemos,,Went well,\n";
+        let imported = parse(body.as_bytes(), &source()).unwrap();
+        let contents = &imported.contents;
+
+        assert_eq!(contents.board.title, DEFAULT_TITLE);
+        assert_eq!(titles(contents.columns.iter().map(|column| &column.title)), vec!["Went well", "To improve"]);
+        assert_eq!(titles(contents.cards.iter().map(|card| &card.title)), vec!["Shipped", "Slow CI", "Demos"]);
+        // positions count within each column
+        assert_eq!(contents.cards.iter().map(|card| card.position).collect::<Vec<i32>>(), vec![0, 0, 1]);
+        assert_eq!(titles(contents.cards.iter().map(|card| &card.author_name)), vec!["Bob", "Alice", "Alice"]);
+        assert!(imported.warnings.is_empty());
+    }
+
+    #[test]
+    fn needs_a_column_and_a_card_header () {
+        match parse(b"card,votes\nShipped,1\n", &source()) {
+            Ok(_) => panic!("parsed a csv without a column header"),
+            Err(why) => assert!(why.starts_with("No column header"), "{}", why),
+        }
+        match parse(b"column,votes\nWent well,1\n", &source()) {
+            Ok(_) => panic!("parsed a csv without a card header"),
+            Err(why) => assert!(why.starts_with("No card header"), "{}", why),
+        }
+    }
+
+    #[test]
+    fn skips_lines_without_column_or_card () {
+        let imported = parse(b"column,card\nWent well,\n,Orphan\nWent well,Shipped\n", &source()).unwrap();
+        assert_eq!(titles(imported.contents.cards.iter().map(|card| &card.title)), vec!["Shipped"]);
+        assert_eq!(imported.warnings, vec![
+            "Skipped line 2, it has no column or card text",
+            "Skipped line 3, it has no column or card text",
+        ]);
+    }
+
+    #[test]
+    fn ignores_vote_counts_that_are_not_numbers () {
+        let imported = parse(b"column,card,votes\nWent well,Shipped,3\nWent well,Demos,lots\nWent well,Pairing,-1\nWent well,Docs,\n", &source()).unwrap();
+        assert_eq!(imported.contents.votes.len(), 3);
+        assert!(imported.contents.votes.iter().all(|vote| vote.card_id.as_deref() == Some("card-0")));
+        assert_eq!(imported.warnings, vec![
+            "Ignored votes 'lots' on line 3, not a number",
+            "Ignored votes '-1' on line 4, not a number",
+        ]);
+    }
+
+    #[test]
+    fn tags_each_card_once_and_shares_tags_by_title () {
+        let imported = parse(b"column,card,tags\nWent well,Shipped,\"release; team,release\"\nWent well,Demos,team;;\n", &source()).unwrap();
+        let contents = &imported.contents;
+        assert_eq!(titles(contents.tags.iter().map(|tag| &tag.title)), vec!["release", "team"]);
+        let card_tags: Vec<(&str, &str)> = contents.card_tags.iter().map(|card_tag| (card_tag.card_id.as_str(), card_tag.tag_id.as_str())).collect();
+        assert_eq!(card_tags, vec![("card-0", "tag-0"), ("card-0", "tag-1"), ("card-1", "tag-1")]);
+    }
+}
@@ -0,0 +1,101 @@
+
+use crate::models::{MyError, Board, BoardContents, Tag, Vote};
+
+mod generic_csv;
+mod trello;
+
+
+pub const FORMAT_CSV: &'static str = "csv";
+pub const FORMAT_TRELLO: &'static str = "trello";
+
+// vote counts are only numbers in the file, but every vote becomes a row,
+// so no card gets more than a retro could plausibly give it
+const MAX_VOTES_PER_CARD: usize = 1000;
+// and the whole import no more than a json export of that many votes would take up
+const BYTES_PER_VOTE: usize = 128;
+
+
+// who is importing, and when, for whatever the other tool's export leaves out
+pub struct Source {
+    pub title: Option<String>,
+    pub author: String,
+    pub author_name: String,
+    pub now: i64,
+    // the most bytes an import may have, which also bounds its votes
+    pub limit_bytes: usize,
+}
+
+// ids here are only placeholders that tie the pieces together,
+// they get swapped for real ones before anything is stored
+pub struct Imported {
+    pub contents: BoardContents,
+    pub warnings: Vec<String>,
+}
+
+pub fn import (format: &str, body: &[u8], source: &Source) -> Result<Imported, MyError> {
+    match format {
+        FORMAT_CSV => generic_csv::parse(body, source),
+        FORMAT_TRELLO => trello::parse(body, source),
+        _ => Err(format!("Unknown import format '{}', expected {} or {}", format, FORMAT_CSV, FORMAT_TRELLO)),
+    }
+}
+
+fn empty_board (title: String, source: &Source) -> BoardContents {
+    BoardContents {
+        board: Board {
+            id: String::from("board"),
+            team_id: String::new(),
+            title: title,
+            owner: source.author.clone(),
+            created_at: source.now,
+            archived_at: None,
+            deleted_at: None,
+        },
+        columns: vec![],
+        groups: vec![],
+        cards: vec![],
+        votes: vec![],
+        tags: vec![],
+        card_tags: vec![],
+        comments: vec![],
+        action_items: vec![],
+    }
+}
+
+// tags are matched by title, other tools rarely have ids for them
+fn tag_id (contents: &mut BoardContents, title: &str, now: i64) -> String {
+    if let Some(tag) = contents.tags.iter().find(|tag| tag.title == title) {
+        return tag.id.clone()
+    }
+    let tag = Tag {
+        id: format!("tag-{}", contents.tags.len()),
+        board_id: contents.board.id.clone(),
+        title: title.to_string(),
+        created_at: now,
+    };
+    let id = tag.id.clone();
+    contents.tags.push(tag);
+    id
+}
+
+// only the count carries over, so the votes are cast in the importer's name
+fn cast_votes (contents: &mut BoardContents, warnings: &mut Vec<String>, card_id: &String, card_title: &String, votes: usize, source: &Source) {
+    let left = (source.limit_bytes / BYTES_PER_VOTE).saturating_sub(contents.votes.len());
+    let cast = votes.min(MAX_VOTES_PER_CARD).min(left);
+    if cast < votes {
+        warnings.push(format!(
+            "Cast {} of the {} votes on card '{}', at most {} per card and {} per import",
+            cast, votes, card_title, MAX_VOTES_PER_CARD, source.limit_bytes / BYTES_PER_VOTE,
+        ));
+    }
+    for _ in 0..cast {
+        contents.votes.push(Vote {
+            id: format!("vote-{}", contents.votes.len()),
+            board_id: contents.board.id.clone(),
+            card_id: Some(card_id.clone()),
+            group_id: None,
+            author: source.author.clone(),
+            created_at: source.now,
+        });
+    }
+}
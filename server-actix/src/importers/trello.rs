@@ -0,0 +1,322 @@
+
+use serde::Deserialize;
+
+use crate::models::{MyError, Column, Card, CardTag, Comment};
+use super::{Source, Imported, empty_board, tag_id, cast_votes};
+
+
+const ACTION_COMMENT: &'static str = "commentCard";
+
+
+// only the parts of a trello board export (Menu > Print and export > JSON) that map onto a retro
+#[derive(Deserialize)]
+struct TrelloBoard {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    lists: Vec<TrelloList>,
+    #[serde(default)]
+    cards: Vec<TrelloCard>,
+    #[serde(default)]
+    labels: Vec<TrelloLabel>,
+    #[serde(default)]
+    members: Vec<TrelloMember>,
+    #[serde(default)]
+    actions: Vec<TrelloAction>,
+}
+
+#[derive(Deserialize)]
+struct TrelloList {
+    id: String,
+    name: String,
+    #[serde(default)]
+    closed: bool,
+    #[serde(default)]
+    pos: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrelloCard {
+    id: String,
+    name: String,
+    #[serde(default)]
+    desc: String,
+    id_list: String,
+    #[serde(default)]
+    closed: bool,
+    #[serde(default)]
+    pos: f64,
+    #[serde(default)]
+    id_labels: Vec<String>,
+    #[serde(default)]
+    id_members: Vec<String>,
+    #[serde(default)]
+    badges: TrelloBadges,
+}
+
+#[derive(Deserialize, Default)]
+struct TrelloBadges {
+    #[serde(default)]
+    votes: usize,
+}
+
+#[derive(Deserialize)]
+struct TrelloLabel {
+    id: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    color: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrelloMember {
+    id: String,
+    #[serde(default)]
+    full_name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrelloAction {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    data: TrelloActionData,
+    member_creator: Option<TrelloMember>,
+}
+
+#[derive(Deserialize, Default)]
+struct TrelloActionData {
+    card: Option<TrelloRef>,
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TrelloRef {
+    id: String,
+}
+
+
+// lists become columns and cards become cards, in trello's order, leaving out archived ones
+// labels become tags, card descriptions and comments become comments
+pub fn parse (body: &[u8], source: &Source) -> Result<Imported, MyError> {
+    let trello: TrelloBoard = serde_json::from_slice(body).map_err(|why| format!("Not a trello board export: {}", why))?;
+
+    let title = source.title.clone().unwrap_or_else(|| trello.name.clone());
+    let mut contents = empty_board(title, source);
+    let mut warnings = vec![];
+    let now = source.now;
+
+    let mut lists: Vec<&TrelloList> = trello.lists.iter().filter(|list| !list.closed).collect();
+    lists.sort_by(|a, b| a.pos.partial_cmp(&b.pos).unwrap_or(std::cmp::Ordering::Equal));
+    let closed_lists = trello.lists.len() - lists.len();
+    if closed_lists > 0 {
+        warnings.push(format!("Skipped {} archived lists", closed_lists));
+    }
+    for list in lists.iter() {
+        contents.columns.push(Column {
+            id: list.id.clone(),
+            board_id: contents.board.id.clone(),
+            title: list.name.clone(),
+            position: contents.columns.len() as i32,
+            created_at: now,
+        });
+    }
+
+    let mut cards: Vec<&TrelloCard> = trello.cards.iter()
+        .filter(|card| !card.closed && lists.iter().any(|list| list.id == card.id_list))
+        .collect();
+    cards.sort_by(|a, b| a.pos.partial_cmp(&b.pos).unwrap_or(std::cmp::Ordering::Equal));
+    let skipped_cards = trello.cards.len() - cards.len();
+    if skipped_cards > 0 {
+        warnings.push(format!("Skipped {} archived cards, or cards on archived lists", skipped_cards));
+    }
+
+    for card in cards.iter() {
+        let author_name = card.id_members.iter()
+            .find_map(|id| trello.members.iter().find(|member| &member.id == id))
+            .map(|member| member.full_name.clone())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| source.author_name.clone());
+
+        contents.cards.push(Card {
+            id: card.id.clone(),
+            board_id: contents.board.id.clone(),
+            column_id: card.id_list.clone(),
+            group_id: None,
+            merged_into: None,
+            title: card.name.clone(),
+            author: source.author.clone(),
+            author_name: author_name.clone(),
+            position: contents.cards.iter().filter(|other| other.column_id == card.id_list).count() as i32,
+            created_at: now,
+        });
+
+        cast_votes(&mut contents, &mut warnings, &card.id, &card.name, card.badges.votes, source);
+
+        for label in card.id_labels.iter().filter_map(|id| trello.labels.iter().find(|label| &label.id == id)) {
+            // unnamed labels are just a colour on trello
+            let tag_title = match (&label.name, &label.color) {
+                (name, _) if !name.is_empty() => name.clone(),
+                (_, Some(color)) => color.clone(),
+                _ => continue,
+            };
+            let tag_id = tag_id(&mut contents, &tag_title, now);
+            if contents.card_tags.iter().any(|card_tag| card_tag.card_id == card.id && card_tag.tag_id == tag_id) {
+                continue
+            }
+            contents.card_tags.push(CardTag {
+                id: format!("card-tag-{}", contents.card_tags.len()),
+                board_id: contents.board.id.clone(),
+                card_id: card.id.clone(),
+                tag_id: tag_id,
+                created_at: now,
+            });
+        }
+
+        if !card.desc.trim().is_empty() {
+            contents.comments.push(Comment {
+                id: format!("comment-{}", contents.comments.len()),
+                board_id: contents.board.id.clone(),
+                card_id: card.id.clone(),
+                author: source.author.clone(),
+                author_name: author_name,
+                text: card.desc.clone(),
+                created_at: now,
+            });
+        }
+    }
+
+    // trello lists actions newest first
+    for action in trello.actions.iter().rev().filter(|action| action.kind == ACTION_COMMENT) {
+        let (card_id, text) = match (&action.data.card, &action.data.text) {
+            (Some(card), Some(text)) if contents.cards.iter().any(|other| other.id == card.id) => (card.id.clone(), text.clone()),
+            _ => continue,
+        };
+        let author_name = action.member_creator.as_ref()
+            .map(|member| member.full_name.clone())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| source.author_name.clone());
+
+        contents.comments.push(Comment {
+            id: format!("comment-{}", contents.comments.len()),
+            board_id: contents.board.id.clone(),
+            card_id: card_id,
+            author: source.author.clone(),
+            author_name: author_name,
+            text: text,
+            // spaced a millisecond apart to keep them in order, rather than parsing trello's dates
+            created_at: now + contents.comments.len() as i64,
+        });
+    }
+
+    Ok(Imported {
+        contents: contents,
+        warnings: warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn source () -> Source {
+        Source { title: None, author: "alice".into(), author_name: "Alice".into(), now: 100, limit_bytes: 1_000_000 }
+    }
+
+    fn parsed (board: serde_json::Value) -> Imported {
+        parse(board.to_string().as_bytes(), &source()).unwrap()
+    }
+
+    fn card (id: &str, list: &str, pos: f64) -> serde_json::Value {
+        json!({ "id": id, "name": format!("Card {}", id), "idList": list, "pos": pos })
+    }
+
+    #[test]
+    fn leaves_out_archived_lists_and_cards () {
+        let imported = parsed(json!({
+            "name": "Sprint 1",
+            "lists": [
+                { "id": "l2", "name": "To improve", "pos": 2.0 },
+                { "id": "l1", "name": "Went well", "pos": 1.0 },
+                { "id": "l3", "name": "Old", "pos": 3.0, "closed": true },
+            ],
+            "cards": [
+                card("k2", "l1", 2.0),
+                card("k1", "l1", 1.0),
+                card("k3", "l3", 1.0),
+                { "id": "k4", "name": "Gone", "idList": "l2", "closed": true },
+                card("k5", "l2", 1.0),
+            ],
+        }));
+        let contents = &imported.contents;
+
+        assert_eq!(contents.board.title, "Sprint 1");
+        let columns: Vec<(&str, i32)> = contents.columns.iter().map(|column| (column.id.as_str(), column.position)).collect();
+        assert_eq!(columns, vec![("l1", 0), ("l2", 1)]);
+        let cards: Vec<(&str, &str, i32)> = contents.cards.iter().map(|card| (card.id.as_str(), card.column_id.as_str(), card.position)).collect();
+        assert_eq!(cards, vec![("k1", "l1", 0), ("k5", "l2", 0), ("k2", "l1", 1)]);
+        assert_eq!(imported.warnings, vec![
+            "Skipped 1 archived lists",
+            "Skipped 2 archived cards, or cards on archived lists",
+        ]);
+    }
+
+    #[test]
+    fn names_labels_by_colour_when_they_have_no_name () {
+        let imported = parsed(json!({
+            "lists": [{ "id": "l1", "name": "Went well" }],
+            "labels": [
+                { "id": "a", "name": "release", "color": "green" },
+                { "id": "b", "name": "", "color": "red" },
+                { "id": "c", "name": "" },
+                { "id": "d", "name": "red" },
+            ],
+            "cards": [{ "id": "k1", "name": "Shipped", "idList": "l1", "idLabels": ["a", "b", "c", "d", "missing"] }],
+        }));
+        let contents = &imported.contents;
+        let tags: Vec<&str> = contents.tags.iter().map(|tag| tag.title.as_str()).collect();
+        assert_eq!(tags, vec!["release", "red"]);
+        // the red label and the label named red are the one tag
+        assert_eq!(contents.card_tags.len(), 2);
+    }
+
+    #[test]
+    fn comments_come_oldest_first_after_the_description () {
+        let comment = |card: &str, text: &str, by: Option<&str>| json!({
+            "type": "commentCard",
+            "data": { "card": { "id": card }, "text": text },
+            "memberCreator": by.map(|name| json!({ "id": name, "fullName": name })),
+        });
+        let imported = parsed(json!({
+            "lists": [{ "id": "l1", "name": "Went well" }],
+            "members": [{ "id": "m1", "fullName": "Bob" }],
+            "cards": [
+                { "id": "k1", "name": "Shipped", "idList": "l1", "desc": "On time", "idMembers": ["m1"] },
+                { "id": "k2", "name": "Gone", "idList": "l1", "closed": true },
+            ],
+            // newest first, as trello lists them
+            "actions": [
+                comment("k1", "third", None),
+                { "type": "updateCard", "data": { "card": { "id": "k1" } } },
+                comment("k2", "on an archived card", Some("Carol")),
+                comment("k1", "second", Some("Carol")),
+                comment("k1", "first", Some("Dave")),
+            ],
+        }));
+        let comments: Vec<(&str, &str, i64)> = imported.contents.comments.iter()
+            .map(|comment| (comment.text.as_str(), comment.author_name.as_str(), comment.created_at))
+            .collect();
+        assert_eq!(comments, vec![
+            ("On time", "Bob", 100),
+            ("first", "Dave", 101),
+            ("second", "Carol", 102),
+            ("third", "Alice", 103),
+        ]);
+        assert_eq!(imported.contents.cards[0].author_name, "Bob");
+    }
+}
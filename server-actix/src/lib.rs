@@ -0,0 +1,37 @@
+// the server in main.rs and the admin tool in bin/ both build on this
+// https://stackoverflow.com/questions/56714619/including-a-file-from-another-that-is-not-main-rs-nor-lib-rs
+// consts spell out 'static, storage takes &String and structs are built field: field throughout,
+// so each module allows just the lints against the ones it has
+#[allow(clippy::redundant_field_names)]
+pub mod time_provider;
+pub mod id_generator;
+#[allow(clippy::redundant_static_lifetimes)]
+pub mod config;
+#[allow(clippy::ptr_arg)]
+pub mod models;
+#[allow(clippy::ptr_arg, clippy::redundant_field_names, clippy::redundant_static_lifetimes)]
+pub mod storage;
+#[allow(clippy::redundant_field_names)]
+pub mod startup;
+#[allow(clippy::ptr_arg, clippy::redundant_field_names, clippy::redundant_static_lifetimes)]
+pub mod auth;
+#[allow(clippy::ptr_arg, clippy::redundant_field_names, clippy::redundant_static_lifetimes)]
+pub mod handlers;
+pub mod routes;
+pub mod purge;
+#[allow(clippy::redundant_field_names)]
+pub mod backup;
+#[allow(clippy::ptr_arg, clippy::redundant_field_names, clippy::redundant_static_lifetimes)]
+pub mod importers;
+#[allow(clippy::ptr_arg, clippy::redundant_field_names, clippy::redundant_static_lifetimes)]
+pub mod search;
+#[allow(clippy::ptr_arg, clippy::redundant_field_names, clippy::redundant_static_lifetimes)]
+pub mod audit;
+#[allow(clippy::ptr_arg, clippy::redundant_field_names)]
+pub mod history;
+#[allow(clippy::ptr_arg, clippy::redundant_field_names)]
+pub mod undo;
+#[allow(clippy::redundant_static_lifetimes)]
+pub mod logging;
+#[allow(clippy::redundant_field_names, clippy::redundant_static_lifetimes)]
+pub mod metrics;
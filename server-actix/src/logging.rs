@@ -0,0 +1,74 @@
+use std::future::Future;
+use std::time::Instant;
+use rand::Rng;
+use actix_web::Error;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse};
+use actix_web::http::{HeaderName, HeaderValue};
+use tracing::{info, error, info_span, Instrument};
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::prelude::*;
+
+use crate::config::Config;
+
+
+pub const LOG_FORMAT_JSON: &'static str = "json";
+pub const LOG_FORMAT_LOGFMT: &'static str = "logfmt";
+pub const DEFAULT_LOG_LEVEL: &'static str = "info";
+pub const REQUEST_ID_HEADER: &'static str = "x-request-id";
+// whatever a proxy hands us goes into every log line, so only so much of it is trusted
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+
+// stands in for secrets wherever settings get logged, still telling whether one was set at all
+pub fn redacted (secret: &str) -> &'static str {
+    if secret.is_empty() { "" } else { "<redacted>" }
+}
+
+// once, before anything gets logged
+// the level takes the same directives as RUST_LOG, like "info,bareretro::storage=debug"
+pub fn init (config: &Config) {
+    // checked along with the rest of the config already, so this is only ever the default
+    let filter = EnvFilter::try_new(&config.log_level).unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_LEVEL));
+    let registry = tracing_subscriber::registry().with(filter);
+    match config.log_format.as_str() {
+        LOG_FORMAT_JSON => registry.with(tracing_subscriber::fmt::layer().json().with_current_span(false).with_span_list(true)).init(),
+        _ => registry.with(tracing_logfmt::layer()).init(),
+    }
+}
+
+fn request_id (req: &ServiceRequest) -> String {
+    let given = req.headers().get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN)
+        .filter(|id| id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    match given {
+        Some(id) => id.to_string(),
+        None => format!("{:016x}", rand::thread_rng().gen::<u64>()),
+    }
+}
+
+// wraps the whole app, so everything logged while handling a request, storage included,
+// happens inside its span and carries its id, which also goes back out as a header
+pub fn trace_request<S, B> (req: ServiceRequest, srv: &S) -> impl Future<Output = Result<ServiceResponse<B>, Error>>
+        where S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> {
+    let id = request_id(&req);
+    let span = info_span!("request", request_id = %id, method = %req.method(), path = %req.path());
+    let started = Instant::now();
+    let call = span.in_scope(|| srv.call(req));
+
+    async move {
+        let result = call.await;
+        let elapsed_ms = started.elapsed().as_secs_f64() * 1_000.0;
+        match &result {
+            Ok(res) if res.status().is_server_error() => error!(status = res.status().as_u16(), elapsed_ms, "request failed"),
+            Ok(res) => info!(status = res.status().as_u16(), elapsed_ms, "request"),
+            Err(why) => error!(error = %why, elapsed_ms, "request failed"),
+        }
+        result.map(|mut res| {
+            if let Ok(value) = HeaderValue::from_str(&id) {
+                res.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+            }
+            res
+        })
+    }.instrument(span)
+}
@@ -1,67 +1,78 @@
 
-// https://stackoverflow.com/questions/56714619/including-a-file-from-another-that-is-not-main-rs-nor-lib-rs
-mod time_provider;
-mod models;
-mod storage;
-mod handlers;
 
 use dotenv::dotenv;
+use std::io::{Error, ErrorKind};
+use tracing::{info, warn, error};
 // https://actix.rs/
 // very fast framework: https://www.techempower.com/benchmarks/#section=data-r19
 use actix_web::{web, App, HttpServer};
 
-use crate::time_provider::{SystemTimeProvider, TimeProvider};
-use crate::models::{Config, Storage, Service};
-use crate::storage::{invalid, postgres};
-use crate::handlers::{not_found, add_board, list_boards, get_board, delete_board};
+use bareretro::logging;
+use bareretro::config::Config;
+use bareretro::startup::build_service;
+use bareretro::purge::run_purge;
+use bareretro::metrics::{Metrics, track_request};
+use bareretro::routes::routes;
+use bareretro::handlers::not_found;
 
 
-fn build_service () -> Service {
-    // https://stackoverflow.com/questions/28219519/are-polymorphic-variables-allowed
-    let time_provider: Box<dyn TimeProvider> = Box::new(SystemTimeProvider {});
-
-    let config = Config::from_env();
-    println!("config {:?}", config);
-
-    // https://stackoverflow.com/questions/25383488/how-to-match-a-string-against-string-literals-in-rust
-    let storage: Box<dyn Storage> = match config.provider.as_str() {
-        "postgres" => match postgres::PostgresStorage::from_env(time_provider.clone()) {
-            Err(why) => Box::new(invalid::InvalidStorage { error: format!("Invalid postgres storage provider! {}", why) }),
-            Ok(storage) => Box::new(storage),
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    dotenv().ok();
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(errors) => {
+            logging::init(&Config::default());
+            for why in errors.iter() {
+                error!(error = %why, "Invalid config");
+            }
+            return Err(Error::new(ErrorKind::InvalidInput, format!("{} invalid config settings", errors.len())))
         },
-        _ => Box::new(invalid::InvalidStorage { error: format!("Invalid or no storage provider given! '{}'", config.provider) })
     };
-
-    println!("created storage: {}", storage.name());
-
-    Service {
-        time_provider: time_provider,
-        config: config,
-        storage: storage,
+    logging::init(&config);
+    info!(config = ?config, "config");
+    if config.proxy_secret.is_empty() {
+        warn!("no proxy_secret configured, X-User-Id is refused and only invited guests can get in");
     }
-}
 
-#[actix_web::main]
-async fn main() -> std::io::Result<()> {
-    dotenv().ok();
+    // the one service, storage and pool, undo stacks and all, that every worker shares
+    let metrics = Metrics::new();
+    let service = web::Data::new(build_service(config.clone(), metrics.clone()).map_err(|why| {
+        error!(error = %why, "Storage required, not starting");
+        Error::other(why)
+    })?);
+    let purge = actix_rt::spawn(run_purge(service.clone()));
+    let shared = service.clone();
+
+    let mut server = HttpServer::new(move || {
+        let metrics = metrics.clone();
 
-    HttpServer::new(|| {
         App::new()
-            .data(build_service())
-            .service(
-                web::scope("/api")
-                    .route("boards", web::post().to(add_board))
-                    .route("boards", web::get().to(list_boards))
-                    .route("boards/{id}", web::get().to(get_board))
-                    .route("boards/{id}", web::delete().to(delete_board))
-            )
+            .wrap_fn(move |req, srv| track_request(&metrics, req, srv))
+            .wrap_fn(logging::trace_request)
+            .app_data(service.clone())
+            .configure(|cfg| routes(cfg, &service.config))
             // https://github.com/actix/actix-website/blob/master/content/docs/url-dispatch.md
             .default_service(
                 // https://docs.rs/actix-web/2.0.0/actix_web/struct.App.html#method.service
                 web::route().to(not_found)
             )
-    })
-    .bind(("0.0.0.0", 8080))?
-    .run()
-    .await
+    });
+    if let Some(workers) = config.workers {
+        server = server.workers(workers);
+    }
+    // on SIGTERM or SIGINT it stops accepting, and waits for what is in flight up to the timeout
+    server.shutdown_timeout(config.shutdown_timeout_secs)
+        .bind((config.bind_address, config.port))?
+        .run()
+        .await?;
+
+    // no request is left running by now, so nothing is using the storage but the purge
+    info!("server stopped, closing storage");
+    purge.abort();
+    if let Err(why) = shared.storage.close().await {
+        error!(error = %why, "Closing storage failed!");
+    }
+    info!("shut down");
+    Ok(())
 }
@@ -0,0 +1,136 @@
+use std::future::Future;
+use std::time::Instant;
+use actix_web::Error;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse};
+use prometheus::{Encoder, TextEncoder, Registry, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts};
+
+use crate::models::{MyError, PoolStatus};
+
+
+// requests that matched no route all count as one, rather than one series per made up path
+const UNMATCHED_ROUTE: &'static str = "unmatched";
+
+
+// one set for the whole server, every worker counts into the same ones
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    storage_duration: HistogramVec,
+    storage_errors: IntCounterVec,
+    audit_errors: IntCounterVec,
+    pool_max_size: IntGauge,
+    pool_size: IntGauge,
+    pool_available: IntGauge,
+    pool_waiting: IntGauge,
+}
+
+fn register<T : prometheus::core::Collector + Clone + 'static> (registry: &Registry, metric: T) -> T {
+    // names are all fixed and different, so this only fails on a typo
+    registry.register(Box::new(metric.clone())).expect("Failed registering metric");
+    metric
+}
+
+impl Default for Metrics {
+    fn default () -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new () -> Self {
+        let registry = Registry::new();
+        Metrics {
+            http_requests: register(&registry, IntCounterVec::new(
+                Opts::new("http_requests_total", "HTTP requests handled"),
+                &["method", "route", "status"],
+            ).unwrap()),
+            http_duration: register(&registry, HistogramVec::new(
+                HistogramOpts::new("http_request_duration_seconds", "Time taken to handle HTTP requests"),
+                &["method", "route", "status"],
+            ).unwrap()),
+            storage_duration: register(&registry, HistogramVec::new(
+                HistogramOpts::new("storage_operation_duration_seconds", "Time taken by storage operations")
+                    .buckets(vec![0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5]),
+                &["backend", "method"],
+            ).unwrap()),
+            storage_errors: register(&registry, IntCounterVec::new(
+                Opts::new("storage_operation_errors_total", "Storage operations that failed"),
+                &["backend", "method"],
+            ).unwrap()),
+            audit_errors: register(&registry, IntCounterVec::new(
+                Opts::new("audit_entry_errors_total", "Changes made without the audit entry to go with them"),
+                &["action", "entity_type"],
+            ).unwrap()),
+            pool_max_size: register(&registry, IntGauge::new("db_pool_max_size", "Most connections the pool will open").unwrap()),
+            pool_size: register(&registry, IntGauge::new("db_pool_size", "Connections the pool has open").unwrap()),
+            pool_available: register(&registry, IntGauge::new("db_pool_available", "Open connections not in use").unwrap()),
+            pool_waiting: register(&registry, IntGauge::new("db_pool_waiting", "Requests waiting for a connection").unwrap()),
+            registry: registry,
+        }
+    }
+
+    pub async fn observe_storage<T> (
+        &self,
+        backend: &'static str,
+        method: &'static str,
+        operation: impl Future<Output = Result<T, MyError>>,
+    ) -> Result<T, MyError> {
+        let started = Instant::now();
+        let result = operation.await;
+        self.storage_duration.with_label_values(&[backend, method]).observe(started.elapsed().as_secs_f64());
+        if result.is_err() {
+            self.storage_errors.with_label_values(&[backend, method]).inc();
+        }
+        result
+    }
+
+    // the change itself went through, so this is the only place the missing entry shows
+    pub fn audit_failed (&self, action: &str, entity_type: &str) {
+        self.audit_errors.with_label_values(&[action, entity_type]).inc();
+    }
+
+    // the pool is only looked at when scraped, nothing has to keep it up to date
+    pub fn set_pool_status (&self, status: Option<PoolStatus>) {
+        if let Some(status) = status {
+            self.pool_max_size.set(status.max_size as i64);
+            self.pool_size.set(status.size as i64);
+            // deadpool counts whoever is waiting as less than nothing available
+            self.pool_available.set(status.available.max(0) as i64);
+            self.pool_waiting.set((-status.available).max(0) as i64);
+        }
+    }
+
+    // the prometheus text format
+    pub fn render (&self) -> Result<String, MyError> {
+        let mut buffer = vec![];
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)
+            .map_err(|why| format!("Failed encoding metrics: {}", why))?;
+        String::from_utf8(buffer).map_err(|why| format!("Failed encoding metrics: {}", why))
+    }
+}
+
+// counts and times every request by the route it matched, rather than its actual path
+pub fn track_request<S, B> (metrics: &Metrics, req: ServiceRequest, srv: &S) -> impl Future<Output = Result<ServiceResponse<B>, Error>>
+        where S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> {
+    let metrics = metrics.clone();
+    let method = req.method().to_string();
+    let started = Instant::now();
+    let call = srv.call(req);
+
+    async move {
+        let result = call.await;
+        let (route, status) = match &result {
+            Ok(res) => (res.request().match_pattern(), res.status().as_u16()),
+            // errors turn into responses further out, which are 500s unless said otherwise
+            Err(why) => (None, why.as_response_error().status_code().as_u16()),
+        };
+        let route = route.unwrap_or_else(|| String::from(UNMATCHED_ROUTE));
+        let status = status.to_string();
+        let labels = [method.as_str(), route.as_str(), status.as_str()];
+        metrics.http_requests.with_label_values(&labels).inc();
+        metrics.http_duration.with_label_values(&labels).observe(started.elapsed().as_secs_f64());
+        result
+    }
+}
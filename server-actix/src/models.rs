@@ -1,90 +1,369 @@
 
-use std::env;
 use serde::{Serialize, Deserialize};
 use async_trait::async_trait;
 use dyn_clonable::clonable;
 
 use crate::time_provider::TimeProvider;
-
-
-const EMPTY_STRING: String = String::new();
+use crate::id_generator::IdGenerator;
+use crate::search;
+use crate::undo::{UndoHistory, RowChange};
+use crate::metrics::Metrics;
+use crate::config::Config;
 
 
 pub type MyError = String;
 
-#[derive(Debug, Clone)]
-pub struct Config {
-    pub provider: String,
-}
-
-impl Config {
-    pub fn env_var_string (name: &str, default: String) -> String {
-        env::var(name).unwrap_or(default)
+// one is shared by every worker, so it and whatever it returns has to be able to move between threads
+#[async_trait]
+#[clonable]
+pub trait Storage : Clone + Send + Sync {
+    fn name(&self) -> &'static str;
+    // only for backends with a connection pool
+    fn pool_status (&self) -> Option<PoolStatus> {
+        None
     }
-
-    #[allow(unused)]
-    fn env_var_parse<T : std::str::FromStr> (name: &str, default: T) -> T {
-        match env::var(name) {
-            Ok(s) => s.parse::<T>().unwrap_or(default),
-            _ => default
-        }
+    // HEALTH
+    // whether requests can be served right now, and why not otherwise
+    async fn health_check (&self) -> Result<(), MyError>;
+    // brings the schema up to date, for backends that have one, answering with the versions applied
+    async fn migrate (&self) -> Result<Vec<String>, MyError> {
+        Ok(vec![])
     }
-
-    // maybe TODO? https://github.com/actix/examples/blob/ec6e14aacc10bf4d44309ddb73fe01f9c27faf6f/async_pg/src/main.rs#L10
-    // seems very ubiquitous: https://crates.io/crates/config
-    pub fn from_env () -> Config {
-        Config {
-            provider: Self::env_var_string("STORAGE_PROVIDER", EMPTY_STRING),
-        }
+    // once, on the way out, after the last request, for whatever should not just be dropped
+    async fn close (&self) -> Result<(), MyError> {
+        Ok(())
     }
-}
-
-// https://github.com/dtolnay/async-trait#non-threadsafe-futures
-#[async_trait(?Send)]
-#[clonable]
-pub trait Storage : Clone {
-    fn name(&self) -> &'static str;
     // BOARDS
     async fn add_board (&self, item: &Board) -> Result<bool, MyError>;
     async fn list_boards (&self) -> Result<Vec<Board>, MyError>;
     async fn get_board (&self, id: &String) -> Result<Board, MyError>;
-    async fn delete_board (&self, id: &String) -> Result<bool, MyError>;
+    async fn update_board (&self, item: &Board) -> Result<bool, MyError>;
+    // removes the board for good, along with everything on it, all or nothing
+    async fn delete_board (&self, id: &String) -> Result<BoardDeletion, MyError>;
+    async fn list_team_boards (&self, team_ids: &Vec<String>) -> Result<Vec<Board>, MyError>;
+    async fn list_deleted_boards (&self, deleted_before: i64) -> Result<Vec<Board>, MyError>;
     // COLUMNS
     async fn add_column (&self, item: &Column) -> Result<bool, MyError>;
     async fn list_columns (&self) -> Result<Vec<Column>, MyError>;
     async fn get_column (&self, id: &String) -> Result<Column, MyError>;
     async fn delete_column (&self, id: &String) -> Result<bool, MyError>;
+    // only while no cards or groups are left in it, false when there was no such column
+    async fn delete_empty_column (&self, id: &String) -> Result<IfEmpty<bool>, MyError>;
+    async fn update_column (&self, item: &Column) -> Result<bool, MyError>;
+    async fn list_board_columns (&self, board_id: &String) -> Result<Vec<Column>, MyError>;
+    // CARDS
+    async fn add_card (&self, item: &Card) -> Result<bool, MyError>;
+    async fn update_card (&self, item: &Card) -> Result<bool, MyError>;
+    async fn list_board_cards (&self, board_id: &String) -> Result<Vec<Card>, MyError>;
+    async fn get_card (&self, id: &String) -> Result<Card, MyError>;
+    async fn delete_card (&self, id: &String) -> Result<bool, MyError>;
+    // the card with its votes, tags, comments and revisions, unmerging whatever was merged into it,
+    // all or nothing, answering with every row it changed in the order it changed them
+    async fn delete_card_cascade (&self, id: &String) -> Result<Vec<RowChange>, MyError>;
+    // the source card and whatever was merged into it end up merged into the target, all or nothing,
+    // refused when the target is by then merged itself
+    async fn merge_cards (&self, source_id: &String, target_id: &String) -> Result<Vec<RowChange>, MyError>;
+    // GROUPS
+    async fn add_group (&self, item: &CardGroup) -> Result<bool, MyError>;
+    async fn update_group (&self, item: &CardGroup) -> Result<bool, MyError>;
+    async fn list_board_groups (&self, board_id: &String) -> Result<Vec<CardGroup>, MyError>;
+    async fn get_group (&self, id: &String) -> Result<CardGroup, MyError>;
+    async fn delete_group (&self, id: &String) -> Result<bool, MyError>;
+    // the group with its votes, its cards staying in the column, all or nothing
+    async fn delete_group_cascade (&self, id: &String) -> Result<Vec<RowChange>, MyError>;
+    // VOTES
+    async fn add_vote (&self, item: &Vote) -> Result<bool, MyError>;
+    async fn list_board_votes (&self, board_id: &String) -> Result<Vec<Vote>, MyError>;
+    async fn get_vote (&self, id: &String) -> Result<Vote, MyError>;
+    async fn delete_vote (&self, id: &String) -> Result<bool, MyError>;
+    // TAGS
+    async fn add_tag (&self, item: &Tag) -> Result<bool, MyError>;
+    async fn list_board_tags (&self, board_id: &String) -> Result<Vec<Tag>, MyError>;
+    async fn get_tag (&self, id: &String) -> Result<Tag, MyError>;
+    async fn delete_tag (&self, id: &String) -> Result<bool, MyError>;
+    // CARD TAGS
+    async fn add_card_tag (&self, item: &CardTag) -> Result<bool, MyError>;
+    async fn list_board_card_tags (&self, board_id: &String) -> Result<Vec<CardTag>, MyError>;
+    async fn delete_card_tag (&self, id: &String) -> Result<bool, MyError>;
+    // COMMENTS
+    async fn add_comment (&self, item: &Comment) -> Result<bool, MyError>;
+    async fn update_comment (&self, item: &Comment) -> Result<bool, MyError>;
+    async fn list_board_comments (&self, board_id: &String) -> Result<Vec<Comment>, MyError>;
+    async fn get_comment (&self, id: &String) -> Result<Comment, MyError>;
+    async fn delete_comment (&self, id: &String) -> Result<bool, MyError>;
+    // REVISIONS
+    async fn add_revision (&self, item: &Revision) -> Result<bool, MyError>;
+    // of the card itself and every comment on it
+    async fn list_card_revisions (&self, card_id: &String) -> Result<Vec<Revision>, MyError>;
+    async fn get_revision (&self, id: &String) -> Result<Revision, MyError>;
+    async fn delete_revision (&self, id: &String) -> Result<bool, MyError>;
+    // ACTION ITEMS
+    async fn add_action_item (&self, item: &ActionItem) -> Result<bool, MyError>;
+    async fn update_action_item (&self, item: &ActionItem) -> Result<bool, MyError>;
+    async fn list_board_action_items (&self, board_id: &String) -> Result<Vec<ActionItem>, MyError>;
+    async fn get_action_item (&self, id: &String) -> Result<ActionItem, MyError>;
+    async fn delete_action_item (&self, id: &String) -> Result<bool, MyError>;
+    // AUDIT
+    // append only, entries are never changed or removed
+    async fn add_audit_entry (&self, item: &AuditEntry) -> Result<bool, MyError>;
+    async fn list_audit_entries (&self, filter: &AuditFilter) -> Result<Vec<AuditEntry>, MyError>;
+    // SEARCH
+    // board titles, card text and comments on these teams' boards, best matches first
+    // backends without full-text search of their own get by with scanning the boards
+    async fn search (&self, team_ids: &Vec<String>, query: &String, limit: usize) -> Result<Vec<SearchHit>, MyError> {
+        let terms = search::terms(query);
+        let mut hits = vec![];
+        for board in self.list_team_boards(team_ids).await?.iter().filter(|board| board.deleted_at.is_none()) {
+            hits.extend(search::hit(SearchKind::Board, &board.id, board, &board.title, board.created_at, &terms));
+            for card in self.list_board_cards(&board.id).await? {
+                hits.extend(search::hit(SearchKind::Card, &card.id, board, &card.title, card.created_at, &terms));
+            }
+            for comment in self.list_board_comments(&board.id).await? {
+                hits.extend(search::hit(SearchKind::Comment, &comment.id, board, &comment.text, comment.created_at, &terms));
+            }
+        }
+        Ok(search::best(hits, limit))
+    }
+    // CONTENTS
+    // adds a whole board at once, all or nothing
+    async fn add_board_contents (&self, item: &BoardContents) -> Result<bool, MyError>;
+    // UNDO
+    // puts rows back, or forward again, in order and all or nothing, answering false with nothing changed
+    // when any row is no longer as the changes expect it, or removing one would take others along
+    // that the changes know nothing about
+    async fn apply_changes (&self, changes: &[RowChange]) -> Result<bool, MyError>;
+    // TEAMS
+    async fn add_team (&self, item: &Team) -> Result<bool, MyError>;
+    // the team along with its first member, all or nothing
+    async fn add_team_with_owner (&self, item: &Team, owner: &Membership) -> Result<bool, MyError>;
+    async fn list_teams (&self, ids: &Vec<String>) -> Result<Vec<Team>, MyError>;
+    // every team there is, for copying the whole storage
+    async fn list_all_teams (&self) -> Result<Vec<Team>, MyError>;
+    async fn get_team (&self, id: &String) -> Result<Team, MyError>;
+    async fn delete_team (&self, id: &String) -> Result<bool, MyError>;
+    // the team with its memberships, only while it has no boards, all or nothing,
+    // answering with every row it removed in the order it removed them
+    async fn delete_team_cascade (&self, id: &String) -> Result<IfEmpty<Vec<RowChange>>, MyError>;
+    // MEMBERSHIPS
+    async fn add_membership (&self, item: &Membership) -> Result<bool, MyError>;
+    async fn update_membership (&self, item: &Membership) -> Result<bool, MyError>;
+    async fn list_team_memberships (&self, team_id: &String) -> Result<Vec<Membership>, MyError>;
+    async fn list_user_memberships (&self, user_id: &String) -> Result<Vec<Membership>, MyError>;
+    async fn delete_membership (&self, id: &String) -> Result<bool, MyError>;
+    // INVITES
+    async fn add_invite (&self, item: &Invite) -> Result<bool, MyError>;
+    async fn list_board_invites (&self, board_id: &String) -> Result<Vec<Invite>, MyError>;
+    async fn get_invite (&self, id: &String) -> Result<Invite, MyError>;
+    async fn delete_invite (&self, id: &String) -> Result<bool, MyError>;
+}
+
+// any one stored item, tagged with what kind it is
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum Row {
+    Board(Board),
+    Column(Column),
+    Card(Card),
+    Group(CardGroup),
+    Vote(Vote),
+    Tag(Tag),
+    CardTag(CardTag),
+    Comment(Comment),
+    Revision(Revision),
+    ActionItem(ActionItem),
+    AuditEntry(AuditEntry),
+    Team(Team),
+    Membership(Membership),
+    Invite(Invite),
 }
 
-#[derive(Clone)]
+impl Row {
+    pub fn name (&self) -> &'static str {
+        match self {
+            Row::Board(_) => "Board",
+            Row::Column(_) => "Column",
+            Row::Card(_) => "Card",
+            Row::Group(_) => "Group",
+            Row::Vote(_) => "Vote",
+            Row::Tag(_) => "Tag",
+            Row::CardTag(_) => "CardTag",
+            Row::Comment(_) => "Comment",
+            Row::Revision(_) => "Revision",
+            Row::ActionItem(_) => "ActionItem",
+            Row::AuditEntry(_) => "AuditEntry",
+            Row::Team(_) => "Team",
+            Row::Membership(_) => "Membership",
+            Row::Invite(_) => "Invite",
+        }
+    }
+
+    // the same names the audit log uses for entity types
+    pub fn kind (&self) -> &'static str {
+        match self {
+            Row::Board(_) => "board",
+            Row::Column(_) => "column",
+            Row::Card(_) => "card",
+            Row::Group(_) => "group",
+            Row::Vote(_) => "vote",
+            Row::Tag(_) => "tag",
+            Row::CardTag(_) => "card_tag",
+            Row::Comment(_) => "comment",
+            Row::Revision(_) => "revision",
+            Row::ActionItem(_) => "action_item",
+            Row::AuditEntry(_) => "audit_entry",
+            Row::Team(_) => "team",
+            Row::Membership(_) => "membership",
+            Row::Invite(_) => "invite",
+        }
+    }
+
+    pub fn id (&self) -> &String {
+        match self {
+            Row::Board(item) => &item.id,
+            Row::Column(item) => &item.id,
+            Row::Card(item) => &item.id,
+            Row::Group(item) => &item.id,
+            Row::Vote(item) => &item.id,
+            Row::Tag(item) => &item.id,
+            Row::CardTag(item) => &item.id,
+            Row::Comment(item) => &item.id,
+            Row::Revision(item) => &item.id,
+            Row::ActionItem(item) => &item.id,
+            Row::AuditEntry(item) => &item.id,
+            Row::Team(item) => &item.id,
+            Row::Membership(item) => &item.id,
+            Row::Invite(item) => &item.id,
+        }
+    }
+}
+
+// built once and shared by every worker, behind the arc web::Data keeps it in
 pub struct Service {
     // box vs generics: dynamic vs static dispatch
     // https://stackoverflow.com/questions/48833009/the-fold-method-cannot-be-invoked-on-a-trait-object
     pub time_provider: Box<dyn TimeProvider>,
+    pub id_generator: Box<dyn IdGenerator>,
     pub config: Config,
     pub storage: Box<dyn Storage>,
+    pub undo: UndoHistory,
+    pub metrics: Metrics,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PoolStatus {
+    pub max_size: usize,
+    pub size: usize,
+    // below zero when there are requests waiting for a connection
+    pub available: isize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub storage: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Board {
     pub id: String,
+    pub team_id: String,
     pub title: String,
     pub owner: String,
     pub created_at: i64,
+    // archived boards are read-only, deleted ones are hidden until restored or purged
+    pub archived_at: Option<i64>,
+    pub deleted_at: Option<i64>,
+}
+
+// a delete that only goes ahead while nothing is left in what it removes,
+// checked in the same go as the delete, so nothing can be added in between
+#[derive(Debug, Clone)]
+pub enum IfEmpty<T> {
+    Deleted(T),
+    // nothing was deleted, this many of these are still there
+    NotEmpty(&'static str, usize),
+}
+
+// how many rows a hard board delete took with it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BoardDeletion {
+    pub boards: u64,
+    pub columns: u64,
+    pub cards: u64,
+    pub groups: u64,
+    pub votes: u64,
+    pub tags: u64,
+    pub card_tags: u64,
+    pub comments: u64,
+    pub revisions: u64,
+    pub action_items: u64,
+    pub invites: u64,
+}
+
+// a board with everything on it, in the order it should be inserted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoardContents {
+    pub board: Board,
+    pub columns: Vec<Column>,
+    pub groups: Vec<CardGroup>,
+    pub cards: Vec<Card>,
+    pub votes: Vec<Vote>,
+    pub tags: Vec<Tag>,
+    pub card_tags: Vec<CardTag>,
+    pub comments: Vec<Comment>,
+    pub action_items: Vec<ActionItem>,
+}
+
+#[derive(Deserialize)]
+pub struct CloneBoard {
+    pub title: Option<String>,
+    pub team_id: Option<String>,
+    #[serde(default)]
+    pub include_cards: bool,
 }
 
 #[derive(Deserialize)]
 pub struct CreateBoard {
+    pub team_id: String,
     pub title: String,
-    pub owner: String,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListFilter {
+    #[default]
+    Exclude,
+    Include,
+    Only,
+}
+
+impl ListFilter {
+    pub fn matches (&self, is_set: bool) -> bool {
+        match self {
+            ListFilter::Exclude => !is_set,
+            ListFilter::Include => true,
+            ListFilter::Only => is_set,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ListBoards {
+    #[serde(default)]
+    pub archived: ListFilter,
+    #[serde(default)]
+    pub deleted: ListFilter,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Column {
     pub id: String,
     pub board_id: String,
     pub title: String,
+    pub position: i32,
     pub created_at: i64,
 }
 
@@ -93,3 +372,437 @@ pub struct CreateColumn {
     pub board_id: String,
     pub title: String,
 }
+
+#[derive(Deserialize)]
+pub struct UpdateColumn {
+    pub title: Option<String>,
+    pub position: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Card {
+    pub id: String,
+    pub board_id: String,
+    pub column_id: String,
+    // stacked under this group, if any
+    pub group_id: Option<String>,
+    // folded into this other card, if any, but kept whole so it can be split back out
+    pub merged_into: Option<String>,
+    pub title: String,
+    pub author: String,
+    pub author_name: String,
+    pub position: i32,
+    pub created_at: i64,
+}
+
+#[derive(Deserialize)]
+pub struct CreateCard {
+    pub column_id: String,
+    pub group_id: Option<String>,
+    pub title: String,
+}
+
+#[derive(Deserialize)]
+pub struct EditCard {
+    pub title: String,
+}
+
+#[derive(Deserialize)]
+pub struct MoveCard {
+    pub column_id: Option<String>,
+    // left out keeps the card in whatever group it is in, null takes it out of it
+    #[serde(default, with = "serde_with::rust::double_option")]
+    pub group_id: Option<Option<String>>,
+    pub position: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct MergeCard {
+    pub card_id: String,
+}
+
+// a card as shown on the board, with the cards merged into it and all their votes
+#[derive(Serialize)]
+pub struct CardStack {
+    #[serde(flatten)]
+    pub card: Card,
+    pub votes: usize,
+    pub tag_ids: Vec<String>,
+    pub merged: Vec<CardStack>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CardGroup {
+    pub id: String,
+    pub board_id: String,
+    pub column_id: String,
+    pub title: String,
+    pub created_at: i64,
+}
+
+#[derive(Deserialize)]
+pub struct CreateGroup {
+    pub column_id: String,
+    pub title: String,
+}
+
+#[derive(Deserialize)]
+pub struct RenameGroup {
+    pub title: String,
+}
+
+#[derive(Serialize)]
+pub struct GroupStack {
+    #[serde(flatten)]
+    pub group: CardGroup,
+    pub votes: usize,
+    pub card_ids: Vec<String>,
+}
+
+// cast on exactly one of a card or a group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Vote {
+    pub id: String,
+    pub board_id: String,
+    pub card_id: Option<String>,
+    pub group_id: Option<String>,
+    pub author: String,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: String,
+    pub board_id: String,
+    pub title: String,
+    pub created_at: i64,
+}
+
+#[derive(Deserialize)]
+pub struct CreateTag {
+    pub board_id: String,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CardTag {
+    pub id: String,
+    pub board_id: String,
+    pub card_id: String,
+    pub tag_id: String,
+    pub created_at: i64,
+}
+
+#[derive(Deserialize)]
+pub struct TagCard {
+    pub tag_id: String,
+}
+
+// a thread under a card, oldest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comment {
+    pub id: String,
+    pub board_id: String,
+    pub card_id: String,
+    pub author: String,
+    pub author_name: String,
+    pub text: String,
+    pub created_at: i64,
+}
+
+#[derive(Deserialize)]
+pub struct CreateComment {
+    pub text: String,
+}
+
+#[derive(Deserialize)]
+pub struct EditComment {
+    pub text: String,
+}
+
+// one version of the text of a card, or of a comment on it when comment_id is set
+// the text it was written with is only kept once it is first edited
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Revision {
+    pub id: String,
+    pub board_id: String,
+    pub card_id: String,
+    pub comment_id: Option<String>,
+    pub text: String,
+    pub editor: String,
+    pub editor_name: String,
+    pub created_at: i64,
+}
+
+// what the team agreed to do about it, the part that outlives the retro
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionItem {
+    pub id: String,
+    pub board_id: String,
+    pub title: String,
+    pub assignee: Option<String>,
+    pub done: bool,
+    pub created_at: i64,
+}
+
+#[derive(Deserialize)]
+pub struct CreateActionItem {
+    pub board_id: String,
+    pub title: String,
+    pub assignee: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateActionItem {
+    pub title: Option<String>,
+    // an empty assignee unassigns it
+    pub assignee: Option<String>,
+    pub done: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct ExportBoard {
+    pub format: String,
+}
+
+#[derive(Deserialize)]
+pub struct ImportBoard {
+    pub team_id: String,
+    pub title: Option<String>,
+    // only reports what would be created
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+// what an import created, or would have
+#[derive(Serialize)]
+pub struct ImportSummary {
+    pub dry_run: bool,
+    pub board: Board,
+    pub columns: usize,
+    pub groups: usize,
+    pub cards: usize,
+    pub votes: usize,
+    pub tags: usize,
+    pub comments: usize,
+    pub action_items: usize,
+    pub warnings: Vec<String>,
+}
+
+// who changed what and from where, with the entity as it was before and after
+// seq is handed out by storage as the entry goes in, it is what tells the order of changes within a millisecond
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: String,
+    #[serde(default)]
+    pub seq: i64,
+    pub actor: String,
+    pub action: String,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub board_id: Option<String>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub ip: Option<String>,
+    pub created_at: i64,
+}
+
+// every field narrows the entries down, newest come first unless asked for oldest first
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuditFilter {
+    pub actor: Option<String>,
+    pub action: Option<String>,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<String>,
+    pub board_id: Option<String>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    // only entries past this seq, whichever way they come
+    pub after: Option<i64>,
+    #[serde(default)]
+    pub oldest_first: bool,
+    pub limit: Option<usize>,
+}
+
+// one change on a board, as the board history plays it back
+#[derive(Debug, Clone, Serialize)]
+pub struct BoardChange {
+    pub id: String,
+    pub seq: i64,
+    pub actor: String,
+    pub action: String,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub created_at: i64,
+}
+
+#[derive(Deserialize)]
+pub struct BoardHistory {
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    // the seq of the last change already seen, the next page starts after it
+    pub after: Option<i64>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct BoardStateAt {
+    pub at: i64,
+}
+
+// a board replayed from its history, which is only as complete as the audit log is,
+// and recording there is best effort, so this says so rather than pass for what was stored
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconstructedBoard {
+    pub reconstructed: bool,
+    pub at: i64,
+    #[serde(flatten)]
+    pub contents: BoardContents,
+}
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    pub team_id: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchKind {
+    Board,
+    Card,
+    Comment,
+}
+
+impl SearchKind {
+    pub fn as_str (&self) -> &'static str {
+        match self {
+            SearchKind::Board => "board",
+            SearchKind::Card => "card",
+            SearchKind::Comment => "comment",
+        }
+    }
+}
+
+impl std::str::FromStr for SearchKind {
+    type Err = MyError;
+
+    fn from_str (s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "board" => Ok(SearchKind::Board),
+            "card" => Ok(SearchKind::Card),
+            "comment" => Ok(SearchKind::Comment),
+            _ => Err(format!("Unknown search kind '{}'", s)),
+        }
+    }
+}
+
+// the snippet marks the matching words **like this**
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub kind: SearchKind,
+    pub id: String,
+    pub board_id: String,
+    pub board_title: String,
+    pub snippet: String,
+    pub rank: f32,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Team {
+    pub id: String,
+    pub name: String,
+    pub created_at: i64,
+}
+
+#[derive(Deserialize)]
+pub struct CreateTeam {
+    pub name: String,
+}
+
+// declared from least to most privileged, so roles can be compared with `>=`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Participant,
+    Facilitator,
+    Owner,
+}
+
+impl Role {
+    pub fn as_str (&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Participant => "participant",
+            Role::Facilitator => "facilitator",
+            Role::Owner => "owner",
+        }
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = MyError;
+
+    fn from_str (s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(Role::Viewer),
+            "participant" => Ok(Role::Participant),
+            "facilitator" => Ok(Role::Facilitator),
+            "owner" => Ok(Role::Owner),
+            _ => Err(format!("Unknown role '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Membership {
+    pub id: String,
+    pub team_id: String,
+    pub user_id: String,
+    pub role: Role,
+    pub created_at: i64,
+}
+
+#[derive(Deserialize)]
+pub struct UpsertMembership {
+    pub user_id: String,
+    pub role: Role,
+}
+
+// a board has at most one live invite, rotating it replaces the row and so
+// revokes every link and guest session signed for the old id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Invite {
+    pub id: String,
+    pub board_id: String,
+    pub created_by: String,
+    pub expires_at: i64,
+    pub created_at: i64,
+}
+
+#[derive(Serialize)]
+pub struct InviteLink {
+    pub board_id: String,
+    pub token: String,
+    pub expires_at: i64,
+}
+
+#[derive(Deserialize)]
+pub struct JoinInvite {
+    pub token: String,
+    pub display_name: String,
+}
+
+#[derive(Serialize)]
+pub struct GuestSession {
+    pub board_id: String,
+    pub user_id: String,
+    pub display_name: String,
+    pub token: String,
+    pub expires_at: i64,
+}
@@ -0,0 +1,38 @@
+
+use std::time::Duration;
+use actix_web::web;
+use tracing::{info, error};
+
+use crate::models::Service;
+use crate::audit::{record, Change, ACTION_PURGE, ENTITY_BOARD, ACTOR_SYSTEM};
+
+
+// hard deletes boards that stayed soft deleted for longer than the retention window
+pub async fn purge_deleted_boards (service: &Service) -> Result<usize, String> {
+    let deleted_before = service.time_provider.unix_ts_ms() - service.config.board_retention_ms;
+    let boards = service.storage.list_deleted_boards(deleted_before).await?;
+
+    let mut purged = 0;
+    for board in boards.iter() {
+        let deletion = service.storage.delete_board(&board.id).await?;
+        service.undo.forget_board(&board.id);
+        info!(board_id = %board.id, deletion = ?deletion, "purged board");
+        record(service, &String::from(ACTOR_SYSTEM), None,
+            Change::new(ACTION_PURGE, ENTITY_BOARD, &board.id, Some(&board.id)).before(board).after(&deletion)).await;
+        purged += deletion.boards as usize;
+    }
+    Ok(purged)
+}
+
+pub async fn run_purge (service: web::Data<Service>) {
+    // https://docs.rs/actix-rt/2.1.0/actix_rt/time/fn.interval.html
+    let mut interval = actix_rt::time::interval(Duration::from_millis(service.config.purge_interval_ms as u64));
+    loop {
+        interval.tick().await;
+        match purge_deleted_boards(&service).await {
+            Ok(0) => (),
+            Ok(purged) => info!(purged, "purged deleted boards"),
+            Err(why) => error!(error = %why, "Purge deleted boards failed!"),
+        }
+    }
+}
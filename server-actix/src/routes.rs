@@ -0,0 +1,107 @@
+use actix_web::web;
+
+use crate::config::Config;
+use crate::handlers::{
+    add_board, list_boards, get_board, delete_board, restore_board, purge_board, archive_board, unarchive_board, clone_board,
+    add_column, list_columns, update_column, delete_column,
+    add_card, list_cards, edit_card, move_card, delete_card, merge_card, unmerge_card,
+    add_group, list_groups, rename_group, delete_group,
+    vote_card, vote_group, list_votes, delete_vote,
+    get_invite, rotate_invite, delete_invite, join_invite,
+    add_team, list_teams, get_team, delete_team,
+    list_members, put_member, delete_member,
+    add_tag, list_tags, delete_tag, tag_card, untag_card,
+    add_comment, list_comments, edit_comment, delete_comment,
+    list_revisions, restore_revision,
+    add_action_item, list_action_items, update_action_item, delete_action_item,
+    export_board, import_board, import_board_from,
+    search,
+    list_audit,
+    board_history, board_state_at,
+    undo_board, redo_board,
+    get_metrics,
+    get_health, get_readiness,
+};
+
+
+// every route the server answers, so tests can mount the very same ones
+pub fn routes (cfg: &mut web::ServiceConfig, config: &Config) {
+    cfg
+        .route("/metrics", web::get().to(get_metrics))
+        .route("/healthz", web::get().to(get_health))
+        .route("/readyz", web::get().to(get_readiness))
+        .service(
+            web::scope("/api")
+                .service(
+                    web::resource("boards/import")
+                        .app_data(web::JsonConfig::default().limit(config.import_limit_bytes))
+                        .route(web::post().to(import_board))
+                )
+                .service(
+                    web::resource("boards/import/{format}")
+                        .app_data(web::PayloadConfig::new(config.import_limit_bytes))
+                        .route(web::post().to(import_board_from))
+                )
+                .route("boards", web::post().to(add_board))
+                .route("boards", web::get().to(list_boards))
+                .route("boards/{id}", web::get().to(get_board))
+                .route("boards/{id}", web::delete().to(delete_board))
+                .route("boards/{id}/restore", web::post().to(restore_board))
+                .route("boards/{id}/purge", web::delete().to(purge_board))
+                .route("boards/{id}/archive", web::post().to(archive_board))
+                .route("boards/{id}/unarchive", web::post().to(unarchive_board))
+                .route("boards/{id}/columns", web::get().to(list_columns))
+                .route("boards/{id}/cards", web::get().to(list_cards))
+                .route("boards/{id}/groups", web::get().to(list_groups))
+                .route("boards/{id}/votes", web::get().to(list_votes))
+                .route("boards/{id}/tags", web::get().to(list_tags))
+                .route("boards/{id}/clone", web::post().to(clone_board))
+                .route("boards/{id}/comments", web::get().to(list_comments))
+                .route("boards/{id}/action-items", web::get().to(list_action_items))
+                .route("boards/{id}/export", web::get().to(export_board))
+                .route("boards/{id}/history", web::get().to(board_history))
+                .route("boards/{id}/history/state", web::get().to(board_state_at))
+                .route("boards/{id}/undo", web::post().to(undo_board))
+                .route("boards/{id}/redo", web::post().to(redo_board))
+                .route("columns", web::post().to(add_column))
+                .route("columns/{id}", web::put().to(update_column))
+                .route("columns/{id}", web::delete().to(delete_column))
+                .route("cards", web::post().to(add_card))
+                .route("cards/{id}", web::put().to(edit_card))
+                .route("cards/{id}", web::delete().to(delete_card))
+                .route("cards/{id}/move", web::put().to(move_card))
+                .route("cards/{id}/merge", web::post().to(merge_card))
+                .route("cards/{id}/unmerge", web::post().to(unmerge_card))
+                .route("cards/{id}/votes", web::post().to(vote_card))
+                .route("cards/{id}/tags", web::post().to(tag_card))
+                .route("cards/{id}/tags/{tag_id}", web::delete().to(untag_card))
+                .route("cards/{id}/comments", web::post().to(add_comment))
+                .route("cards/{id}/revisions", web::get().to(list_revisions))
+                .route("cards/{id}/revisions/{revision_id}/restore", web::post().to(restore_revision))
+                .route("comments/{id}", web::put().to(edit_comment))
+                .route("comments/{id}", web::delete().to(delete_comment))
+                .route("action-items", web::post().to(add_action_item))
+                .route("action-items/{id}", web::put().to(update_action_item))
+                .route("action-items/{id}", web::delete().to(delete_action_item))
+                .route("groups", web::post().to(add_group))
+                .route("groups/{id}", web::put().to(rename_group))
+                .route("groups/{id}", web::delete().to(delete_group))
+                .route("groups/{id}/votes", web::post().to(vote_group))
+                .route("votes/{id}", web::delete().to(delete_vote))
+                .route("tags", web::post().to(add_tag))
+                .route("tags/{id}", web::delete().to(delete_tag))
+                .route("boards/{id}/invite", web::get().to(get_invite))
+                .route("boards/{id}/invite", web::post().to(rotate_invite))
+                .route("boards/{id}/invite", web::delete().to(delete_invite))
+                .route("invites/join", web::post().to(join_invite))
+                .route("search", web::get().to(search))
+                .route("admin/audit", web::get().to(list_audit))
+                .route("teams", web::post().to(add_team))
+                .route("teams", web::get().to(list_teams))
+                .route("teams/{id}", web::get().to(get_team))
+                .route("teams/{id}", web::delete().to(delete_team))
+                .route("teams/{id}/members", web::get().to(list_members))
+                .route("teams/{id}/members", web::put().to(put_member))
+                .route("teams/{id}/members/{user_id}", web::delete().to(delete_member))
+        );
+}
@@ -0,0 +1,75 @@
+
+use crate::models::{SearchKind, SearchHit, Board};
+
+
+// around the first match, like postgres' ts_headline defaults
+const SNIPPET_WORDS_BEFORE: usize = 5;
+const SNIPPET_WORDS_AFTER: usize = 15;
+const HIGHLIGHT_START: &'static str = "**";
+const HIGHLIGHT_STOP: &'static str = "**";
+const ELLIPSIS: &'static str = "...";
+
+
+// the simple index for backends without full-text search of their own:
+// lowercase words split on anything that is not a letter or digit,
+// and a query term matches any word it starts, so "deploy" finds "deploys"
+
+pub fn terms (text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+// a word here is whatever sits between spaces, so it can hold several terms, as in "ci/cd"
+fn matches (word: &str, terms: &Vec<String>) -> bool {
+    self::terms(word).iter().any(|part| terms.iter().any(|term| part.starts_with(term.as_str())))
+}
+
+fn snippet (text: &str, terms: &Vec<String>) -> String {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let first = words.iter().position(|word| matches(word, terms)).unwrap_or(0);
+    let start = first.saturating_sub(SNIPPET_WORDS_BEFORE);
+    let end = (first + SNIPPET_WORDS_AFTER).min(words.len());
+
+    let mut snippet: Vec<String> = words[start..end].iter()
+        .map(|word| match matches(word, terms) {
+            true => format!("{}{}{}", HIGHLIGHT_START, word, HIGHLIGHT_STOP),
+            false => word.to_string(),
+        })
+        .collect();
+    if start > 0 {
+        snippet.insert(0, String::from(ELLIPSIS));
+    }
+    if end < words.len() {
+        snippet.push(String::from(ELLIPSIS));
+    }
+    snippet.join(" ")
+}
+
+// every term has to be in the text, and the more of the text they make up the better
+pub fn hit (kind: SearchKind, id: &String, board: &Board, text: &String, created_at: i64, terms: &Vec<String>) -> Option<SearchHit> {
+    let words = self::terms(text);
+    if terms.is_empty() || !terms.iter().all(|term| words.iter().any(|word| word.starts_with(term.as_str()))) {
+        return None
+    }
+    let matched = words.iter().filter(|word| terms.iter().any(|term| word.starts_with(term.as_str()))).count();
+
+    Some(SearchHit {
+        kind: kind,
+        id: id.clone(),
+        board_id: board.id.clone(),
+        board_title: board.title.clone(),
+        snippet: snippet(text, terms),
+        rank: matched as f32 / words.len() as f32,
+        created_at: created_at,
+    })
+}
+
+pub fn best (mut hits: Vec<SearchHit>, limit: usize) -> Vec<SearchHit> {
+    hits.sort_by(|a, b| b.rank.partial_cmp(&a.rank)
+        .unwrap_or(std::cmp::Ordering::Equal)
+        .then(b.created_at.cmp(&a.created_at)));
+    hits.truncate(limit);
+    hits
+}
@@ -0,0 +1,53 @@
+use tracing::info;
+
+use crate::time_provider::{SystemTimeProvider, TimeProvider};
+use crate::id_generator::{IdGenerator, RandomIdGenerator};
+use crate::config::Config;
+use crate::models::{MyError, Storage, Service};
+use crate::storage::{invalid, postgres, events, metered};
+use crate::undo::UndoHistory;
+use crate::metrics::Metrics;
+
+
+pub fn build_storage (config: &Config, time_provider: Box<dyn TimeProvider>) -> Result<Box<dyn Storage>, MyError> {
+    // https://stackoverflow.com/questions/25383488/how-to-match-a-string-against-string-literals-in-rust
+    match config.provider.as_str() {
+        "postgres" => match postgres::PostgresStorage::new(time_provider, &config.postgres) {
+            Err(why) => Err(format!("Invalid postgres storage provider! {}", why)),
+            Ok(storage) => Ok(Box::new(storage)),
+        },
+        "events" => match events::EventStorage::new(time_provider, &config.events) {
+            Err(why) => Err(format!("Invalid events storage provider! {}", why)),
+            Ok(storage) => Ok(Box::new(storage)),
+        },
+        // nothing survives a restart, for trying things out
+        "memory" => Ok(Box::new(events::EventStorage::in_memory(time_provider))),
+        _ => Err(format!("Invalid or no storage provider given! '{}'", config.provider)),
+    }
+}
+
+// an invalid storage still makes a service, answering everything with its error, unless it is required
+pub fn build_service (config: Config, metrics: Metrics) -> Result<Service, MyError> {
+    // https://stackoverflow.com/questions/28219519/are-polymorphic-variables-allowed
+    build_service_with(config, metrics, Box::new(SystemTimeProvider {}), Box::new(RandomIdGenerator {}))
+}
+
+// the same, on whatever clock and ids it is given, so tests can have them fixed
+pub fn build_service_with (config: Config, metrics: Metrics, time_provider: Box<dyn TimeProvider>, id_generator: Box<dyn IdGenerator>) -> Result<Service, MyError> {
+    let storage = match build_storage(&config, time_provider.clone()) {
+        Ok(storage) => storage,
+        Err(why) if config.require_storage => return Err(why),
+        Err(why) => Box::new(invalid::InvalidStorage { error: why }),
+    };
+    info!(storage = storage.name(), "created storage");
+    let storage: Box<dyn Storage> = Box::new(metered::MeteredStorage::new(storage, metrics.clone()));
+
+    Ok(Service {
+        time_provider: time_provider,
+        id_generator: id_generator,
+        undo: UndoHistory::new(config.undo_limit),
+        config: config,
+        storage: storage,
+        metrics: metrics,
+    })
+}
@@ -0,0 +1,1468 @@
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, ErrorKind, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use async_trait::async_trait;
+use actix_rt::task::spawn_blocking;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use serde::{Serialize, Deserialize};
+use tracing::{warn, error};
+
+use crate::time_provider::TimeProvider;
+use crate::config::EventsConfig;
+use crate::undo::{RowChange, same, touched, check_undoable};
+use crate::search;
+use crate::models::{MyError, Storage, Row, SearchKind, SearchHit, Board, BoardDeletion, BoardContents, Column, Card, CardGroup, Vote, Tag, CardTag, Comment, Revision, ActionItem, AuditEntry, AuditFilter, Team, Membership, Invite, IfEmpty};
+
+
+const EVENTS_FILE: &'static str = "events.jsonl";
+const SNAPSHOT_FILE: &'static str = "snapshot.json";
+const SNAPSHOT_TMP_FILE: &'static str = "snapshot.json.tmp";
+const LOCK_FILE: &'static str = "lock";
+
+
+// deleted rows are kept whole, so the log alone says what was lost
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    Added { row: Row },
+    Updated { row: Row },
+    Deleted { row: Row },
+}
+
+impl Event {
+    pub fn row (&self) -> &Row {
+        match self {
+            Event::Added { row } | Event::Updated { row } | Event::Deleted { row } => row,
+        }
+    }
+}
+
+// one line of the log, every event in it applies together or not at all
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub seq: u64,
+    pub at: i64,
+    pub events: Vec<Event>,
+}
+
+
+fn require<T> (table: &BTreeMap<String, T>, name: &'static str, id: &String) -> Result<(), MyError> {
+    if table.contains_key(id) {
+        Ok(())
+    } else {
+        Err(format!("No {} with id {}", name, id))
+    }
+}
+
+fn require_some<T> (table: &BTreeMap<String, T>, name: &'static str, id: &Option<String>) -> Result<(), MyError> {
+    match id {
+        Some(id) => require(table, name, id),
+        None => Ok(()),
+    }
+}
+
+fn put<T> (table: &mut BTreeMap<String, T>, id: &String, item: T, wrap: fn(T) -> Row) -> Option<Row> {
+    table.insert(id.clone(), item).map(wrap)
+}
+
+fn take<T> (table: &mut BTreeMap<String, T>, id: &String, wrap: fn(T) -> Row) -> Option<Row> {
+    table.remove(id).map(wrap)
+}
+
+fn cloned<T : Clone> (table: &BTreeMap<String, T>, keep: impl Fn(&T) -> bool) -> Vec<T> {
+    table.values().filter(|item| keep(item)).cloned().collect()
+}
+
+// the text search goes by, for the rows it finds
+fn searchable (row: &Row) -> Option<(SearchKind, String, String)> {
+    match row {
+        Row::Board(item) => Some((SearchKind::Board, item.id.clone(), item.title.clone())),
+        Row::Card(item) => Some((SearchKind::Card, item.id.clone(), item.title.clone())),
+        Row::Comment(item) => Some((SearchKind::Comment, item.id.clone(), item.text.clone())),
+        _ => None,
+    }
+}
+
+
+// current state of everything, as derived from the log
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Projection {
+    boards: BTreeMap<String, Board>,
+    columns: BTreeMap<String, Column>,
+    cards: BTreeMap<String, Card>,
+    groups: BTreeMap<String, CardGroup>,
+    votes: BTreeMap<String, Vote>,
+    tags: BTreeMap<String, Tag>,
+    card_tags: BTreeMap<String, CardTag>,
+    comments: BTreeMap<String, Comment>,
+    // newer than the rest, older snapshots come without
+    #[serde(default)]
+    revisions: BTreeMap<String, Revision>,
+    action_items: BTreeMap<String, ActionItem>,
+    audit_entries: BTreeMap<String, AuditEntry>,
+    teams: BTreeMap<String, Team>,
+    memberships: BTreeMap<String, Membership>,
+    invites: BTreeMap<String, Invite>,
+    // every search term and what has it, kept up with every put and take rather than written down
+    #[serde(skip)]
+    terms: BTreeMap<String, BTreeSet<(SearchKind, String)>>,
+}
+
+impl Projection {
+    fn contains (&self, row: &Row) -> bool {
+        let id = row.id();
+        match row {
+            Row::Board(_) => self.boards.contains_key(id),
+            Row::Column(_) => self.columns.contains_key(id),
+            Row::Card(_) => self.cards.contains_key(id),
+            Row::Group(_) => self.groups.contains_key(id),
+            Row::Vote(_) => self.votes.contains_key(id),
+            Row::Tag(_) => self.tags.contains_key(id),
+            Row::CardTag(_) => self.card_tags.contains_key(id),
+            Row::Comment(_) => self.comments.contains_key(id),
+            Row::Revision(_) => self.revisions.contains_key(id),
+            Row::ActionItem(_) => self.action_items.contains_key(id),
+            Row::AuditEntry(_) => self.audit_entries.contains_key(id),
+            Row::Team(_) => self.teams.contains_key(id),
+            Row::Membership(_) => self.memberships.contains_key(id),
+            Row::Invite(_) => self.invites.contains_key(id),
+        }
+    }
+
+    // the row as it is now, if it is there at all
+    fn current (&self, like: &Row) -> Option<Row> {
+        let id = like.id();
+        match like {
+            Row::Board(_) => self.boards.get(id).cloned().map(Row::Board),
+            Row::Column(_) => self.columns.get(id).cloned().map(Row::Column),
+            Row::Card(_) => self.cards.get(id).cloned().map(Row::Card),
+            Row::Group(_) => self.groups.get(id).cloned().map(Row::Group),
+            Row::Vote(_) => self.votes.get(id).cloned().map(Row::Vote),
+            Row::Tag(_) => self.tags.get(id).cloned().map(Row::Tag),
+            Row::CardTag(_) => self.card_tags.get(id).cloned().map(Row::CardTag),
+            Row::Comment(_) => self.comments.get(id).cloned().map(Row::Comment),
+            Row::Revision(_) => self.revisions.get(id).cloned().map(Row::Revision),
+            Row::ActionItem(_) => self.action_items.get(id).cloned().map(Row::ActionItem),
+            Row::AuditEntry(_) => self.audit_entries.get(id).cloned().map(Row::AuditEntry),
+            Row::Team(_) => self.teams.get(id).cloned().map(Row::Team),
+            Row::Membership(_) => self.memberships.get(id).cloned().map(Row::Membership),
+            Row::Invite(_) => self.invites.get(id).cloned().map(Row::Invite),
+        }
+    }
+
+    // kind and id of whatever points straight at the row, and would go or be unlinked along with it
+    fn dependents (&self, row: &Row) -> Vec<(&'static str, String)> {
+        let id = row.id();
+        let mut found = vec![];
+        match row {
+            Row::Column(_) => {
+                found.extend(cloned(&self.cards, |item| &item.column_id == id).into_iter().map(|item| ("card", item.id)));
+                found.extend(cloned(&self.groups, |item| &item.column_id == id).into_iter().map(|item| ("group", item.id)));
+            },
+            Row::Card(_) => {
+                found.extend(cloned(&self.cards, |item| item.merged_into.as_ref() == Some(id)).into_iter().map(|item| ("card", item.id)));
+                found.extend(cloned(&self.votes, |item| item.card_id.as_ref() == Some(id)).into_iter().map(|item| ("vote", item.id)));
+                found.extend(cloned(&self.card_tags, |item| &item.card_id == id).into_iter().map(|item| ("card_tag", item.id)));
+                found.extend(cloned(&self.comments, |item| &item.card_id == id).into_iter().map(|item| ("comment", item.id)));
+                found.extend(cloned(&self.revisions, |item| &item.card_id == id).into_iter().map(|item| ("revision", item.id)));
+            },
+            Row::Group(_) => {
+                found.extend(cloned(&self.cards, |item| item.group_id.as_ref() == Some(id)).into_iter().map(|item| ("card", item.id)));
+                found.extend(cloned(&self.votes, |item| item.group_id.as_ref() == Some(id)).into_iter().map(|item| ("vote", item.id)));
+            },
+            Row::Tag(_) => {
+                found.extend(cloned(&self.card_tags, |item| &item.tag_id == id).into_iter().map(|item| ("card_tag", item.id)));
+            },
+            Row::Comment(_) => {
+                found.extend(cloned(&self.revisions, |item| item.comment_id.as_ref() == Some(id)).into_iter().map(|item| ("revision", item.id)));
+            },
+            _ => (),
+        }
+        found
+    }
+
+    // the same references the postgres foreign keys hold to
+    fn check_references (&self, row: &Row) -> Result<(), MyError> {
+        match row {
+            Row::Column(item) => require(&self.boards, "Board", &item.board_id),
+            Row::Card(item) => {
+                require(&self.boards, "Board", &item.board_id)?;
+                require(&self.columns, "Column", &item.column_id)?;
+                require_some(&self.groups, "Group", &item.group_id)?;
+                require_some(&self.cards, "Card", &item.merged_into)
+            },
+            Row::Group(item) => {
+                require(&self.boards, "Board", &item.board_id)?;
+                require(&self.columns, "Column", &item.column_id)
+            },
+            Row::Vote(item) => {
+                require(&self.boards, "Board", &item.board_id)?;
+                require_some(&self.cards, "Card", &item.card_id)?;
+                require_some(&self.groups, "Group", &item.group_id)
+            },
+            Row::Tag(item) => require(&self.boards, "Board", &item.board_id),
+            Row::CardTag(item) => {
+                require(&self.boards, "Board", &item.board_id)?;
+                require(&self.cards, "Card", &item.card_id)?;
+                require(&self.tags, "Tag", &item.tag_id)
+            },
+            Row::Comment(item) => {
+                require(&self.boards, "Board", &item.board_id)?;
+                require(&self.cards, "Card", &item.card_id)
+            },
+            Row::Revision(item) => {
+                require(&self.boards, "Board", &item.board_id)?;
+                require(&self.cards, "Card", &item.card_id)?;
+                require_some(&self.comments, "Comment", &item.comment_id)
+            },
+            Row::ActionItem(item) => require(&self.boards, "Board", &item.board_id),
+            Row::Membership(item) => {
+                require(&self.teams, "Team", &item.team_id)?;
+                let taken = self.memberships.values()
+                    .any(|other| other.id != item.id && other.team_id == item.team_id && other.user_id == item.user_id);
+                if taken {
+                    return Err(format!("User {} is already a member of team {}", item.user_id, item.team_id))
+                }
+                Ok(())
+            },
+            Row::Invite(item) => require(&self.boards, "Board", &item.board_id),
+            Row::Board(_) | Row::AuditEntry(_) | Row::Team(_) => Ok(()),
+        }
+    }
+
+    fn check (&self, event: &Event) -> Result<(), MyError> {
+        let row = event.row();
+        match event {
+            Event::Updated { row: Row::AuditEntry(_) } | Event::Deleted { row: Row::AuditEntry(_) } =>
+                Err(String::from("Audit entries cannot be changed or removed")),
+            Event::Added { .. } if self.contains(row) => Err(format!("Duplicate id {}", row.id())),
+            Event::Updated { .. } | Event::Deleted { .. } if !self.contains(row) => Err(format!("No {} with id {}", row.name(), row.id())),
+            Event::Added { .. } | Event::Updated { .. } => self.check_references(row),
+            Event::Deleted { .. } => Ok(()),
+        }
+    }
+
+    fn put (&mut self, row: Row) -> Option<Row> {
+        let indexed = searchable(&row);
+        let replaced = match row {
+            Row::Board(item) => put(&mut self.boards, &item.id.clone(), item, Row::Board),
+            Row::Column(item) => put(&mut self.columns, &item.id.clone(), item, Row::Column),
+            Row::Card(item) => put(&mut self.cards, &item.id.clone(), item, Row::Card),
+            Row::Group(item) => put(&mut self.groups, &item.id.clone(), item, Row::Group),
+            Row::Vote(item) => put(&mut self.votes, &item.id.clone(), item, Row::Vote),
+            Row::Tag(item) => put(&mut self.tags, &item.id.clone(), item, Row::Tag),
+            Row::CardTag(item) => put(&mut self.card_tags, &item.id.clone(), item, Row::CardTag),
+            Row::Comment(item) => put(&mut self.comments, &item.id.clone(), item, Row::Comment),
+            Row::Revision(item) => put(&mut self.revisions, &item.id.clone(), item, Row::Revision),
+            Row::ActionItem(item) => put(&mut self.action_items, &item.id.clone(), item, Row::ActionItem),
+            Row::AuditEntry(item) => put(&mut self.audit_entries, &item.id.clone(), item, Row::AuditEntry),
+            Row::Team(item) => put(&mut self.teams, &item.id.clone(), item, Row::Team),
+            Row::Membership(item) => put(&mut self.memberships, &item.id.clone(), item, Row::Membership),
+            Row::Invite(item) => put(&mut self.invites, &item.id.clone(), item, Row::Invite),
+        };
+        if let Some(previous) = &replaced {
+            self.unindex(previous);
+        }
+        if let Some((kind, id, text)) = indexed {
+            for term in search::terms(&text) {
+                self.terms.entry(term).or_default().insert((kind, id.clone()));
+            }
+        }
+        replaced
+    }
+
+    fn take (&mut self, row: &Row) -> Option<Row> {
+        let id = row.id();
+        let taken = match row {
+            Row::Board(_) => take(&mut self.boards, id, Row::Board),
+            Row::Column(_) => take(&mut self.columns, id, Row::Column),
+            Row::Card(_) => take(&mut self.cards, id, Row::Card),
+            Row::Group(_) => take(&mut self.groups, id, Row::Group),
+            Row::Vote(_) => take(&mut self.votes, id, Row::Vote),
+            Row::Tag(_) => take(&mut self.tags, id, Row::Tag),
+            Row::CardTag(_) => take(&mut self.card_tags, id, Row::CardTag),
+            Row::Comment(_) => take(&mut self.comments, id, Row::Comment),
+            Row::Revision(_) => take(&mut self.revisions, id, Row::Revision),
+            Row::ActionItem(_) => take(&mut self.action_items, id, Row::ActionItem),
+            Row::AuditEntry(_) => take(&mut self.audit_entries, id, Row::AuditEntry),
+            Row::Team(_) => take(&mut self.teams, id, Row::Team),
+            Row::Membership(_) => take(&mut self.memberships, id, Row::Membership),
+            Row::Invite(_) => take(&mut self.invites, id, Row::Invite),
+        };
+        if let Some(taken) = &taken {
+            self.unindex(taken);
+        }
+        taken
+    }
+
+    fn unindex (&mut self, row: &Row) {
+        if let Some((kind, id, text)) = searchable(row) {
+            for term in search::terms(&text) {
+                if let Some(found) = self.terms.get_mut(&term) {
+                    found.remove(&(kind, id.clone()));
+                    if found.is_empty() {
+                        self.terms.remove(&term);
+                    }
+                }
+            }
+        }
+    }
+
+    // snapshots come without the terms, so they are put together again from the rows
+    fn reindex (&mut self) {
+        let rows: Vec<Row> = self.boards.values().cloned().map(Row::Board)
+            .chain(self.cards.values().cloned().map(Row::Card))
+            .chain(self.comments.values().cloned().map(Row::Comment))
+            .collect();
+        for row in rows {
+            self.put(row);
+        }
+    }
+
+    // what has every one of the terms, each matching any word it starts
+    fn search (&self, terms: &[String]) -> BTreeSet<(SearchKind, String)> {
+        let mut found: Option<BTreeSet<(SearchKind, String)>> = None;
+        for term in terms.iter() {
+            let having: BTreeSet<(SearchKind, String)> = self.terms.range(term.clone()..)
+                .take_while(|(word, _)| word.starts_with(term.as_str()))
+                .flat_map(|(_, rows)| rows.iter().cloned())
+                .collect();
+            found = Some(match found {
+                Some(found) => found.intersection(&having).cloned().collect(),
+                None => having,
+            });
+        }
+        found.unwrap_or_default()
+    }
+
+    // hands back whatever the event replaced, so it can be undone
+    pub fn apply (&mut self, event: &Event) -> Option<Row> {
+        match event {
+            Event::Added { row } | Event::Updated { row } => self.put(row.clone()),
+            Event::Deleted { row } => self.take(row),
+        }
+    }
+
+    fn undo (&mut self, event: &Event, previous: Option<Row>) {
+        self.take(event.row());
+        if let Some(previous) = previous {
+            self.put(previous);
+        }
+    }
+
+    // checks and applies every event in turn, and puts everything back as it was if any one fails
+    fn apply_all (&mut self, events: &[Event]) -> Result<Vec<Option<Row>>, MyError> {
+        let mut replaced = vec![];
+        for event in events.iter() {
+            if let Err(why) = self.check(event) {
+                self.undo_all(&events[..replaced.len()], replaced);
+                return Err(why)
+            }
+            replaced.push(self.apply(event));
+        }
+        Ok(replaced)
+    }
+
+    fn undo_all (&mut self, events: &[Event], replaced: Vec<Option<Row>>) {
+        for (event, previous) in events.iter().zip(replaced).rev() {
+            self.undo(event, previous);
+        }
+    }
+
+    // the row and everything that cascades from it, the same as the postgres foreign keys would
+    fn deletion (&self, row: Row) -> Vec<Event> {
+        let mut cascade = Cascade { projection: self, gone: HashSet::new(), cards: BTreeMap::new(), events: vec![] };
+        cascade.delete(row);
+        cascade.finish()
+    }
+}
+
+struct Cascade<'a> {
+    projection: &'a Projection,
+    gone: HashSet<String>,
+    // cards that only lose a reference, changed in place until the end
+    cards: BTreeMap<String, Card>,
+    events: Vec<Event>,
+}
+
+impl<'a> Cascade<'a> {
+    fn card (&mut self, id: &String) -> Option<&mut Card> {
+        let projection = self.projection;
+        if !self.cards.contains_key(id) {
+            self.cards.insert(id.clone(), projection.cards.get(id)?.clone());
+        }
+        self.cards.get_mut(id)
+    }
+
+    fn delete (&mut self, row: Row) {
+        if !self.gone.insert(row.id().clone()) {
+            return
+        }
+        let projection = self.projection;
+        match &row {
+            Row::Board(board) => {
+                let id = &board.id;
+                let children = cloned(&projection.revisions, |item| &item.board_id == id).into_iter().map(Row::Revision)
+                    .chain(cloned(&projection.comments, |item| &item.board_id == id).into_iter().map(Row::Comment))
+                    .chain(cloned(&projection.action_items, |item| &item.board_id == id).into_iter().map(Row::ActionItem))
+                    .chain(cloned(&projection.card_tags, |item| &item.board_id == id).into_iter().map(Row::CardTag))
+                    .chain(cloned(&projection.tags, |item| &item.board_id == id).into_iter().map(Row::Tag))
+                    .chain(cloned(&projection.votes, |item| &item.board_id == id).into_iter().map(Row::Vote))
+                    .chain(cloned(&projection.cards, |item| &item.board_id == id).into_iter().map(Row::Card))
+                    .chain(cloned(&projection.groups, |item| &item.board_id == id).into_iter().map(Row::Group))
+                    .chain(cloned(&projection.columns, |item| &item.board_id == id).into_iter().map(Row::Column))
+                    .chain(cloned(&projection.invites, |item| &item.board_id == id).into_iter().map(Row::Invite))
+                    .collect::<Vec<Row>>();
+                // everything on the board goes, so nothing needs unlinking along the way
+                for child in children.into_iter() {
+                    if self.gone.insert(child.id().clone()) {
+                        self.events.push(Event::Deleted { row: child });
+                    }
+                }
+            },
+            Row::Column(column) => {
+                for group in cloned(&projection.groups, |item| item.column_id == column.id) {
+                    self.delete(Row::Group(group));
+                }
+                for card in cloned(&projection.cards, |item| item.column_id == column.id) {
+                    self.delete(Row::Card(card));
+                }
+            },
+            Row::Group(group) => {
+                for vote in cloned(&projection.votes, |item| item.group_id.as_ref() == Some(&group.id)) {
+                    self.delete(Row::Vote(vote));
+                }
+                for card in cloned(&projection.cards, |item| item.group_id.as_ref() == Some(&group.id)) {
+                    if let Some(card) = self.card(&card.id) {
+                        card.group_id = None;
+                    }
+                }
+            },
+            Row::Card(card) => {
+                for vote in cloned(&projection.votes, |item| item.card_id.as_ref() == Some(&card.id)) {
+                    self.delete(Row::Vote(vote));
+                }
+                for card_tag in cloned(&projection.card_tags, |item| item.card_id == card.id) {
+                    self.delete(Row::CardTag(card_tag));
+                }
+                for revision in cloned(&projection.revisions, |item| item.card_id == card.id) {
+                    self.delete(Row::Revision(revision));
+                }
+                for comment in cloned(&projection.comments, |item| item.card_id == card.id) {
+                    self.delete(Row::Comment(comment));
+                }
+                for merged in cloned(&projection.cards, |item| item.merged_into.as_ref() == Some(&card.id)) {
+                    if let Some(merged) = self.card(&merged.id) {
+                        merged.merged_into = None;
+                    }
+                }
+            },
+            Row::Comment(comment) => {
+                for revision in cloned(&projection.revisions, |item| item.comment_id.as_ref() == Some(&comment.id)) {
+                    self.delete(Row::Revision(revision));
+                }
+            },
+            Row::Tag(tag) => {
+                for card_tag in cloned(&projection.card_tags, |item| item.tag_id == tag.id) {
+                    self.delete(Row::CardTag(card_tag));
+                }
+            },
+            Row::Team(team) => {
+                for membership in cloned(&projection.memberships, |item| item.team_id == team.id) {
+                    self.delete(Row::Membership(membership));
+                }
+            },
+            _ => (),
+        }
+        self.events.push(Event::Deleted { row: row });
+    }
+
+    fn finish (self) -> Vec<Event> {
+        let gone = self.gone;
+        let mut events = self.events;
+        events.extend(self.cards.into_iter()
+            .filter(|(id, _)| !gone.contains(id))
+            .map(|(_, card)| Event::Updated { row: Row::Card(card) }));
+        events
+    }
+}
+
+
+#[derive(Serialize)]
+struct SnapshotOut<'a> {
+    seq: u64,
+    projection: &'a Projection,
+}
+
+#[derive(Deserialize)]
+struct SnapshotIn {
+    seq: u64,
+    projection: Projection,
+}
+
+// what records get appended to, the events file itself but for tests that need writes to fail
+trait Journal: Write + Send {
+    fn len (&self) -> io::Result<u64>;
+    fn set_len (&self, len: u64) -> io::Result<()>;
+    fn sync_data (&self) -> io::Result<()>;
+}
+
+impl Journal for File {
+    fn len (&self) -> io::Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+
+    fn set_len (&self, len: u64) -> io::Result<()> {
+        File::set_len(self, len)
+    }
+
+    fn sync_data (&self) -> io::Result<()> {
+        File::sync_data(self)
+    }
+}
+
+// a rename is only durable once the directory holding it is synced too
+fn sync_dir (dir: &PathBuf) -> Result<(), MyError> {
+    File::open(dir).and_then(|dir| dir.sync_all())
+        .map_err(|why| format!("Failed syncing {:?}: {}", dir, why))
+}
+
+fn open_events (path: &PathBuf) -> Result<File, MyError> {
+    OpenOptions::new().create(true).append(true).open(path)
+        .map_err(|why| format!("Failed opening event log: {}", why))
+}
+
+// nothing there yet is fine, anything else unreadable has to stop the start
+fn read_if_there (path: &PathBuf, name: &'static str) -> Result<Option<String>, MyError> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text)),
+        Err(why) if why.kind() == ErrorKind::NotFound => Ok(None),
+        Err(why) => Err(format!("Failed reading {}: {}", name, why)),
+    }
+}
+
+struct LogFiles {
+    dir: PathBuf,
+    // held for as long as the log is open, and let go of by the system however the process ends
+    _lock: File,
+    events: Box<dyn Journal>,
+    snapshot_every: u64,
+    // set once the log could not be put back to its last record, after which nothing more is written
+    broken: Option<String>,
+}
+
+impl LogFiles {
+    // the whole line or none of it, anything partial would end up in the middle of the log with the next one
+    fn append (&mut self, record: &Record) -> Result<(), MyError> {
+        if let Some(why) = &self.broken {
+            return Err(format!("Event log stopped taking writes: {}", why))
+        }
+        let line = serde_json::to_string(record).map_err(|why| format!("Failed writing event: {}", why))?;
+        let len = self.events.len().map_err(|why| format!("Failed writing event: {}", why))?;
+        let written = self.events.write_all(format!("{}\n", line).as_bytes())
+            .and_then(|_| self.events.sync_data());
+        if let Err(why) = written {
+            // whatever made it to disk goes again, or the next start would replay a record never acknowledged
+            if let Err(cut) = self.events.set_len(len).and_then(|_| self.events.sync_data()) {
+                error!(error = %cut, "Failed cutting the event log back, no longer writing to it!");
+                self.broken = Some(format!("failed cutting it back after {}: {}", why, cut));
+            }
+            return Err(format!("Failed writing event: {}", why))
+        }
+        Ok(())
+    }
+
+    // everything in the log so far is in the snapshot now, so the next start reads only what comes after it,
+    // the old log is kept alongside as events-<seq>.jsonl and never read again
+    fn roll_over (&mut self, seq: u64) -> Result<(), MyError> {
+        let path = self.dir.join(EVENTS_FILE);
+        fs::rename(&path, self.dir.join(format!("events-{}.jsonl", seq)))
+            .map_err(|why| format!("Failed rolling over event log: {}", why))?;
+        match open_events(&path) {
+            Ok(events) => self.events = Box::new(events),
+            // still appending to the renamed one would lose those writes on the next start
+            Err(why) => {
+                self.broken = Some(why.clone());
+                return Err(why)
+            },
+        }
+        sync_dir(&self.dir)
+    }
+}
+
+pub struct EventLog {
+    projection: Projection,
+    seq: u64,
+    snapshot_seq: u64,
+    // none when kept in memory only
+    files: Option<LogFiles>,
+}
+
+impl EventLog {
+    fn in_memory () -> Self {
+        EventLog {
+            projection: Projection::default(),
+            seq: 0,
+            snapshot_seq: 0,
+            files: None,
+        }
+    }
+
+    // starts from the latest snapshot, if any, and replays whatever came after it
+    fn open (dir: PathBuf, snapshot_every: u64) -> Result<Self, MyError> {
+        fs::create_dir_all(&dir).map_err(|why| format!("Failed creating {:?}: {}", dir, why))?;
+
+        // two processes appending to the one log would each replay only their own writes
+        let lock = OpenOptions::new().create(true).truncate(false).write(true).open(dir.join(LOCK_FILE))
+            .map_err(|why| format!("Failed opening event log lock: {}", why))?;
+        lock.try_lock().map_err(|why| match why {
+            TryLockError::WouldBlock => format!("Event log {:?} is already open in another process", dir),
+            TryLockError::Error(why) => format!("Failed locking event log: {}", why),
+        })?;
+
+        let (mut projection, snapshot_seq) = match read_if_there(&dir.join(SNAPSHOT_FILE), "snapshot")? {
+            Some(text) => {
+                let snapshot: SnapshotIn = serde_json::from_str(&text)
+                    .map_err(|why| format!("Failed reading snapshot: {}", why))?;
+                let mut projection = snapshot.projection;
+                projection.reindex();
+                (projection, snapshot.seq)
+            },
+            None => (Projection::default(), 0),
+        };
+
+        let events_path = dir.join(EVENTS_FILE);
+        let text = read_if_there(&events_path, "event log")?.unwrap_or_default();
+        let mut seq = snapshot_seq;
+        let mut offset = 0;
+        for line in text.split_inclusive('\n') {
+            let record: Record = match serde_json::from_str(line) {
+                Ok(record) => record,
+                // only the very last line can be cut short, by a crash halfway through writing it
+                Err(why) if offset + line.len() == text.len() && !line.ends_with('\n') => {
+                    warn!(offset, error = %why, "Dropping incomplete last event record");
+                    let file = OpenOptions::new().write(true).open(&events_path)
+                        .map_err(|why| format!("Failed opening event log: {}", why))?;
+                    file.set_len(offset as u64).map_err(|why| format!("Failed truncating event log: {}", why))?;
+                    break
+                },
+                Err(why) => return Err(format!("Corrupt event record at byte {}: {}", offset, why)),
+            };
+            offset += line.len();
+            if record.seq <= seq {
+                continue
+            }
+            for event in record.events.iter() {
+                projection.apply(event);
+            }
+            seq = record.seq;
+        }
+
+        let events = open_events(&events_path)?;
+
+        Ok(EventLog {
+            projection: projection,
+            seq: seq,
+            snapshot_seq: snapshot_seq,
+            files: Some(LogFiles {
+                dir: dir,
+                _lock: lock,
+                events: Box::new(events),
+                snapshot_every: snapshot_every,
+                broken: None,
+            }),
+        })
+    }
+
+    // applied first, so nothing invalid is ever written, then undone again if writing fails
+    fn commit (&mut self, at: i64, events: Vec<Event>) -> Result<(), MyError> {
+        if events.is_empty() {
+            return Ok(())
+        }
+        let replaced = self.projection.apply_all(&events)?;
+
+        let record = Record { seq: self.seq + 1, at: at, events: events };
+        if let Some(files) = &mut self.files {
+            if let Err(why) = files.append(&record) {
+                self.projection.undo_all(&record.events, replaced);
+                return Err(why)
+            }
+        }
+        self.seq = record.seq;
+
+        if let Some(files) = &self.files {
+            if self.seq - self.snapshot_seq >= files.snapshot_every {
+                // the log has everything regardless, a missed snapshot only means a slower start
+                if let Err(why) = self.snapshot() {
+                    error!(error = %why, "Snapshot failed!");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn snapshot (&mut self) -> Result<(), MyError> {
+        self.write_snapshot()?;
+        self.snapshot_seq = self.seq;
+        match &mut self.files {
+            Some(files) => files.roll_over(self.seq),
+            None => Ok(()),
+        }
+    }
+
+    // the directory going away, or out from under a mount, only shows on the next write otherwise
+    fn check (&self) -> Result<(), MyError> {
+        match &self.files {
+            Some(files) => fs::metadata(&files.dir).map(|_| ())
+                .map_err(|why| format!("Event log directory {:?} unreachable: {}", files.dir, why)),
+            None => Ok(()),
+        }
+    }
+
+    // every event is on disk already, a last snapshot only saves the next start replaying them
+    fn close (&mut self) -> Result<(), MyError> {
+        if self.files.is_some() && self.seq > self.snapshot_seq {
+            self.snapshot()?;
+        }
+        Ok(())
+    }
+
+    fn write_snapshot (&self) -> Result<(), MyError> {
+        let files = match &self.files {
+            Some(files) => files,
+            None => return Ok(()),
+        };
+        let text = serde_json::to_string(&SnapshotOut { seq: self.seq, projection: &self.projection })
+            .map_err(|why| format!("Failed serializing snapshot: {}", why))?;
+        // written aside, synced, and renamed over, so a crash never leaves half a snapshot behind
+        let tmp = files.dir.join(SNAPSHOT_TMP_FILE);
+        File::create(&tmp)
+            .and_then(|mut file| file.write_all(text.as_bytes()).and_then(|_| file.sync_all()))
+            .map_err(|why| format!("Failed writing snapshot: {}", why))?;
+        fs::rename(&tmp, files.dir.join(SNAPSHOT_FILE)).map_err(|why| format!("Failed renaming snapshot: {}", why))?;
+        sync_dir(&files.dir)
+    }
+}
+
+
+#[derive(Clone)]
+pub struct EventStorage {
+    time_provider: Box<dyn TimeProvider>,
+    // an async lock, so whoever waits on a write still syncing to disk doesn't hold up a worker
+    log: Arc<Mutex<EventLog>>,
+}
+
+impl EventStorage {
+    // opens the log, which is only ever opened once, by the one storage every worker shares
+    pub fn new (time_provider: Box<dyn TimeProvider>, config: &EventsConfig) -> Result<Self, MyError> {
+        Ok(Self {
+            time_provider: time_provider,
+            log: Arc::new(Mutex::new(EventLog::open(PathBuf::from(&config.dir), config.snapshot_every)?)),
+        })
+    }
+
+    // nothing is written anywhere, and each one starts out empty
+    pub fn in_memory (time_provider: Box<dyn TimeProvider>) -> Self {
+        Self {
+            time_provider: time_provider,
+            log: Arc::new(Mutex::new(EventLog::in_memory())),
+        }
+    }
+
+    async fn log (&self) -> OwnedMutexGuard<EventLog> {
+        self.log.clone().lock_owned().await
+    }
+
+    // anything touching the files runs on a blocking thread, which keeps the log locked until it is done
+    async fn blocking<T> (&self, work: impl FnOnce(&mut EventLog) -> Result<T, MyError> + Send + 'static) -> Result<T, MyError> where T: Send + 'static {
+        let log = self.log().await;
+        write(log, work).await
+    }
+
+    async fn read<T> (&self, read: impl FnOnce(&Projection) -> T) -> Result<T, MyError> {
+        Ok(read(&self.log().await.projection))
+    }
+
+    async fn get<T> (&self, name: &'static str, id: &String, get: impl FnOnce(&Projection) -> Option<&T>) -> Result<T, MyError> where T: Clone {
+        let log = self.log().await;
+        get(&log.projection).cloned().ok_or_else(|| format!("No {} with id {}", name, id))
+    }
+
+    async fn commit (&self, action: &'static str, name: &'static str, events: Vec<Event>) -> Result<(), MyError> {
+        let now = self.time_provider.unix_ts_ms();
+        self.blocking(move |log| log.commit(now, events)).await.map_err(|why| format!("{} {} failed: {}", action, name, why))
+    }
+
+    async fn add (&self, row: Row) -> Result<bool, MyError> {
+        self.commit("Add", row.name(), vec![Event::Added { row: row }]).await?;
+        Ok(true)
+    }
+
+    async fn update (&self, row: Row) -> Result<bool, MyError> {
+        let log = self.log().await;
+        if !log.projection.contains(&row) {
+            return Ok(false)
+        }
+        let now = self.time_provider.unix_ts_ms();
+        let name = row.name();
+        write(log, move |log| log.commit(now, vec![Event::Updated { row: row }])).await
+            .map_err(|why| format!("Update {} failed: {}", name, why))?;
+        Ok(true)
+    }
+
+    // every row the delete changed, those it only unlinked first, so putting it all back goes the other way round
+    async fn cascade (&self, name: &'static str, id: &String, find: impl FnOnce(&Projection) -> Option<Row>) -> Result<Vec<RowChange>, MyError> {
+        let log = self.log().await;
+        let row = find(&log.projection).ok_or_else(|| format!("No {} with id {}", name, id))?;
+        let events = log.projection.deletion(row);
+        let mut unlinked = vec![];
+        let mut removed = vec![];
+        for event in events.iter() {
+            match event {
+                Event::Updated { row: Row::Card(card) } => unlinked.push(RowChange {
+                    before: log.projection.cards.get(&card.id).cloned().map(Row::Card),
+                    after: Some(Row::Card(card.clone())),
+                }),
+                Event::Deleted { row } => removed.push(RowChange { before: Some(row.clone()), after: None }),
+                _ => (),
+            }
+        }
+        let now = self.time_provider.unix_ts_ms();
+        write(log, move |log| log.commit(now, events)).await
+            .map_err(|why| format!("Delete {} failed: {}", name, why))?;
+        unlinked.extend(removed);
+        Ok(unlinked)
+    }
+
+    // false when there was nothing to delete
+    async fn delete (&self, find: impl FnOnce(&Projection) -> Option<Row>) -> Result<Option<Vec<Event>>, MyError> {
+        let log = self.log().await;
+        let row = match find(&log.projection) {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        let name = row.name();
+        let events = log.projection.deletion(row);
+        let now = self.time_provider.unix_ts_ms();
+        let written = events.clone();
+        write(log, move |log| log.commit(now, written)).await
+            .map_err(|why| format!("Delete {} failed: {}", name, why))?;
+        Ok(Some(events))
+    }
+}
+
+// kept in memory there is nothing to wait for, so that runs right here
+async fn write<T> (mut log: OwnedMutexGuard<EventLog>, work: impl FnOnce(&mut EventLog) -> Result<T, MyError> + Send + 'static) -> Result<T, MyError> where T: Send + 'static {
+    if log.files.is_none() {
+        return work(&mut log)
+    }
+    spawn_blocking(move || work(&mut log)).await
+        .map_err(|why| format!("Event log writer failed: {}", why))?
+}
+
+#[async_trait]
+impl Storage for EventStorage {
+    fn name (&self) -> &'static str {
+        "Events"
+    }
+
+    // HEALTH
+    async fn health_check (&self) -> Result<(), MyError> {
+        self.blocking(|log| log.check()).await
+    }
+
+    async fn close (&self) -> Result<(), MyError> {
+        self.blocking(|log| log.close()).await
+    }
+
+    // BOARDS
+    async fn add_board (&self, item: &Board) -> Result<bool, MyError> {
+        self.add(Row::Board(item.clone())).await
+    }
+
+    async fn list_boards (&self) -> Result<Vec<Board>, MyError> {
+        self.read(|p| cloned(&p.boards, |_| true)).await
+    }
+
+    async fn get_board (&self, id: &String) -> Result<Board, MyError> {
+        self.get("Board", id, |p| p.boards.get(id)).await
+    }
+
+    async fn update_board (&self, item: &Board) -> Result<bool, MyError> {
+        self.update(Row::Board(item.clone())).await
+    }
+
+    async fn delete_board (&self, id: &String) -> Result<BoardDeletion, MyError> {
+        let events = self.delete(|p| p.boards.get(id).cloned().map(Row::Board)).await?
+            .ok_or_else(|| format!("No Board with id {}", id))?;
+        let mut deletion = BoardDeletion::default();
+        for event in events.iter() {
+            let count = match event {
+                Event::Deleted { row: Row::Board(_) } => &mut deletion.boards,
+                Event::Deleted { row: Row::Column(_) } => &mut deletion.columns,
+                Event::Deleted { row: Row::Card(_) } => &mut deletion.cards,
+                Event::Deleted { row: Row::Group(_) } => &mut deletion.groups,
+                Event::Deleted { row: Row::Vote(_) } => &mut deletion.votes,
+                Event::Deleted { row: Row::Tag(_) } => &mut deletion.tags,
+                Event::Deleted { row: Row::CardTag(_) } => &mut deletion.card_tags,
+                Event::Deleted { row: Row::Comment(_) } => &mut deletion.comments,
+                Event::Deleted { row: Row::Revision(_) } => &mut deletion.revisions,
+                Event::Deleted { row: Row::ActionItem(_) } => &mut deletion.action_items,
+                Event::Deleted { row: Row::Invite(_) } => &mut deletion.invites,
+                _ => continue,
+            };
+            *count += 1;
+        }
+        Ok(deletion)
+    }
+
+    async fn list_team_boards (&self, team_ids: &Vec<String>) -> Result<Vec<Board>, MyError> {
+        self.read(|p| cloned(&p.boards, |item| team_ids.contains(&item.team_id))).await
+    }
+
+    async fn list_deleted_boards (&self, deleted_before: i64) -> Result<Vec<Board>, MyError> {
+        self.read(|p| cloned(&p.boards, |item| item.deleted_at.is_some_and(|deleted_at| deleted_at < deleted_before))).await
+    }
+
+    // COLUMNS
+    async fn add_column (&self, item: &Column) -> Result<bool, MyError> {
+        self.add(Row::Column(item.clone())).await
+    }
+
+    async fn list_columns (&self) -> Result<Vec<Column>, MyError> {
+        self.read(|p| cloned(&p.columns, |_| true)).await
+    }
+
+    async fn get_column (&self, id: &String) -> Result<Column, MyError> {
+        self.get("Column", id, |p| p.columns.get(id)).await
+    }
+
+    async fn delete_column (&self, id: &String) -> Result<bool, MyError> {
+        Ok(self.delete(|p| p.columns.get(id).cloned().map(Row::Column)).await?.is_some())
+    }
+
+    async fn delete_empty_column (&self, id: &String) -> Result<IfEmpty<bool>, MyError> {
+        let log = self.log().await;
+        let column = match log.projection.columns.get(id) {
+            Some(column) => column.clone(),
+            None => return Ok(IfEmpty::Deleted(false)),
+        };
+        let cards = log.projection.cards.values().filter(|card| &card.column_id == id).count();
+        if cards > 0 {
+            return Ok(IfEmpty::NotEmpty("cards", cards))
+        }
+        let groups = log.projection.groups.values().filter(|group| &group.column_id == id).count();
+        if groups > 0 {
+            return Ok(IfEmpty::NotEmpty("groups", groups))
+        }
+        let now = self.time_provider.unix_ts_ms();
+        write(log, move |log| log.commit(now, vec![Event::Deleted { row: Row::Column(column) }])).await
+            .map_err(|why| format!("Delete Column failed: {}", why))?;
+        Ok(IfEmpty::Deleted(true))
+    }
+
+    async fn update_column (&self, item: &Column) -> Result<bool, MyError> {
+        self.update(Row::Column(item.clone())).await
+    }
+
+    async fn list_board_columns (&self, board_id: &String) -> Result<Vec<Column>, MyError> {
+        self.read(|p| cloned(&p.columns, |item| &item.board_id == board_id)).await
+    }
+
+    // CARDS
+    async fn add_card (&self, item: &Card) -> Result<bool, MyError> {
+        self.add(Row::Card(item.clone())).await
+    }
+
+    async fn update_card (&self, item: &Card) -> Result<bool, MyError> {
+        self.update(Row::Card(item.clone())).await
+    }
+
+    async fn list_board_cards (&self, board_id: &String) -> Result<Vec<Card>, MyError> {
+        self.read(|p| cloned(&p.cards, |item| &item.board_id == board_id)).await
+    }
+
+    async fn get_card (&self, id: &String) -> Result<Card, MyError> {
+        self.get("Card", id, |p| p.cards.get(id)).await
+    }
+
+    async fn delete_card (&self, id: &String) -> Result<bool, MyError> {
+        Ok(self.delete(|p| p.cards.get(id).cloned().map(Row::Card)).await?.is_some())
+    }
+
+    async fn delete_card_cascade (&self, id: &String) -> Result<Vec<RowChange>, MyError> {
+        self.cascade("Card", id, |p| p.cards.get(id).cloned().map(Row::Card)).await
+    }
+
+    async fn merge_cards (&self, source_id: &String, target_id: &String) -> Result<Vec<RowChange>, MyError> {
+        let log = self.log().await;
+        if source_id == target_id {
+            return Err(format!("Cannot merge Card {} into itself", source_id))
+        }
+        let source = log.projection.cards.get(source_id).cloned().ok_or_else(|| format!("No Card with id {}", source_id))?;
+        let target = log.projection.cards.get(target_id).ok_or_else(|| format!("No Card with id {}", target_id))?;
+        // stacks are one level deep
+        if target.merged_into.is_some() {
+            return Err(format!("Card {} is itself merged", target_id))
+        }
+        let changes: Vec<RowChange> = cloned(&log.projection.cards, |item| item.merged_into.as_ref() == Some(source_id))
+            .into_iter()
+            .chain(std::iter::once(source))
+            .map(|card| RowChange {
+                after: Some(Row::Card(Card { merged_into: Some(target_id.clone()), ..card.clone() })),
+                before: Some(Row::Card(card)),
+            })
+            .collect();
+        let events = changes.iter().filter_map(|change| change.after.clone()).map(|row| Event::Updated { row: row }).collect();
+        let now = self.time_provider.unix_ts_ms();
+        write(log, move |log| log.commit(now, events)).await
+            .map_err(|why| format!("Merge Card failed: {}", why))?;
+        Ok(changes)
+    }
+
+    // GROUPS
+    async fn add_group (&self, item: &CardGroup) -> Result<bool, MyError> {
+        self.add(Row::Group(item.clone())).await
+    }
+
+    async fn update_group (&self, item: &CardGroup) -> Result<bool, MyError> {
+        self.update(Row::Group(item.clone())).await
+    }
+
+    async fn list_board_groups (&self, board_id: &String) -> Result<Vec<CardGroup>, MyError> {
+        self.read(|p| cloned(&p.groups, |item| &item.board_id == board_id)).await
+    }
+
+    async fn get_group (&self, id: &String) -> Result<CardGroup, MyError> {
+        self.get("Group", id, |p| p.groups.get(id)).await
+    }
+
+    async fn delete_group (&self, id: &String) -> Result<bool, MyError> {
+        Ok(self.delete(|p| p.groups.get(id).cloned().map(Row::Group)).await?.is_some())
+    }
+
+    async fn delete_group_cascade (&self, id: &String) -> Result<Vec<RowChange>, MyError> {
+        self.cascade("Group", id, |p| p.groups.get(id).cloned().map(Row::Group)).await
+    }
+
+    // VOTES
+    async fn add_vote (&self, item: &Vote) -> Result<bool, MyError> {
+        self.add(Row::Vote(item.clone())).await
+    }
+
+    async fn list_board_votes (&self, board_id: &String) -> Result<Vec<Vote>, MyError> {
+        self.read(|p| cloned(&p.votes, |item| &item.board_id == board_id)).await
+    }
+
+    async fn get_vote (&self, id: &String) -> Result<Vote, MyError> {
+        self.get("Vote", id, |p| p.votes.get(id)).await
+    }
+
+    async fn delete_vote (&self, id: &String) -> Result<bool, MyError> {
+        Ok(self.delete(|p| p.votes.get(id).cloned().map(Row::Vote)).await?.is_some())
+    }
+
+    // TAGS
+    async fn add_tag (&self, item: &Tag) -> Result<bool, MyError> {
+        self.add(Row::Tag(item.clone())).await
+    }
+
+    async fn list_board_tags (&self, board_id: &String) -> Result<Vec<Tag>, MyError> {
+        self.read(|p| cloned(&p.tags, |item| &item.board_id == board_id)).await
+    }
+
+    async fn get_tag (&self, id: &String) -> Result<Tag, MyError> {
+        self.get("Tag", id, |p| p.tags.get(id)).await
+    }
+
+    async fn delete_tag (&self, id: &String) -> Result<bool, MyError> {
+        Ok(self.delete(|p| p.tags.get(id).cloned().map(Row::Tag)).await?.is_some())
+    }
+
+    // CARD TAGS
+    async fn add_card_tag (&self, item: &CardTag) -> Result<bool, MyError> {
+        self.add(Row::CardTag(item.clone())).await
+    }
+
+    async fn list_board_card_tags (&self, board_id: &String) -> Result<Vec<CardTag>, MyError> {
+        self.read(|p| cloned(&p.card_tags, |item| &item.board_id == board_id)).await
+    }
+
+    async fn delete_card_tag (&self, id: &String) -> Result<bool, MyError> {
+        Ok(self.delete(|p| p.card_tags.get(id).cloned().map(Row::CardTag)).await?.is_some())
+    }
+
+    // COMMENTS
+    async fn add_comment (&self, item: &Comment) -> Result<bool, MyError> {
+        self.add(Row::Comment(item.clone())).await
+    }
+
+    async fn update_comment (&self, item: &Comment) -> Result<bool, MyError> {
+        self.update(Row::Comment(item.clone())).await
+    }
+
+    async fn list_board_comments (&self, board_id: &String) -> Result<Vec<Comment>, MyError> {
+        self.read(|p| cloned(&p.comments, |item| &item.board_id == board_id)).await
+    }
+
+    async fn get_comment (&self, id: &String) -> Result<Comment, MyError> {
+        self.get("Comment", id, |p| p.comments.get(id)).await
+    }
+
+    async fn delete_comment (&self, id: &String) -> Result<bool, MyError> {
+        Ok(self.delete(|p| p.comments.get(id).cloned().map(Row::Comment)).await?.is_some())
+    }
+
+    // REVISIONS
+    async fn add_revision (&self, item: &Revision) -> Result<bool, MyError> {
+        self.add(Row::Revision(item.clone())).await
+    }
+
+    async fn list_card_revisions (&self, card_id: &String) -> Result<Vec<Revision>, MyError> {
+        self.read(|p| cloned(&p.revisions, |item| &item.card_id == card_id)).await
+    }
+
+    async fn get_revision (&self, id: &String) -> Result<Revision, MyError> {
+        self.get("Revision", id, |p| p.revisions.get(id)).await
+    }
+
+    async fn delete_revision (&self, id: &String) -> Result<bool, MyError> {
+        Ok(self.delete(|p| p.revisions.get(id).cloned().map(Row::Revision)).await?.is_some())
+    }
+
+    // ACTION ITEMS
+    async fn add_action_item (&self, item: &ActionItem) -> Result<bool, MyError> {
+        self.add(Row::ActionItem(item.clone())).await
+    }
+
+    async fn update_action_item (&self, item: &ActionItem) -> Result<bool, MyError> {
+        self.update(Row::ActionItem(item.clone())).await
+    }
+
+    async fn list_board_action_items (&self, board_id: &String) -> Result<Vec<ActionItem>, MyError> {
+        self.read(|p| cloned(&p.action_items, |item| &item.board_id == board_id)).await
+    }
+
+    async fn get_action_item (&self, id: &String) -> Result<ActionItem, MyError> {
+        self.get("ActionItem", id, |p| p.action_items.get(id)).await
+    }
+
+    async fn delete_action_item (&self, id: &String) -> Result<bool, MyError> {
+        Ok(self.delete(|p| p.action_items.get(id).cloned().map(Row::ActionItem)).await?.is_some())
+    }
+
+    // AUDIT
+    // the entry takes the seq of the record it goes in, so the log order is the order entries are listed in
+    async fn add_audit_entry (&self, item: &AuditEntry) -> Result<bool, MyError> {
+        let log = self.log().await;
+        let entry = AuditEntry { seq: log.seq as i64 + 1, ..item.clone() };
+        let now = self.time_provider.unix_ts_ms();
+        write(log, move |log| log.commit(now, vec![Event::Added { row: Row::AuditEntry(entry) }])).await
+            .map_err(|why| format!("Add AuditEntry failed: {}", why))?;
+        Ok(true)
+    }
+
+    async fn list_audit_entries (&self, filter: &AuditFilter) -> Result<Vec<AuditEntry>, MyError> {
+        let matches = |value: &String, wanted: &Option<String>| wanted.as_ref().is_none_or(|wanted| value == wanted);
+        let mut entries = self.read(|p| cloned(&p.audit_entries, |item|
+            matches(&item.actor, &filter.actor)
+                && matches(&item.action, &filter.action)
+                && matches(&item.entity_type, &filter.entity_type)
+                && matches(&item.entity_id, &filter.entity_id)
+                && filter.board_id.as_ref().is_none_or(|board_id| item.board_id.as_ref() == Some(board_id))
+                && filter.since.is_none_or(|since| item.created_at >= since)
+                && filter.until.is_none_or(|until| item.created_at < until)
+                && filter.after.is_none_or(|after| if filter.oldest_first { item.seq > after } else { item.seq < after })
+        )).await?;
+        entries.sort_by(|a, b| (a.seq, a.created_at, &a.id).cmp(&(b.seq, b.created_at, &b.id)));
+        if !filter.oldest_first {
+            entries.reverse();
+        }
+        if let Some(limit) = filter.limit {
+            entries.truncate(limit);
+        }
+        Ok(entries)
+    }
+
+    // SEARCH
+    // the same matching as the default scan, but only over what the terms index says has every term
+    async fn search (&self, team_ids: &Vec<String>, query: &String, limit: usize) -> Result<Vec<SearchHit>, MyError> {
+        let terms = search::terms(query);
+        self.read(|p| {
+            let visible = |board_id: &String| p.boards.get(board_id)
+                .filter(|board| board.deleted_at.is_none() && team_ids.contains(&board.team_id));
+            let mut hits = vec![];
+            for (kind, id) in p.search(&terms) {
+                let found = match kind {
+                    SearchKind::Board => p.boards.get(&id).map(|item| (&item.id, &item.title, item.created_at)),
+                    SearchKind::Card => p.cards.get(&id).map(|item| (&item.board_id, &item.title, item.created_at)),
+                    SearchKind::Comment => p.comments.get(&id).map(|item| (&item.board_id, &item.text, item.created_at)),
+                };
+                if let Some((board_id, text, created_at)) = found {
+                    if let Some(board) = visible(board_id) {
+                        hits.extend(search::hit(kind, &id, board, text, created_at, &terms));
+                    }
+                }
+            }
+            search::best(hits, limit)
+        }).await
+    }
+
+    // CONTENTS
+    async fn add_board_contents (&self, item: &BoardContents) -> Result<bool, MyError> {
+        let mut events = vec![Event::Added { row: Row::Board(item.board.clone()) }];
+        events.extend(item.columns.iter().cloned().map(|row| Event::Added { row: Row::Column(row) }));
+        events.extend(item.groups.iter().cloned().map(|row| Event::Added { row: Row::Group(row) }));
+        // merged cards point at other cards, which have to exist first
+        events.extend(item.cards.iter().filter(|card| card.merged_into.is_none()).cloned().map(|row| Event::Added { row: Row::Card(row) }));
+        events.extend(item.cards.iter().filter(|card| card.merged_into.is_some()).cloned().map(|row| Event::Added { row: Row::Card(row) }));
+        events.extend(item.votes.iter().cloned().map(|row| Event::Added { row: Row::Vote(row) }));
+        events.extend(item.tags.iter().cloned().map(|row| Event::Added { row: Row::Tag(row) }));
+        events.extend(item.card_tags.iter().cloned().map(|row| Event::Added { row: Row::CardTag(row) }));
+        events.extend(item.comments.iter().cloned().map(|row| Event::Added { row: Row::Comment(row) }));
+        events.extend(item.action_items.iter().cloned().map(|row| Event::Added { row: Row::ActionItem(row) }));
+
+        self.commit("Add", "Board", events).await?;
+        Ok(true)
+    }
+
+    // UNDO
+    // checked and written under the one lock, so nobody gets in between
+    async fn apply_changes (&self, changes: &[RowChange]) -> Result<bool, MyError> {
+        let log = self.log().await;
+        let touched = touched(changes);
+        let mut events = vec![];
+        for change in changes.iter() {
+            let row = match change.row() {
+                Some(row) => row,
+                None => continue,
+            };
+            check_undoable(row)?;
+            if !same(&log.projection.current(row), &change.before) {
+                return Ok(false)
+            }
+            events.push(match (&change.before, &change.after) {
+                (None, Some(after)) => Event::Added { row: after.clone() },
+                (Some(_), Some(after)) => Event::Updated { row: after.clone() },
+                (Some(before), None) => {
+                    if log.projection.dependents(before).iter().any(|(kind, id)| !touched.contains(&(*kind, id))) {
+                        return Ok(false)
+                    }
+                    Event::Deleted { row: before.clone() }
+                },
+                (None, None) => continue,
+            });
+        }
+        let now = self.time_provider.unix_ts_ms();
+        write(log, move |log| log.commit(now, events)).await
+            .map_err(|why| format!("Apply changes failed: {}", why))?;
+        Ok(true)
+    }
+
+    // TEAMS
+    async fn add_team (&self, item: &Team) -> Result<bool, MyError> {
+        self.add(Row::Team(item.clone())).await
+    }
+
+    async fn add_team_with_owner (&self, item: &Team, owner: &Membership) -> Result<bool, MyError> {
+        self.commit("Add", "Team", vec![
+            Event::Added { row: Row::Team(item.clone()) },
+            Event::Added { row: Row::Membership(owner.clone()) },
+        ]).await?;
+        Ok(true)
+    }
+
+    async fn list_teams (&self, ids: &Vec<String>) -> Result<Vec<Team>, MyError> {
+        self.read(|p| cloned(&p.teams, |item| ids.contains(&item.id))).await
+    }
+
+    async fn list_all_teams (&self) -> Result<Vec<Team>, MyError> {
+        self.read(|p| cloned(&p.teams, |_| true)).await
+    }
+
+    async fn get_team (&self, id: &String) -> Result<Team, MyError> {
+        self.get("Team", id, |p| p.teams.get(id)).await
+    }
+
+    async fn delete_team (&self, id: &String) -> Result<bool, MyError> {
+        Ok(self.delete(|p| p.teams.get(id).cloned().map(Row::Team)).await?.is_some())
+    }
+
+    async fn delete_team_cascade (&self, id: &String) -> Result<IfEmpty<Vec<RowChange>>, MyError> {
+        let log = self.log().await;
+        let team = log.projection.teams.get(id).cloned().ok_or_else(|| format!("No Team with id {}", id))?;
+        let boards = log.projection.boards.values().filter(|board| &board.team_id == id).count();
+        if boards > 0 {
+            return Ok(IfEmpty::NotEmpty("boards", boards))
+        }
+        let events = log.projection.deletion(Row::Team(team));
+        let changes = events.iter().map(|event| RowChange { before: Some(event.row().clone()), after: None }).collect();
+        let now = self.time_provider.unix_ts_ms();
+        write(log, move |log| log.commit(now, events)).await
+            .map_err(|why| format!("Delete Team failed: {}", why))?;
+        Ok(IfEmpty::Deleted(changes))
+    }
+
+    // MEMBERSHIPS
+    async fn add_membership (&self, item: &Membership) -> Result<bool, MyError> {
+        self.add(Row::Membership(item.clone())).await
+    }
+
+    async fn update_membership (&self, item: &Membership) -> Result<bool, MyError> {
+        self.update(Row::Membership(item.clone())).await
+    }
+
+    async fn list_team_memberships (&self, team_id: &String) -> Result<Vec<Membership>, MyError> {
+        self.read(|p| cloned(&p.memberships, |item| &item.team_id == team_id)).await
+    }
+
+    async fn list_user_memberships (&self, user_id: &String) -> Result<Vec<Membership>, MyError> {
+        self.read(|p| cloned(&p.memberships, |item| &item.user_id == user_id)).await
+    }
+
+    async fn delete_membership (&self, id: &String) -> Result<bool, MyError> {
+        Ok(self.delete(|p| p.memberships.get(id).cloned().map(Row::Membership)).await?.is_some())
+    }
+
+    // INVITES
+    async fn add_invite (&self, item: &Invite) -> Result<bool, MyError> {
+        self.add(Row::Invite(item.clone())).await
+    }
+
+    async fn list_board_invites (&self, board_id: &String) -> Result<Vec<Invite>, MyError> {
+        self.read(|p| cloned(&p.invites, |item| &item.board_id == board_id)).await
+    }
+
+    async fn get_invite (&self, id: &String) -> Result<Invite, MyError> {
+        self.get("Invite", id, |p| p.invites.get(id)).await
+    }
+
+    async fn delete_invite (&self, id: &String) -> Result<bool, MyError> {
+        Ok(self.delete(|p| p.invites.get(id).cloned().map(Row::Invite)).await?.is_some())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // passes writes through to the real file until its budget runs out, and fails the next sync or the cut back if told to
+    struct Failing {
+        file: File,
+        budget: usize,
+        fail_sync: std::cell::Cell<bool>,
+        fail_cut: bool,
+    }
+
+    impl Failing {
+        fn on (dir: &PathBuf, budget: usize) -> Self {
+            let file = open_events(&dir.join(EVENTS_FILE)).unwrap();
+            Failing { file: file, budget: budget, fail_sync: Default::default(), fail_cut: false }
+        }
+    }
+
+    impl Write for Failing {
+        fn write (&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.budget == 0 {
+                return Err(io::Error::other("disk full"))
+            }
+            let written = self.file.write(&buf[..buf.len().min(self.budget)])?;
+            self.budget -= written;
+            Ok(written)
+        }
+
+        fn flush (&mut self) -> io::Result<()> {
+            self.file.flush()
+        }
+    }
+
+    impl Journal for Failing {
+        fn len (&self) -> io::Result<u64> {
+            Journal::len(&self.file)
+        }
+
+        fn set_len (&self, len: u64) -> io::Result<()> {
+            if self.fail_cut {
+                return Err(io::Error::other("read only"))
+            }
+            self.file.set_len(len)
+        }
+
+        fn sync_data (&self) -> io::Result<()> {
+            if self.fail_sync.replace(false) {
+                return Err(io::Error::other("sync failed"))
+            }
+            self.file.sync_data()
+        }
+    }
+
+    fn dir (name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bareretro-events-unit-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn board (id: &str) -> Event {
+        Event::Added { row: Row::Board(Board {
+            id: id.into(),
+            team_id: "t1".into(),
+            title: format!("Board {}", id),
+            owner: "alice".into(),
+            created_at: 0,
+            archived_at: None,
+            deleted_at: None,
+        }) }
+    }
+
+    fn boards (log: &EventLog) -> Vec<String> {
+        log.projection.boards.keys().cloned().collect()
+    }
+
+    fn swap (log: &mut EventLog, events: impl Journal + 'static) {
+        log.files.as_mut().unwrap().events = Box::new(events);
+    }
+
+    #[test]
+    fn failed_appends_leave_nothing_behind () {
+        let dir = dir("failed_appends");
+        let mut log = EventLog::open(dir.clone(), 1000).unwrap();
+        log.commit(0, vec![board("b1")]).unwrap();
+
+        // cut off halfway through the line
+        swap(&mut log, Failing::on(&dir, 10));
+        assert!(log.commit(0, vec![board("b2")]).is_err());
+        // all written, but never made durable
+        swap(&mut log, Failing { fail_sync: true.into(), ..Failing::on(&dir, usize::MAX) });
+        assert!(log.commit(0, vec![board("b3")]).is_err());
+        assert_eq!(boards(&log), vec!["b1"]);
+
+        swap(&mut log, open_events(&dir.join(EVENTS_FILE)).unwrap());
+        log.commit(0, vec![board("b4")]).unwrap();
+        drop(log);
+
+        let log = EventLog::open(dir.clone(), 1000).unwrap();
+        assert_eq!(boards(&log), vec!["b1", "b4"]);
+        assert_eq!(log.seq, 2);
+        drop(log);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stops_writing_when_it_cannot_cut_back () {
+        let dir = dir("cannot_cut_back");
+        let mut log = EventLog::open(dir.clone(), 1000).unwrap();
+        swap(&mut log, Failing { fail_cut: true, ..Failing::on(&dir, 10) });
+        assert!(log.commit(0, vec![board("b1")]).is_err());
+
+        swap(&mut log, open_events(&dir.join(EVENTS_FILE)).unwrap());
+        let why = log.commit(0, vec![board("b2")]).unwrap_err();
+        assert!(why.contains("stopped taking writes"), "{}", why);
+        assert!(boards(&log).is_empty());
+        drop(log);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
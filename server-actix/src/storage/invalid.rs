@@ -1,7 +1,8 @@
 
 use async_trait::async_trait;
 
-use crate::models::{MyError, Storage, Board, Column};
+use crate::models::{MyError, Storage, Board, BoardDeletion, BoardContents, Column, Card, CardGroup, Vote, Tag, CardTag, Comment, Revision, ActionItem, AuditEntry, AuditFilter, Team, Membership, Invite, IfEmpty};
+use crate::undo::RowChange;
 
 
 #[derive(Clone)]
@@ -9,13 +10,21 @@ pub struct InvalidStorage {
     pub error: String,
 }
 
-// https://github.com/dtolnay/async-trait#non-threadsafe-futures
-#[async_trait(?Send)]
+#[async_trait]
 impl Storage for InvalidStorage {
     fn name(&self) -> &'static str {
         "INVALID"
     }
 
+    // HEALTH
+    async fn health_check (&self) -> Result<(), MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn migrate (&self) -> Result<Vec<String>, MyError> {
+        Err(self.error.clone())
+    }
+
     // BOARDS
     async fn add_board (&self, _item: &Board) -> Result<bool, MyError> {
         Err(self.error.clone())
@@ -29,7 +38,19 @@ impl Storage for InvalidStorage {
         Err(self.error.clone())
     }
 
-    async fn delete_board (&self, _id: &String) -> Result<bool, MyError> {
+    async fn update_board (&self, _item: &Board) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn delete_board (&self, _id: &String) -> Result<BoardDeletion, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn list_team_boards (&self, _team_ids: &Vec<String>) -> Result<Vec<Board>, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn list_deleted_boards (&self, _deleted_before: i64) -> Result<Vec<Board>, MyError> {
         Err(self.error.clone())
     }
 
@@ -49,4 +70,262 @@ impl Storage for InvalidStorage {
     async fn delete_column (&self, _id: &String) -> Result<bool, MyError> {
         Err(self.error.clone())
     }
+
+    async fn delete_empty_column (&self, _id: &String) -> Result<IfEmpty<bool>, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn update_column (&self, _item: &Column) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn list_board_columns (&self, _board_id: &String) -> Result<Vec<Column>, MyError> {
+        Err(self.error.clone())
+    }
+
+    // CARDS
+    async fn add_card (&self, _item: &Card) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn update_card (&self, _item: &Card) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn list_board_cards (&self, _board_id: &String) -> Result<Vec<Card>, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn get_card (&self, _id: &String) -> Result<Card, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn delete_card (&self, _id: &String) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn delete_card_cascade (&self, _id: &String) -> Result<Vec<RowChange>, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn merge_cards (&self, _source_id: &String, _target_id: &String) -> Result<Vec<RowChange>, MyError> {
+        Err(self.error.clone())
+    }
+
+    // GROUPS
+    async fn add_group (&self, _item: &CardGroup) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn update_group (&self, _item: &CardGroup) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn list_board_groups (&self, _board_id: &String) -> Result<Vec<CardGroup>, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn get_group (&self, _id: &String) -> Result<CardGroup, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn delete_group (&self, _id: &String) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn delete_group_cascade (&self, _id: &String) -> Result<Vec<RowChange>, MyError> {
+        Err(self.error.clone())
+    }
+
+    // VOTES
+    async fn add_vote (&self, _item: &Vote) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn list_board_votes (&self, _board_id: &String) -> Result<Vec<Vote>, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn get_vote (&self, _id: &String) -> Result<Vote, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn delete_vote (&self, _id: &String) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    // TAGS
+    async fn add_tag (&self, _item: &Tag) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn list_board_tags (&self, _board_id: &String) -> Result<Vec<Tag>, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn get_tag (&self, _id: &String) -> Result<Tag, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn delete_tag (&self, _id: &String) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    // CARD TAGS
+    async fn add_card_tag (&self, _item: &CardTag) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn list_board_card_tags (&self, _board_id: &String) -> Result<Vec<CardTag>, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn delete_card_tag (&self, _id: &String) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    // COMMENTS
+    async fn add_comment (&self, _item: &Comment) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn update_comment (&self, _item: &Comment) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn list_board_comments (&self, _board_id: &String) -> Result<Vec<Comment>, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn get_comment (&self, _id: &String) -> Result<Comment, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn delete_comment (&self, _id: &String) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    // REVISIONS
+    async fn add_revision (&self, _item: &Revision) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn list_card_revisions (&self, _card_id: &String) -> Result<Vec<Revision>, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn get_revision (&self, _id: &String) -> Result<Revision, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn delete_revision (&self, _id: &String) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    // ACTION ITEMS
+    async fn add_action_item (&self, _item: &ActionItem) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn update_action_item (&self, _item: &ActionItem) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn list_board_action_items (&self, _board_id: &String) -> Result<Vec<ActionItem>, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn get_action_item (&self, _id: &String) -> Result<ActionItem, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn delete_action_item (&self, _id: &String) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    // AUDIT
+    async fn add_audit_entry (&self, _item: &AuditEntry) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn list_audit_entries (&self, _filter: &AuditFilter) -> Result<Vec<AuditEntry>, MyError> {
+        Err(self.error.clone())
+    }
+
+    // CONTENTS
+    async fn add_board_contents (&self, _item: &BoardContents) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    // UNDO
+    async fn apply_changes (&self, _changes: &[RowChange]) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    // TEAMS
+    async fn add_team (&self, _item: &Team) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn add_team_with_owner (&self, _item: &Team, _owner: &Membership) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn list_teams (&self, _ids: &Vec<String>) -> Result<Vec<Team>, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn list_all_teams (&self) -> Result<Vec<Team>, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn get_team (&self, _id: &String) -> Result<Team, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn delete_team (&self, _id: &String) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn delete_team_cascade (&self, _id: &String) -> Result<IfEmpty<Vec<RowChange>>, MyError> {
+        Err(self.error.clone())
+    }
+
+    // MEMBERSHIPS
+    async fn add_membership (&self, _item: &Membership) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn update_membership (&self, _item: &Membership) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn list_team_memberships (&self, _team_id: &String) -> Result<Vec<Membership>, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn list_user_memberships (&self, _user_id: &String) -> Result<Vec<Membership>, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn delete_membership (&self, _id: &String) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    // INVITES
+    async fn add_invite (&self, _item: &Invite) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn list_board_invites (&self, _board_id: &String) -> Result<Vec<Invite>, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn get_invite (&self, _id: &String) -> Result<Invite, MyError> {
+        Err(self.error.clone())
+    }
+
+    async fn delete_invite (&self, _id: &String) -> Result<bool, MyError> {
+        Err(self.error.clone())
+    }
 }
@@ -0,0 +1,360 @@
+use std::future::Future;
+use async_trait::async_trait;
+
+use crate::metrics::Metrics;
+use crate::undo::RowChange;
+use crate::models::{MyError, Storage, PoolStatus, Board, BoardDeletion, BoardContents, Column, Card, CardGroup, Vote, Tag, CardTag, Comment, Revision, ActionItem, AuditEntry, AuditFilter, SearchHit, Team, Membership, Invite, IfEmpty};
+
+
+// times and counts every call into whichever backend it wraps, under that backend's name
+#[derive(Clone)]
+pub struct MeteredStorage {
+    inner: Box<dyn Storage>,
+    metrics: Metrics,
+}
+
+impl MeteredStorage {
+    pub fn new (inner: Box<dyn Storage>, metrics: Metrics) -> Self {
+        MeteredStorage {
+            inner: inner,
+            metrics: metrics,
+        }
+    }
+
+    async fn observe<T> (&self, method: &'static str, operation: impl Future<Output = Result<T, MyError>>) -> Result<T, MyError> {
+        self.metrics.observe_storage(self.inner.name(), method, operation).await
+    }
+}
+
+#[async_trait]
+impl Storage for MeteredStorage {
+    fn name (&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn pool_status (&self) -> Option<PoolStatus> {
+        self.inner.pool_status()
+    }
+
+    // HEALTH
+    async fn health_check (&self) -> Result<(), MyError> {
+        self.observe("health_check", self.inner.health_check()).await
+    }
+
+    async fn migrate (&self) -> Result<Vec<String>, MyError> {
+        self.observe("migrate", self.inner.migrate()).await
+    }
+
+    async fn close (&self) -> Result<(), MyError> {
+        self.observe("close", self.inner.close()).await
+    }
+
+    // BOARDS
+    async fn add_board (&self, item: &Board) -> Result<bool, MyError> {
+        self.observe("add_board", self.inner.add_board(item)).await
+    }
+
+    async fn list_boards (&self) -> Result<Vec<Board>, MyError> {
+        self.observe("list_boards", self.inner.list_boards()).await
+    }
+
+    async fn get_board (&self, id: &String) -> Result<Board, MyError> {
+        self.observe("get_board", self.inner.get_board(id)).await
+    }
+
+    async fn update_board (&self, item: &Board) -> Result<bool, MyError> {
+        self.observe("update_board", self.inner.update_board(item)).await
+    }
+
+    async fn delete_board (&self, id: &String) -> Result<BoardDeletion, MyError> {
+        self.observe("delete_board", self.inner.delete_board(id)).await
+    }
+
+    async fn list_team_boards (&self, team_ids: &Vec<String>) -> Result<Vec<Board>, MyError> {
+        self.observe("list_team_boards", self.inner.list_team_boards(team_ids)).await
+    }
+
+    async fn list_deleted_boards (&self, deleted_before: i64) -> Result<Vec<Board>, MyError> {
+        self.observe("list_deleted_boards", self.inner.list_deleted_boards(deleted_before)).await
+    }
+
+    // COLUMNS
+    async fn add_column (&self, item: &Column) -> Result<bool, MyError> {
+        self.observe("add_column", self.inner.add_column(item)).await
+    }
+
+    async fn list_columns (&self) -> Result<Vec<Column>, MyError> {
+        self.observe("list_columns", self.inner.list_columns()).await
+    }
+
+    async fn get_column (&self, id: &String) -> Result<Column, MyError> {
+        self.observe("get_column", self.inner.get_column(id)).await
+    }
+
+    async fn delete_column (&self, id: &String) -> Result<bool, MyError> {
+        self.observe("delete_column", self.inner.delete_column(id)).await
+    }
+
+    async fn delete_empty_column (&self, id: &String) -> Result<IfEmpty<bool>, MyError> {
+        self.observe("delete_empty_column", self.inner.delete_empty_column(id)).await
+    }
+
+    async fn update_column (&self, item: &Column) -> Result<bool, MyError> {
+        self.observe("update_column", self.inner.update_column(item)).await
+    }
+
+    async fn list_board_columns (&self, board_id: &String) -> Result<Vec<Column>, MyError> {
+        self.observe("list_board_columns", self.inner.list_board_columns(board_id)).await
+    }
+
+    // CARDS
+    async fn add_card (&self, item: &Card) -> Result<bool, MyError> {
+        self.observe("add_card", self.inner.add_card(item)).await
+    }
+
+    async fn update_card (&self, item: &Card) -> Result<bool, MyError> {
+        self.observe("update_card", self.inner.update_card(item)).await
+    }
+
+    async fn list_board_cards (&self, board_id: &String) -> Result<Vec<Card>, MyError> {
+        self.observe("list_board_cards", self.inner.list_board_cards(board_id)).await
+    }
+
+    async fn get_card (&self, id: &String) -> Result<Card, MyError> {
+        self.observe("get_card", self.inner.get_card(id)).await
+    }
+
+    async fn delete_card (&self, id: &String) -> Result<bool, MyError> {
+        self.observe("delete_card", self.inner.delete_card(id)).await
+    }
+
+    async fn delete_card_cascade (&self, id: &String) -> Result<Vec<RowChange>, MyError> {
+        self.observe("delete_card_cascade", self.inner.delete_card_cascade(id)).await
+    }
+
+    async fn merge_cards (&self, source_id: &String, target_id: &String) -> Result<Vec<RowChange>, MyError> {
+        self.observe("merge_cards", self.inner.merge_cards(source_id, target_id)).await
+    }
+
+    // GROUPS
+    async fn add_group (&self, item: &CardGroup) -> Result<bool, MyError> {
+        self.observe("add_group", self.inner.add_group(item)).await
+    }
+
+    async fn update_group (&self, item: &CardGroup) -> Result<bool, MyError> {
+        self.observe("update_group", self.inner.update_group(item)).await
+    }
+
+    async fn list_board_groups (&self, board_id: &String) -> Result<Vec<CardGroup>, MyError> {
+        self.observe("list_board_groups", self.inner.list_board_groups(board_id)).await
+    }
+
+    async fn get_group (&self, id: &String) -> Result<CardGroup, MyError> {
+        self.observe("get_group", self.inner.get_group(id)).await
+    }
+
+    async fn delete_group (&self, id: &String) -> Result<bool, MyError> {
+        self.observe("delete_group", self.inner.delete_group(id)).await
+    }
+
+    async fn delete_group_cascade (&self, id: &String) -> Result<Vec<RowChange>, MyError> {
+        self.observe("delete_group_cascade", self.inner.delete_group_cascade(id)).await
+    }
+
+    // VOTES
+    async fn add_vote (&self, item: &Vote) -> Result<bool, MyError> {
+        self.observe("add_vote", self.inner.add_vote(item)).await
+    }
+
+    async fn list_board_votes (&self, board_id: &String) -> Result<Vec<Vote>, MyError> {
+        self.observe("list_board_votes", self.inner.list_board_votes(board_id)).await
+    }
+
+    async fn get_vote (&self, id: &String) -> Result<Vote, MyError> {
+        self.observe("get_vote", self.inner.get_vote(id)).await
+    }
+
+    async fn delete_vote (&self, id: &String) -> Result<bool, MyError> {
+        self.observe("delete_vote", self.inner.delete_vote(id)).await
+    }
+
+    // TAGS
+    async fn add_tag (&self, item: &Tag) -> Result<bool, MyError> {
+        self.observe("add_tag", self.inner.add_tag(item)).await
+    }
+
+    async fn list_board_tags (&self, board_id: &String) -> Result<Vec<Tag>, MyError> {
+        self.observe("list_board_tags", self.inner.list_board_tags(board_id)).await
+    }
+
+    async fn get_tag (&self, id: &String) -> Result<Tag, MyError> {
+        self.observe("get_tag", self.inner.get_tag(id)).await
+    }
+
+    async fn delete_tag (&self, id: &String) -> Result<bool, MyError> {
+        self.observe("delete_tag", self.inner.delete_tag(id)).await
+    }
+
+    // CARD TAGS
+    async fn add_card_tag (&self, item: &CardTag) -> Result<bool, MyError> {
+        self.observe("add_card_tag", self.inner.add_card_tag(item)).await
+    }
+
+    async fn list_board_card_tags (&self, board_id: &String) -> Result<Vec<CardTag>, MyError> {
+        self.observe("list_board_card_tags", self.inner.list_board_card_tags(board_id)).await
+    }
+
+    async fn delete_card_tag (&self, id: &String) -> Result<bool, MyError> {
+        self.observe("delete_card_tag", self.inner.delete_card_tag(id)).await
+    }
+
+    // COMMENTS
+    async fn add_comment (&self, item: &Comment) -> Result<bool, MyError> {
+        self.observe("add_comment", self.inner.add_comment(item)).await
+    }
+
+    async fn update_comment (&self, item: &Comment) -> Result<bool, MyError> {
+        self.observe("update_comment", self.inner.update_comment(item)).await
+    }
+
+    async fn list_board_comments (&self, board_id: &String) -> Result<Vec<Comment>, MyError> {
+        self.observe("list_board_comments", self.inner.list_board_comments(board_id)).await
+    }
+
+    async fn get_comment (&self, id: &String) -> Result<Comment, MyError> {
+        self.observe("get_comment", self.inner.get_comment(id)).await
+    }
+
+    async fn delete_comment (&self, id: &String) -> Result<bool, MyError> {
+        self.observe("delete_comment", self.inner.delete_comment(id)).await
+    }
+
+    // REVISIONS
+    async fn add_revision (&self, item: &Revision) -> Result<bool, MyError> {
+        self.observe("add_revision", self.inner.add_revision(item)).await
+    }
+
+    async fn list_card_revisions (&self, card_id: &String) -> Result<Vec<Revision>, MyError> {
+        self.observe("list_card_revisions", self.inner.list_card_revisions(card_id)).await
+    }
+
+    async fn get_revision (&self, id: &String) -> Result<Revision, MyError> {
+        self.observe("get_revision", self.inner.get_revision(id)).await
+    }
+
+    async fn delete_revision (&self, id: &String) -> Result<bool, MyError> {
+        self.observe("delete_revision", self.inner.delete_revision(id)).await
+    }
+
+    // ACTION ITEMS
+    async fn add_action_item (&self, item: &ActionItem) -> Result<bool, MyError> {
+        self.observe("add_action_item", self.inner.add_action_item(item)).await
+    }
+
+    async fn update_action_item (&self, item: &ActionItem) -> Result<bool, MyError> {
+        self.observe("update_action_item", self.inner.update_action_item(item)).await
+    }
+
+    async fn list_board_action_items (&self, board_id: &String) -> Result<Vec<ActionItem>, MyError> {
+        self.observe("list_board_action_items", self.inner.list_board_action_items(board_id)).await
+    }
+
+    async fn get_action_item (&self, id: &String) -> Result<ActionItem, MyError> {
+        self.observe("get_action_item", self.inner.get_action_item(id)).await
+    }
+
+    async fn delete_action_item (&self, id: &String) -> Result<bool, MyError> {
+        self.observe("delete_action_item", self.inner.delete_action_item(id)).await
+    }
+
+    // AUDIT
+    async fn add_audit_entry (&self, item: &AuditEntry) -> Result<bool, MyError> {
+        self.observe("add_audit_entry", self.inner.add_audit_entry(item)).await
+    }
+
+    async fn list_audit_entries (&self, filter: &AuditFilter) -> Result<Vec<AuditEntry>, MyError> {
+        self.observe("list_audit_entries", self.inner.list_audit_entries(filter)).await
+    }
+
+    // SEARCH
+    async fn search (&self, team_ids: &Vec<String>, query: &String, limit: usize) -> Result<Vec<SearchHit>, MyError> {
+        self.observe("search", self.inner.search(team_ids, query, limit)).await
+    }
+
+    // CONTENTS
+    async fn add_board_contents (&self, item: &BoardContents) -> Result<bool, MyError> {
+        self.observe("add_board_contents", self.inner.add_board_contents(item)).await
+    }
+
+    // UNDO
+    async fn apply_changes (&self, changes: &[RowChange]) -> Result<bool, MyError> {
+        self.observe("apply_changes", self.inner.apply_changes(changes)).await
+    }
+
+    // TEAMS
+    async fn add_team (&self, item: &Team) -> Result<bool, MyError> {
+        self.observe("add_team", self.inner.add_team(item)).await
+    }
+
+    async fn add_team_with_owner (&self, item: &Team, owner: &Membership) -> Result<bool, MyError> {
+        self.observe("add_team_with_owner", self.inner.add_team_with_owner(item, owner)).await
+    }
+
+    async fn list_teams (&self, ids: &Vec<String>) -> Result<Vec<Team>, MyError> {
+        self.observe("list_teams", self.inner.list_teams(ids)).await
+    }
+
+    async fn list_all_teams (&self) -> Result<Vec<Team>, MyError> {
+        self.observe("list_all_teams", self.inner.list_all_teams()).await
+    }
+
+    async fn get_team (&self, id: &String) -> Result<Team, MyError> {
+        self.observe("get_team", self.inner.get_team(id)).await
+    }
+
+    async fn delete_team (&self, id: &String) -> Result<bool, MyError> {
+        self.observe("delete_team", self.inner.delete_team(id)).await
+    }
+
+    async fn delete_team_cascade (&self, id: &String) -> Result<IfEmpty<Vec<RowChange>>, MyError> {
+        self.observe("delete_team_cascade", self.inner.delete_team_cascade(id)).await
+    }
+
+    // MEMBERSHIPS
+    async fn add_membership (&self, item: &Membership) -> Result<bool, MyError> {
+        self.observe("add_membership", self.inner.add_membership(item)).await
+    }
+
+    async fn update_membership (&self, item: &Membership) -> Result<bool, MyError> {
+        self.observe("update_membership", self.inner.update_membership(item)).await
+    }
+
+    async fn list_team_memberships (&self, team_id: &String) -> Result<Vec<Membership>, MyError> {
+        self.observe("list_team_memberships", self.inner.list_team_memberships(team_id)).await
+    }
+
+    async fn list_user_memberships (&self, user_id: &String) -> Result<Vec<Membership>, MyError> {
+        self.observe("list_user_memberships", self.inner.list_user_memberships(user_id)).await
+    }
+
+    async fn delete_membership (&self, id: &String) -> Result<bool, MyError> {
+        self.observe("delete_membership", self.inner.delete_membership(id)).await
+    }
+
+    // INVITES
+    async fn add_invite (&self, item: &Invite) -> Result<bool, MyError> {
+        self.observe("add_invite", self.inner.add_invite(item)).await
+    }
+
+    async fn list_board_invites (&self, board_id: &String) -> Result<Vec<Invite>, MyError> {
+        self.observe("list_board_invites", self.inner.list_board_invites(board_id)).await
+    }
+
+    async fn get_invite (&self, id: &String) -> Result<Invite, MyError> {
+        self.observe("get_invite", self.inner.get_invite(id)).await
+    }
+
+    async fn delete_invite (&self, id: &String) -> Result<bool, MyError> {
+        self.observe("delete_invite", self.inner.delete_invite(id)).await
+    }
+}
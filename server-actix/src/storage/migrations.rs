@@ -0,0 +1,36 @@
+// the sql files under migrations/, built in, in the order they have to run, each recorded by
+// version once applied, so a backend can bring its schema up to date and tell when it is behind
+pub struct Migration {
+    pub version: &'static str,
+    pub sql: &'static str,
+}
+
+// the one that started recording the others, and works out which of those had run before it
+pub const VERSION_TRACKING: &'static str = "0012_schema_migrations";
+
+// new ones go at the end, and nothing above them ever changes, since they may have run already
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: "0001_init", sql: include_str!("../../migrations/0001_init.sql") },
+    Migration { version: "0002_teams", sql: include_str!("../../migrations/0002_teams.sql") },
+    Migration { version: "0003_invites", sql: include_str!("../../migrations/0003_invites.sql") },
+    Migration { version: "0004_cards", sql: include_str!("../../migrations/0004_cards.sql") },
+    Migration { version: "0005_board_states", sql: include_str!("../../migrations/0005_board_states.sql") },
+    Migration { version: "0006_foreign_keys", sql: include_str!("../../migrations/0006_foreign_keys.sql") },
+    Migration { version: "0007_tags_and_positions", sql: include_str!("../../migrations/0007_tags_and_positions.sql") },
+    Migration { version: "0008_comments_and_action_items", sql: include_str!("../../migrations/0008_comments_and_action_items.sql") },
+    Migration { version: "0009_search", sql: include_str!("../../migrations/0009_search.sql") },
+    Migration { version: "0010_audit_log", sql: include_str!("../../migrations/0010_audit_log.sql") },
+    Migration { version: "0011_revisions", sql: include_str!("../../migrations/0011_revisions.sql") },
+    Migration { version: "0012_schema_migrations", sql: include_str!("../../migrations/0012_schema_migrations.sql") },
+    Migration { version: "0013_audit_seq", sql: include_str!("../../migrations/0013_audit_seq.sql") },
+];
+
+// the ones this build knows of that have not been applied
+pub fn pending (applied: &[String]) -> Vec<&'static Migration> {
+    MIGRATIONS.iter().filter(|migration| !applied.iter().any(|version| version == migration.version)).collect()
+}
+
+// fills in the {schema} and {table_...} placeholders the files are written with
+pub fn render (sql: &str, names: &[(&str, &String)]) -> String {
+    names.iter().fold(sql.to_string(), |sql, (placeholder, name)| sql.replace(&format!("{{{}}}", placeholder), name))
+}
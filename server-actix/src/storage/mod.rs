@@ -3,3 +3,6 @@ mod util;
 
 pub mod invalid;
 pub mod postgres;
+pub mod events;
+pub mod metered;
+pub mod migrations;
@@ -1,69 +1,167 @@
 
 use std::convert::TryFrom;
+use std::fmt::Display;
+use std::future::Future;
+use std::time::Instant;
 use async_trait::async_trait;
+use tracing::{debug, info, warn, debug_span, Instrument};
+use bytes::BytesMut;
 
 use deadpool_postgres::{Client, Config as DeadpoolConfig, Pool};
-use tokio_postgres::{NoTls, row::Row, types::{FromSql, ToSql}};
+use tokio_postgres::{NoTls, Transaction, row::Row, types::{accepts, to_sql_checked, FromSql, IsNull, ToSql, Type}};
 
 use crate::time_provider::TimeProvider;
-use crate::models::{MyError, Config, Storage, Board, Column};
+use crate::logging::redacted;
+use crate::config::PostgresConfig;
+use crate::models::{MyError, Storage, Board, BoardDeletion, BoardContents, Column, Card, CardGroup, Vote, Tag, CardTag, Comment, Revision, ActionItem, SearchKind, SearchHit, AuditEntry, AuditFilter, Team, Role, Membership, Invite, IfEmpty, PoolStatus, Row as MyRow};
+use crate::undo::{RowChange, same, touched, check_undoable};
 use super::util::{try_from_vec};
+use super::migrations::{MIGRATIONS, VERSION_TRACKING, pending, render};
 use const_format::formatcp;
 
 
-const DEFAULT_SCHEMA: &'static str = "bareretro";
-const DEFAULT_TABLE_BOARDS: &'static str = "boards";
+// any number, as long as nothing else sharing the database takes the same lock
+const MIGRATION_LOCK_ID: i64 = 0x6261_7265_7265_7472;
 
-const DEFAULT_HOST: &'static str = "postgres";
-const DEFAULT_PORT: &'static str = "5432";
-const DEFAULT_USER: &'static str = "postgres";
-const DEFAULT_PASSWORD: &'static str = "";
-const DEFAULT_DBNAME: &'static str = "postgres";
+const SEARCH_HEADLINE_OPTIONS: &'static str = "StartSel=**, StopSel=**, MinWords=5, MaxWords=20";
 
 const FIELD_ID: &'static str = "id";
 const FIELD_TITLE: &'static str = "title";
+const FIELD_NAME: &'static str = "name";
 const FIELD_OWNER: &'static str = "owner";
+const FIELD_ROLE: &'static str = "role";
 // const FIELD_CONTENTS: &'static str = "contents";
-// const FIELD_AUTHOR: &'static str = "author";
+const FIELD_AUTHOR: &'static str = "author";
+const FIELD_AUTHOR_NAME: &'static str = "author_name";
+const FIELD_TEXT: &'static str = "text";
+const FIELD_EDITOR: &'static str = "editor";
+const FIELD_EDITOR_NAME: &'static str = "editor_name";
+const FIELD_ASSIGNEE: &'static str = "assignee";
+const FIELD_DONE: &'static str = "done";
+const FIELD_ACTOR: &'static str = "actor";
+const FIELD_ACTION: &'static str = "action";
+const FIELD_ENTITY_TYPE: &'static str = "entity_type";
+const FIELD_ENTITY_ID: &'static str = "entity_id";
+const FIELD_BEFORE: &'static str = "before";
+const FIELD_AFTER: &'static str = "after";
+const FIELD_IP: &'static str = "ip";
+const FIELD_SEQ: &'static str = "seq";
+const FIELD_MERGED_INTO: &'static str = "merged_into";
+const FIELD_POSITION: &'static str = "position";
+const FIELD_CREATED_BY: &'static str = "created_by";
+const FIELD_EXPIRES_AT: &'static str = "expires_at";
 const FIELD_CREATED_AT: &'static str = "created_at";
+const FIELD_ARCHIVED_AT: &'static str = "archived_at";
+const FIELD_DELETED_AT: &'static str = "deleted_at";
 //const FIELD_UPDATED_AT: &'static str = "updated_at";
 
 const FIELD_BOARD_ID: &'static str = "board_id";
-// const FIELD_COLUMN_ID: &'static str = "column_id";
-// const FIELD_CARD_ID: &'static str = "card_id";
+const FIELD_TEAM_ID: &'static str = "team_id";
+const FIELD_USER_ID: &'static str = "user_id";
+const FIELD_COLUMN_ID: &'static str = "column_id";
+const FIELD_CARD_ID: &'static str = "card_id";
+const FIELD_GROUP_ID: &'static str = "group_id";
+const FIELD_TAG_ID: &'static str = "tag_id";
+const FIELD_COMMENT_ID: &'static str = "comment_id";
+// migrations only
+const FIELD_VERSION: &'static str = "version";
+// search results only
+const FIELD_KIND: &'static str = "kind";
+const FIELD_BOARD_TITLE: &'static str = "board_title";
+const FIELD_SNIPPET: &'static str = "snippet";
+const FIELD_RANK: &'static str = "rank";
 
 
 #[derive(Clone)]
 pub struct PostgresStorage {
+    #[allow(dead_code)]
     time_provider: Box<dyn TimeProvider>,
     schema: String,
+    search_config: String,
     table_boards: String,
+    table_columns: String,
+    table_teams: String,
+    table_memberships: String,
+    table_invites: String,
+    table_cards: String,
+    table_groups: String,
+    table_votes: String,
+    table_tags: String,
+    table_card_tags: String,
+    table_comments: String,
+    table_revisions: String,
+    table_action_items: String,
+    table_audit_log: String,
+    table_migrations: String,
     pool: Pool,
 }
 
 impl PostgresStorage {
-    pub fn from_env (time_provider: Box<dyn TimeProvider>) -> Result<Self, MyError> {
+    pub fn new (time_provider: Box<dyn TimeProvider>, config: &PostgresConfig) -> Result<Self, MyError> {
         // https://crates.io/crates/deadpool-postgres
-        let cfg = DeadpoolConfig {
-            host: Some(Config::env_var_string("PG_HOST", String::from(DEFAULT_HOST))),
-            port: Some(
-                Config::env_var_string("PG_PORT", String::from(DEFAULT_PORT))
-                    .parse::<u16>().map_err(|why| format!("Port is not a valid number! {}", why))?
-            ),
-            user: Some(Config::env_var_string("PG_USER", String::from(DEFAULT_USER))),
-            password: Some(Config::env_var_string("PG_PASS", String::from(DEFAULT_PASSWORD))),
-            dbname: Some(Config::env_var_string("PG_DBNAME", String::from(DEFAULT_DBNAME))),
+        let mut cfg = DeadpoolConfig {
+            host: Some(config.host.clone()),
+            port: Some(config.port),
+            user: Some(config.user.clone()),
+            password: Some(config.password.clone()),
+            dbname: Some(config.dbname.clone()),
             ..Default::default()
         };
+        if let Some(pool_size) = config.pool_size {
+            let mut pool = cfg.get_pool_config();
+            pool.max_size = pool_size;
+            cfg.pool = Some(pool);
+        }
+        info!(
+            host = %config.host, port = config.port, user = %config.user, dbname = %config.dbname,
+            password = redacted(&config.password), pool_size = ?config.pool_size,
+            "postgres",
+        );
 
-        let storage = Self {
+        Ok(Self {
             time_provider: time_provider,
-            schema: Config::env_var_string("PG_SCHEMA", String::from(DEFAULT_SCHEMA)),
-            table_boards: Config::env_var_string("PG_TABLE_BOARDS", String::from(DEFAULT_TABLE_BOARDS)),
+            schema: config.schema.clone(),
+            search_config: config.search_config.clone(),
+            table_boards: config.table_boards.clone(),
+            table_columns: config.table_columns.clone(),
+            table_teams: config.table_teams.clone(),
+            table_memberships: config.table_memberships.clone(),
+            table_invites: config.table_invites.clone(),
+            table_cards: config.table_cards.clone(),
+            table_groups: config.table_groups.clone(),
+            table_votes: config.table_votes.clone(),
+            table_tags: config.table_tags.clone(),
+            table_card_tags: config.table_card_tags.clone(),
+            table_comments: config.table_comments.clone(),
+            table_revisions: config.table_revisions.clone(),
+            table_action_items: config.table_action_items.clone(),
+            table_audit_log: config.table_audit_log.clone(),
+            table_migrations: config.table_migrations.clone(),
             pool: cfg.create_pool(NoTls).map_err(|why| format!("Failed creating pool: {}", why))?,
-        };
+        })
+    }
 
-        Ok(storage)
+    // what the {placeholders} in the migration files stand for
+    fn placeholders (&self) -> Vec<(&'static str, &String)> {
+        vec![
+            ("schema", &self.schema),
+            ("search_config", &self.search_config),
+            ("table_boards", &self.table_boards),
+            ("table_columns", &self.table_columns),
+            ("table_teams", &self.table_teams),
+            ("table_memberships", &self.table_memberships),
+            ("table_invites", &self.table_invites),
+            ("table_cards", &self.table_cards),
+            ("table_groups", &self.table_groups),
+            ("table_votes", &self.table_votes),
+            ("table_tags", &self.table_tags),
+            ("table_card_tags", &self.table_card_tags),
+            ("table_comments", &self.table_comments),
+            ("table_revisions", &self.table_revisions),
+            ("table_action_items", &self.table_action_items),
+            ("table_audit_log", &self.table_audit_log),
+            ("table_migrations", &self.table_migrations),
+        ]
     }
 
     async fn client (&self) -> Result<Client, MyError> {
@@ -77,6 +175,10 @@ trait RowCrud {
     fn table_name (storage: &PostgresStorage) -> &String;
 //    fn field_names () -> Vec<&'static str>;
     fn field_names () -> &'static str;
+    // what reading a row back takes, more than what goes in when the database fills some in itself
+    fn select_names () -> &'static str {
+        Self::field_names()
+    }
     // https://docs.rs/tokio-postgres/0.7.0/tokio_postgres/struct.Client.html#method.execute
     fn row_values (&self) -> Vec<&(dyn ToSql + Sync)>;
 }
@@ -85,9 +187,22 @@ fn get_field<'a, T> (row: &'a Row, field: &'static str) -> Result<T, MyError> wh
     row.try_get(field).map_err(|why| format!("Could not get {}! {}", field, why))
 }
 
+// each query gets a span of its own, under the request it runs for, and says how long it took
+async fn timed<T, E> (op: &'static str, table: &str, query: impl Future<Output = Result<T, E>>) -> Result<T, E> where E: Display {
+    let span = debug_span!("query", op, table);
+    let started = Instant::now();
+    let result = query.instrument(span.clone()).await;
+    let elapsed_ms = started.elapsed().as_secs_f64() * 1_000.0;
+    span.in_scope(|| match &result {
+        Ok(_) => debug!(elapsed_ms, "query"),
+        Err(why) => warn!(elapsed_ms, error = %why, "query failed"),
+    });
+    result
+}
+
 fn values_str<T> (values: &Vec<T>) -> String {
     // TODO: have prepared consts for all anticipated lengths? or at least cache for sizes?
-    (1..values.len())
+    (1..=values.len())
         .map(|n| format!("${}", n))
         .collect::<Vec<String>>()
         .join(", ")
@@ -95,52 +210,79 @@ fn values_str<T> (values: &Vec<T>) -> String {
 
 async fn add<T> (storage: &PostgresStorage, item: &T) -> Result<bool, MyError> where T: RowCrud {
     let values = item.row_values();
-    match storage.client().await?.execute(
+    match timed("insert", T::table_name(storage), storage.client().await?.execute(
         format!(
             "INSERT INTO {}.{} ({}) VALUES ({})",
             storage.schema,
-            T::table_name(&storage),
+            T::table_name(storage),
             T::field_names(),
             values_str(&values),
         ).as_str(),
         &values,
-    ).await {
-        Err(why) => Err(format!("Add {} failed: {}", T::name_single(), why.to_string())),
+    )).await {
+        Err(why) => Err(format!("Add {} failed: {}", T::name_single(), why)),
         Ok(_) => Ok(true)
     }
 }
 
 async fn list<T> (storage: &PostgresStorage) -> Result<Vec<T>, MyError>
         where T: RowCrud + TryFrom<Row, Error=MyError> {
-    match storage.client().await?.query(
+    match timed("select", T::table_name(storage), storage.client().await?.query(
         format!(
             "SELECT {} FROM {}.{}",
-            T::field_names(),
+            T::select_names(),
             storage.schema,
-            T::table_name(&storage),
+            T::table_name(storage),
         ).as_str(),
         &[
         ],
-    ).await {
-        Err(why) => Err(format!("List {} failed: {}", T::name_plural(), why.to_string())),
+    )).await {
+        Err(why) => Err(format!("List {} failed: {}", T::name_plural(), why)),
+        Ok(rows) => try_from_vec(rows, T::name_plural()),
+    }
+}
+
+async fn list_filtered<T> (storage: &PostgresStorage, filter: String, values: &[&(dyn ToSql + Sync)]) -> Result<Vec<T>, MyError>
+        where T: RowCrud + TryFrom<Row, Error=MyError> {
+    match timed("select", T::table_name(storage), storage.client().await?.query(
+        format!(
+            "SELECT {} FROM {}.{} WHERE {}",
+            T::select_names(),
+            storage.schema,
+            T::table_name(storage),
+            filter,
+        ).as_str(),
+        values,
+    )).await {
+        Err(why) => Err(format!("List {} failed: {}", T::name_plural(), why)),
         Ok(rows) => try_from_vec(rows, T::name_plural()),
     }
 }
 
+async fn list_where<T> (storage: &PostgresStorage, field: &'static str, value: &(dyn ToSql + Sync)) -> Result<Vec<T>, MyError>
+        where T: RowCrud + TryFrom<Row, Error=MyError> {
+    list_filtered(storage, format!("{} = $1", field), &[value]).await
+}
+
+async fn list_where_in<T> (storage: &PostgresStorage, field: &'static str, values: &Vec<String>) -> Result<Vec<T>, MyError>
+        where T: RowCrud + TryFrom<Row, Error=MyError> {
+    list_filtered(storage, format!("{} = ANY($1)", field), &[values]).await
+}
+
 async fn get<T> (storage: &PostgresStorage, id: &String) -> Result<T, MyError>
         where T: RowCrud + TryFrom<Row, Error=MyError> {
-    match storage.client().await?.query_one(
+    match timed("select", T::table_name(storage), storage.client().await?.query_one(
         format!(
             "SELECT * FROM {}.{} WHERE {} = $1",
             storage.schema,
-            T::table_name(&storage),
+            T::table_name(storage),
             FIELD_ID,
         ).as_str(),
         &[
             id,
         ],
-    ).await {
-        Err(why) => Err(format!("List {} failed: {}", T::name_plural(), why.to_string())),
+    )).await {
+        Err(why) => Err(format!("List {} failed: {}", T::name_plural(), why)),
         Ok(row) => match T::try_from(row) {
             Err(why) => Err(format!("Failed converting {}: {}", T::name_single(), why)),
             Ok(item) => Ok(item),
@@ -148,31 +290,412 @@ async fn get<T> (storage: &PostgresStorage, id: &String) -> Result<T, MyError>
     }
 }
 
+async fn update<T> (storage: &PostgresStorage, id: &String, item: &T) -> Result<bool, MyError> where T: RowCrud {
+    let mut values = item.row_values();
+    let placeholders = values_str(&values);
+    // the id goes last, after every field value
+    values.push(id);
+    match timed("update", T::table_name(storage), storage.client().await?.execute(
+        format!(
+            "UPDATE {}.{} SET ({}) = ({}) WHERE {} = ${}",
+            storage.schema,
+            T::table_name(storage),
+            T::field_names(),
+            placeholders,
+            FIELD_ID,
+            values.len(),
+        ).as_str(),
+        &values,
+    )).await {
+        Err(why) => Err(format!("Update {} failed: {}", T::name_single(), why)),
+        Ok(update_count) => Ok(update_count == 1)
+    }
+}
+
 async fn delete<T> (storage: &PostgresStorage, id: &String) -> Result<bool, MyError> where T: RowCrud {
-    match storage.client().await?.execute(
+    match timed("delete", T::table_name(storage), storage.client().await?.execute(
+        format!(
+            "DELETE FROM {}.{} WHERE {} = $1",
+            storage.schema,
+            T::table_name(storage),
+            FIELD_ID,
+        ).as_str(),
+        &[
+            id,
+        ],
+    )).await {
+        Err(why) => Err(format!("Delete {} failed: {}", T::name_single(), why)),
+        Ok(update_count) => Ok(update_count == 1)
+    }
+}
+
+async fn add_tx<T> (storage: &PostgresStorage, tx: &Transaction<'_>, item: &T) -> Result<(), MyError> where T: RowCrud {
+    let values = item.row_values();
+    match timed("insert", T::table_name(storage), tx.execute(
+        format!(
+            "INSERT INTO {}.{} ({}) VALUES ({})",
+            storage.schema,
+            T::table_name(storage),
+            T::field_names(),
+            values_str(&values),
+        ).as_str(),
+        &values,
+    )).await {
+        Err(why) => Err(format!("Add {} failed: {}", T::name_single(), why)),
+        Ok(_) => Ok(())
+    }
+}
+
+async fn update_tx<T> (storage: &PostgresStorage, tx: &Transaction<'_>, id: &String, item: &T) -> Result<bool, MyError> where T: RowCrud {
+    let mut values = item.row_values();
+    let placeholders = values_str(&values);
+    // the id goes last, after every field value
+    values.push(id);
+    match timed("update", T::table_name(storage), tx.execute(
+        format!(
+            "UPDATE {}.{} SET ({}) = ({}) WHERE {} = ${}",
+            storage.schema,
+            T::table_name(storage),
+            T::field_names(),
+            placeholders,
+            FIELD_ID,
+            values.len(),
+        ).as_str(),
+        &values,
+    )).await {
+        Err(why) => Err(format!("Update {} failed: {}", T::name_single(), why)),
+        Ok(update_count) => Ok(update_count == 1)
+    }
+}
+
+async fn add_all_tx<T> (storage: &PostgresStorage, tx: &Transaction<'_>, items: &Vec<T>) -> Result<(), MyError> where T: RowCrud {
+    for item in items.iter() {
+        add_tx(storage, tx, item).await?;
+    }
+    Ok(())
+}
+
+async fn delete_where_tx<T> (storage: &PostgresStorage, tx: &Transaction<'_>, field: &'static str, value: &String) -> Result<u64, MyError> where T: RowCrud {
+    match timed("delete", T::table_name(storage), tx.execute(
         format!(
             "DELETE FROM {}.{} WHERE {} = $1",
             storage.schema,
-            T::table_name(&storage),
+            T::table_name(storage),
+            field,
+        ).as_str(),
+        &[
+            value,
+        ],
+    )).await {
+        Err(why) => Err(format!("Delete {} failed: {}", T::name_plural(), why)),
+        Ok(update_count) => Ok(update_count)
+    }
+}
+
+// the rows as they were, for telling exactly what went
+async fn delete_returning_tx<T> (storage: &PostgresStorage, tx: &Transaction<'_>, field: &'static str, value: &String) -> Result<Vec<T>, MyError>
+        where T: RowCrud + TryFrom<Row, Error=MyError> {
+    match timed("delete", T::table_name(storage), tx.query(
+        format!(
+            "DELETE FROM {}.{} WHERE {} = $1 RETURNING {}",
+            storage.schema,
+            T::table_name(storage),
+            field,
+            T::select_names(),
+        ).as_str(),
+        &[
+            value,
+        ],
+    )).await {
+        Err(why) => Err(format!("Delete {} failed: {}", T::name_plural(), why)),
+        Ok(rows) => try_from_vec(rows, T::name_plural()),
+    }
+}
+
+// points one reference somewhere else, or at nothing, wherever it points at value, answering with the rows as they are now
+async fn relink_tx<T> (storage: &PostgresStorage, tx: &Transaction<'_>, field: &'static str, value: &String, to: Option<&String>) -> Result<Vec<T>, MyError>
+        where T: RowCrud + TryFrom<Row, Error=MyError> {
+    match timed("update", T::table_name(storage), tx.query(
+        format!(
+            "UPDATE {}.{} SET {} = $2 WHERE {} = $1 RETURNING {}",
+            storage.schema,
+            T::table_name(storage),
+            field,
+            field,
+            T::select_names(),
+        ).as_str(),
+        &[
+            value,
+            &to,
+        ],
+    )).await {
+        Err(why) => Err(format!("Update {} failed: {}", T::name_plural(), why)),
+        Ok(rows) => try_from_vec(rows, T::name_plural()),
+    }
+}
+
+fn removed<T> (rows: Vec<T>, wrap: fn(T) -> MyRow) -> impl Iterator<Item = RowChange> {
+    rows.into_iter().map(move |row| RowChange { before: Some(wrap(row)), after: None })
+}
+
+// the row as it is now, locked until the transaction ends, so nothing can change it or start pointing at it
+async fn lock_tx<T> (storage: &PostgresStorage, tx: &Transaction<'_>, id: &String) -> Result<Option<T>, MyError>
+        where T: RowCrud + TryFrom<Row, Error=MyError> {
+    match timed("select", T::table_name(storage), tx.query_opt(
+        format!(
+            "SELECT {} FROM {}.{} WHERE {} = $1 FOR UPDATE",
+            T::select_names(),
+            storage.schema,
+            T::table_name(storage),
             FIELD_ID,
         ).as_str(),
         &[
             id,
         ],
-    ).await {
-        Err(why) => Err(format!("Delete {} failed: {}", T::name_single(), why.to_string())),
-        Ok(update_count) => Ok(update_count == 0)
+    )).await {
+        Err(why) => Err(format!("Get {} failed: {}", T::name_single(), why)),
+        Ok(None) => Ok(None),
+        Ok(Some(row)) => T::try_from(row).map(Some),
+    }
+}
+
+// false when there is one with that id already
+async fn insert_tx<T> (storage: &PostgresStorage, tx: &Transaction<'_>, item: &T) -> Result<bool, MyError> where T: RowCrud {
+    let values = item.row_values();
+    match timed("insert", T::table_name(storage), tx.execute(
+        format!(
+            "INSERT INTO {}.{} ({}) VALUES ({}) ON CONFLICT ({}) DO NOTHING",
+            storage.schema,
+            T::table_name(storage),
+            T::field_names(),
+            values_str(&values),
+            FIELD_ID,
+        ).as_str(),
+        &values,
+    )).await {
+        Err(why) => Err(format!("Add {} failed: {}", T::name_single(), why)),
+        Ok(insert_count) => Ok(insert_count == 1)
+    }
+}
+
+async fn delete_tx<T> (storage: &PostgresStorage, tx: &Transaction<'_>, id: &String) -> Result<bool, MyError> where T: RowCrud {
+    Ok(delete_where_tx::<T>(storage, tx, FIELD_ID, id).await? == 1)
+}
+
+async fn ids_where_tx<T> (storage: &PostgresStorage, tx: &Transaction<'_>, field: &'static str, value: &String) -> Result<Vec<String>, MyError> where T: RowCrud {
+    match timed("select", T::table_name(storage), tx.query(
+        format!(
+            "SELECT {} FROM {}.{} WHERE {} = $1",
+            FIELD_ID,
+            storage.schema,
+            T::table_name(storage),
+            field,
+        ).as_str(),
+        &[
+            value,
+        ],
+    )).await {
+        Err(why) => Err(format!("List {} failed: {}", T::name_plural(), why)),
+        Ok(rows) => rows.iter().map(|row| get_field(row, FIELD_ID)).collect(),
+    }
+}
+
+async fn lock_row_tx (storage: &PostgresStorage, tx: &Transaction<'_>, like: &MyRow) -> Result<Option<MyRow>, MyError> {
+    let id = like.id();
+    Ok(match like {
+        MyRow::Column(_) => lock_tx(storage, tx, id).await?.map(MyRow::Column),
+        MyRow::Card(_) => lock_tx(storage, tx, id).await?.map(MyRow::Card),
+        MyRow::Group(_) => lock_tx(storage, tx, id).await?.map(MyRow::Group),
+        MyRow::Vote(_) => lock_tx(storage, tx, id).await?.map(MyRow::Vote),
+        MyRow::Tag(_) => lock_tx(storage, tx, id).await?.map(MyRow::Tag),
+        MyRow::CardTag(_) => lock_tx(storage, tx, id).await?.map(MyRow::CardTag),
+        MyRow::Comment(_) => lock_tx(storage, tx, id).await?.map(MyRow::Comment),
+        MyRow::Revision(_) => lock_tx(storage, tx, id).await?.map(MyRow::Revision),
+        MyRow::ActionItem(_) => lock_tx(storage, tx, id).await?.map(MyRow::ActionItem),
+        _ => return Err(format!("Cannot undo changes to a {}", like.kind())),
+    })
+}
+
+// kind and id of whatever points straight at the row, and would go or be unlinked along with it
+async fn dependents_tx (storage: &PostgresStorage, tx: &Transaction<'_>, row: &MyRow) -> Result<Vec<(&'static str, String)>, MyError> {
+    let id = row.id();
+    let found = match row {
+        MyRow::Column(_) => vec![
+            ("card", ids_where_tx::<Card>(storage, tx, FIELD_COLUMN_ID, id).await?),
+            ("group", ids_where_tx::<CardGroup>(storage, tx, FIELD_COLUMN_ID, id).await?),
+        ],
+        MyRow::Card(_) => vec![
+            ("card", ids_where_tx::<Card>(storage, tx, FIELD_MERGED_INTO, id).await?),
+            ("vote", ids_where_tx::<Vote>(storage, tx, FIELD_CARD_ID, id).await?),
+            ("card_tag", ids_where_tx::<CardTag>(storage, tx, FIELD_CARD_ID, id).await?),
+            ("comment", ids_where_tx::<Comment>(storage, tx, FIELD_CARD_ID, id).await?),
+            ("revision", ids_where_tx::<Revision>(storage, tx, FIELD_CARD_ID, id).await?),
+        ],
+        MyRow::Group(_) => vec![
+            ("card", ids_where_tx::<Card>(storage, tx, FIELD_GROUP_ID, id).await?),
+            ("vote", ids_where_tx::<Vote>(storage, tx, FIELD_GROUP_ID, id).await?),
+        ],
+        MyRow::Tag(_) => vec![
+            ("card_tag", ids_where_tx::<CardTag>(storage, tx, FIELD_TAG_ID, id).await?),
+        ],
+        MyRow::Comment(_) => vec![
+            ("revision", ids_where_tx::<Revision>(storage, tx, FIELD_COMMENT_ID, id).await?),
+        ],
+        _ => vec![],
+    };
+    Ok(found.into_iter().flat_map(|(kind, ids)| ids.into_iter().map(move |id| (kind, id))).collect())
+}
+
+// false when the row was not there to change, or was there already to add
+async fn write_row_tx (storage: &PostgresStorage, tx: &Transaction<'_>, change: &RowChange) -> Result<bool, MyError> {
+    match (&change.before, &change.after) {
+        (None, Some(after)) => match after {
+            MyRow::Column(item) => insert_tx(storage, tx, item).await,
+            MyRow::Card(item) => insert_tx(storage, tx, item).await,
+            MyRow::Group(item) => insert_tx(storage, tx, item).await,
+            MyRow::Vote(item) => insert_tx(storage, tx, item).await,
+            MyRow::Tag(item) => insert_tx(storage, tx, item).await,
+            MyRow::CardTag(item) => insert_tx(storage, tx, item).await,
+            MyRow::Comment(item) => insert_tx(storage, tx, item).await,
+            MyRow::Revision(item) => insert_tx(storage, tx, item).await,
+            MyRow::ActionItem(item) => insert_tx(storage, tx, item).await,
+            _ => Err(format!("Cannot undo changes to a {}", after.kind())),
+        },
+        (Some(_), Some(after)) => match after {
+            MyRow::Column(item) => update_tx(storage, tx, &item.id, item).await,
+            MyRow::Card(item) => update_tx(storage, tx, &item.id, item).await,
+            MyRow::Group(item) => update_tx(storage, tx, &item.id, item).await,
+            MyRow::Vote(item) => update_tx(storage, tx, &item.id, item).await,
+            MyRow::Tag(item) => update_tx(storage, tx, &item.id, item).await,
+            MyRow::CardTag(item) => update_tx(storage, tx, &item.id, item).await,
+            MyRow::Comment(item) => update_tx(storage, tx, &item.id, item).await,
+            MyRow::Revision(item) => update_tx(storage, tx, &item.id, item).await,
+            MyRow::ActionItem(item) => update_tx(storage, tx, &item.id, item).await,
+            _ => Err(format!("Cannot undo changes to a {}", after.kind())),
+        },
+        (Some(before), None) => {
+            let id = before.id();
+            match before {
+                MyRow::Column(_) => delete_tx::<Column>(storage, tx, id).await,
+                MyRow::Card(_) => delete_tx::<Card>(storage, tx, id).await,
+                MyRow::Group(_) => delete_tx::<CardGroup>(storage, tx, id).await,
+                MyRow::Vote(_) => delete_tx::<Vote>(storage, tx, id).await,
+                MyRow::Tag(_) => delete_tx::<Tag>(storage, tx, id).await,
+                MyRow::CardTag(_) => delete_tx::<CardTag>(storage, tx, id).await,
+                MyRow::Comment(_) => delete_tx::<Comment>(storage, tx, id).await,
+                MyRow::Revision(_) => delete_tx::<Revision>(storage, tx, id).await,
+                MyRow::ActionItem(_) => delete_tx::<ActionItem>(storage, tx, id).await,
+                _ => Err(format!("Cannot undo changes to a {}", before.kind())),
+            }
+        },
+        (None, None) => Ok(true),
     }
 }
 
 
-// https://github.com/dtolnay/async-trait#non-threadsafe-futures
-#[async_trait(?Send)]
+#[async_trait]
 impl Storage for PostgresStorage {
     fn name (&self) -> &'static str {
         "Postgres"
     }
 
+    fn pool_status (&self) -> Option<PoolStatus> {
+        let status = self.pool.status();
+        Some(PoolStatus {
+            max_size: status.max_size,
+            size: status.size,
+            available: status.available,
+        })
+    }
+
+    // HEALTH
+    // reaching the database at all is half of it, the other is having every migration applied
+    async fn health_check (&self) -> Result<(), MyError> {
+        let rows = match timed("select", &self.table_migrations, self.client().await?.query(
+            format!(
+                "SELECT {} FROM {}.{}",
+                FIELD_VERSION,
+                self.schema,
+                self.table_migrations,
+            ).as_str(),
+            &[
+            ],
+        )).await {
+            Err(why) => return Err(format!("List migrations failed: {}", why)),
+            Ok(rows) => rows,
+        };
+        let applied = rows.iter()
+            .map(|row| get_field(row, FIELD_VERSION))
+            .collect::<Result<Vec<String>, MyError>>()?;
+
+        let pending: Vec<&str> = pending(&applied).iter().map(|migration| migration.version).collect();
+        if !pending.is_empty() {
+            return Err(format!("Migrations not applied: {}", pending.join(", ")))
+        }
+        Ok(())
+    }
+
+    async fn migrate (&self) -> Result<Vec<String>, MyError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(|why| format!("Migrate failed: {}", why))?;
+        // whoever migrates at the same time waits here, then finds nothing left to do
+        tx.execute("SELECT pg_advisory_xact_lock($1)", &[&MIGRATION_LOCK_ID]).await
+            .map_err(|why| format!("Migrate failed: {}", why))?;
+
+        let migrations_table = format!("{}.{}", self.schema, self.table_migrations);
+        let boards_table = format!("{}.{}", self.schema, self.table_boards);
+        let found = tx.query_one("SELECT to_regclass($1) IS NOT NULL, to_regclass($2) IS NOT NULL", &[&migrations_table, &boards_table]).await
+            .map_err(|why| format!("Migrate failed: {}", why))?;
+        let (tracked, existing): (bool, bool) = (found.get(0), found.get(1));
+        // the earlier files do not all run twice cleanly, so an untracked schema gets 0012 first,
+        // which records the ones it finds there and leaves the rest to run below
+        let mut ran = vec![];
+        if existing && !tracked {
+            let tracking = MIGRATIONS.iter().find(|migration| migration.version == VERSION_TRACKING).unwrap();
+            info!(version = tracking.version, "migrating");
+            tx.batch_execute(render(tracking.sql, &self.placeholders()).as_str()).await
+                .map_err(|why| format!("Migration {} failed: {}", tracking.version, why))?;
+            ran.push(tracking.version.to_string());
+        }
+
+        // the same as 0012 makes it, but needed before any of the others can be recorded
+        tx.batch_execute(format!(
+            "CREATE SCHEMA IF NOT EXISTS {}; CREATE TABLE IF NOT EXISTS {} ({} TEXT PRIMARY KEY, applied_at TIMESTAMPTZ NOT NULL DEFAULT now())",
+            self.schema, migrations_table, FIELD_VERSION,
+        ).as_str()).await.map_err(|why| format!("Migrate failed: {}", why))?;
+
+        let rows = tx.query(format!("SELECT {} FROM {}", FIELD_VERSION, migrations_table).as_str(), &[]).await
+            .map_err(|why| format!("List migrations failed: {}", why))?;
+        let applied = rows.iter()
+            .map(|row| get_field(row, FIELD_VERSION))
+            .collect::<Result<Vec<String>, MyError>>()?;
+
+        // all in the one transaction, so a failing file leaves the schema as it was
+        for migration in pending(&applied) {
+            info!(version = migration.version, "migrating");
+            tx.batch_execute(render(migration.sql, &self.placeholders()).as_str()).await
+                .map_err(|why| format!("Migration {} failed: {}", migration.version, why))?;
+            tx.execute(format!("INSERT INTO {} ({}) VALUES ($1) ON CONFLICT DO NOTHING", migrations_table, FIELD_VERSION).as_str(), &[&migration.version]).await
+                .map_err(|why| format!("Recording migration {} failed: {}", migration.version, why))?;
+            ran.push(migration.version.to_string());
+        }
+        tx.commit().await.map_err(|why| format!("Migrate failed: {}", why))?;
+        Ok(ran)
+    }
+
+    // this deadpool has no close of its own, so the idle connections are taken out and dropped,
+    // which ends each one properly, rather than leaving postgres to find them gone
+    async fn close (&self) -> Result<(), MyError> {
+        let idle = self.pool.status().available.max(0);
+        for _ in 0..idle {
+            match self.pool.try_get().await {
+                Ok(client) => drop(Client::take(client)),
+                Err(_) => break,
+            }
+        }
+        Ok(())
+    }
+
     // BOARDS
     async fn add_board (&self, item: &Board) -> Result<bool, MyError> {
         add(self, item).await
@@ -186,8 +709,43 @@ impl Storage for PostgresStorage {
         get(self, id).await
     }
 
-    async fn delete_board(&self, id: &String) -> Result<bool, MyError> {
-        delete::<Board>(self, id).await
+    async fn update_board (&self, item: &Board) -> Result<bool, MyError> {
+        update(self, &item.id, item).await
+    }
+
+    async fn delete_board(&self, id: &String) -> Result<BoardDeletion, MyError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(|why| format!("Delete {} failed: {}", BOARD_SINGLE, why))?;
+
+        // children first, so this also holds up wherever the foreign keys are missing
+        let deletion = BoardDeletion {
+            revisions: delete_where_tx::<Revision>(self, &tx, FIELD_BOARD_ID, id).await?,
+            comments: delete_where_tx::<Comment>(self, &tx, FIELD_BOARD_ID, id).await?,
+            action_items: delete_where_tx::<ActionItem>(self, &tx, FIELD_BOARD_ID, id).await?,
+            card_tags: delete_where_tx::<CardTag>(self, &tx, FIELD_BOARD_ID, id).await?,
+            tags: delete_where_tx::<Tag>(self, &tx, FIELD_BOARD_ID, id).await?,
+            votes: delete_where_tx::<Vote>(self, &tx, FIELD_BOARD_ID, id).await?,
+            cards: delete_where_tx::<Card>(self, &tx, FIELD_BOARD_ID, id).await?,
+            groups: delete_where_tx::<CardGroup>(self, &tx, FIELD_BOARD_ID, id).await?,
+            columns: delete_where_tx::<Column>(self, &tx, FIELD_BOARD_ID, id).await?,
+            invites: delete_where_tx::<Invite>(self, &tx, FIELD_BOARD_ID, id).await?,
+            boards: delete_where_tx::<Board>(self, &tx, FIELD_ID, id).await?,
+        };
+        // dropping the transaction rolls back whatever children a missing board had left behind
+        if deletion.boards == 0 {
+            return Err(format!("No {} with id {}", BOARD_SINGLE, id))
+        }
+
+        tx.commit().await.map_err(|why| format!("Delete {} failed: {}", BOARD_SINGLE, why))?;
+        Ok(deletion)
+    }
+
+    async fn list_team_boards (&self, team_ids: &Vec<String>) -> Result<Vec<Board>, MyError> {
+        list_where_in(self, FIELD_TEAM_ID, team_ids).await
+    }
+
+    async fn list_deleted_boards (&self, deleted_before: i64) -> Result<Vec<Board>, MyError> {
+        list_filtered(self, format!("{} < $1", FIELD_DELETED_AT), &[&deleted_before]).await
     }
 
     // COLUMNS
@@ -206,108 +764,1351 @@ impl Storage for PostgresStorage {
     async fn delete_column(&self, id: &String) -> Result<bool, MyError> {
         delete::<Column>(self, id).await
     }
-}
 
+    async fn delete_empty_column (&self, id: &String) -> Result<IfEmpty<bool>, MyError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(|why| format!("Delete {} failed: {}", COLUMN_SINGLE, why))?;
 
-const BOARD_SINGLE: &'static str = "Board";
-const BOARD_PLURAL: &'static str = "Boards";
-const BOARD_FIELDS: &'static str = formatcp!(
-    "{}, {}, {}, {}",
-    FIELD_ID,
-    FIELD_TITLE,
-    FIELD_OWNER,
-    FIELD_CREATED_AT,
-);
+        // adding a card or group to the column waits on this lock, for its foreign key, until the delete is done
+        if lock_tx::<Column>(self, &tx, id).await?.is_none() {
+            return Ok(IfEmpty::Deleted(false))
+        }
+        let cards = ids_where_tx::<Card>(self, &tx, FIELD_COLUMN_ID, id).await?;
+        if !cards.is_empty() {
+            return Ok(IfEmpty::NotEmpty("cards", cards.len()))
+        }
+        let groups = ids_where_tx::<CardGroup>(self, &tx, FIELD_COLUMN_ID, id).await?;
+        if !groups.is_empty() {
+            return Ok(IfEmpty::NotEmpty("groups", groups.len()))
+        }
+        delete_tx::<Column>(self, &tx, id).await?;
 
-impl RowCrud for Board {
-    fn name_single () -> &'static str {
-        BOARD_SINGLE
+        tx.commit().await.map_err(|why| format!("Delete {} failed: {}", COLUMN_SINGLE, why))?;
+        Ok(IfEmpty::Deleted(true))
     }
 
-    fn name_plural () -> &'static str {
-        BOARD_PLURAL
+    async fn update_column (&self, item: &Column) -> Result<bool, MyError> {
+        update(self, &item.id, item).await
     }
 
-    fn table_name (storage: &PostgresStorage) -> &String {
-        &storage.table_boards
+    async fn list_board_columns (&self, board_id: &String) -> Result<Vec<Column>, MyError> {
+        list_where(self, FIELD_BOARD_ID, board_id).await
     }
 
-    fn field_names () -> &'static str {
-        BOARD_FIELDS
+    // CARDS
+    async fn add_card (&self, item: &Card) -> Result<bool, MyError> {
+        add(self, item).await
     }
 
-    fn row_values (&self) -> Vec<&(dyn ToSql + Sync)> {
-        vec![
-            &self.id,
-            &self.title,
-            &self.owner,
-            &self.created_at,
-        ]
+    async fn update_card (&self, item: &Card) -> Result<bool, MyError> {
+        update(self, &item.id, item).await
     }
-}
 
-impl TryFrom<Row> for Board {
-    type Error = MyError;
+    async fn list_board_cards (&self, board_id: &String) -> Result<Vec<Card>, MyError> {
+        list_where(self, FIELD_BOARD_ID, board_id).await
+    }
 
-    fn try_from (row: Row) -> Result<Self, Self::Error> {
-        // consider: https://docs.rs/tokio-pg-mapper/0.1.8/tokio_pg_mapper/
-        // https://docs.rs/tokio-postgres/0.5.5/tokio_postgres/row/struct.Row.html#method.try_get
-        Ok(Self {
-            id: get_field(&row, FIELD_ID)?,
-            title: get_field(&row, FIELD_TITLE)?,
-            owner: get_field(&row, FIELD_OWNER)?,
-            created_at: get_field(&row, FIELD_CREATED_AT)?,
-        })
+    async fn get_card (&self, id: &String) -> Result<Card, MyError> {
+        get(self, id).await
     }
-}
 
+    async fn delete_card (&self, id: &String) -> Result<bool, MyError> {
+        delete::<Card>(self, id).await
+    }
 
+    async fn delete_card_cascade (&self, id: &String) -> Result<Vec<RowChange>, MyError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(|why| format!("Delete {} failed: {}", CARD_SINGLE, why))?;
 
-const COLUMN_SINGLE: &'static str = "Column";
-const COLUMN_PLURAL: &'static str = "Columns";
-const COLUMN_FIELDS: &'static str = formatcp!(
-    "{}, {}, {}, {}",
-    FIELD_ID,
-    FIELD_BOARD_ID,
-    FIELD_TITLE,
-    FIELD_CREATED_AT,
-);
+        // unlinked and emptied first, the same order the foreign keys would go in
+        let mut changes: Vec<RowChange> = relink_tx::<Card>(self, &tx, FIELD_MERGED_INTO, id, None).await?.into_iter()
+            .map(|card| RowChange {
+                before: Some(MyRow::Card(Card { merged_into: Some(id.clone()), ..card.clone() })),
+                after: Some(MyRow::Card(card)),
+            })
+            .collect();
+        changes.extend(removed(delete_returning_tx::<Vote>(self, &tx, FIELD_CARD_ID, id).await?, MyRow::Vote));
+        changes.extend(removed(delete_returning_tx::<CardTag>(self, &tx, FIELD_CARD_ID, id).await?, MyRow::CardTag));
+        changes.extend(removed(delete_returning_tx::<Revision>(self, &tx, FIELD_CARD_ID, id).await?, MyRow::Revision));
+        changes.extend(removed(delete_returning_tx::<Comment>(self, &tx, FIELD_CARD_ID, id).await?, MyRow::Comment));
+        let cards = delete_returning_tx::<Card>(self, &tx, FIELD_ID, id).await?;
+        if cards.is_empty() {
+            return Err(format!("No {} with id {}", CARD_SINGLE, id))
+        }
+        changes.extend(removed(cards, MyRow::Card));
 
-impl RowCrud for Column {
-    fn name_single () -> &'static str {
-        COLUMN_SINGLE
+        tx.commit().await.map_err(|why| format!("Delete {} failed: {}", CARD_SINGLE, why))?;
+        Ok(changes)
     }
 
-    fn name_plural () -> &'static str {
-        COLUMN_PLURAL
+    async fn merge_cards (&self, source_id: &String, target_id: &String) -> Result<Vec<RowChange>, MyError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(|why| format!("Merge {} failed: {}", CARD_SINGLE, why))?;
+
+        if source_id == target_id {
+            return Err(format!("Cannot merge {} {} into itself", CARD_SINGLE, source_id))
+        }
+        // both locked, in the one order so two merges the other way round cannot wait on each other,
+        // and the target checked again, it may have been merged itself since the handler looked
+        let mut ids = [source_id, target_id];
+        ids.sort();
+        let mut locked = vec![];
+        for id in ids.iter() {
+            match lock_tx::<Card>(self, &tx, id).await? {
+                Some(card) => locked.push(card),
+                None => return Err(format!("No {} with id {}", CARD_SINGLE, id)),
+            }
+        }
+        let source = locked.iter().find(|card| &card.id == source_id).cloned().unwrap();
+        let target = locked.iter().find(|card| &card.id == target_id).unwrap();
+        if target.merged_into.is_some() {
+            return Err(format!("{} {} is itself merged", CARD_SINGLE, target_id))
+        }
+
+        // whatever was merged into the source moves over first, keeping stacks one level deep
+        let mut changes: Vec<RowChange> = relink_tx::<Card>(self, &tx, FIELD_MERGED_INTO, source_id, Some(target_id)).await?.into_iter()
+            .map(|card| RowChange {
+                before: Some(MyRow::Card(Card { merged_into: Some(source_id.clone()), ..card.clone() })),
+                after: Some(MyRow::Card(card)),
+            })
+            .collect();
+        let merged = Card { merged_into: Some(target_id.clone()), ..source.clone() };
+        update_tx(self, &tx, &merged.id, &merged).await?;
+        changes.push(RowChange { before: Some(MyRow::Card(source)), after: Some(MyRow::Card(merged)) });
+
+        tx.commit().await.map_err(|why| format!("Merge {} failed: {}", CARD_SINGLE, why))?;
+        Ok(changes)
     }
 
-    fn table_name (storage: &PostgresStorage) -> &String {
-        &storage.table_boards
+    // GROUPS
+    async fn add_group (&self, item: &CardGroup) -> Result<bool, MyError> {
+        add(self, item).await
     }
 
-    fn field_names () -> &'static str {
-        COLUMN_FIELDS
+    async fn update_group (&self, item: &CardGroup) -> Result<bool, MyError> {
+        update(self, &item.id, item).await
     }
 
-    fn row_values (&self) -> Vec<&(dyn ToSql + Sync)> {
-        vec![
-            &self.id,
-            &self.board_id,
-            &self.title,
-            &self.created_at,
-        ]
+    async fn list_board_groups (&self, board_id: &String) -> Result<Vec<CardGroup>, MyError> {
+        list_where(self, FIELD_BOARD_ID, board_id).await
     }
-}
 
-impl TryFrom<Row> for Column {
-    type Error = MyError;
+    async fn get_group (&self, id: &String) -> Result<CardGroup, MyError> {
+        get(self, id).await
+    }
 
-    fn try_from (row: Row) -> Result<Self, Self::Error> {
-        Ok(Self {
-            id: get_field(&row, FIELD_ID)?,
-            board_id: get_field(&row, FIELD_BOARD_ID)?,
-            title: get_field(&row, FIELD_TITLE)?,
+    async fn delete_group (&self, id: &String) -> Result<bool, MyError> {
+        delete::<CardGroup>(self, id).await
+    }
+
+    async fn delete_group_cascade (&self, id: &String) -> Result<Vec<RowChange>, MyError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(|why| format!("Delete {} failed: {}", GROUP_SINGLE, why))?;
+
+        // its cards stay where they are, only out of the group
+        let mut changes: Vec<RowChange> = relink_tx::<Card>(self, &tx, FIELD_GROUP_ID, id, None).await?.into_iter()
+            .map(|card| RowChange {
+                before: Some(MyRow::Card(Card { group_id: Some(id.clone()), ..card.clone() })),
+                after: Some(MyRow::Card(card)),
+            })
+            .collect();
+        changes.extend(removed(delete_returning_tx::<Vote>(self, &tx, FIELD_GROUP_ID, id).await?, MyRow::Vote));
+        let groups = delete_returning_tx::<CardGroup>(self, &tx, FIELD_ID, id).await?;
+        if groups.is_empty() {
+            return Err(format!("No {} with id {}", GROUP_SINGLE, id))
+        }
+        changes.extend(removed(groups, MyRow::Group));
+
+        tx.commit().await.map_err(|why| format!("Delete {} failed: {}", GROUP_SINGLE, why))?;
+        Ok(changes)
+    }
+
+    // VOTES
+    async fn add_vote (&self, item: &Vote) -> Result<bool, MyError> {
+        add(self, item).await
+    }
+
+    async fn list_board_votes (&self, board_id: &String) -> Result<Vec<Vote>, MyError> {
+        list_where(self, FIELD_BOARD_ID, board_id).await
+    }
+
+    async fn get_vote (&self, id: &String) -> Result<Vote, MyError> {
+        get(self, id).await
+    }
+
+    async fn delete_vote (&self, id: &String) -> Result<bool, MyError> {
+        delete::<Vote>(self, id).await
+    }
+
+    // TAGS
+    async fn add_tag (&self, item: &Tag) -> Result<bool, MyError> {
+        add(self, item).await
+    }
+
+    async fn list_board_tags (&self, board_id: &String) -> Result<Vec<Tag>, MyError> {
+        list_where(self, FIELD_BOARD_ID, board_id).await
+    }
+
+    async fn get_tag (&self, id: &String) -> Result<Tag, MyError> {
+        get(self, id).await
+    }
+
+    async fn delete_tag (&self, id: &String) -> Result<bool, MyError> {
+        delete::<Tag>(self, id).await
+    }
+
+    // CARD TAGS
+    async fn add_card_tag (&self, item: &CardTag) -> Result<bool, MyError> {
+        add(self, item).await
+    }
+
+    async fn list_board_card_tags (&self, board_id: &String) -> Result<Vec<CardTag>, MyError> {
+        list_where(self, FIELD_BOARD_ID, board_id).await
+    }
+
+    async fn delete_card_tag (&self, id: &String) -> Result<bool, MyError> {
+        delete::<CardTag>(self, id).await
+    }
+
+    // COMMENTS
+    async fn add_comment (&self, item: &Comment) -> Result<bool, MyError> {
+        add(self, item).await
+    }
+
+    async fn update_comment (&self, item: &Comment) -> Result<bool, MyError> {
+        update(self, &item.id, item).await
+    }
+
+    async fn list_board_comments (&self, board_id: &String) -> Result<Vec<Comment>, MyError> {
+        list_where(self, FIELD_BOARD_ID, board_id).await
+    }
+
+    async fn get_comment (&self, id: &String) -> Result<Comment, MyError> {
+        get(self, id).await
+    }
+
+    async fn delete_comment (&self, id: &String) -> Result<bool, MyError> {
+        delete::<Comment>(self, id).await
+    }
+
+    // REVISIONS
+    async fn add_revision (&self, item: &Revision) -> Result<bool, MyError> {
+        add(self, item).await
+    }
+
+    async fn list_card_revisions (&self, card_id: &String) -> Result<Vec<Revision>, MyError> {
+        list_where(self, FIELD_CARD_ID, card_id).await
+    }
+
+    async fn get_revision (&self, id: &String) -> Result<Revision, MyError> {
+        get(self, id).await
+    }
+
+    async fn delete_revision (&self, id: &String) -> Result<bool, MyError> {
+        delete::<Revision>(self, id).await
+    }
+
+    // ACTION ITEMS
+    async fn add_action_item (&self, item: &ActionItem) -> Result<bool, MyError> {
+        add(self, item).await
+    }
+
+    async fn update_action_item (&self, item: &ActionItem) -> Result<bool, MyError> {
+        update(self, &item.id, item).await
+    }
+
+    async fn list_board_action_items (&self, board_id: &String) -> Result<Vec<ActionItem>, MyError> {
+        list_where(self, FIELD_BOARD_ID, board_id).await
+    }
+
+    async fn get_action_item (&self, id: &String) -> Result<ActionItem, MyError> {
+        get(self, id).await
+    }
+
+    async fn delete_action_item (&self, id: &String) -> Result<bool, MyError> {
+        delete::<ActionItem>(self, id).await
+    }
+
+    // AUDIT
+    async fn add_audit_entry (&self, item: &AuditEntry) -> Result<bool, MyError> {
+        add(self, item).await
+    }
+
+    async fn list_audit_entries (&self, filter: &AuditFilter) -> Result<Vec<AuditEntry>, MyError> {
+        let mut conditions = vec![String::from("TRUE")];
+        let mut values: Vec<&(dyn ToSql + Sync)> = vec![];
+
+        let text_fields: [(&'static str, &Option<String>); 5] = [
+            (FIELD_ACTOR, &filter.actor),
+            (FIELD_ACTION, &filter.action),
+            (FIELD_ENTITY_TYPE, &filter.entity_type),
+            (FIELD_ENTITY_ID, &filter.entity_id),
+            (FIELD_BOARD_ID, &filter.board_id),
+        ];
+        for (field, value) in text_fields.iter() {
+            if let Some(value) = value {
+                values.push(value);
+                conditions.push(format!("{} = ${}", field, values.len()));
+            }
+        }
+        if let Some(since) = &filter.since {
+            values.push(since);
+            conditions.push(format!("{} >= ${}", FIELD_CREATED_AT, values.len()));
+        }
+        if let Some(until) = &filter.until {
+            values.push(until);
+            conditions.push(format!("{} < ${}", FIELD_CREATED_AT, values.len()));
+        }
+
+        // past after is further along whichever way the entries come
+        let (past, order) = if filter.oldest_first { (">", "ASC") } else { ("<", "DESC") };
+        if let Some(after) = &filter.after {
+            values.push(after);
+            conditions.push(format!("{} {} ${}", FIELD_SEQ, past, values.len()));
+        }
+
+        let limit = match filter.limit {
+            Some(limit) => format!(" LIMIT {}", limit),
+            None => String::new(),
+        };
+        list_filtered(self, format!(
+            "{} ORDER BY {} {}{}",
+            conditions.join(" AND "), FIELD_SEQ, order, limit,
+        ), &values).await
+    }
+
+    // SEARCH
+    // https://www.postgresql.org/docs/current/textsearch-controls.html
+    async fn search (&self, team_ids: &Vec<String>, query: &String, limit: usize) -> Result<Vec<SearchHit>, MyError> {
+        let config = &self.search_config;
+        let vector = |field: &str| format!("to_tsvector('{}', {})", config, field);
+        let hit = |kind: SearchKind, id: &str, text: &str, created_at: &str| format!(
+            "SELECT '{}' AS kind, {} AS id, b.{} AS board_id, b.{} AS board_title, \
+                ts_headline('{}', {}, q.query, '{}') AS snippet, ts_rank({}, q.query) AS rank, {} AS created_at",
+            kind.as_str(), id, FIELD_ID, FIELD_TITLE,
+            config, text, SEARCH_HEADLINE_OPTIONS, vector(text), created_at,
+        );
+        let visible = format!("b.{} = ANY($1) AND b.{} IS NULL", FIELD_TEAM_ID, FIELD_DELETED_AT);
+
+        let sql = format!(
+            "WITH q AS (SELECT websearch_to_tsquery('{config}', $2) AS query) \
+            SELECT * FROM ( \
+                {boards} FROM {schema}.{table_boards} b, q WHERE {visible} AND {board_vector} @@ q.query \
+                UNION ALL \
+                {cards} FROM {schema}.{table_cards} c JOIN {schema}.{table_boards} b ON b.{id} = c.{board_id}, q \
+                    WHERE {visible} AND {card_vector} @@ q.query \
+                UNION ALL \
+                {comments} FROM {schema}.{table_comments} m JOIN {schema}.{table_boards} b ON b.{id} = m.{board_id}, q \
+                    WHERE {visible} AND {comment_vector} @@ q.query \
+            ) hits ORDER BY rank DESC, created_at DESC LIMIT $3",
+            config = config,
+            schema = self.schema,
+            table_boards = self.table_boards,
+            table_cards = self.table_cards,
+            table_comments = self.table_comments,
+            id = FIELD_ID,
+            board_id = FIELD_BOARD_ID,
+            visible = visible,
+            boards = hit(SearchKind::Board, &format!("b.{}", FIELD_ID), &format!("b.{}", FIELD_TITLE), &format!("b.{}", FIELD_CREATED_AT)),
+            board_vector = vector(&format!("b.{}", FIELD_TITLE)),
+            cards = hit(SearchKind::Card, &format!("c.{}", FIELD_ID), &format!("c.{}", FIELD_TITLE), &format!("c.{}", FIELD_CREATED_AT)),
+            card_vector = vector(&format!("c.{}", FIELD_TITLE)),
+            comments = hit(SearchKind::Comment, &format!("m.{}", FIELD_ID), &format!("m.{}", FIELD_TEXT), &format!("m.{}", FIELD_CREATED_AT)),
+            comment_vector = vector(&format!("m.{}", FIELD_TEXT)),
+        );
+
+        let rows = timed("search", &self.schema, self.client().await?.query(sql.as_str(), &[team_ids, query, &(limit as i64)])).await
+            .map_err(|why| format!("Search failed: {}", why))?;
+        rows.iter().map(|row| Ok(SearchHit {
+            kind: get_field::<String>(row, FIELD_KIND)?.parse()?,
+            id: get_field(row, FIELD_ID)?,
+            board_id: get_field(row, FIELD_BOARD_ID)?,
+            board_title: get_field(row, FIELD_BOARD_TITLE)?,
+            snippet: get_field(row, FIELD_SNIPPET)?,
+            rank: get_field(row, FIELD_RANK)?,
+            created_at: get_field(row, FIELD_CREATED_AT)?,
+        })).collect()
+    }
+
+    // CONTENTS
+    async fn add_board_contents (&self, item: &BoardContents) -> Result<bool, MyError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(|why| format!("Add {} failed: {}", BOARD_SINGLE, why))?;
+
+        add_tx(self, &tx, &item.board).await?;
+        add_all_tx(self, &tx, &item.columns).await?;
+        add_all_tx(self, &tx, &item.groups).await?;
+        // merged cards point at other cards, which have to exist first
+        add_all_tx(self, &tx, &item.cards.iter().filter(|card| card.merged_into.is_none()).cloned().collect()).await?;
+        add_all_tx(self, &tx, &item.cards.iter().filter(|card| card.merged_into.is_some()).cloned().collect()).await?;
+        add_all_tx(self, &tx, &item.votes).await?;
+        add_all_tx(self, &tx, &item.tags).await?;
+        add_all_tx(self, &tx, &item.card_tags).await?;
+        add_all_tx(self, &tx, &item.comments).await?;
+        add_all_tx(self, &tx, &item.action_items).await?;
+
+        tx.commit().await.map_err(|why| format!("Add {} failed: {}", BOARD_SINGLE, why))?;
+        Ok(true)
+    }
+
+    // UNDO
+    // every row is locked as it is checked, so nothing changes between the checks and the writes,
+    // and returning early drops the transaction, rolling back whatever it wrote
+    async fn apply_changes (&self, changes: &[RowChange]) -> Result<bool, MyError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(|why| format!("Apply changes failed: {}", why))?;
+        let touched = touched(changes);
+
+        for change in changes.iter() {
+            let row = match change.row() {
+                Some(row) => row,
+                None => continue,
+            };
+            check_undoable(row)?;
+            if !same(&lock_row_tx(self, &tx, row).await?, &change.before) {
+                return Ok(false)
+            }
+            if let (Some(before), None) = (&change.before, &change.after) {
+                if dependents_tx(self, &tx, before).await?.iter().any(|(kind, id)| !touched.contains(&(*kind, id))) {
+                    return Ok(false)
+                }
+            }
+        }
+        for change in changes.iter() {
+            if !write_row_tx(self, &tx, change).await? {
+                return Ok(false)
+            }
+        }
+
+        tx.commit().await.map_err(|why| format!("Apply changes failed: {}", why))?;
+        Ok(true)
+    }
+
+    // TEAMS
+    async fn add_team (&self, item: &Team) -> Result<bool, MyError> {
+        add(self, item).await
+    }
+
+    async fn add_team_with_owner (&self, item: &Team, owner: &Membership) -> Result<bool, MyError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(|why| format!("Add {} failed: {}", TEAM_SINGLE, why))?;
+        add_tx(self, &tx, item).await?;
+        add_tx(self, &tx, owner).await?;
+        tx.commit().await.map_err(|why| format!("Add {} failed: {}", TEAM_SINGLE, why))?;
+        Ok(true)
+    }
+
+    async fn list_teams (&self, ids: &Vec<String>) -> Result<Vec<Team>, MyError> {
+        list_where_in(self, FIELD_ID, ids).await
+    }
+
+    async fn list_all_teams (&self) -> Result<Vec<Team>, MyError> {
+        list(self).await
+    }
+
+    async fn get_team (&self, id: &String) -> Result<Team, MyError> {
+        get(self, id).await
+    }
+
+    async fn delete_team (&self, id: &String) -> Result<bool, MyError> {
+        delete::<Team>(self, id).await
+    }
+
+    async fn delete_team_cascade (&self, id: &String) -> Result<IfEmpty<Vec<RowChange>>, MyError> {
+        let mut client = self.client().await?;
+        let tx = client.transaction().await.map_err(|why| format!("Delete {} failed: {}", TEAM_SINGLE, why))?;
+
+        // locked first, so whatever else goes for the team waits until this is done
+        let team = match lock_tx::<Team>(self, &tx, id).await? {
+            Some(team) => team,
+            None => return Err(format!("No {} with id {}", TEAM_SINGLE, id)),
+        };
+        let boards = ids_where_tx::<Board>(self, &tx, FIELD_TEAM_ID, id).await?;
+        if !boards.is_empty() {
+            return Ok(IfEmpty::NotEmpty("boards", boards.len()))
+        }
+        let mut changes: Vec<RowChange> = removed(delete_returning_tx::<Membership>(self, &tx, FIELD_TEAM_ID, id).await?, MyRow::Membership).collect();
+        delete_tx::<Team>(self, &tx, id).await?;
+        changes.push(RowChange { before: Some(MyRow::Team(team)), after: None });
+
+        tx.commit().await.map_err(|why| format!("Delete {} failed: {}", TEAM_SINGLE, why))?;
+        Ok(IfEmpty::Deleted(changes))
+    }
+
+    // MEMBERSHIPS
+    async fn add_membership (&self, item: &Membership) -> Result<bool, MyError> {
+        add(self, item).await
+    }
+
+    async fn update_membership (&self, item: &Membership) -> Result<bool, MyError> {
+        update(self, &item.id, item).await
+    }
+
+    async fn list_team_memberships (&self, team_id: &String) -> Result<Vec<Membership>, MyError> {
+        list_where(self, FIELD_TEAM_ID, team_id).await
+    }
+
+    async fn list_user_memberships (&self, user_id: &String) -> Result<Vec<Membership>, MyError> {
+        list_where(self, FIELD_USER_ID, user_id).await
+    }
+
+    async fn delete_membership (&self, id: &String) -> Result<bool, MyError> {
+        delete::<Membership>(self, id).await
+    }
+
+    // INVITES
+    async fn add_invite (&self, item: &Invite) -> Result<bool, MyError> {
+        add(self, item).await
+    }
+
+    async fn list_board_invites (&self, board_id: &String) -> Result<Vec<Invite>, MyError> {
+        list_where(self, FIELD_BOARD_ID, board_id).await
+    }
+
+    async fn get_invite (&self, id: &String) -> Result<Invite, MyError> {
+        get(self, id).await
+    }
+
+    async fn delete_invite (&self, id: &String) -> Result<bool, MyError> {
+        delete::<Invite>(self, id).await
+    }
+}
+
+
+const BOARD_SINGLE: &'static str = "Board";
+const BOARD_PLURAL: &'static str = "Boards";
+const BOARD_FIELDS: &'static str = formatcp!(
+    "{}, {}, {}, {}, {}, {}, {}",
+    FIELD_ID,
+    FIELD_TEAM_ID,
+    FIELD_TITLE,
+    FIELD_OWNER,
+    FIELD_CREATED_AT,
+    FIELD_ARCHIVED_AT,
+    FIELD_DELETED_AT,
+);
+
+impl RowCrud for Board {
+    fn name_single () -> &'static str {
+        BOARD_SINGLE
+    }
+
+    fn name_plural () -> &'static str {
+        BOARD_PLURAL
+    }
+
+    fn table_name (storage: &PostgresStorage) -> &String {
+        &storage.table_boards
+    }
+
+    fn field_names () -> &'static str {
+        BOARD_FIELDS
+    }
+
+    fn row_values (&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.id,
+            &self.team_id,
+            &self.title,
+            &self.owner,
+            &self.created_at,
+            &self.archived_at,
+            &self.deleted_at,
+        ]
+    }
+}
+
+impl TryFrom<Row> for Board {
+    type Error = MyError;
+
+    fn try_from (row: Row) -> Result<Self, Self::Error> {
+        // consider: https://docs.rs/tokio-pg-mapper/0.1.8/tokio_pg_mapper/
+        // https://docs.rs/tokio-postgres/0.5.5/tokio_postgres/row/struct.Row.html#method.try_get
+        Ok(Self {
+            id: get_field(&row, FIELD_ID)?,
+            team_id: get_field(&row, FIELD_TEAM_ID)?,
+            title: get_field(&row, FIELD_TITLE)?,
+            owner: get_field(&row, FIELD_OWNER)?,
+            created_at: get_field(&row, FIELD_CREATED_AT)?,
+            archived_at: get_field(&row, FIELD_ARCHIVED_AT)?,
+            deleted_at: get_field(&row, FIELD_DELETED_AT)?,
+        })
+    }
+}
+
+
+
+const COLUMN_SINGLE: &'static str = "Column";
+const COLUMN_PLURAL: &'static str = "Columns";
+const COLUMN_FIELDS: &'static str = formatcp!(
+    "{}, {}, {}, {}, {}",
+    FIELD_ID,
+    FIELD_BOARD_ID,
+    FIELD_TITLE,
+    FIELD_POSITION,
+    FIELD_CREATED_AT,
+);
+
+impl RowCrud for Column {
+    fn name_single () -> &'static str {
+        COLUMN_SINGLE
+    }
+
+    fn name_plural () -> &'static str {
+        COLUMN_PLURAL
+    }
+
+    fn table_name (storage: &PostgresStorage) -> &String {
+        &storage.table_columns
+    }
+
+    fn field_names () -> &'static str {
+        COLUMN_FIELDS
+    }
+
+    fn row_values (&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.id,
+            &self.board_id,
+            &self.title,
+            &self.position,
+            &self.created_at,
+        ]
+    }
+}
+
+impl TryFrom<Row> for Column {
+    type Error = MyError;
+
+    fn try_from (row: Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: get_field(&row, FIELD_ID)?,
+            board_id: get_field(&row, FIELD_BOARD_ID)?,
+            title: get_field(&row, FIELD_TITLE)?,
+            position: get_field(&row, FIELD_POSITION)?,
+            created_at: get_field(&row, FIELD_CREATED_AT)?,
+        })
+    }
+}
+
+
+
+const CARD_SINGLE: &'static str = "Card";
+const CARD_PLURAL: &'static str = "Cards";
+const CARD_FIELDS: &'static str = formatcp!(
+    "{}, {}, {}, {}, {}, {}, {}, {}, {}, {}",
+    FIELD_ID,
+    FIELD_BOARD_ID,
+    FIELD_COLUMN_ID,
+    FIELD_GROUP_ID,
+    FIELD_MERGED_INTO,
+    FIELD_TITLE,
+    FIELD_AUTHOR,
+    FIELD_AUTHOR_NAME,
+    FIELD_POSITION,
+    FIELD_CREATED_AT,
+);
+
+impl RowCrud for Card {
+    fn name_single () -> &'static str {
+        CARD_SINGLE
+    }
+
+    fn name_plural () -> &'static str {
+        CARD_PLURAL
+    }
+
+    fn table_name (storage: &PostgresStorage) -> &String {
+        &storage.table_cards
+    }
+
+    fn field_names () -> &'static str {
+        CARD_FIELDS
+    }
+
+    fn row_values (&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.id,
+            &self.board_id,
+            &self.column_id,
+            &self.group_id,
+            &self.merged_into,
+            &self.title,
+            &self.author,
+            &self.author_name,
+            &self.position,
+            &self.created_at,
+        ]
+    }
+}
+
+impl TryFrom<Row> for Card {
+    type Error = MyError;
+
+    fn try_from (row: Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: get_field(&row, FIELD_ID)?,
+            board_id: get_field(&row, FIELD_BOARD_ID)?,
+            column_id: get_field(&row, FIELD_COLUMN_ID)?,
+            group_id: get_field(&row, FIELD_GROUP_ID)?,
+            merged_into: get_field(&row, FIELD_MERGED_INTO)?,
+            title: get_field(&row, FIELD_TITLE)?,
+            author: get_field(&row, FIELD_AUTHOR)?,
+            author_name: get_field(&row, FIELD_AUTHOR_NAME)?,
+            position: get_field(&row, FIELD_POSITION)?,
+            created_at: get_field(&row, FIELD_CREATED_AT)?,
+        })
+    }
+}
+
+
+
+const GROUP_SINGLE: &'static str = "Group";
+const GROUP_PLURAL: &'static str = "Groups";
+const GROUP_FIELDS: &'static str = formatcp!(
+    "{}, {}, {}, {}, {}",
+    FIELD_ID,
+    FIELD_BOARD_ID,
+    FIELD_COLUMN_ID,
+    FIELD_TITLE,
+    FIELD_CREATED_AT,
+);
+
+impl RowCrud for CardGroup {
+    fn name_single () -> &'static str {
+        GROUP_SINGLE
+    }
+
+    fn name_plural () -> &'static str {
+        GROUP_PLURAL
+    }
+
+    fn table_name (storage: &PostgresStorage) -> &String {
+        &storage.table_groups
+    }
+
+    fn field_names () -> &'static str {
+        GROUP_FIELDS
+    }
+
+    fn row_values (&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.id,
+            &self.board_id,
+            &self.column_id,
+            &self.title,
+            &self.created_at,
+        ]
+    }
+}
+
+impl TryFrom<Row> for CardGroup {
+    type Error = MyError;
+
+    fn try_from (row: Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: get_field(&row, FIELD_ID)?,
+            board_id: get_field(&row, FIELD_BOARD_ID)?,
+            column_id: get_field(&row, FIELD_COLUMN_ID)?,
+            title: get_field(&row, FIELD_TITLE)?,
+            created_at: get_field(&row, FIELD_CREATED_AT)?,
+        })
+    }
+}
+
+
+
+const VOTE_SINGLE: &'static str = "Vote";
+const VOTE_PLURAL: &'static str = "Votes";
+const VOTE_FIELDS: &'static str = formatcp!(
+    "{}, {}, {}, {}, {}, {}",
+    FIELD_ID,
+    FIELD_BOARD_ID,
+    FIELD_CARD_ID,
+    FIELD_GROUP_ID,
+    FIELD_AUTHOR,
+    FIELD_CREATED_AT,
+);
+
+impl RowCrud for Vote {
+    fn name_single () -> &'static str {
+        VOTE_SINGLE
+    }
+
+    fn name_plural () -> &'static str {
+        VOTE_PLURAL
+    }
+
+    fn table_name (storage: &PostgresStorage) -> &String {
+        &storage.table_votes
+    }
+
+    fn field_names () -> &'static str {
+        VOTE_FIELDS
+    }
+
+    fn row_values (&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.id,
+            &self.board_id,
+            &self.card_id,
+            &self.group_id,
+            &self.author,
+            &self.created_at,
+        ]
+    }
+}
+
+impl TryFrom<Row> for Vote {
+    type Error = MyError;
+
+    fn try_from (row: Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: get_field(&row, FIELD_ID)?,
+            board_id: get_field(&row, FIELD_BOARD_ID)?,
+            card_id: get_field(&row, FIELD_CARD_ID)?,
+            group_id: get_field(&row, FIELD_GROUP_ID)?,
+            author: get_field(&row, FIELD_AUTHOR)?,
+            created_at: get_field(&row, FIELD_CREATED_AT)?,
+        })
+    }
+}
+
+
+const TAG_SINGLE: &'static str = "Tag";
+const TAG_PLURAL: &'static str = "Tags";
+const TAG_FIELDS: &'static str = formatcp!(
+    "{}, {}, {}, {}",
+    FIELD_ID,
+    FIELD_BOARD_ID,
+    FIELD_TITLE,
+    FIELD_CREATED_AT,
+);
+
+impl RowCrud for Tag {
+    fn name_single () -> &'static str {
+        TAG_SINGLE
+    }
+
+    fn name_plural () -> &'static str {
+        TAG_PLURAL
+    }
+
+    fn table_name (storage: &PostgresStorage) -> &String {
+        &storage.table_tags
+    }
+
+    fn field_names () -> &'static str {
+        TAG_FIELDS
+    }
+
+    fn row_values (&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.id,
+            &self.board_id,
+            &self.title,
+            &self.created_at,
+        ]
+    }
+}
+
+impl TryFrom<Row> for Tag {
+    type Error = MyError;
+
+    fn try_from (row: Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: get_field(&row, FIELD_ID)?,
+            board_id: get_field(&row, FIELD_BOARD_ID)?,
+            title: get_field(&row, FIELD_TITLE)?,
+            created_at: get_field(&row, FIELD_CREATED_AT)?,
+        })
+    }
+}
+
+
+
+const CARD_TAG_SINGLE: &'static str = "CardTag";
+const CARD_TAG_PLURAL: &'static str = "CardTags";
+const CARD_TAG_FIELDS: &'static str = formatcp!(
+    "{}, {}, {}, {}, {}",
+    FIELD_ID,
+    FIELD_BOARD_ID,
+    FIELD_CARD_ID,
+    FIELD_TAG_ID,
+    FIELD_CREATED_AT,
+);
+
+impl RowCrud for CardTag {
+    fn name_single () -> &'static str {
+        CARD_TAG_SINGLE
+    }
+
+    fn name_plural () -> &'static str {
+        CARD_TAG_PLURAL
+    }
+
+    fn table_name (storage: &PostgresStorage) -> &String {
+        &storage.table_card_tags
+    }
+
+    fn field_names () -> &'static str {
+        CARD_TAG_FIELDS
+    }
+
+    fn row_values (&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.id,
+            &self.board_id,
+            &self.card_id,
+            &self.tag_id,
+            &self.created_at,
+        ]
+    }
+}
+
+impl TryFrom<Row> for CardTag {
+    type Error = MyError;
+
+    fn try_from (row: Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: get_field(&row, FIELD_ID)?,
+            board_id: get_field(&row, FIELD_BOARD_ID)?,
+            card_id: get_field(&row, FIELD_CARD_ID)?,
+            tag_id: get_field(&row, FIELD_TAG_ID)?,
+            created_at: get_field(&row, FIELD_CREATED_AT)?,
+        })
+    }
+}
+
+
+const COMMENT_SINGLE: &'static str = "Comment";
+const COMMENT_PLURAL: &'static str = "Comments";
+const COMMENT_FIELDS: &'static str = formatcp!(
+    "{}, {}, {}, {}, {}, {}, {}",
+    FIELD_ID,
+    FIELD_BOARD_ID,
+    FIELD_CARD_ID,
+    FIELD_AUTHOR,
+    FIELD_AUTHOR_NAME,
+    FIELD_TEXT,
+    FIELD_CREATED_AT,
+);
+
+impl RowCrud for Comment {
+    fn name_single () -> &'static str {
+        COMMENT_SINGLE
+    }
+
+    fn name_plural () -> &'static str {
+        COMMENT_PLURAL
+    }
+
+    fn table_name (storage: &PostgresStorage) -> &String {
+        &storage.table_comments
+    }
+
+    fn field_names () -> &'static str {
+        COMMENT_FIELDS
+    }
+
+    fn row_values (&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.id,
+            &self.board_id,
+            &self.card_id,
+            &self.author,
+            &self.author_name,
+            &self.text,
+            &self.created_at,
+        ]
+    }
+}
+
+impl TryFrom<Row> for Comment {
+    type Error = MyError;
+
+    fn try_from (row: Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: get_field(&row, FIELD_ID)?,
+            board_id: get_field(&row, FIELD_BOARD_ID)?,
+            card_id: get_field(&row, FIELD_CARD_ID)?,
+            author: get_field(&row, FIELD_AUTHOR)?,
+            author_name: get_field(&row, FIELD_AUTHOR_NAME)?,
+            text: get_field(&row, FIELD_TEXT)?,
+            created_at: get_field(&row, FIELD_CREATED_AT)?,
+        })
+    }
+}
+
+
+const REVISION_SINGLE: &'static str = "Revision";
+const REVISION_PLURAL: &'static str = "Revisions";
+const REVISION_FIELDS: &'static str = formatcp!(
+    "{}, {}, {}, {}, {}, {}, {}, {}",
+    FIELD_ID,
+    FIELD_BOARD_ID,
+    FIELD_CARD_ID,
+    FIELD_COMMENT_ID,
+    FIELD_TEXT,
+    FIELD_EDITOR,
+    FIELD_EDITOR_NAME,
+    FIELD_CREATED_AT,
+);
+
+impl RowCrud for Revision {
+    fn name_single () -> &'static str {
+        REVISION_SINGLE
+    }
+
+    fn name_plural () -> &'static str {
+        REVISION_PLURAL
+    }
+
+    fn table_name (storage: &PostgresStorage) -> &String {
+        &storage.table_revisions
+    }
+
+    fn field_names () -> &'static str {
+        REVISION_FIELDS
+    }
+
+    fn row_values (&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.id,
+            &self.board_id,
+            &self.card_id,
+            &self.comment_id,
+            &self.text,
+            &self.editor,
+            &self.editor_name,
+            &self.created_at,
+        ]
+    }
+}
+
+impl TryFrom<Row> for Revision {
+    type Error = MyError;
+
+    fn try_from (row: Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: get_field(&row, FIELD_ID)?,
+            board_id: get_field(&row, FIELD_BOARD_ID)?,
+            card_id: get_field(&row, FIELD_CARD_ID)?,
+            comment_id: get_field(&row, FIELD_COMMENT_ID)?,
+            text: get_field(&row, FIELD_TEXT)?,
+            editor: get_field(&row, FIELD_EDITOR)?,
+            editor_name: get_field(&row, FIELD_EDITOR_NAME)?,
+            created_at: get_field(&row, FIELD_CREATED_AT)?,
+        })
+    }
+}
+
+
+const ACTION_ITEM_SINGLE: &'static str = "ActionItem";
+const ACTION_ITEM_PLURAL: &'static str = "ActionItems";
+const ACTION_ITEM_FIELDS: &'static str = formatcp!(
+    "{}, {}, {}, {}, {}, {}",
+    FIELD_ID,
+    FIELD_BOARD_ID,
+    FIELD_TITLE,
+    FIELD_ASSIGNEE,
+    FIELD_DONE,
+    FIELD_CREATED_AT,
+);
+
+impl RowCrud for ActionItem {
+    fn name_single () -> &'static str {
+        ACTION_ITEM_SINGLE
+    }
+
+    fn name_plural () -> &'static str {
+        ACTION_ITEM_PLURAL
+    }
+
+    fn table_name (storage: &PostgresStorage) -> &String {
+        &storage.table_action_items
+    }
+
+    fn field_names () -> &'static str {
+        ACTION_ITEM_FIELDS
+    }
+
+    fn row_values (&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.id,
+            &self.board_id,
+            &self.title,
+            &self.assignee,
+            &self.done,
+            &self.created_at,
+        ]
+    }
+}
+
+impl TryFrom<Row> for ActionItem {
+    type Error = MyError;
+
+    fn try_from (row: Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: get_field(&row, FIELD_ID)?,
+            board_id: get_field(&row, FIELD_BOARD_ID)?,
+            title: get_field(&row, FIELD_TITLE)?,
+            assignee: get_field(&row, FIELD_ASSIGNEE)?,
+            done: get_field(&row, FIELD_DONE)?,
+            created_at: get_field(&row, FIELD_CREATED_AT)?,
+        })
+    }
+}
+
+
+const AUDIT_SINGLE: &'static str = "AuditEntry";
+const AUDIT_PLURAL: &'static str = "AuditEntries";
+const AUDIT_FIELDS: &'static str = formatcp!(
+    "{}, {}, {}, {}, {}, {}, {}, {}, {}, {}",
+    FIELD_ID,
+    FIELD_ACTOR,
+    FIELD_ACTION,
+    FIELD_ENTITY_TYPE,
+    FIELD_ENTITY_ID,
+    FIELD_BOARD_ID,
+    FIELD_BEFORE,
+    FIELD_AFTER,
+    FIELD_IP,
+    FIELD_CREATED_AT,
+);
+// seq comes from the database, so it is only ever read
+const AUDIT_SELECT: &'static str = formatcp!("{}, {}", AUDIT_FIELDS, FIELD_SEQ);
+
+impl RowCrud for AuditEntry {
+    fn name_single () -> &'static str {
+        AUDIT_SINGLE
+    }
+
+    fn name_plural () -> &'static str {
+        AUDIT_PLURAL
+    }
+
+    fn table_name (storage: &PostgresStorage) -> &String {
+        &storage.table_audit_log
+    }
+
+    fn field_names () -> &'static str {
+        AUDIT_FIELDS
+    }
+
+    fn select_names () -> &'static str {
+        AUDIT_SELECT
+    }
+
+    fn row_values (&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.id,
+            &self.actor,
+            &self.action,
+            &self.entity_type,
+            &self.entity_id,
+            &self.board_id,
+            &self.before,
+            &self.after,
+            &self.ip,
+            &self.created_at,
+        ]
+    }
+}
+
+impl TryFrom<Row> for AuditEntry {
+    type Error = MyError;
+
+    fn try_from (row: Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: get_field(&row, FIELD_ID)?,
+            seq: get_field(&row, FIELD_SEQ)?,
+            actor: get_field(&row, FIELD_ACTOR)?,
+            action: get_field(&row, FIELD_ACTION)?,
+            entity_type: get_field(&row, FIELD_ENTITY_TYPE)?,
+            entity_id: get_field(&row, FIELD_ENTITY_ID)?,
+            board_id: get_field(&row, FIELD_BOARD_ID)?,
+            before: get_field(&row, FIELD_BEFORE)?,
+            after: get_field(&row, FIELD_AFTER)?,
+            ip: get_field(&row, FIELD_IP)?,
+            created_at: get_field(&row, FIELD_CREATED_AT)?,
+        })
+    }
+}
+
+
+const TEAM_SINGLE: &'static str = "Team";
+const TEAM_PLURAL: &'static str = "Teams";
+const TEAM_FIELDS: &'static str = formatcp!(
+    "{}, {}, {}",
+    FIELD_ID,
+    FIELD_NAME,
+    FIELD_CREATED_AT,
+);
+
+impl RowCrud for Team {
+    fn name_single () -> &'static str {
+        TEAM_SINGLE
+    }
+
+    fn name_plural () -> &'static str {
+        TEAM_PLURAL
+    }
+
+    fn table_name (storage: &PostgresStorage) -> &String {
+        &storage.table_teams
+    }
+
+    fn field_names () -> &'static str {
+        TEAM_FIELDS
+    }
+
+    fn row_values (&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.id,
+            &self.name,
+            &self.created_at,
+        ]
+    }
+}
+
+impl TryFrom<Row> for Team {
+    type Error = MyError;
+
+    fn try_from (row: Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: get_field(&row, FIELD_ID)?,
+            name: get_field(&row, FIELD_NAME)?,
+            created_at: get_field(&row, FIELD_CREATED_AT)?,
+        })
+    }
+}
+
+
+
+// roles are stored by name, so the column stays readable and reorderable
+// https://docs.rs/postgres-types/0.2.0/postgres_types/trait.ToSql.html
+impl ToSql for Role {
+    fn to_sql (&self, ty: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        self.as_str().to_sql(ty, out)
+    }
+
+    accepts!(TEXT, VARCHAR);
+    to_sql_checked!();
+}
+
+impl<'a> FromSql<'a> for Role {
+    fn from_sql (ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        let name: &str = FromSql::from_sql(ty, raw)?;
+        Ok(name.parse::<Role>()?)
+    }
+
+    accepts!(TEXT, VARCHAR);
+}
+
+const MEMBERSHIP_SINGLE: &'static str = "Membership";
+const MEMBERSHIP_PLURAL: &'static str = "Memberships";
+const MEMBERSHIP_FIELDS: &'static str = formatcp!(
+    "{}, {}, {}, {}, {}",
+    FIELD_ID,
+    FIELD_TEAM_ID,
+    FIELD_USER_ID,
+    FIELD_ROLE,
+    FIELD_CREATED_AT,
+);
+
+impl RowCrud for Membership {
+    fn name_single () -> &'static str {
+        MEMBERSHIP_SINGLE
+    }
+
+    fn name_plural () -> &'static str {
+        MEMBERSHIP_PLURAL
+    }
+
+    fn table_name (storage: &PostgresStorage) -> &String {
+        &storage.table_memberships
+    }
+
+    fn field_names () -> &'static str {
+        MEMBERSHIP_FIELDS
+    }
+
+    fn row_values (&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.id,
+            &self.team_id,
+            &self.user_id,
+            &self.role,
+            &self.created_at,
+        ]
+    }
+}
+
+impl TryFrom<Row> for Membership {
+    type Error = MyError;
+
+    fn try_from (row: Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: get_field(&row, FIELD_ID)?,
+            team_id: get_field(&row, FIELD_TEAM_ID)?,
+            user_id: get_field(&row, FIELD_USER_ID)?,
+            role: get_field(&row, FIELD_ROLE)?,
+            created_at: get_field(&row, FIELD_CREATED_AT)?,
+        })
+    }
+}
+
+
+
+const INVITE_SINGLE: &'static str = "Invite";
+const INVITE_PLURAL: &'static str = "Invites";
+const INVITE_FIELDS: &'static str = formatcp!(
+    "{}, {}, {}, {}, {}",
+    FIELD_ID,
+    FIELD_BOARD_ID,
+    FIELD_CREATED_BY,
+    FIELD_EXPIRES_AT,
+    FIELD_CREATED_AT,
+);
+
+impl RowCrud for Invite {
+    fn name_single () -> &'static str {
+        INVITE_SINGLE
+    }
+
+    fn name_plural () -> &'static str {
+        INVITE_PLURAL
+    }
+
+    fn table_name (storage: &PostgresStorage) -> &String {
+        &storage.table_invites
+    }
+
+    fn field_names () -> &'static str {
+        INVITE_FIELDS
+    }
+
+    fn row_values (&self) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.id,
+            &self.board_id,
+            &self.created_by,
+            &self.expires_at,
+            &self.created_at,
+        ]
+    }
+}
+
+impl TryFrom<Row> for Invite {
+    type Error = MyError;
+
+    fn try_from (row: Row) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: get_field(&row, FIELD_ID)?,
+            board_id: get_field(&row, FIELD_BOARD_ID)?,
+            created_by: get_field(&row, FIELD_CREATED_BY)?,
+            expires_at: get_field(&row, FIELD_EXPIRES_AT)?,
             created_at: get_field(&row, FIELD_CREATED_AT)?,
         })
     }
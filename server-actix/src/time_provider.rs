@@ -12,7 +12,7 @@ use dyn_clonable::clonable;
 // https://stackoverflow.com/questions/30353462/how-to-clone-a-struct-storing-a-boxed-trait-object
 // https://stackoverflow.com/questions/50017987/cant-clone-vecboxtrait-because-trait-cannot-be-made-into-an-object
 #[clonable]
-pub trait TimeProvider : Clone {
+pub trait TimeProvider : Clone + Send + Sync {
     fn unix_ts_ms (&self) -> i64;
 }
 
@@ -36,7 +36,12 @@ pub struct FixedTimeProvider {
 }
 
 impl FixedTimeProvider {
-    #[allow(dead_code)]
+    pub fn new (fixed_unix_ts_ms: i64) -> Self {
+        FixedTimeProvider {
+            fixed_unix_ts_ms: fixed_unix_ts_ms,
+        }
+    }
+
     pub fn set_fixed_unix_ts_ms (&mut self, new_unix_ts_ms: i64) {
         self.fixed_unix_ts_ms = new_unix_ts_ms;
     }
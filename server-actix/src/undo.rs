@@ -0,0 +1,171 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+use serde::Serialize;
+
+use crate::models::{MyError, Service, Row};
+
+
+// one row as it was before and after, none when it did not exist
+#[derive(Debug, Clone, Serialize)]
+pub struct RowChange {
+    pub before: Option<Row>,
+    pub after: Option<Row>,
+}
+
+impl RowChange {
+    pub fn row (&self) -> Option<&Row> {
+        self.before.as_ref().or(self.after.as_ref())
+    }
+}
+
+// everything a single request changed, including whatever went along with it,
+// like the votes and comments of a deleted card, in the order it was changed
+#[derive(Debug, Clone, Default)]
+pub struct Step {
+    changes: Vec<RowChange>,
+}
+
+impl Step {
+    pub fn new () -> Self {
+        Step::default()
+    }
+
+    pub fn added (mut self, row: Row) -> Self {
+        self.changes.push(RowChange { before: None, after: Some(row) });
+        self
+    }
+
+    pub fn changed (mut self, before: Row, after: Row) -> Self {
+        self.changes.push(RowChange { before: Some(before), after: Some(after) });
+        self
+    }
+
+    pub fn removed (mut self, row: Row) -> Self {
+        self.changes.push(RowChange { before: Some(row), after: None });
+        self
+    }
+
+    // whatever storage reports it changed in one go, in that order
+    pub fn with (mut self, changes: Vec<RowChange>) -> Self {
+        self.changes.extend(changes);
+        self
+    }
+
+    pub fn changes (&self) -> &Vec<RowChange> {
+        &self.changes
+    }
+
+    // back to how things were, last change first
+    pub fn inverse (&self) -> Step {
+        Step {
+            changes: self.changes.iter().rev()
+                .map(|change| RowChange { before: change.after.clone(), after: change.before.clone() })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Stacks {
+    undo: VecDeque<Step>,
+    redo: Vec<Step>,
+}
+
+impl Stacks {
+    fn is_empty (&self) -> bool {
+        self.undo.is_empty() && self.redo.is_empty()
+    }
+}
+
+// per board and user, kept in memory on the one service every worker shares
+pub struct UndoHistory {
+    limit: usize,
+    stacks: Mutex<HashMap<(String, String), Stacks>>,
+}
+
+impl UndoHistory {
+    pub fn new (limit: usize) -> Self {
+        UndoHistory {
+            limit: limit,
+            stacks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn with_stacks<T> (&self, board_id: &String, user_id: &String, with: impl FnOnce(&mut Stacks) -> T) -> T {
+        // a panic elsewhere while holding the lock leaves nothing half done in here, so carry on regardless
+        let mut stacks = self.stacks.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let key = (board_id.clone(), user_id.clone());
+        let result = with(stacks.entry(key.clone()).or_default());
+        // nothing left to undo or redo is the same as never having been there
+        if stacks.get(&key).is_some_and(Stacks::is_empty) {
+            stacks.remove(&key);
+        }
+        result
+    }
+
+    fn push_undo (&self, board_id: &String, user_id: &String, step: Step) {
+        let limit = self.limit;
+        self.with_stacks(board_id, user_id, |stacks| {
+            stacks.undo.push_back(step);
+            while stacks.undo.len() > limit {
+                stacks.undo.pop_front();
+            }
+        })
+    }
+
+    // anything new makes the undone steps unreachable, like in any editor
+    pub fn remember (&self, board_id: &String, user_id: &String, step: Step) {
+        if self.limit == 0 || step.changes.is_empty() {
+            return
+        }
+        self.push_undo(board_id, user_id, step);
+        self.with_stacks(board_id, user_id, |stacks| stacks.redo.clear());
+    }
+
+    pub fn pop_undo (&self, board_id: &String, user_id: &String) -> Option<Step> {
+        self.with_stacks(board_id, user_id, |stacks| stacks.undo.pop_back())
+    }
+
+    pub fn pop_redo (&self, board_id: &String, user_id: &String) -> Option<Step> {
+        self.with_stacks(board_id, user_id, |stacks| stacks.redo.pop())
+    }
+
+    // puts a step back where it came from, or moves it across once it was applied
+    pub fn undone (&self, board_id: &String, user_id: &String, step: Step) {
+        self.with_stacks(board_id, user_id, |stacks| stacks.redo.push(step))
+    }
+
+    pub fn redone (&self, board_id: &String, user_id: &String, step: Step) {
+        self.push_undo(board_id, user_id, step)
+    }
+
+    // everyone's steps on a board that is gone for good
+    pub fn forget_board (&self, board_id: &String) {
+        let mut stacks = self.stacks.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        stacks.retain(|(board, _), _| board != board_id);
+    }
+}
+
+
+pub fn same (a: &Option<Row>, b: &Option<Row>) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+}
+
+// kind and id of every row the changes touch, which removing a row may take along with it
+pub fn touched (changes: &[RowChange]) -> HashSet<(&'static str, &String)> {
+    changes.iter().filter_map(RowChange::row).map(|row| (row.kind(), row.id())).collect()
+}
+
+// only what is on a board can be undone, the rest is not kept per board
+pub fn check_undoable (row: &Row) -> Result<(), MyError> {
+    match row {
+        Row::Column(_) | Row::Card(_) | Row::Group(_) | Row::Vote(_) | Row::Tag(_) | Row::CardTag(_)
+            | Row::Comment(_) | Row::Revision(_) | Row::ActionItem(_) => Ok(()),
+        _ => Err(format!("Cannot undo changes to a {}", row.kind())),
+    }
+}
+
+// false, with nothing changed, when someone else got to any of it in between
+pub async fn apply (service: &Service, step: &Step) -> Result<bool, MyError> {
+    service.storage.apply_changes(&step.changes).await
+}
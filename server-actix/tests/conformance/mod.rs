@@ -0,0 +1,882 @@
+// what every storage backend has to do the same way, whatever it keeps rows in
+// each check gets a fresh, empty storage of its own, see conformance_tests! at the bottom
+#![allow(clippy::redundant_field_names)]
+
+use serde::Serialize;
+
+use bareretro::models::{
+    Storage, Board, BoardContents, BoardDeletion, Column, Card, CardGroup, Vote, Tag, CardTag, Comment, Revision,
+    ActionItem, AuditEntry, AuditFilter, SearchKind, Team, Membership, Role, Invite, Row, IfEmpty,
+};
+use bareretro::undo::RowChange;
+
+
+// ids are only unique within a check, and times are made up, so they are easy to compare
+pub const T0: i64 = 1_600_000_000_000;
+
+pub fn team (id: &str) -> Team {
+    Team { id: id.into(), name: format!("Team {}", id), created_at: T0 }
+}
+
+pub fn membership (id: &str, team_id: &str, user_id: &str, role: Role) -> Membership {
+    Membership { id: id.into(), team_id: team_id.into(), user_id: user_id.into(), role: role, created_at: T0 }
+}
+
+pub fn board (id: &str, team_id: &str) -> Board {
+    Board {
+        id: id.into(),
+        team_id: team_id.into(),
+        title: format!("Board {}", id),
+        owner: "alice".into(),
+        created_at: T0,
+        archived_at: None,
+        deleted_at: None,
+    }
+}
+
+pub fn column (id: &str, board_id: &str) -> Column {
+    Column { id: id.into(), board_id: board_id.into(), title: format!("Column {}", id), position: 0, created_at: T0 }
+}
+
+pub fn group (id: &str, board_id: &str, column_id: &str) -> CardGroup {
+    CardGroup { id: id.into(), board_id: board_id.into(), column_id: column_id.into(), title: format!("Group {}", id), created_at: T0 }
+}
+
+pub fn card (id: &str, board_id: &str, column_id: &str) -> Card {
+    Card {
+        id: id.into(),
+        board_id: board_id.into(),
+        column_id: column_id.into(),
+        group_id: None,
+        merged_into: None,
+        title: format!("Card {}", id),
+        author: "alice".into(),
+        author_name: "Alice".into(),
+        position: 0,
+        created_at: T0,
+    }
+}
+
+pub fn card_vote (id: &str, board_id: &str, card_id: &str) -> Vote {
+    Vote { id: id.into(), board_id: board_id.into(), card_id: Some(card_id.into()), group_id: None, author: "alice".into(), created_at: T0 }
+}
+
+pub fn group_vote (id: &str, board_id: &str, group_id: &str) -> Vote {
+    Vote { id: id.into(), board_id: board_id.into(), card_id: None, group_id: Some(group_id.into()), author: "alice".into(), created_at: T0 }
+}
+
+pub fn tag (id: &str, board_id: &str) -> Tag {
+    Tag { id: id.into(), board_id: board_id.into(), title: format!("Tag {}", id), created_at: T0 }
+}
+
+pub fn card_tag (id: &str, board_id: &str, card_id: &str, tag_id: &str) -> CardTag {
+    CardTag { id: id.into(), board_id: board_id.into(), card_id: card_id.into(), tag_id: tag_id.into(), created_at: T0 }
+}
+
+pub fn comment (id: &str, board_id: &str, card_id: &str) -> Comment {
+    Comment {
+        id: id.into(),
+        board_id: board_id.into(),
+        card_id: card_id.into(),
+        author: "bob".into(),
+        author_name: "Bob".into(),
+        text: format!("Comment {}", id),
+        created_at: T0,
+    }
+}
+
+pub fn revision (id: &str, board_id: &str, card_id: &str, comment_id: Option<&str>) -> Revision {
+    Revision {
+        id: id.into(),
+        board_id: board_id.into(),
+        card_id: card_id.into(),
+        comment_id: comment_id.map(String::from),
+        text: format!("Revision {}", id),
+        editor: "alice".into(),
+        editor_name: "Alice".into(),
+        created_at: T0,
+    }
+}
+
+pub fn action_item (id: &str, board_id: &str) -> ActionItem {
+    ActionItem { id: id.into(), board_id: board_id.into(), title: format!("Action {}", id), assignee: None, done: false, created_at: T0 }
+}
+
+pub fn invite (id: &str, board_id: &str) -> Invite {
+    Invite { id: id.into(), board_id: board_id.into(), created_by: "alice".into(), expires_at: T0 + 1_000, created_at: T0 }
+}
+
+pub fn audit_entry (id: &str, actor: &str, action: &str, board_id: Option<&str>, created_at: i64) -> AuditEntry {
+    AuditEntry {
+        id: id.into(),
+        seq: 0,
+        actor: actor.into(),
+        action: action.into(),
+        entity_type: "card".into(),
+        entity_id: format!("card-{}", id),
+        board_id: board_id.map(String::from),
+        before: None,
+        after: Some(serde_json::json!({ "title": format!("Card {}", id), "position": 2 })),
+        ip: Some("127.0.0.1".into()),
+        created_at: created_at,
+    }
+}
+
+// none of the models compare, but all of them serialize
+pub fn assert_same<T : Serialize> (actual: &T, expected: &T) {
+    assert_eq!(serde_json::to_value(actual).unwrap(), serde_json::to_value(expected).unwrap());
+}
+
+// in no particular order, backends list rows however they like
+pub fn ids<T : Serialize> (items: &[T]) -> Vec<String> {
+    let mut ids: Vec<String> = items.iter()
+        .map(|item| serde_json::to_value(item).unwrap()["id"].as_str().unwrap().to_string())
+        .collect();
+    ids.sort();
+    ids
+}
+
+// kind and id of what was removed, and of what is still there but changed, in no particular order
+pub fn removed (changes: &[RowChange]) -> Vec<String> {
+    let mut removed: Vec<String> = changes.iter()
+        .filter(|change| change.after.is_none())
+        .filter_map(|change| change.before.as_ref().map(|row| format!("{} {}", row.kind(), row.id())))
+        .collect();
+    removed.sort();
+    removed
+}
+
+pub fn changed (changes: &[RowChange]) -> Vec<Row> {
+    changes.iter().filter(|change| change.before.is_some()).filter_map(|change| change.after.clone()).collect()
+}
+
+pub fn strings (ids: &[&str]) -> Vec<String> {
+    let mut ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+    ids.sort();
+    ids
+}
+
+// a team with one board and a column on it, which most checks start from
+pub async fn seed (storage: &dyn Storage) {
+    assert!(storage.add_team(&team("t1")).await.unwrap());
+    assert!(storage.add_board(&board("b1", "t1")).await.unwrap());
+    assert!(storage.add_column(&column("c1", "b1")).await.unwrap());
+}
+
+
+pub async fn health (storage: &dyn Storage) {
+    assert!(!storage.name().is_empty());
+    storage.health_check().await.unwrap();
+    // whatever was applied when the storage was set up, there is nothing left to apply
+    assert_eq!(storage.migrate().await.unwrap(), Vec::<String>::new());
+    if let Some(status) = storage.pool_status() {
+        assert!(status.size <= status.max_size);
+    }
+}
+
+pub async fn boards (storage: &dyn Storage) {
+    storage.add_team(&team("t1")).await.unwrap();
+    storage.add_team(&team("t2")).await.unwrap();
+    let b1 = board("b1", "t1");
+    let b2 = Board { archived_at: Some(T0 + 5), ..board("b2", "t2") };
+    assert!(storage.add_board(&b1).await.unwrap());
+    assert!(storage.add_board(&b2).await.unwrap());
+
+    assert_same(&storage.get_board(&b1.id).await.unwrap(), &b1);
+    assert_same(&storage.get_board(&b2.id).await.unwrap(), &b2);
+    assert!(storage.get_board(&"missing".into()).await.is_err());
+    assert_eq!(ids(&storage.list_boards().await.unwrap()), strings(&["b1", "b2"]));
+    assert_eq!(ids(&storage.list_team_boards(&vec!["t2".into()]).await.unwrap()), strings(&["b2"]));
+    assert_eq!(ids(&storage.list_team_boards(&vec!["t1".into(), "t2".into()]).await.unwrap()), strings(&["b1", "b2"]));
+    assert_eq!(ids(&storage.list_team_boards(&vec![]).await.unwrap()), strings(&[]));
+
+    let renamed = Board { title: "Renamed".into(), archived_at: None, ..b2.clone() };
+    assert!(storage.update_board(&renamed).await.unwrap());
+    assert_same(&storage.get_board(&b2.id).await.unwrap(), &renamed);
+    assert!(!storage.update_board(&board("missing", "t1")).await.unwrap());
+    assert!(storage.get_board(&"missing".into()).await.is_err());
+}
+
+pub async fn deleted_boards (storage: &dyn Storage) {
+    storage.add_team(&team("t1")).await.unwrap();
+    storage.add_board(&board("b1", "t1")).await.unwrap();
+    storage.add_board(&Board { deleted_at: Some(T0 + 10), ..board("b2", "t1") }).await.unwrap();
+    storage.add_board(&Board { deleted_at: Some(T0 + 20), ..board("b3", "t1") }).await.unwrap();
+
+    // deleted strictly before the cutoff
+    assert_eq!(ids(&storage.list_deleted_boards(T0 + 10).await.unwrap()), strings(&[]));
+    assert_eq!(ids(&storage.list_deleted_boards(T0 + 11).await.unwrap()), strings(&["b2"]));
+    assert_eq!(ids(&storage.list_deleted_boards(T0 + 21).await.unwrap()), strings(&["b2", "b3"]));
+    // soft deleted boards are still there to be listed and restored
+    assert_eq!(ids(&storage.list_boards().await.unwrap()), strings(&["b1", "b2", "b3"]));
+}
+
+// one of everything that can hang off a board, with a second board that has to stay untouched
+pub async fn full_board (storage: &dyn Storage, id: &str) {
+    let b = |suffix: &str| format!("{}-{}", id, suffix);
+    storage.add_board(&board(id, "t1")).await.unwrap();
+    storage.add_column(&column(&b("c"), id)).await.unwrap();
+    storage.add_group(&group(&b("g"), id, &b("c"))).await.unwrap();
+    storage.add_card(&Card { group_id: Some(b("g")), ..card(&b("k1"), id, &b("c")) }).await.unwrap();
+    storage.add_card(&Card { merged_into: Some(b("k1")), ..card(&b("k2"), id, &b("c")) }).await.unwrap();
+    storage.add_vote(&card_vote(&b("v1"), id, &b("k1"))).await.unwrap();
+    storage.add_vote(&group_vote(&b("v2"), id, &b("g"))).await.unwrap();
+    storage.add_tag(&tag(&b("t"), id)).await.unwrap();
+    storage.add_card_tag(&card_tag(&b("ct"), id, &b("k1"), &b("t"))).await.unwrap();
+    storage.add_comment(&comment(&b("m"), id, &b("k1"))).await.unwrap();
+    storage.add_revision(&revision(&b("r1"), id, &b("k1"), None)).await.unwrap();
+    storage.add_revision(&revision(&b("r2"), id, &b("k1"), Some(&b("m")))).await.unwrap();
+    storage.add_action_item(&action_item(&b("a"), id)).await.unwrap();
+    storage.add_invite(&invite(&b("i"), id)).await.unwrap();
+}
+
+pub async fn delete_board (storage: &dyn Storage) {
+    storage.add_team(&team("t1")).await.unwrap();
+    full_board(storage, "b1").await;
+    full_board(storage, "b2").await;
+
+    let deletion = storage.delete_board(&"b1".into()).await.unwrap();
+    assert_eq!(deletion, BoardDeletion {
+        boards: 1, columns: 1, cards: 2, groups: 1, votes: 2, tags: 1, card_tags: 1,
+        comments: 1, revisions: 2, action_items: 1, invites: 1,
+    });
+    assert!(storage.get_board(&"b1".into()).await.is_err());
+    assert!(storage.get_column(&"b1-c".into()).await.is_err());
+    assert!(storage.get_card(&"b1-k2".into()).await.is_err());
+    assert!(storage.get_revision(&"b1-r2".into()).await.is_err());
+    assert!(storage.get_invite(&"b1-i".into()).await.is_err());
+    assert_eq!(storage.list_board_cards(&"b1".into()).await.unwrap().len(), 0);
+    assert_eq!(storage.list_board_votes(&"b1".into()).await.unwrap().len(), 0);
+    assert_eq!(storage.list_board_card_tags(&"b1".into()).await.unwrap().len(), 0);
+    assert_eq!(storage.list_board_action_items(&"b1".into()).await.unwrap().len(), 0);
+
+    // the other board keeps everything
+    assert_eq!(ids(&storage.list_boards().await.unwrap()), strings(&["b2"]));
+    assert_eq!(storage.list_board_cards(&"b2".into()).await.unwrap().len(), 2);
+    assert_eq!(storage.list_card_revisions(&"b2-k1".into()).await.unwrap().len(), 2);
+    assert_eq!(storage.list_board_invites(&"b2".into()).await.unwrap().len(), 1);
+
+    // nothing left to delete the second time, which is no more a success than a board that never was
+    for id in ["b1", "missing"] {
+        match storage.delete_board(&id.into()).await {
+            Ok(deletion) => panic!("deleted board {} that is not there: {:?}", id, deletion),
+            Err(why) => assert!(why.contains(&format!("No Board with id {}", id)), "{}", why),
+        }
+    }
+}
+
+pub async fn columns (storage: &dyn Storage) {
+    seed(storage).await;
+    storage.add_board(&board("b2", "t1")).await.unwrap();
+    let c2 = Column { position: 1, ..column("c2", "b1") };
+    assert!(storage.add_column(&c2).await.unwrap());
+    assert!(storage.add_column(&column("c3", "b2")).await.unwrap());
+
+    assert_same(&storage.get_column(&c2.id).await.unwrap(), &c2);
+    assert!(storage.get_column(&"missing".into()).await.is_err());
+    assert_eq!(ids(&storage.list_columns().await.unwrap()), strings(&["c1", "c2", "c3"]));
+    assert_eq!(ids(&storage.list_board_columns(&"b1".into()).await.unwrap()), strings(&["c1", "c2"]));
+    assert_eq!(ids(&storage.list_board_columns(&"missing".into()).await.unwrap()), strings(&[]));
+
+    let moved = Column { title: "Moved".into(), position: 0, ..c2.clone() };
+    assert!(storage.update_column(&moved).await.unwrap());
+    assert_same(&storage.get_column(&c2.id).await.unwrap(), &moved);
+    assert!(!storage.update_column(&column("missing", "b1")).await.unwrap());
+
+    assert!(storage.delete_column(&c2.id).await.unwrap());
+    assert!(!storage.delete_column(&c2.id).await.unwrap());
+    assert!(!storage.delete_column(&"missing".into()).await.unwrap());
+    assert!(storage.get_column(&c2.id).await.is_err());
+    assert_eq!(ids(&storage.list_columns().await.unwrap()), strings(&["c1", "c3"]));
+}
+
+pub async fn delete_column (storage: &dyn Storage) {
+    seed(storage).await;
+    storage.add_column(&column("c2", "b1")).await.unwrap();
+    storage.add_group(&group("g1", "b1", "c1")).await.unwrap();
+    storage.add_card(&card("k1", "b1", "c1")).await.unwrap();
+    storage.add_card(&card("k2", "b1", "c2")).await.unwrap();
+
+    // its groups and cards go with it
+    assert!(storage.delete_column(&"c1".into()).await.unwrap());
+    assert!(storage.get_group(&"g1".into()).await.is_err());
+    assert_eq!(ids(&storage.list_board_cards(&"b1".into()).await.unwrap()), strings(&["k2"]));
+}
+
+fn still_has<T> (deletion: IfEmpty<T>) -> Option<(&'static str, usize)> {
+    match deletion {
+        IfEmpty::NotEmpty(what, count) => Some((what, count)),
+        IfEmpty::Deleted(_) => None,
+    }
+}
+
+pub async fn delete_empty_column (storage: &dyn Storage) {
+    seed(storage).await;
+    storage.add_group(&group("g1", "b1", "c1")).await.unwrap();
+    storage.add_card(&card("k1", "b1", "c1")).await.unwrap();
+    storage.add_card(&Card { merged_into: Some("k1".into()), ..card("k2", "b1", "c1") }).await.unwrap();
+
+    // refused while anything is left in it, with nothing deleted
+    assert_eq!(still_has(storage.delete_empty_column(&"c1".into()).await.unwrap()), Some(("cards", 2)));
+    assert!(storage.delete_card(&"k2".into()).await.unwrap());
+    assert!(storage.delete_card(&"k1".into()).await.unwrap());
+    assert_eq!(still_has(storage.delete_empty_column(&"c1".into()).await.unwrap()), Some(("groups", 1)));
+    assert!(storage.get_column(&"c1".into()).await.is_ok());
+
+    assert!(storage.delete_group(&"g1".into()).await.unwrap());
+    assert!(matches!(storage.delete_empty_column(&"c1".into()).await.unwrap(), IfEmpty::Deleted(true)));
+    assert!(storage.get_column(&"c1".into()).await.is_err());
+    assert!(matches!(storage.delete_empty_column(&"c1".into()).await.unwrap(), IfEmpty::Deleted(false)));
+}
+
+pub async fn cards (storage: &dyn Storage) {
+    seed(storage).await;
+    let k1 = card("k1", "b1", "c1");
+    let k2 = Card { position: 1, ..card("k2", "b1", "c1") };
+    assert!(storage.add_card(&k1).await.unwrap());
+    assert!(storage.add_card(&k2).await.unwrap());
+
+    assert_same(&storage.get_card(&k1.id).await.unwrap(), &k1);
+    assert!(storage.get_card(&"missing".into()).await.is_err());
+    assert_eq!(ids(&storage.list_board_cards(&"b1".into()).await.unwrap()), strings(&["k1", "k2"]));
+
+    let merged = Card { title: "Edited".into(), merged_into: Some(k1.id.clone()), position: 3, ..k2.clone() };
+    assert!(storage.update_card(&merged).await.unwrap());
+    assert_same(&storage.get_card(&k2.id).await.unwrap(), &merged);
+    assert!(!storage.update_card(&card("missing", "b1", "c1")).await.unwrap());
+
+    assert!(storage.delete_card(&k2.id).await.unwrap());
+    assert!(!storage.delete_card(&k2.id).await.unwrap());
+    assert!(!storage.delete_card(&"missing".into()).await.unwrap());
+    assert_eq!(ids(&storage.list_board_cards(&"b1".into()).await.unwrap()), strings(&["k1"]));
+}
+
+pub async fn delete_card (storage: &dyn Storage) {
+    seed(storage).await;
+    storage.add_card(&card("k1", "b1", "c1")).await.unwrap();
+    storage.add_card(&Card { merged_into: Some("k1".into()), ..card("k2", "b1", "c1") }).await.unwrap();
+    storage.add_vote(&card_vote("v1", "b1", "k1")).await.unwrap();
+    storage.add_tag(&tag("t1", "b1")).await.unwrap();
+    storage.add_card_tag(&card_tag("ct1", "b1", "k1", "t1")).await.unwrap();
+    storage.add_comment(&comment("m1", "b1", "k1")).await.unwrap();
+    storage.add_revision(&revision("r1", "b1", "k1", None)).await.unwrap();
+
+    // what is on the card goes, the card merged into it is split back out
+    assert!(storage.delete_card(&"k1".into()).await.unwrap());
+    assert!(storage.get_vote(&"v1".into()).await.is_err());
+    assert!(storage.get_comment(&"m1".into()).await.is_err());
+    assert!(storage.get_revision(&"r1".into()).await.is_err());
+    assert_eq!(storage.list_board_card_tags(&"b1".into()).await.unwrap().len(), 0);
+    assert_eq!(storage.get_card(&"k2".into()).await.unwrap().merged_into, None);
+    assert_eq!(ids(&storage.list_board_tags(&"b1".into()).await.unwrap()), strings(&["t1"]));
+}
+
+// the same, all at once, reporting every row it touched with the card itself last
+pub async fn delete_card_cascade (storage: &dyn Storage) {
+    seed(storage).await;
+    storage.add_card(&card("k1", "b1", "c1")).await.unwrap();
+    storage.add_card(&Card { merged_into: Some("k1".into()), ..card("k2", "b1", "c1") }).await.unwrap();
+    storage.add_card(&card("k3", "b1", "c1")).await.unwrap();
+    storage.add_vote(&card_vote("v1", "b1", "k1")).await.unwrap();
+    storage.add_vote(&card_vote("v2", "b1", "k3")).await.unwrap();
+    storage.add_tag(&tag("t1", "b1")).await.unwrap();
+    storage.add_card_tag(&card_tag("ct1", "b1", "k1", "t1")).await.unwrap();
+    storage.add_comment(&comment("m1", "b1", "k1")).await.unwrap();
+    storage.add_revision(&revision("r1", "b1", "k1", Some("m1"))).await.unwrap();
+
+    let changes = storage.delete_card_cascade(&"k1".into()).await.unwrap();
+    assert_eq!(removed(&changes), strings(&["card k1", "card_tag ct1", "comment m1", "revision r1", "vote v1"]));
+    assert_eq!(changes.last().unwrap().before.as_ref().unwrap().id(), "k1");
+    match changed(&changes).as_slice() {
+        [Row::Card(unmerged)] => assert_same(unmerged, &card("k2", "b1", "c1")),
+        other => panic!("expected k2 unmerged, got {:?}", other),
+    }
+    assert_eq!(ids(&storage.list_board_cards(&"b1".into()).await.unwrap()), strings(&["k2", "k3"]));
+    assert_eq!(ids(&storage.list_board_votes(&"b1".into()).await.unwrap()), strings(&["v2"]));
+    assert_eq!(storage.list_board_comments(&"b1".into()).await.unwrap().len(), 0);
+    assert!(storage.get_revision(&"r1".into()).await.is_err());
+
+    assert!(storage.delete_card_cascade(&"k1".into()).await.is_err());
+}
+
+// the source and whatever was merged into it all end up merged into the target
+pub async fn merge_cards (storage: &dyn Storage) {
+    seed(storage).await;
+    storage.add_card(&card("k1", "b1", "c1")).await.unwrap();
+    storage.add_card(&card("k2", "b1", "c1")).await.unwrap();
+    storage.add_card(&Card { merged_into: Some("k2".into()), ..card("k3", "b1", "c1") }).await.unwrap();
+    storage.add_card(&card("k4", "b1", "c1")).await.unwrap();
+
+    let changes = storage.merge_cards(&"k2".into(), &"k1".into()).await.unwrap();
+    assert_eq!(removed(&changes).len(), 0);
+    let mut merged: Vec<String> = changed(&changes).iter().map(|row| row.id().clone()).collect();
+    merged.sort();
+    assert_eq!(merged, strings(&["k2", "k3"]));
+    for id in ["k2", "k3"].iter() {
+        assert_eq!(storage.get_card(&id.to_string()).await.unwrap().merged_into, Some("k1".into()));
+    }
+    assert_eq!(storage.get_card(&"k4".into()).await.unwrap().merged_into, None);
+
+    assert!(storage.merge_cards(&"missing".into(), &"k1".into()).await.is_err());
+    assert!(storage.merge_cards(&"k4".into(), &"missing".into()).await.is_err());
+    assert!(storage.merge_cards(&"k4".into(), &"k4".into()).await.is_err());
+    // a target merged since, into a stack of its own, is refused with nothing changed
+    assert!(storage.merge_cards(&"k4".into(), &"k2".into()).await.is_err());
+    assert_eq!(storage.get_card(&"k4".into()).await.unwrap().merged_into, None);
+}
+
+pub async fn delete_group_cascade (storage: &dyn Storage) {
+    seed(storage).await;
+    storage.add_group(&group("g1", "b1", "c1")).await.unwrap();
+    storage.add_card(&Card { group_id: Some("g1".into()), ..card("k1", "b1", "c1") }).await.unwrap();
+    storage.add_vote(&group_vote("v1", "b1", "g1")).await.unwrap();
+
+    let changes = storage.delete_group_cascade(&"g1".into()).await.unwrap();
+    assert_eq!(removed(&changes), strings(&["group g1", "vote v1"]));
+    assert_eq!(changes.last().unwrap().before.as_ref().unwrap().id(), "g1");
+    match changed(&changes).as_slice() {
+        [Row::Card(unstacked)] => assert_same(unstacked, &card("k1", "b1", "c1")),
+        other => panic!("expected k1 out of the group, got {:?}", other),
+    }
+    assert_eq!(storage.get_card(&"k1".into()).await.unwrap().group_id, None);
+
+    assert!(storage.delete_group_cascade(&"g1".into()).await.is_err());
+}
+
+// undo puts back exactly what a cascade took, or nothing at all
+pub async fn apply_changes (storage: &dyn Storage) {
+    seed(storage).await;
+    storage.add_card(&card("k1", "b1", "c1")).await.unwrap();
+    storage.add_vote(&card_vote("v1", "b1", "k1")).await.unwrap();
+    storage.add_comment(&comment("m1", "b1", "k1")).await.unwrap();
+    let deleted = storage.delete_card_cascade(&"k1".into()).await.unwrap();
+    let restore: Vec<RowChange> = deleted.iter().rev()
+        .map(|change| RowChange { before: change.after.clone(), after: change.before.clone() })
+        .collect();
+
+    assert!(storage.apply_changes(&restore).await.unwrap());
+    assert_eq!(ids(&storage.list_board_votes(&"b1".into()).await.unwrap()), strings(&["v1"]));
+    assert_eq!(ids(&storage.list_board_comments(&"b1".into()).await.unwrap()), strings(&["m1"]));
+    // already back, so once more is a conflict
+    assert!(!storage.apply_changes(&restore).await.unwrap());
+
+    // someone else voted since, and the card cannot go without taking that along
+    storage.add_vote(&card_vote("v2", "b1", "k1")).await.unwrap();
+    assert!(!storage.apply_changes(&deleted).await.unwrap());
+    assert_eq!(ids(&storage.list_board_votes(&"b1".into()).await.unwrap()), strings(&["v1", "v2"]));
+
+    // the last change no longer matches, so the first one does not happen either
+    let renamed = Card { title: "Renamed".into(), ..card("k1", "b1", "c1") };
+    let stale = vec![
+        RowChange { before: Some(Row::Card(card("k1", "b1", "c1"))), after: Some(Row::Card(renamed)) },
+        RowChange { before: Some(Row::Vote(card_vote("v1", "b1", "missing"))), after: None },
+    ];
+    assert!(!storage.apply_changes(&stale).await.unwrap());
+    assert_same(&storage.get_card(&"k1".into()).await.unwrap(), &card("k1", "b1", "c1"));
+
+    let team = RowChange { before: None, after: Some(Row::Team(team("t2"))) };
+    assert!(storage.apply_changes(&[team]).await.is_err());
+}
+
+pub async fn groups (storage: &dyn Storage) {
+    seed(storage).await;
+    let g1 = group("g1", "b1", "c1");
+    assert!(storage.add_group(&g1).await.unwrap());
+    assert!(storage.add_group(&group("g2", "b1", "c1")).await.unwrap());
+
+    assert_same(&storage.get_group(&g1.id).await.unwrap(), &g1);
+    assert!(storage.get_group(&"missing".into()).await.is_err());
+    assert_eq!(ids(&storage.list_board_groups(&"b1".into()).await.unwrap()), strings(&["g1", "g2"]));
+
+    let renamed = CardGroup { title: "Renamed".into(), ..g1.clone() };
+    assert!(storage.update_group(&renamed).await.unwrap());
+    assert_same(&storage.get_group(&g1.id).await.unwrap(), &renamed);
+    assert!(!storage.update_group(&group("missing", "b1", "c1")).await.unwrap());
+
+    // cards in it are kept, only out of the group, its votes go
+    storage.add_card(&Card { group_id: Some(g1.id.clone()), ..card("k1", "b1", "c1") }).await.unwrap();
+    storage.add_vote(&group_vote("v1", "b1", "g1")).await.unwrap();
+    assert!(storage.delete_group(&g1.id).await.unwrap());
+    assert!(!storage.delete_group(&g1.id).await.unwrap());
+    assert!(!storage.delete_group(&"missing".into()).await.unwrap());
+    assert_eq!(storage.get_card(&"k1".into()).await.unwrap().group_id, None);
+    assert!(storage.get_vote(&"v1".into()).await.is_err());
+    assert_eq!(ids(&storage.list_board_groups(&"b1".into()).await.unwrap()), strings(&["g2"]));
+}
+
+pub async fn votes (storage: &dyn Storage) {
+    seed(storage).await;
+    storage.add_card(&card("k1", "b1", "c1")).await.unwrap();
+    storage.add_group(&group("g1", "b1", "c1")).await.unwrap();
+    let v1 = card_vote("v1", "b1", "k1");
+    let v2 = group_vote("v2", "b1", "g1");
+    assert!(storage.add_vote(&v1).await.unwrap());
+    assert!(storage.add_vote(&v2).await.unwrap());
+
+    assert_same(&storage.get_vote(&v1.id).await.unwrap(), &v1);
+    assert_same(&storage.get_vote(&v2.id).await.unwrap(), &v2);
+    assert!(storage.get_vote(&"missing".into()).await.is_err());
+    assert_eq!(ids(&storage.list_board_votes(&"b1".into()).await.unwrap()), strings(&["v1", "v2"]));
+
+    assert!(storage.delete_vote(&v1.id).await.unwrap());
+    assert!(!storage.delete_vote(&v1.id).await.unwrap());
+    assert!(!storage.delete_vote(&"missing".into()).await.unwrap());
+    assert_eq!(ids(&storage.list_board_votes(&"b1".into()).await.unwrap()), strings(&["v2"]));
+}
+
+pub async fn tags (storage: &dyn Storage) {
+    seed(storage).await;
+    storage.add_card(&card("k1", "b1", "c1")).await.unwrap();
+    let t1 = tag("t1", "b1");
+    assert!(storage.add_tag(&t1).await.unwrap());
+    assert!(storage.add_tag(&tag("t2", "b1")).await.unwrap());
+
+    assert_same(&storage.get_tag(&t1.id).await.unwrap(), &t1);
+    assert!(storage.get_tag(&"missing".into()).await.is_err());
+    assert_eq!(ids(&storage.list_board_tags(&"b1".into()).await.unwrap()), strings(&["t1", "t2"]));
+
+    // cards lose the tag, nothing else
+    storage.add_card_tag(&card_tag("ct1", "b1", "k1", "t1")).await.unwrap();
+    storage.add_card_tag(&card_tag("ct2", "b1", "k1", "t2")).await.unwrap();
+    assert!(storage.delete_tag(&t1.id).await.unwrap());
+    assert!(!storage.delete_tag(&t1.id).await.unwrap());
+    assert!(!storage.delete_tag(&"missing".into()).await.unwrap());
+    assert_eq!(ids(&storage.list_board_card_tags(&"b1".into()).await.unwrap()), strings(&["ct2"]));
+    assert!(storage.get_card(&"k1".into()).await.is_ok());
+}
+
+pub async fn card_tags (storage: &dyn Storage) {
+    seed(storage).await;
+    storage.add_board(&board("b2", "t1")).await.unwrap();
+    storage.add_column(&column("c2", "b2")).await.unwrap();
+    storage.add_card(&card("k1", "b1", "c1")).await.unwrap();
+    storage.add_card(&card("k2", "b2", "c2")).await.unwrap();
+    storage.add_tag(&tag("t1", "b1")).await.unwrap();
+    storage.add_tag(&tag("t2", "b2")).await.unwrap();
+    let ct1 = card_tag("ct1", "b1", "k1", "t1");
+    assert!(storage.add_card_tag(&ct1).await.unwrap());
+    assert!(storage.add_card_tag(&card_tag("ct2", "b2", "k2", "t2")).await.unwrap());
+
+    let listed = storage.list_board_card_tags(&"b1".into()).await.unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_same(&listed[0], &ct1);
+
+    assert!(storage.delete_card_tag(&ct1.id).await.unwrap());
+    assert!(!storage.delete_card_tag(&ct1.id).await.unwrap());
+    assert!(!storage.delete_card_tag(&"missing".into()).await.unwrap());
+    assert_eq!(storage.list_board_card_tags(&"b1".into()).await.unwrap().len(), 0);
+    assert_eq!(storage.list_board_card_tags(&"b2".into()).await.unwrap().len(), 1);
+}
+
+pub async fn comments (storage: &dyn Storage) {
+    seed(storage).await;
+    storage.add_card(&card("k1", "b1", "c1")).await.unwrap();
+    let m1 = comment("m1", "b1", "k1");
+    assert!(storage.add_comment(&m1).await.unwrap());
+    assert!(storage.add_comment(&comment("m2", "b1", "k1")).await.unwrap());
+
+    assert_same(&storage.get_comment(&m1.id).await.unwrap(), &m1);
+    assert!(storage.get_comment(&"missing".into()).await.is_err());
+    assert_eq!(ids(&storage.list_board_comments(&"b1".into()).await.unwrap()), strings(&["m1", "m2"]));
+
+    let edited = Comment { text: "Edited".into(), ..m1.clone() };
+    assert!(storage.update_comment(&edited).await.unwrap());
+    assert_same(&storage.get_comment(&m1.id).await.unwrap(), &edited);
+    assert!(!storage.update_comment(&comment("missing", "b1", "k1")).await.unwrap());
+
+    // its revisions go with it, the card's own stay
+    storage.add_revision(&revision("r1", "b1", "k1", Some("m1"))).await.unwrap();
+    storage.add_revision(&revision("r2", "b1", "k1", None)).await.unwrap();
+    assert!(storage.delete_comment(&m1.id).await.unwrap());
+    assert!(!storage.delete_comment(&m1.id).await.unwrap());
+    assert!(!storage.delete_comment(&"missing".into()).await.unwrap());
+    assert_eq!(ids(&storage.list_board_comments(&"b1".into()).await.unwrap()), strings(&["m2"]));
+    assert_eq!(ids(&storage.list_card_revisions(&"k1".into()).await.unwrap()), strings(&["r2"]));
+}
+
+pub async fn revisions (storage: &dyn Storage) {
+    seed(storage).await;
+    storage.add_card(&card("k1", "b1", "c1")).await.unwrap();
+    storage.add_card(&card("k2", "b1", "c1")).await.unwrap();
+    storage.add_comment(&comment("m1", "b1", "k1")).await.unwrap();
+    let r1 = revision("r1", "b1", "k1", None);
+    let r2 = revision("r2", "b1", "k1", Some("m1"));
+    assert!(storage.add_revision(&r1).await.unwrap());
+    assert!(storage.add_revision(&r2).await.unwrap());
+    assert!(storage.add_revision(&revision("r3", "b1", "k2", None)).await.unwrap());
+
+    assert_same(&storage.get_revision(&r1.id).await.unwrap(), &r1);
+    assert_same(&storage.get_revision(&r2.id).await.unwrap(), &r2);
+    assert!(storage.get_revision(&"missing".into()).await.is_err());
+    // the card's and its comments'
+    assert_eq!(ids(&storage.list_card_revisions(&"k1".into()).await.unwrap()), strings(&["r1", "r2"]));
+
+    assert!(storage.delete_revision(&r1.id).await.unwrap());
+    assert!(!storage.delete_revision(&r1.id).await.unwrap());
+    assert!(!storage.delete_revision(&"missing".into()).await.unwrap());
+    assert_eq!(ids(&storage.list_card_revisions(&"k1".into()).await.unwrap()), strings(&["r2"]));
+}
+
+pub async fn action_items (storage: &dyn Storage) {
+    seed(storage).await;
+    let a1 = action_item("a1", "b1");
+    assert!(storage.add_action_item(&a1).await.unwrap());
+    assert!(storage.add_action_item(&action_item("a2", "b1")).await.unwrap());
+
+    assert_same(&storage.get_action_item(&a1.id).await.unwrap(), &a1);
+    assert!(storage.get_action_item(&"missing".into()).await.is_err());
+    assert_eq!(ids(&storage.list_board_action_items(&"b1".into()).await.unwrap()), strings(&["a1", "a2"]));
+
+    let done = ActionItem { assignee: Some("bob".into()), done: true, ..a1.clone() };
+    assert!(storage.update_action_item(&done).await.unwrap());
+    assert_same(&storage.get_action_item(&a1.id).await.unwrap(), &done);
+    assert!(!storage.update_action_item(&action_item("missing", "b1")).await.unwrap());
+
+    assert!(storage.delete_action_item(&a1.id).await.unwrap());
+    assert!(!storage.delete_action_item(&a1.id).await.unwrap());
+    assert!(!storage.delete_action_item(&"missing".into()).await.unwrap());
+    assert_eq!(ids(&storage.list_board_action_items(&"b1".into()).await.unwrap()), strings(&["a2"]));
+}
+
+pub async fn audit (storage: &dyn Storage) {
+    let e1 = audit_entry("e1", "alice", "create", Some("b1"), T0 + 1);
+    let e2 = audit_entry("e2", "bob", "update", Some("b1"), T0 + 2);
+    let e3 = audit_entry("e3", "alice", "update", None, T0 + 3);
+    for entry in [&e1, &e2, &e3] {
+        assert!(storage.add_audit_entry(entry).await.unwrap());
+    }
+    let list = |filter: AuditFilter| async move {
+        storage.list_audit_entries(&filter).await.unwrap().into_iter().map(|entry| entry.id).collect::<Vec<String>>()
+    };
+
+    // newest first, whole entries as they went in, numbered by storage
+    let all = storage.list_audit_entries(&AuditFilter::default()).await.unwrap();
+    assert_eq!(all.len(), 3);
+    assert_same(&all[0], &AuditEntry { seq: all[0].seq, ..e3.clone() });
+    assert_same(&all[2], &AuditEntry { seq: all[2].seq, ..e1.clone() });
+    assert_eq!(list(AuditFilter { actor: Some("alice".into()), ..Default::default() }).await, vec!["e3", "e1"]);
+    assert_eq!(list(AuditFilter { action: Some("update".into()), ..Default::default() }).await, vec!["e3", "e2"]);
+    assert_eq!(list(AuditFilter { entity_type: Some("card".into()), ..Default::default() }).await, vec!["e3", "e2", "e1"]);
+    assert_eq!(list(AuditFilter { entity_type: Some("board".into()), ..Default::default() }).await, Vec::<String>::new());
+    assert_eq!(list(AuditFilter { entity_id: Some("card-e2".into()), ..Default::default() }).await, vec!["e2"]);
+    assert_eq!(list(AuditFilter { board_id: Some("b1".into()), ..Default::default() }).await, vec!["e2", "e1"]);
+    // since is inclusive, until is not
+    assert_eq!(list(AuditFilter { since: Some(T0 + 2), ..Default::default() }).await, vec!["e3", "e2"]);
+    assert_eq!(list(AuditFilter { until: Some(T0 + 2), ..Default::default() }).await, vec!["e1"]);
+    assert_eq!(list(AuditFilter { limit: Some(2), ..Default::default() }).await, vec!["e3", "e2"]);
+    assert_eq!(list(AuditFilter { actor: Some("alice".into()), limit: Some(1), ..Default::default() }).await, vec!["e3"]);
+
+    // in the order they went in, even within a millisecond and whatever the ids
+    let e0 = audit_entry("e0", "bob", "delete", Some("b1"), T0 + 3);
+    assert!(storage.add_audit_entry(&e0).await.unwrap());
+    let all = storage.list_audit_entries(&AuditFilter::default()).await.unwrap();
+    assert!(all.windows(2).all(|pair| pair[0].seq > pair[1].seq));
+    assert_eq!(list(AuditFilter::default()).await, vec!["e0", "e3", "e2", "e1"]);
+    assert_eq!(list(AuditFilter { oldest_first: true, ..Default::default() }).await, vec!["e1", "e2", "e3", "e0"]);
+    // paging on from the last one seen, whichever way
+    assert_eq!(list(AuditFilter { after: Some(all[1].seq), ..Default::default() }).await, vec!["e2", "e1"]);
+    assert_eq!(list(AuditFilter { after: Some(all[2].seq), oldest_first: true, limit: Some(1), ..Default::default() }).await, vec!["e3"]);
+    assert_eq!(list(AuditFilter { board_id: Some("b1".into()), after: Some(all[2].seq), oldest_first: true, ..Default::default() }).await, vec!["e0"]);
+}
+
+pub async fn search (storage: &dyn Storage) {
+    seed(storage).await;
+    storage.add_team(&team("t2")).await.unwrap();
+    storage.add_board(&Board { title: "Pineapple retro".into(), ..board("b2", "t1") }).await.unwrap();
+    storage.add_board(&Board { title: "Pineapple planning".into(), ..board("b3", "t2") }).await.unwrap();
+    storage.add_board(&Board { title: "Pineapple leftovers".into(), deleted_at: Some(T0), ..board("b4", "t1") }).await.unwrap();
+    storage.add_card(&Card { title: "More pineapple on pizza".into(), ..card("k1", "b1", "c1") }).await.unwrap();
+    storage.add_card(&Card { title: "Less meetings".into(), ..card("k2", "b1", "c1") }).await.unwrap();
+    storage.add_comment(&Comment { text: "Pineapple does not belong there".into(), ..comment("m1", "b1", "k2") }).await.unwrap();
+
+    let hits = storage.search(&vec!["t1".into()], &"pineapple".into(), 10).await.unwrap();
+    let mut found: Vec<(SearchKind, String)> = hits.iter().map(|hit| (hit.kind, hit.id.clone())).collect();
+    found.sort_by(|a, b| a.1.cmp(&b.1));
+    // only on the team's boards, and not on deleted ones
+    assert_eq!(found, vec![
+        (SearchKind::Board, String::from("b2")),
+        (SearchKind::Card, String::from("k1")),
+        (SearchKind::Comment, String::from("m1")),
+    ]);
+    let comment_hit = hits.iter().find(|hit| hit.kind == SearchKind::Comment).unwrap();
+    assert_eq!(comment_hit.board_id, "b1");
+    assert_eq!(comment_hit.board_title, "Board b1");
+
+    assert_eq!(storage.search(&vec!["t1".into(), "t2".into()], &"pineapple".into(), 10).await.unwrap().len(), 4);
+    assert_eq!(storage.search(&vec!["t1".into()], &"pineapple".into(), 2).await.unwrap().len(), 2);
+    assert_eq!(storage.search(&vec!["t1".into()], &"durian".into(), 10).await.unwrap().len(), 0);
+    assert_eq!(storage.search(&vec![], &"pineapple".into(), 10).await.unwrap().len(), 0);
+    // every term has to be there
+    let hits = storage.search(&vec!["t1".into()], &"pineapple pizza".into(), 10).await.unwrap();
+    assert_eq!(hits.iter().map(|hit| hit.id.as_str()).collect::<Vec<&str>>(), vec!["k1"]);
+
+    // and found as the rows are now, not as they were
+    storage.update_card(&Card { title: "Fewer meetings".into(), ..card("k1", "b1", "c1") }).await.unwrap();
+    storage.update_card(&Card { title: "Pineapple after all".into(), ..card("k2", "b1", "c1") }).await.unwrap();
+    assert!(storage.delete_comment(&"m1".into()).await.unwrap());
+    let mut found: Vec<String> = storage.search(&vec!["t1".into()], &"pineapple".into(), 10).await.unwrap()
+        .into_iter().map(|hit| hit.id).collect();
+    found.sort();
+    assert_eq!(found, strings(&["b2", "k2"]));
+    assert_eq!(storage.search(&vec!["t1".into()], &"pizza".into(), 10).await.unwrap().len(), 0);
+}
+
+pub async fn board_contents (storage: &dyn Storage) {
+    storage.add_team(&team("t1")).await.unwrap();
+    let contents = BoardContents {
+        board: board("b1", "t1"),
+        columns: vec![column("c1", "b1"), column("c2", "b1")],
+        groups: vec![group("g1", "b1", "c1")],
+        // the merged card comes first, it is up to the storage to add what it points at before it
+        cards: vec![
+            Card { merged_into: Some("k1".into()), ..card("k2", "b1", "c1") },
+            Card { group_id: Some("g1".into()), ..card("k1", "b1", "c1") },
+        ],
+        votes: vec![card_vote("v1", "b1", "k1"), group_vote("v2", "b1", "g1")],
+        tags: vec![tag("t1", "b1")],
+        card_tags: vec![card_tag("ct1", "b1", "k1", "t1")],
+        comments: vec![comment("m1", "b1", "k1")],
+        action_items: vec![action_item("a1", "b1")],
+    };
+    assert!(storage.add_board_contents(&contents).await.unwrap());
+
+    assert_same(&storage.get_board(&"b1".into()).await.unwrap(), &contents.board);
+    assert_eq!(ids(&storage.list_board_columns(&"b1".into()).await.unwrap()), ids(&contents.columns));
+    assert_eq!(ids(&storage.list_board_groups(&"b1".into()).await.unwrap()), ids(&contents.groups));
+    assert_eq!(ids(&storage.list_board_cards(&"b1".into()).await.unwrap()), ids(&contents.cards));
+    assert_eq!(ids(&storage.list_board_votes(&"b1".into()).await.unwrap()), ids(&contents.votes));
+    assert_eq!(ids(&storage.list_board_tags(&"b1".into()).await.unwrap()), ids(&contents.tags));
+    assert_eq!(ids(&storage.list_board_card_tags(&"b1".into()).await.unwrap()), ids(&contents.card_tags));
+    assert_eq!(ids(&storage.list_board_comments(&"b1".into()).await.unwrap()), ids(&contents.comments));
+    assert_eq!(ids(&storage.list_board_action_items(&"b1".into()).await.unwrap()), ids(&contents.action_items));
+    assert_same(&storage.get_card(&"k2".into()).await.unwrap(), &contents.cards[0]);
+}
+
+pub async fn teams (storage: &dyn Storage) {
+    let t1 = team("t1");
+    assert!(storage.add_team(&t1).await.unwrap());
+    assert!(storage.add_team(&team("t2")).await.unwrap());
+    assert!(storage.add_team(&team("t3")).await.unwrap());
+
+    assert_same(&storage.get_team(&t1.id).await.unwrap(), &t1);
+    assert!(storage.get_team(&"missing".into()).await.is_err());
+    assert_eq!(ids(&storage.list_teams(&vec!["t1".into(), "t3".into(), "missing".into()]).await.unwrap()), strings(&["t1", "t3"]));
+    assert_eq!(ids(&storage.list_teams(&vec![]).await.unwrap()), strings(&[]));
+    assert_eq!(ids(&storage.list_all_teams().await.unwrap()), strings(&["t1", "t2", "t3"]));
+
+    // its memberships go with it
+    storage.add_membership(&membership("ms1", "t1", "alice", Role::Owner)).await.unwrap();
+    assert!(storage.delete_team(&t1.id).await.unwrap());
+    assert!(!storage.delete_team(&t1.id).await.unwrap());
+    assert!(!storage.delete_team(&"missing".into()).await.unwrap());
+    assert!(storage.get_team(&t1.id).await.is_err());
+    assert_eq!(storage.list_user_memberships(&"alice".into()).await.unwrap().len(), 0);
+    assert_eq!(ids(&storage.list_all_teams().await.unwrap()), strings(&["t2", "t3"]));
+}
+
+pub async fn teams_cascade (storage: &dyn Storage) {
+    let t1 = team("t1");
+    let owner = membership("ms1", "t1", "alice", Role::Owner);
+    assert!(storage.add_team_with_owner(&t1, &owner).await.unwrap());
+    assert_eq!(ids(&storage.list_team_memberships(&t1.id).await.unwrap()), strings(&["ms1"]));
+    // all or nothing, a team under an id already taken leaves no member behind
+    assert!(storage.add_team_with_owner(&t1, &membership("ms2", "t1", "bob", Role::Owner)).await.is_err());
+    assert_eq!(ids(&storage.list_team_memberships(&t1.id).await.unwrap()), strings(&["ms1"]));
+    storage.add_membership(&membership("ms3", "t1", "bob", Role::Participant)).await.unwrap();
+
+    // refused while it has boards, with nothing deleted
+    storage.add_board(&board("b1", "t1")).await.unwrap();
+    match storage.delete_team_cascade(&t1.id).await.unwrap() {
+        IfEmpty::NotEmpty(what, count) => assert_eq!((what, count), ("boards", 1)),
+        IfEmpty::Deleted(_) => panic!("deleted a team that still has boards"),
+    }
+    assert_eq!(ids(&storage.list_team_memberships(&t1.id).await.unwrap()), strings(&["ms1", "ms3"]));
+
+    storage.delete_board(&"b1".into()).await.unwrap();
+    let changes = match storage.delete_team_cascade(&t1.id).await.unwrap() {
+        IfEmpty::Deleted(changes) => changes,
+        IfEmpty::NotEmpty(what, _) => panic!("team still has {}", what),
+    };
+    assert_eq!(removed(&changes), strings(&["membership ms1", "membership ms3", "team t1"]));
+    assert!(storage.get_team(&t1.id).await.is_err());
+    assert_eq!(storage.list_team_memberships(&t1.id).await.unwrap().len(), 0);
+    assert!(storage.delete_team_cascade(&t1.id).await.is_err());
+}
+
+pub async fn memberships (storage: &dyn Storage) {
+    storage.add_team(&team("t1")).await.unwrap();
+    storage.add_team(&team("t2")).await.unwrap();
+    let ms1 = membership("ms1", "t1", "alice", Role::Owner);
+    assert!(storage.add_membership(&ms1).await.unwrap());
+    assert!(storage.add_membership(&membership("ms2", "t1", "bob", Role::Participant)).await.unwrap());
+    assert!(storage.add_membership(&membership("ms3", "t2", "alice", Role::Viewer)).await.unwrap());
+
+    assert_eq!(ids(&storage.list_team_memberships(&"t1".into()).await.unwrap()), strings(&["ms1", "ms2"]));
+    assert_eq!(ids(&storage.list_user_memberships(&"alice".into()).await.unwrap()), strings(&["ms1", "ms3"]));
+    assert_eq!(ids(&storage.list_user_memberships(&"nobody".into()).await.unwrap()), strings(&[]));
+
+    let demoted = Membership { role: Role::Facilitator, ..ms1.clone() };
+    assert!(storage.update_membership(&demoted).await.unwrap());
+    let listed = storage.list_team_memberships(&"t1".into()).await.unwrap();
+    assert_same(listed.iter().find(|item| item.id == ms1.id).unwrap(), &demoted);
+    assert!(!storage.update_membership(&membership("missing", "t1", "carol", Role::Viewer)).await.unwrap());
+
+    assert!(storage.delete_membership(&ms1.id).await.unwrap());
+    assert!(!storage.delete_membership(&ms1.id).await.unwrap());
+    assert!(!storage.delete_membership(&"missing".into()).await.unwrap());
+    assert_eq!(ids(&storage.list_user_memberships(&"alice".into()).await.unwrap()), strings(&["ms3"]));
+}
+
+pub async fn invites (storage: &dyn Storage) {
+    seed(storage).await;
+    let i1 = invite("i1", "b1");
+    assert!(storage.add_invite(&i1).await.unwrap());
+
+    assert_same(&storage.get_invite(&i1.id).await.unwrap(), &i1);
+    assert!(storage.get_invite(&"missing".into()).await.is_err());
+    assert_eq!(ids(&storage.list_board_invites(&"b1".into()).await.unwrap()), strings(&["i1"]));
+    assert_eq!(ids(&storage.list_board_invites(&"missing".into()).await.unwrap()), strings(&[]));
+
+    assert!(storage.delete_invite(&i1.id).await.unwrap());
+    assert!(!storage.delete_invite(&i1.id).await.unwrap());
+    assert!(!storage.delete_invite(&"missing".into()).await.unwrap());
+    assert_eq!(ids(&storage.list_board_invites(&"b1".into()).await.unwrap()), strings(&[]));
+}
+
+
+// one test per check, for a backend given as an async fn (name) -> impl Storage, where the name
+// is that of the check, for backends that have to keep checks apart themselves, and optionally
+// a fn (name) to clean up after each check that passed, a failed one is left to look at
+// anything after it, like #[ignore], goes on every test
+#[macro_export]
+macro_rules! conformance_tests {
+    ($storage:path, then $done:path $(, #[$attr:meta])*) => {
+        conformance_tests!(@checks $storage, $done, [$(#[$attr])*],
+            health, boards, deleted_boards, delete_board, columns, delete_column, delete_empty_column, cards, delete_card,
+            delete_card_cascade, merge_cards, delete_group_cascade, apply_changes, groups, votes, tags, card_tags, comments,
+            revisions, action_items, audit, search, board_contents, teams, teams_cascade, memberships, invites
+        );
+    };
+    ($storage:path $(, #[$attr:meta])*) => {
+        conformance_tests!($storage, then conformance::keep $(, #[$attr])*);
+    };
+    (@checks $storage:path, $done:path, $attrs:tt, $($check:ident),*) => {
+        $(
+            conformance_tests!(@check $storage, $done, $attrs, $check);
+        )*
+    };
+    (@check $storage:path, $done:path, [$($attr:tt)*], $check:ident) => {
+        #[actix_rt::test]
+        $($attr)*
+        async fn $check () {
+            let storage = $storage(stringify!($check)).await;
+            conformance::$check(&storage).await;
+            storage.close().await.unwrap();
+            drop(storage);
+            $done(stringify!($check));
+        }
+    };
+}
+
+// nothing to clean up
+pub fn keep (_check: &str) {}
@@ -0,0 +1,135 @@
+// the whole app, routes, middleware and all, on in-memory storage with a clock that stands still
+// and ids that count up, so the same requests get the very same responses every run
+#![allow(clippy::redundant_static_lifetimes, clippy::redundant_field_names, dead_code)]
+
+use std::net::SocketAddr;
+use actix_web::{web, test, App};
+use actix_web::http::{StatusCode, Method};
+use serde::Serialize;
+
+use bareretro::config::Config;
+use bareretro::models::Service;
+use bareretro::startup::build_service_with;
+use bareretro::time_provider::FixedTimeProvider;
+use bareretro::id_generator::{IdGenerator, SequentialIdGenerator, RandomIdGenerator};
+use bareretro::metrics::{Metrics, track_request};
+use bareretro::routes::routes;
+use bareretro::handlers::not_found;
+use bareretro::logging;
+
+
+pub const NOW: i64 = 1_600_000_000_000;
+pub const ADMIN: &'static str = "admin";
+pub const PROXY_SECRET: &'static str = "harness proxy secret";
+
+// ids as the harness hands them out, the nth one made
+pub fn id (n: u64) -> String {
+    format!("{:016x}{:016x}", NOW, n)
+}
+
+pub fn config () -> Config {
+    Config {
+        provider: String::from("memory"),
+        require_storage: true,
+        admin_user_ids: vec![String::from(ADMIN)],
+        invite_secret: String::from("harness secret"),
+        proxy_secret: String::from(PROXY_SECRET),
+        ..Config::default()
+    }
+}
+
+pub struct Reply {
+    pub status: StatusCode,
+    // the body as json, or as a json string when it is not json
+    pub body: serde_json::Value,
+}
+
+pub struct Harness {
+    pub service: web::Data<Service>,
+    metrics: Metrics,
+}
+
+impl Harness {
+    pub fn new () -> Self {
+        Harness::with_config(config())
+    }
+
+    pub fn with_config (config: Config) -> Self {
+        Harness::with_ids(config, Box::new(SequentialIdGenerator::new(1)))
+    }
+
+    // ids as the server makes them, for what the order of the ids must not matter to
+    pub fn with_random_ids () -> Self {
+        Harness::with_ids(config(), Box::new(RandomIdGenerator {}))
+    }
+
+    fn with_ids (config: Config, ids: Box<dyn IdGenerator>) -> Self {
+        let metrics = Metrics::new();
+        let service = build_service_with(config, metrics.clone(), Box::new(FixedTimeProvider::new(NOW)), ids)
+            .unwrap_or_else(|why| panic!("could not build service: {}", why));
+        Harness {
+            service: web::Data::new(service),
+            metrics: metrics,
+        }
+    }
+
+    // put together like main does, the service is what carries over from one request to the next
+    pub async fn send (&self, req: test::TestRequest) -> Reply {
+        let metrics = self.metrics.clone();
+        let app = test::init_service(
+            App::new()
+                .wrap_fn(move |req, srv| track_request(&metrics, req, srv))
+                .wrap_fn(logging::trace_request)
+                .app_data(self.service.clone())
+                .configure(|cfg| routes(cfg, &self.service.config))
+                .default_service(web::route().to(not_found))
+        ).await;
+
+        let req = req
+            .peer_addr("127.0.0.1:40000".parse::<SocketAddr>().unwrap())
+            .insert_header(("X-Request-Id", "harness"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let status = res.status();
+        let body = test::read_body(res).await;
+        let body = serde_json::from_slice(&body)
+            .unwrap_or_else(|_| serde_json::Value::String(String::from_utf8_lossy(&body).into_owned()));
+        Reply { status: status, body: body }
+    }
+
+    // users come as the proxy in front would pass them on
+    pub async fn call<T : Serialize> (&self, method: Method, user: Option<&str>, path: &str, json: Option<&T>) -> Reply {
+        let mut req = test::TestRequest::default().method(method).uri(path);
+        if let Some(user) = user {
+            req = req.insert_header(("X-User-Id", user)).insert_header(("X-Proxy-Secret", PROXY_SECRET));
+        }
+        if let Some(json) = json {
+            req = req.set_json(json);
+        }
+        self.send(req).await
+    }
+
+    pub async fn get (&self, user: &str, path: &str) -> Reply {
+        self.call::<()>(Method::GET, Some(user), path, None).await
+    }
+
+    pub async fn post<T : Serialize> (&self, user: &str, path: &str, json: &T) -> Reply {
+        self.call(Method::POST, Some(user), path, Some(json)).await
+    }
+
+    pub async fn put<T : Serialize> (&self, user: &str, path: &str, json: &T) -> Reply {
+        self.call(Method::PUT, Some(user), path, Some(json)).await
+    }
+
+    pub async fn delete (&self, user: &str, path: &str) -> Reply {
+        self.call::<()>(Method::DELETE, Some(user), path, None).await
+    }
+}
+
+// compares the whole response, and shows both when they differ
+pub fn assert_reply (reply: &Reply, status: StatusCode, body: serde_json::Value) {
+    assert_eq!(
+        (reply.status, &reply.body), (status, &body),
+        "\n got: {}\nwant: {}\n", serde_json::to_string_pretty(&reply.body).unwrap(), serde_json::to_string_pretty(&body).unwrap(),
+    );
+}
@@ -0,0 +1,341 @@
+mod harness;
+
+use actix_web::test;
+use actix_web::http::{StatusCode, Method};
+use serde_json::json;
+
+use bareretro::config::Config;
+use bareretro::auth::{ApiKey, sign_api_key};
+use bareretro::audit::{record, Change, ACTION_DELETE, ENTITY_TAG};
+
+use harness::{Harness, Reply, assert_reply, id, NOW, ADMIN, PROXY_SECRET};
+
+
+// a team, a board on it, a column, a card and a vote, as alice, answering with every reply
+async fn retro (harness: &Harness) -> Vec<Reply> {
+    let team = harness.post("alice", "/api/teams", &json!({ "name": "Platform" })).await;
+    let board = harness.post("alice", "/api/boards", &json!({ "title": "Sprint 1", "team_id": team.body["id"] })).await;
+    let column = harness.post("alice", "/api/columns", &json!({ "title": "Went well", "board_id": board.body["id"] })).await;
+    let card = harness.post("alice", "/api/cards", &json!({ "title": "Pairing", "column_id": column.body["id"] })).await;
+    let vote = harness.post("alice", &format!("/api/cards/{}/votes", card.body["id"].as_str().unwrap()), &json!({})).await;
+    vec![team, board, column, card, vote]
+}
+
+#[actix_rt::test]
+async fn health () {
+    let harness = Harness::new();
+    assert_reply(&harness.get("alice", "/healthz").await, StatusCode::OK, json!("ok"));
+    assert_reply(&harness.get("alice", "/readyz").await, StatusCode::OK, json!({ "ready": true, "storage": "Events" }));
+}
+
+#[actix_rt::test]
+async fn create_retro () {
+    let harness = Harness::new();
+    let replies = retro(&harness).await;
+
+    // each create also takes an id for its audit entry, the team one more for its owner
+    assert_reply(&replies[0], StatusCode::OK, json!({ "id": id(1), "name": "Platform", "created_at": NOW }));
+    assert_reply(&replies[1], StatusCode::OK, json!({
+        "id": id(5), "team_id": id(1), "title": "Sprint 1", "owner": "alice",
+        "created_at": NOW, "archived_at": null, "deleted_at": null,
+    }));
+    assert_reply(&replies[2], StatusCode::OK, json!({ "id": id(7), "board_id": id(5), "title": "Went well", "position": 0, "created_at": NOW }));
+    assert_reply(&replies[3], StatusCode::OK, json!({
+        "id": id(9), "board_id": id(5), "column_id": id(7), "group_id": null, "merged_into": null,
+        "title": "Pairing", "author": "alice", "author_name": "alice", "position": 0, "created_at": NOW,
+    }));
+    assert_reply(&replies[4], StatusCode::OK, json!({
+        "id": id(11), "board_id": id(5), "card_id": id(9), "group_id": null, "author": "alice", "created_at": NOW,
+    }));
+}
+
+#[actix_rt::test]
+async fn board_reads () {
+    let harness = Harness::new();
+    retro(&harness).await;
+
+    assert_reply(&harness.get("alice", &format!("/api/boards/{}/cards", id(5))).await, StatusCode::OK, json!([{
+        "id": id(9), "board_id": id(5), "column_id": id(7), "group_id": null, "merged_into": null,
+        "title": "Pairing", "author": "alice", "author_name": "alice", "position": 0, "created_at": NOW,
+        "votes": 1, "tag_ids": [], "merged": [],
+    }]));
+    assert_reply(&harness.get("alice", "/api/teams").await, StatusCode::OK, json!([{ "id": id(1), "name": "Platform", "created_at": NOW }]));
+    assert_reply(
+        &harness.get("alice", &format!("/api/boards/{}/export?format=markdown", id(5))).await,
+        StatusCode::OK, json!("# Sprint 1\n\n## Went well\n\n- **Pairing** (1 vote) _alice_\n"),
+    );
+}
+
+#[actix_rt::test]
+async fn audit_log () {
+    let harness = Harness::new();
+    retro(&harness).await;
+
+    assert_reply(&harness.get(ADMIN, "/api/admin/audit?limit=1").await, StatusCode::OK, json!([{
+        "id": id(12), "seq": 11, "actor": "alice", "action": "create", "entity_type": "vote", "entity_id": id(11),
+        "board_id": id(5), "before": null,
+        "after": { "id": id(11), "board_id": id(5), "card_id": id(9), "group_id": null, "author": "alice", "created_at": NOW },
+        "ip": "127.0.0.1", "created_at": NOW,
+    }]));
+    assert_eq!(harness.get("alice", "/api/admin/audit").await.status, StatusCode::FORBIDDEN);
+}
+
+#[actix_rt::test]
+async fn refusals () {
+    let harness = Harness::new();
+    retro(&harness).await;
+
+    assert_reply(&harness.call::<()>(Method::GET, None, "/api/boards", None).await, StatusCode::UNAUTHORIZED, json!("Missing X-User-Id header!"));
+    for secret in [None, Some("guessed")] {
+        let mut req = test::TestRequest::get().uri("/api/boards").insert_header(("X-User-Id", ADMIN));
+        if let Some(secret) = secret {
+            req = req.insert_header(("X-Proxy-Secret", secret));
+        }
+        assert_reply(&harness.send(req).await, StatusCode::UNAUTHORIZED, json!("X-User-Id is only accepted from the trusted proxy!"));
+    }
+    assert_reply(
+        &harness.post("bob", &format!("/api/cards/{}/votes", id(9)), &json!({})).await,
+        StatusCode::FORBIDDEN, json!(format!("User bob is not a member of team {}", id(1))),
+    );
+    assert_reply(&harness.post("alice", "/api/teams", &json!({ "name": " " })).await, StatusCode::BAD_REQUEST, json!("Invalid request for create team!"));
+    assert_reply(&harness.delete("alice", &format!("/api/columns/{}", id(7))).await, StatusCode::CONFLICT, json!(format!("Column {} still has cards", id(7))));
+    assert_reply(&harness.get("alice", "/nowhere").await, StatusCode::NOT_FOUND, json!("404 DNE"));
+}
+
+#[actix_rt::test]
+async fn same_every_run () {
+    let bodies = |replies: Vec<Reply>| replies.into_iter().map(|reply| (reply.status, reply.body)).collect::<Vec<_>>();
+    let first = bodies(retro(&Harness::new()).await);
+    let second = bodies(retro(&Harness::new()).await);
+    assert_eq!(first, second);
+}
+
+#[actix_rt::test]
+async fn deleting_a_team_takes_its_members () {
+    let harness = Harness::new();
+    retro(&harness).await;
+    harness.put("alice", &format!("/api/teams/{}/members", id(1)), &json!({ "user_id": "bob", "role": "participant" })).await;
+    assert_reply(&harness.delete("alice", &format!("/api/teams/{}", id(1))).await, StatusCode::CONFLICT, json!(format!("Team {} still has 1 boards", id(1))));
+
+    let team = harness.post("alice", "/api/teams", &json!({ "name": "Empty" })).await;
+    let team_id = team.body["id"].as_str().unwrap();
+    harness.put("alice", &format!("/api/teams/{}/members", team_id), &json!({ "user_id": "bob", "role": "viewer" })).await;
+    assert_eq!(harness.delete("alice", &format!("/api/teams/{}", team_id)).await.status, StatusCode::OK);
+    assert_eq!(harness.get("bob", "/api/teams").await.body.as_array().unwrap().len(), 1);
+
+    // every membership that went is in the audit log, not only the team
+    let entries = harness.get(ADMIN, "/api/admin/audit?action=delete&limit=10").await.body;
+    let deleted: Vec<&str> = entries.as_array().unwrap().iter().map(|entry| entry["entity_type"].as_str().unwrap()).collect();
+    assert_eq!(deleted, vec!["team", "membership", "membership"]);
+}
+
+#[actix_rt::test]
+async fn deleting_a_tag_takes_it_off_cards () {
+    let harness = Harness::new();
+    retro(&harness).await;
+    let tag = harness.post("alice", "/api/tags", &json!({ "title": "ci", "board_id": id(5) })).await;
+    let tag_id = tag.body["id"].as_str().unwrap();
+    let card_tag = harness.post("alice", &format!("/api/cards/{}/tags", id(9)), &json!({ "tag_id": tag_id })).await;
+    assert_eq!(harness.delete("alice", &format!("/api/tags/{}", tag_id)).await.status, StatusCode::OK);
+
+    let entries = harness.get(ADMIN, "/api/admin/audit?action=delete&limit=10").await.body;
+    let deleted: Vec<(&str, &str)> = entries.as_array().unwrap().iter()
+        .map(|entry| (entry["entity_type"].as_str().unwrap(), entry["entity_id"].as_str().unwrap()))
+        .collect();
+    assert_eq!(deleted, vec![("tag", tag_id), ("card_tag", card_tag.body["id"].as_str().unwrap())]);
+}
+
+// the change stands without its entry, which only the metric tells
+#[actix_rt::test]
+async fn failed_audits_are_counted () {
+    let harness = Harness::with_config(Config { provider: String::from("nowhere"), require_storage: false, ..harness::config() });
+    record(&harness.service, &String::from("alice"), None, Change::new(ACTION_DELETE, ENTITY_TAG, &String::from("t1"), None)).await;
+    let metrics = harness.service.metrics.render().unwrap();
+    assert!(metrics.contains("audit_entry_errors_total{action=\"delete\",entity_type=\"tag\"} 1"), "{}", metrics);
+}
+
+// ids in a file only have to be unique within a kind, the column and card here both go by "x"
+#[actix_rt::test]
+async fn imports_ids_shared_across_kinds () {
+    let harness = Harness::new();
+    retro(&harness).await;
+    let mut contents = harness.get("alice", &format!("/api/boards/{}/export?format=json", id(5))).await.body;
+    contents["columns"][0]["id"] = json!("x");
+    contents["cards"][0]["id"] = json!("x");
+    contents["cards"][0]["column_id"] = json!("x");
+    contents["votes"][0]["card_id"] = json!("x");
+
+    let imported = harness.post("alice", &format!("/api/boards/import?team_id={}", id(1)), &contents).await;
+    assert_eq!(imported.status, StatusCode::OK);
+    let board_id = imported.body["board"]["id"].as_str().unwrap();
+    let columns = harness.get("alice", &format!("/api/boards/{}/columns", board_id)).await.body;
+    let cards = harness.get("alice", &format!("/api/boards/{}/cards", board_id)).await.body;
+    assert_ne!(cards[0]["id"], columns[0]["id"]);
+    assert_eq!((&cards[0]["column_id"], &cards[0]["votes"]), (&columns[0]["id"], &json!(1)));
+}
+
+#[actix_rt::test]
+async fn exports_stay_text () {
+    let harness = Harness::new();
+    retro(&harness).await;
+    // made before the last card in the first column, but it still comes after all of them
+    let column = harness.post("alice", "/api/columns", &json!({ "title": "To improve", "board_id": id(5) })).await;
+    harness.post("alice", "/api/cards", &json!({ "title": "\t=1+1", "column_id": column.body["id"] })).await;
+    harness.post("alice", "/api/cards", &json!({ "title": "=HYPERLINK(\"x\") *bold* [link](y)", "column_id": id(7) })).await;
+    let tag = harness.post("alice", "/api/tags", &json!({ "title": "`ci`", "board_id": id(5) })).await;
+    harness.post("alice", &format!("/api/cards/{}/tags", id(9)), &json!({ "tag_id": tag.body["id"] })).await;
+
+    assert_reply(
+        &harness.get("alice", &format!("/api/boards/{}/export?format=csv", id(5))).await,
+        StatusCode::OK, json!(format!(
+            "id,column,group,title,author,author_name,votes,tags,merged_into,created_at\n\
+            {},Went well,,Pairing,alice,alice,1,`ci`,,{}\n\
+            {},Went well,,\"'=HYPERLINK(\"\"x\"\") *bold* [link](y)\",alice,alice,0,,,{}\n\
+            {},To improve,,'\t=1+1,alice,alice,0,,,{}\n",
+            id(9), NOW, id(17), NOW, id(15), NOW,
+        )),
+    );
+    assert_reply(
+        &harness.get("alice", &format!("/api/boards/{}/export?format=markdown", id(5))).await,
+        StatusCode::OK, json!(
+            "# Sprint 1\n\n## Went well\n\n\
+            - **Pairing** (1 vote) _alice_ `` `ci` ``\n\
+            - **=HYPERLINK(\"x\") \\*bold\\* \\[link\\](y)** (0 votes) _alice_\n\n\
+            ## To improve\n\n\
+            - **=1+1** (0 votes) _alice_\n"
+        ),
+    );
+}
+
+#[actix_rt::test]
+async fn guest_session_ends_with_its_invite () {
+    let harness = Harness::with_config(Config {
+        invite_ttl_ms: 1_000,
+        guest_session_ttl_ms: 60_000,
+        ..harness::config()
+    });
+    retro(&harness).await;
+
+    let invite = harness.post("alice", &format!("/api/boards/{}/invite", id(5)), &json!({})).await;
+    assert_eq!(invite.body["expires_at"], json!(NOW + 1_000));
+    let guest = harness.call(Method::POST, None, "/api/invites/join", Some(&json!({ "token": invite.body["token"], "display_name": "Guest" }))).await;
+    assert_eq!(guest.body["expires_at"], json!(NOW + 1_000));
+}
+
+// a key is as good as its user, until it expires or the secret it was signed with changes
+#[actix_rt::test]
+async fn api_keys_act_as_their_user () {
+    let harness = Harness::with_config(Config { api_key_secret: String::from("harness api key secret"), ..harness::config() });
+    retro(&harness).await;
+    let key = |exp: i64| sign_api_key(&harness.service, &ApiKey {
+        id: String::from("k1"), user_id: String::from("alice"), name: String::from("ci"), expires_at: exp,
+    }).unwrap();
+    let boards = |key: String| harness.send(test::TestRequest::get().uri("/api/boards").insert_header(("Authorization", format!("Bearer {}", key))));
+
+    let reply = boards(key(NOW + 1)).await;
+    assert_eq!((reply.status, reply.body[0]["id"].clone()), (StatusCode::OK, json!(id(5))));
+    assert_reply(&boards(key(NOW)).await, StatusCode::UNAUTHORIZED, json!("Invalid API key! Token expired"));
+
+    let rotated = Harness::with_config(Config { api_key_secret: String::from("rotated"), ..harness::config() });
+    let reply = rotated.send(test::TestRequest::get().uri("/api/boards").insert_header(("Authorization", format!("Bearer {}", key(NOW + 1))))).await;
+    assert_reply(&reply, StatusCode::UNAUTHORIZED, json!("Invalid API key! Invalid token signature"));
+    let disabled = Harness::new();
+    let reply = disabled.send(test::TestRequest::get().uri("/api/boards").insert_header(("Authorization", format!("Bearer {}", key(NOW + 1))))).await;
+    assert_reply(&reply, StatusCode::UNAUTHORIZED, json!("Invalid API key! API keys are disabled, no API_KEY_SECRET is configured"));
+    assert!(sign_api_key(&disabled.service, &ApiKey { id: String::from("k2"), user_id: String::from("alice"), name: String::new(), expires_at: NOW + 1 }).is_err());
+}
+
+#[actix_rt::test]
+async fn imported_votes_are_capped () {
+    let harness = Harness::with_config(Config {
+        import_limit_bytes: 128 * 1500,
+        ..harness::config()
+    });
+    retro(&harness).await;
+
+    let csv = "column,card,votes\nWent well,Pairing,99999999999\nWent well,Demos,800\n";
+    let reply = harness.send(test::TestRequest::post()
+        .uri(&format!("/api/boards/import/csv?team_id={}&dry_run=true", id(1)))
+        .insert_header(("X-User-Id", "alice"))
+        .insert_header(("X-Proxy-Secret", PROXY_SECRET))
+        .set_payload(csv)).await;
+    assert_eq!(reply.status, StatusCode::OK);
+    assert_eq!(reply.body["votes"], json!(1500));
+    assert_eq!(reply.body["warnings"], json!([
+        "Cast 1000 of the 99999999999 votes on card 'Pairing', at most 1000 per card and 1500 per import",
+        "Cast 500 of the 800 votes on card 'Demos', at most 1000 per card and 1500 per import",
+    ]));
+}
+
+#[actix_rt::test]
+async fn history_pages_within_a_millisecond () {
+    let harness = Harness::new();
+    retro(&harness).await;
+    let history = format!("/api/boards/{}/history", id(5));
+
+    // everything happened at NOW, so only the seq can tell where a page ended
+    let all = harness.get("alice", &history).await.body;
+    let first = harness.get("alice", &format!("{}?limit=2", history)).await.body;
+    let rest = harness.get("alice", &format!("{}?since={}&after={}", history, NOW, first[1]["seq"])).await.body;
+    let paged: Vec<serde_json::Value> = first.as_array().unwrap().iter().chain(rest.as_array().unwrap()).cloned().collect();
+    assert_eq!(json!(paged), all);
+    assert_eq!(all.as_array().unwrap().len(), 4);
+
+    assert_eq!(harness.get("alice", &format!("{}?after=nope", history)).await.status, StatusCode::BAD_REQUEST);
+
+    let state = harness.get("alice", &format!("/api/boards/{}/history/state?at={}", id(5), NOW)).await.body;
+    assert_eq!((&state["reconstructed"], &state["at"], &state["board"]["id"]), (&json!(true), &json!(NOW), &json!(id(5))));
+    assert_eq!(state["votes"].as_array().unwrap().len(), 1);
+}
+
+// random ids within the one millisecond, nothing but the order they went in can tell the edits apart
+#[actix_rt::test]
+async fn history_keeps_the_order_of_changes () {
+    let harness = Harness::with_random_ids();
+    let replies = retro(&harness).await;
+    let board_id = replies[1].body["id"].as_str().unwrap();
+    let card = format!("/api/cards/{}", replies[3].body["id"].as_str().unwrap());
+    let titles: Vec<String> = (1..=8).map(|n| format!("Pairing {}", n)).collect();
+    for title in titles.iter() {
+        assert_eq!(harness.put("alice", &card, &json!({ "title": title })).await.status, StatusCode::OK);
+    }
+
+    let history = harness.get("alice", &format!("/api/boards/{}/history", board_id)).await.body;
+    let edits: Vec<serde_json::Value> = history.as_array().unwrap().iter()
+        .filter(|change| change["action"] == json!("update"))
+        .map(|change| change["after"]["title"].clone())
+        .collect();
+    assert_eq!(json!(edits), json!(titles));
+
+    let state = harness.get("alice", &format!("/api/boards/{}/history/state?at={}", board_id, NOW)).await.body;
+    assert_eq!(state["cards"][0]["title"], json!("Pairing 8"));
+}
+
+#[actix_rt::test]
+async fn undo_keeps_what_others_added () {
+    let harness = Harness::new();
+    retro(&harness).await;
+    let undo = format!("/api/boards/{}/undo", id(5));
+    assert_eq!(harness.post("alice", &undo, &json!({})).await.status, StatusCode::OK);
+
+    // taking back the card would take bob's vote with it
+    harness.put("alice", &format!("/api/teams/{}/members", id(1)), &json!({ "user_id": "bob", "role": "participant" })).await;
+    assert_eq!(harness.post("bob", &format!("/api/cards/{}/votes", id(9)), &json!({})).await.status, StatusCode::OK);
+    assert_reply(&harness.post("alice", &undo, &json!({})).await, StatusCode::CONFLICT, json!("Changed by someone else since, cannot undo"));
+    assert_eq!(harness.get("alice", &format!("/api/boards/{}/votes", id(5))).await.body.as_array().unwrap().len(), 1);
+    assert_eq!(harness.get("alice", &format!("/api/boards/{}/cards", id(5))).await.body[0]["id"], json!(id(9)));
+}
+
+#[actix_rt::test]
+async fn reordering_keeps_the_group () {
+    let harness = Harness::new();
+    retro(&harness).await;
+    let group = harness.post("alice", "/api/groups", &json!({ "title": "Process", "column_id": id(7) })).await;
+    let moved = format!("/api/cards/{}/move", id(9));
+    harness.put("alice", &moved, &json!({ "group_id": group.body["id"] })).await;
+
+    let reordered = harness.put("alice", &moved, &json!({ "position": 3 })).await;
+    assert_eq!((&reordered.body["group_id"], &reordered.body["position"]), (&group.body["id"], &json!(3)));
+    let ungrouped = harness.put("alice", &moved, &json!({ "group_id": null })).await;
+    assert_eq!((&ungrouped.body["group_id"], &ungrouped.body["position"]), (&json!(null), &json!(3)));
+}
@@ -0,0 +1,273 @@
+#![allow(clippy::redundant_field_names)]
+
+#[macro_use]
+mod conformance;
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use bareretro::config::EventsConfig;
+use bareretro::models::Storage;
+use bareretro::time_provider::SystemTimeProvider;
+use bareretro::storage::events::EventStorage;
+use bareretro::storage::metered::MeteredStorage;
+use bareretro::metrics::Metrics;
+
+
+async fn in_memory (_check: &str) -> EventStorage {
+    EventStorage::in_memory(Box::new(SystemTimeProvider {}))
+}
+
+conformance_tests!(in_memory);
+
+// the same checks once more through the metrics wrapper, which has to pass every call through unchanged
+mod metered {
+    use super::*;
+
+    async fn metered (_check: &str) -> MeteredStorage {
+        let inner: Box<dyn Storage> = Box::new(EventStorage::in_memory(Box::new(SystemTimeProvider {})));
+        MeteredStorage::new(inner, Metrics::new())
+    }
+
+    conformance_tests!(metered);
+}
+
+// and once more writing to disk, in a directory of its own for every check
+mod on_disk {
+    use super::*;
+
+    pub fn dir (check: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bareretro-events-{}-{}", std::process::id(), check));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    pub fn remove (check: &str) {
+        std::fs::remove_dir_all(dir(check)).ok();
+    }
+
+    pub fn config (dir: &Path) -> EventsConfig {
+        EventsConfig {
+            dir: dir.to_string_lossy().into_owned(),
+            snapshot_every: 5,
+        }
+    }
+
+    pub fn open (dir: &Path, snapshot_every: u64) -> Result<EventStorage, String> {
+        EventStorage::new(Box::new(SystemTimeProvider {}), &EventsConfig { snapshot_every: snapshot_every, ..config(dir) })
+    }
+
+    async fn on_disk (check: &str) -> EventStorage {
+        EventStorage::new(Box::new(SystemTimeProvider {}), &config(&dir(check))).unwrap()
+    }
+
+    conformance_tests!(on_disk, then remove);
+}
+
+async fn add_boards (storage: &EventStorage, ids: &[&str]) {
+    for id in ids.iter() {
+        assert!(storage.add_board(&conformance::board(id, "t1")).await.unwrap());
+    }
+}
+
+async fn board_ids (storage: &EventStorage) -> Vec<String> {
+    conformance::ids(&storage.list_boards().await.unwrap())
+}
+
+fn lines (dir: &Path) -> usize {
+    std::fs::read_to_string(dir.join("events.jsonl")).unwrap().lines().count()
+}
+
+#[actix_rt::test]
+async fn log_opens_once () {
+    let dir = on_disk::dir("log_opens_once");
+    let first = EventStorage::new(Box::new(SystemTimeProvider {}), &on_disk::config(&dir)).unwrap();
+    match EventStorage::new(Box::new(SystemTimeProvider {}), &on_disk::config(&dir)) {
+        Ok(_) => panic!("opened the event log twice"),
+        Err(why) => assert!(why.contains("already open"), "{}", why),
+    }
+
+    drop(first);
+    assert!(EventStorage::new(Box::new(SystemTimeProvider {}), &on_disk::config(&dir)).is_ok());
+    on_disk::remove("log_opens_once");
+}
+
+// dropped without closing, as a crash would, so all there is to go on is the log
+#[actix_rt::test]
+async fn reopens_from_the_log () {
+    let dir = on_disk::dir("reopens_from_the_log");
+    let storage = on_disk::open(&dir, 1000).unwrap();
+    add_boards(&storage, &["b1", "b2", "b3"]).await;
+    assert!(storage.delete_board(&"b2".into()).await.is_ok());
+    drop(storage);
+    assert!(!dir.join("snapshot.json").exists());
+
+    let storage = on_disk::open(&dir, 1000).unwrap();
+    assert_eq!(board_ids(&storage).await, conformance::strings(&["b1", "b3"]));
+    // and carries on numbering where the log left off
+    add_boards(&storage, &["b4"]).await;
+    drop(storage);
+    let storage = on_disk::open(&dir, 1000).unwrap();
+    assert_eq!(board_ids(&storage).await, conformance::strings(&["b1", "b3", "b4"]));
+    drop(storage);
+    on_disk::remove("reopens_from_the_log");
+}
+
+#[actix_rt::test]
+async fn reopens_from_snapshot_and_tail () {
+    let dir = on_disk::dir("reopens_from_snapshot_and_tail");
+    let storage = on_disk::open(&dir, 5).unwrap();
+    add_boards(&storage, &["b1", "b2", "b3", "b4", "b5", "b6", "b7"]).await;
+    drop(storage);
+
+    // the log starts over after the snapshot, the rest of it is kept aside
+    assert!(dir.join("snapshot.json").exists());
+    assert!(dir.join("events-5.jsonl").exists());
+    assert_eq!(lines(&dir), 2);
+
+    let storage = on_disk::open(&dir, 5).unwrap();
+    assert_eq!(board_ids(&storage).await, conformance::strings(&["b1", "b2", "b3", "b4", "b5", "b6", "b7"]));
+    // the search terms are not in the snapshot, they are put together again from it
+    assert_eq!(storage.search(&vec!["t1".into()], &"board".into(), 10).await.unwrap().len(), 7);
+    drop(storage);
+    on_disk::remove("reopens_from_snapshot_and_tail");
+}
+
+#[actix_rt::test]
+async fn reopens_past_a_cut_off_last_line () {
+    let dir = on_disk::dir("reopens_past_a_cut_off_last_line");
+    let storage = on_disk::open(&dir, 1000).unwrap();
+    add_boards(&storage, &["b1", "b2"]).await;
+    drop(storage);
+    let whole = std::fs::metadata(dir.join("events.jsonl")).unwrap().len();
+    let mut log = std::fs::OpenOptions::new().append(true).open(dir.join("events.jsonl")).unwrap();
+    log.write_all(b"{\"seq\":3,\"at\":").unwrap();
+    drop(log);
+
+    let storage = on_disk::open(&dir, 1000).unwrap();
+    assert_eq!(board_ids(&storage).await, conformance::strings(&["b1", "b2"]));
+    assert_eq!(std::fs::metadata(dir.join("events.jsonl")).unwrap().len(), whole);
+    add_boards(&storage, &["b3"]).await;
+    drop(storage);
+    let storage = on_disk::open(&dir, 1000).unwrap();
+    assert_eq!(board_ids(&storage).await, conformance::strings(&["b1", "b2", "b3"]));
+    drop(storage);
+    on_disk::remove("reopens_past_a_cut_off_last_line");
+}
+
+// not a log nobody wrote yet, so starting out empty would write over what is in it
+#[actix_rt::test]
+async fn refuses_an_unreadable_log () {
+    let dir = on_disk::dir("refuses_an_unreadable_log");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("events.jsonl"), [0xff, 0xfe, b'\n']).unwrap();
+    match on_disk::open(&dir, 1000) {
+        Ok(_) => panic!("opened an unreadable event log"),
+        Err(why) => assert!(why.contains("Failed reading event log"), "{}", why),
+    }
+    on_disk::remove("refuses_an_unreadable_log");
+}
+
+// dumps go in and out of any backend through the storage trait, so an in memory log and the metrics wrapper will do
+mod backup {
+    use super::*;
+    use bareretro::backup::{dump, restore, manifest, read_rows, Dump};
+    use bareretro::models::{Role, Row};
+
+    fn metered () -> MeteredStorage {
+        MeteredStorage::new(Box::new(EventStorage::in_memory(Box::new(SystemTimeProvider {}))), Metrics::new())
+    }
+
+    // audit entries in between the rest, so the log numbers them apart from how a restore does
+    async fn filled () -> EventStorage {
+        let storage = EventStorage::in_memory(Box::new(SystemTimeProvider {}));
+        storage.add_team(&conformance::team("t1")).await.unwrap();
+        storage.add_membership(&conformance::membership("m1", "t1", "alice", Role::Owner)).await.unwrap();
+        storage.add_audit_entry(&conformance::audit_entry("e1", "alice", "create", None, conformance::T0)).await.unwrap();
+        conformance::full_board(&storage, "b1").await;
+        storage.add_audit_entry(&conformance::audit_entry("e2", "alice", "update", Some("b1"), conformance::T0)).await.unwrap();
+        conformance::full_board(&storage, "b2").await;
+        storage.add_audit_entry(&conformance::audit_entry("e3", "bob", "delete", Some("b2"), conformance::T0 + 1)).await.unwrap();
+        storage
+    }
+
+    async fn audit_ids (storage: &dyn Storage) -> Vec<String> {
+        read_rows(storage).await.unwrap().iter()
+            .filter(|row| matches!(row, Row::AuditEntry(_)))
+            .map(|row| row.id().clone())
+            .collect()
+    }
+
+    #[actix_rt::test]
+    async fn restores_between_backends () {
+        let events = filled().await;
+        let first = dump(&events, conformance::T0).await.unwrap();
+        assert_eq!(first.source, events.name());
+
+        let metered = metered();
+        assert_eq!(restore(&metered, &first).await.unwrap(), first.manifest);
+        assert_eq!(audit_ids(&metered).await, conformance::strings(&["e1", "e2", "e3"]));
+
+        // and back again, ending up where it started
+        let second = dump(&metered, conformance::T0).await.unwrap();
+        assert_eq!(second.manifest, first.manifest);
+        let events = EventStorage::in_memory(Box::new(SystemTimeProvider {}));
+        assert_eq!(restore(&events, &second).await.unwrap(), first.manifest);
+        assert_eq!(manifest(&read_rows(&events).await.unwrap()).unwrap(), first.manifest);
+    }
+
+    #[actix_rt::test]
+    async fn refuses_a_tampered_dump () {
+        let mut tampered = dump(&filled().await, conformance::T0).await.unwrap();
+        for row in tampered.rows.iter_mut() {
+            if let Row::Card(card) = row {
+                card.title = "Edited".into();
+            }
+        }
+        let target = metered();
+        match restore(&target, &tampered).await {
+            Ok(_) => panic!("restored a dump that does not match its manifest"),
+            Err(why) => assert!(why.contains("Dump does not match its manifest: card: checksum"), "{}", why),
+        }
+
+        // rows cut off the end show up as a count
+        let mut cut = dump(&filled().await, conformance::T0).await.unwrap();
+        cut.rows.pop();
+        match restore(&target, &cut).await {
+            Ok(_) => panic!("restored a dump that was cut short"),
+            Err(why) => assert!(why.contains("audit_entry: 2 rows, expected 3"), "{}", why),
+        }
+        assert!(read_rows(&target).await.unwrap().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn refuses_a_storage_that_is_not_empty () {
+        let dumped = dump(&filled().await, conformance::T0).await.unwrap();
+        let target = metered();
+        target.add_team(&conformance::team("t9")).await.unwrap();
+        match restore(&target, &dumped).await {
+            Ok(_) => panic!("restored into a storage that already had rows"),
+            Err(why) => assert!(why.contains("not empty"), "{}", why),
+        }
+        assert_eq!(read_rows(&target).await.unwrap().len(), 1);
+    }
+
+    // an invite twice over gets as far as the boards before it fails, which then come out again
+    #[actix_rt::test]
+    async fn takes_out_a_failed_restore () {
+        let dumped = dump(&filled().await, conformance::T0).await.unwrap();
+        let mut rows = dumped.rows.clone();
+        rows.push(Row::Invite(conformance::invite("b1-i", "b1")));
+        let twice = Dump { manifest: manifest(&rows).unwrap(), rows: rows, ..dumped };
+
+        let target = metered();
+        match restore(&target, &twice).await {
+            Ok(_) => panic!("restored an invite twice"),
+            Err(why) => assert!(why.contains("Restore invite b1-i failed") && why.contains("took out what it had restored"), "{}", why),
+        }
+        assert!(read_rows(&target).await.unwrap().is_empty());
+        // and it can be restored into again
+        let dumped = dump(&filled().await, conformance::T0).await.unwrap();
+        assert_eq!(restore(&target, &dumped).await.unwrap(), dumped.manifest);
+    }
+}
@@ -0,0 +1,31 @@
+// needs a database, configured like the server, so these only run when asked for:
+// PG_HOST=localhost cargo test --test storage_postgres -- --ignored
+#[macro_use]
+mod conformance;
+
+use tokio_postgres::NoTls;
+
+use bareretro::config::Config;
+use bareretro::models::Storage;
+use bareretro::time_provider::SystemTimeProvider;
+use bareretro::storage::postgres::PostgresStorage;
+
+
+// every check gets a schema of its own, dropped and migrated from scratch, and left behind to look at
+async fn postgres (check: &str) -> PostgresStorage {
+    let mut config = Config::load().unwrap_or_else(|errors| panic!("invalid config: {}", errors.join(", "))).postgres;
+    config.schema = format!("conformance_{}", check);
+
+    let (client, connection) = tokio_postgres::connect(&format!(
+        "host={} port={} user={} password={} dbname={}",
+        config.host, config.port, config.user, config.password, config.dbname,
+    ), NoTls).await.expect("could not connect to postgres");
+    actix_rt::spawn(async move { let _ = connection.await; });
+    client.batch_execute(&format!("DROP SCHEMA IF EXISTS {} CASCADE", config.schema)).await.unwrap();
+
+    let storage = PostgresStorage::new(Box::new(SystemTimeProvider {}), &config).unwrap();
+    storage.migrate().await.unwrap();
+    storage
+}
+
+conformance_tests!(postgres, #[ignore = "needs postgres"]);